@@ -0,0 +1,78 @@
+// acid chunk of loop libraries (ACID, and most DAWs reading loops), with
+// the tempo and meter that place positions on bars and beats:
+//   0    Flags  4: 0x01 one-shot, 0x02 root note set, 0x04 stretch,
+//               0x08 disk based, 0x10 high octave
+//   4    RootNote  2 (MIDI note, 0x30 is C), unknown  2, unknown  4
+//   12   NumBeats  4
+//   16   MeterDenominator  2, MeterNumerator  2
+//   20   Tempo  4 (float, beats per minute)
+use crate::{Error, WaveError};
+
+const SIZE: usize = 24;
+const ONE_SHOT: u32 = 0x01;
+const ROOT_NOTE_SET: u32 = 0x02;
+const STRETCH: u32 = 0x04;
+const DISK_BASED: u32 = 0x08;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Acid {
+    pub one_shot: bool,
+    pub stretch: bool,
+    pub disk_based: bool,
+    // None unless the flags say it is set
+    pub root_note: Option<u16>,
+    pub beats: u32,
+    pub meter_numerator: u16,
+    pub meter_denominator: u16,
+    pub tempo: f32,
+}
+
+impl Acid {
+    // One-shots have no tempo to go by, even if one is given
+    pub fn has_tempo(&self) -> bool {
+        !self.one_shot && self.tempo > 0.0
+    }
+
+    // Bars and beats of the position as bar:beat, both counted from 1, the
+    // beat with the given decimals; None for one-shots and files without
+    // a tempo
+    pub fn bars_beats(&self, sample: u64, sampling_rate: u32, decimals: u32) -> Option<String> {
+        if !self.has_tempo() || sampling_rate == 0 {
+            return None;
+        }
+        let beats = sample as f64 / sampling_rate as f64 * self.tempo as f64 / 60.0;
+        let beats_per_bar = self.meter_numerator.max(1) as f64;
+        // rounded first, so that 3.9999 doesn't show as beat 4 of the bar
+        let scale = 10f64.powi(decimals as i32);
+        let beats = (beats * scale).round() / scale;
+        let bar = (beats / beats_per_bar).floor();
+        let beat = beats - bar * beats_per_bar;
+        Some(format!(
+            "{}:{:.*}",
+            bar as u64 + 1,
+            decimals as usize,
+            beat + 1.0
+        ))
+    }
+}
+
+pub fn parse(data: &[u8]) -> Result<Acid, Error> {
+    if data.len() < SIZE {
+        return Err(Error::from(WaveError {
+            message: format!("acid chunk is too small: {}", data.len()),
+        }));
+    }
+    let flags = u32::from_le_bytes(*array_ref!(data, 0, 4));
+    Ok(Acid {
+        one_shot: flags & ONE_SHOT != 0,
+        stretch: flags & STRETCH != 0,
+        disk_based: flags & DISK_BASED != 0,
+        root_note: (flags & ROOT_NOTE_SET != 0)
+            .then(|| u16::from_le_bytes(*array_ref!(data, 4, 2))),
+        beats: u32::from_le_bytes(*array_ref!(data, 12, 4)),
+        meter_denominator: u16::from_le_bytes(*array_ref!(data, 16, 2)),
+        meter_numerator: u16::from_le_bytes(*array_ref!(data, 18, 2)),
+        tempo: f32::from_le_bytes(*array_ref!(data, 20, 4)),
+    })
+}
@@ -0,0 +1,196 @@
+// Audio Definition Model (ITU-R BS.2076) metadata of the axml chunk, as
+// BW64 deliverables for immersive audio carry it:
+// <ebuCoreMain>
+//   <coreMetadata>
+//     <format>
+//       <audioFormatExtended>
+//         <audioProgramme audioProgrammeID="APR_1001" audioProgrammeName="Main">
+//           <audioContentIDRef>ACO_1001</audioContentIDRef>
+//         </audioProgramme>
+//         <audioContent audioContentID="ACO_1001" audioContentName="Dialogue">
+//           <audioObjectIDRef>AO_1001</audioObjectIDRef>
+//         </audioContent>
+//         <audioObject audioObjectID="AO_1001" audioObjectName="Narrator">
+//           <audioPackFormatIDRef>AP_00010001</audioPackFormatIDRef>
+//           <audioTrackUIDRef>ATU_00000001</audioTrackUIDRef>
+//         </audioObject>
+//         <audioPackFormat audioPackFormatID=... typeDefinition="DirectSpeakers">
+//           <audioChannelFormatIDRef>AC_00010003</audioChannelFormatIDRef>
+//         </audioPackFormat>
+//         <audioChannelFormat audioChannelFormatID=... audioChannelFormatName="FrontCentre"/>
+//         <audioTrackUID UID="ATU_00000001">
+//           <audioChannelFormatIDRef>AC_00010003</audioChannelFormatIDRef>
+//         </audioTrackUID>
+// Pack and channel formats from the common definitions (ITU-R BS.2094)
+// are referenced without being in the file.
+use crate::xml::{self, Element};
+use crate::Error;
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Programme {
+    pub id: String,
+    pub name: String,
+    pub content_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Content {
+    pub id: String,
+    pub name: String,
+    pub object_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Object {
+    pub id: String,
+    pub name: String,
+    pub pack_format_ids: Vec<String>,
+    pub track_uids: Vec<String>,
+    // objects grouped under this one
+    pub object_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PackFormat {
+    pub id: String,
+    pub name: String,
+    // DirectSpeakers, Matrix, Objects, HOA or Binaural
+    pub type_definition: String,
+    pub channel_format_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelFormat {
+    pub id: String,
+    pub name: String,
+    pub type_definition: String,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackUid {
+    pub uid: String,
+    pub channel_format_id: Option<String>,
+    pub track_format_id: Option<String>,
+    pub pack_format_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Adm {
+    pub programmes: Vec<Programme>,
+    pub contents: Vec<Content>,
+    pub objects: Vec<Object>,
+    pub pack_formats: Vec<PackFormat>,
+    pub channel_formats: Vec<ChannelFormat>,
+    pub track_uids: Vec<TrackUid>,
+}
+
+// Element names without a namespace prefix
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn find<'a>(element: &'a Element, name: &str) -> Option<&'a Element> {
+    if local_name(&element.name) == name {
+        return Some(element);
+    }
+    element.children.iter().find_map(|child| find(child, name))
+}
+
+fn children<'a>(element: &'a Element, name: &'a str) -> impl Iterator<Item = &'a Element> {
+    element
+        .children
+        .iter()
+        .filter(move |child| local_name(&child.name) == name)
+}
+
+fn attribute(element: &Element, name: &str) -> String {
+    element
+        .attributes
+        .iter()
+        .find(|(attribute, _)| local_name(attribute) == name)
+        .map(|(_, value)| value.clone())
+        .unwrap_or_default()
+}
+
+// Texts of the reference elements
+fn references(element: &Element, name: &str) -> Vec<String> {
+    children(element, name)
+        .map(|child| child.text.trim().to_string())
+        .collect()
+}
+
+fn reference(element: &Element, name: &str) -> Option<String> {
+    references(element, name).into_iter().next()
+}
+
+pub fn parse(data: &[u8]) -> Result<Adm, Error> {
+    let root = xml::parse(&String::from_utf8_lossy(data))?;
+    let mut adm = Adm::default();
+    let format = match find(&root, "audioFormatExtended") {
+        Some(format) => format,
+        None => return Ok(adm),
+    };
+    for element in children(format, "audioProgramme") {
+        adm.programmes.push(Programme {
+            id: attribute(element, "audioProgrammeID"),
+            name: attribute(element, "audioProgrammeName"),
+            content_ids: references(element, "audioContentIDRef"),
+        });
+    }
+    for element in children(format, "audioContent") {
+        adm.contents.push(Content {
+            id: attribute(element, "audioContentID"),
+            name: attribute(element, "audioContentName"),
+            object_ids: references(element, "audioObjectIDRef"),
+        });
+    }
+    for element in children(format, "audioObject") {
+        adm.objects.push(Object {
+            id: attribute(element, "audioObjectID"),
+            name: attribute(element, "audioObjectName"),
+            pack_format_ids: references(element, "audioPackFormatIDRef"),
+            track_uids: references(element, "audioTrackUIDRef"),
+            object_ids: references(element, "audioObjectIDRef"),
+        });
+    }
+    for element in children(format, "audioPackFormat") {
+        adm.pack_formats.push(PackFormat {
+            id: attribute(element, "audioPackFormatID"),
+            name: attribute(element, "audioPackFormatName"),
+            type_definition: attribute(element, "typeDefinition"),
+            channel_format_ids: references(element, "audioChannelFormatIDRef"),
+        });
+    }
+    for element in children(format, "audioChannelFormat") {
+        adm.channel_formats.push(ChannelFormat {
+            id: attribute(element, "audioChannelFormatID"),
+            name: attribute(element, "audioChannelFormatName"),
+            type_definition: attribute(element, "typeDefinition"),
+        });
+    }
+    for element in children(format, "audioTrackUID") {
+        adm.track_uids.push(TrackUid {
+            uid: attribute(element, "UID"),
+            channel_format_id: reference(element, "audioChannelFormatIDRef"),
+            track_format_id: reference(element, "audioTrackFormatIDRef"),
+            pack_format_id: reference(element, "audioPackFormatIDRef"),
+        });
+    }
+    Ok(adm)
+}
+
+impl Adm {
+    // The object playing the track with this audioTrackUID
+    pub fn object_of_track(&self, uid: &str) -> Option<&Object> {
+        self.objects
+            .iter()
+            .find(|object| object.track_uids.iter().any(|track| track == uid))
+    }
+}
@@ -0,0 +1,198 @@
+// Associated data list: https://www.recordingblogs.com/wiki/associated-data-list-chunk-of-a-wave-file
+// LIST chunk with the type adtl, containing sub-chunks that refer to cue points
+//   labl: cue id, NUL terminated text
+//   note: cue id, NUL terminated text
+//   ltxt: cue id, sample length, purpose, country, language, dialect,
+//         code page, text
+use crate::riff::{self, Chunk, ListItem};
+use crate::{Error, WaveError};
+use std::io::{Read, Seek};
+
+// Labeled text of a region starting at a cue point. Country is a telephone
+// country code, language and dialect are the Windows LANG_ and SUBLANG_ codes
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    pub cue_id: u32,
+    pub sample_length: u32,
+    pub purpose: [u8; 4],
+    pub country: u16,
+    pub language: u16,
+    pub dialect: u16,
+    pub code_page: u16,
+    pub text: String,
+}
+
+impl Region {
+    pub fn new(cue_id: u32) -> Region {
+        Region {
+            cue_id,
+            sample_length: 0,
+            purpose: *b"rgn ",
+            country: 0,
+            language: 0,
+            dialect: 0,
+            code_page: 0,
+            text: String::new(),
+        }
+    }
+
+    fn parse(payload: &[u8]) -> Option<Region> {
+        if payload.len() < 20 {
+            return None;
+        }
+        let number = |ofs| u16::from_le_bytes(*array_ref!(payload, ofs, 2));
+        Some(Region {
+            cue_id: u32::from_le_bytes(*array_ref!(payload, 0, 4)),
+            sample_length: u32::from_le_bytes(*array_ref!(payload, 4, 4)),
+            purpose: *array_ref!(payload, 8, 4),
+            country: number(12),
+            language: number(14),
+            dialect: number(16),
+            code_page: number(18),
+            text: text(&payload[20..]),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(20 + self.text.len());
+        data.extend_from_slice(&self.cue_id.to_le_bytes());
+        data.extend_from_slice(&self.sample_length.to_le_bytes());
+        data.extend_from_slice(&self.purpose);
+        data.extend_from_slice(&self.country.to_le_bytes());
+        data.extend_from_slice(&self.language.to_le_bytes());
+        data.extend_from_slice(&self.dialect.to_le_bytes());
+        data.extend_from_slice(&self.code_page.to_le_bytes());
+        if !self.text.is_empty() {
+            data.extend_from_slice(self.text.as_bytes());
+            data.push(0);
+        }
+        data
+    }
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AssociatedData {
+    pub labels: Vec<(u32, String)>,
+    pub notes: Vec<(u32, String)>,
+    pub regions: Vec<Region>,
+}
+
+// Value of a key=value (or key:value) attribute among the words of a note,
+// such as color=#FF8000 or url=https://example.com/
+pub fn note_attribute(note: &str, names: &[&str]) -> Option<String> {
+    note.split(|ch: char| ch.is_whitespace() || ch == ',' || ch == ';')
+        .find_map(|word| {
+            let (name, value) = word.split_once(['=', ':'])?;
+            names
+                .iter()
+                .any(|candidate| name.eq_ignore_ascii_case(candidate))
+                .then(|| value.to_string())
+        })
+}
+
+fn text(data: &[u8]) -> String {
+    let end = data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).to_string()
+}
+
+// labl or note sub-chunk
+pub fn text_item(id: &[u8; 4], cue_id: u32, text: &str) -> ListItem {
+    let mut data = cue_id.to_le_bytes().to_vec();
+    data.extend_from_slice(text.as_bytes());
+    data.push(0);
+    (*id, data)
+}
+
+// LIST chunk payload with a labl for each cue
+pub fn build_labels(labels: &[(u32, String)]) -> Vec<u8> {
+    let mut data = Vec::from(&b"adtl"[..]);
+    for (cue_id, label) in labels {
+        let size = 4 + label.len() + 1;
+        data.extend_from_slice(b"labl");
+        data.extend_from_slice(&(size as u32).to_le_bytes());
+        data.extend_from_slice(&cue_id.to_le_bytes());
+        data.extend_from_slice(label.as_bytes());
+        data.push(0);
+        if size % 2 == 1 {
+            data.push(0);
+        }
+    }
+    data
+}
+
+// data is the payload of the LIST chunk, starting with the list type
+pub fn parse(data: &[u8]) -> Result<AssociatedData, Error> {
+    let mut adtl = AssociatedData::default();
+    let mut ofs = 4;
+    while ofs + 8 <= data.len() {
+        let id = array_ref!(data, ofs, 4);
+        let size = u32::from_le_bytes(*array_ref!(data, ofs + 4, 4)) as usize;
+        ofs += 8;
+        if ofs + size > data.len() || size < 4 {
+            return Err(Error::from(WaveError {
+                message: format!(
+                    "adtl sub-chunk {} has unexpected size: {size}",
+                    String::from_utf8_lossy(id)
+                ),
+            }));
+        }
+        let payload = &data[ofs..ofs + size];
+        let cue_id = u32::from_le_bytes(*array_ref!(payload, 0, 4));
+        if id == b"labl" {
+            adtl.labels.push((cue_id, text(&payload[4..])));
+        } else if id == b"note" {
+            adtl.notes.push((cue_id, text(&payload[4..])));
+        } else if id == b"ltxt" {
+            match Region::parse(payload) {
+                Some(region) => adtl.regions.push(region),
+                None => {
+                    return Err(Error::from(WaveError {
+                        message: format!("ltxt for cue {cue_id} is too small: {size}"),
+                    }))
+                }
+            }
+        }
+        // sub-chunks are word aligned
+        ofs += size + (size & 1);
+    }
+    Ok(adtl)
+}
+
+// Edits the sub-chunks of the adtl LIST, adding the LIST if there is none
+pub fn edit_list<R, F>(reader: &mut R, chunks: &mut Vec<Chunk>, edit: F) -> Result<(), Error>
+where
+    R: Read + Seek,
+    F: FnOnce(&mut Vec<ListItem>) -> Result<(), Error>,
+{
+    for chunk in chunks.iter_mut() {
+        if &chunk.id != b"LIST" {
+            continue;
+        }
+        let (list_type, mut items) = riff::parse_list(&chunk.payload(reader)?)?;
+        if &list_type == b"adtl" {
+            edit(&mut items)?;
+            *chunk = Chunk::new(b"LIST", riff::build_list(&list_type, &items));
+            return Ok(());
+        }
+    }
+    let mut items = Vec::new();
+    edit(&mut items)?;
+    chunks.push(Chunk::new(b"LIST", riff::build_list(b"adtl", &items)));
+    Ok(())
+}
+
+// Replaces the ltxt of the region's cue, or adds one
+pub fn set_region(items: &mut Vec<ListItem>, region: &Region) {
+    let existing = items.iter_mut().find(|(id, data)| {
+        id == b"ltxt" && data.len() >= 4 && data[0..4] == region.cue_id.to_le_bytes()
+    });
+    match existing {
+        Some((_, data)) => *data = region.to_bytes(),
+        None => items.push((*b"ltxt", region.to_bytes())),
+    }
+}
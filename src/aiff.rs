@@ -0,0 +1,416 @@
+// AIFF and AIFC files, read into the model of a WAV file: the markers of
+// the MARK chunk as cue points labelled with their names, and the loops
+// of the INST chunk as sampler loops.
+// http://paulbourke.net/dataformats/audio/AIFF-1.3.pdf
+//   FORM  size 4, AIFF or AIFC, then chunks with big-endian sizes, word
+//         aligned
+//   COMM  NumChannels 2, NumSampleFrames 4, SampleSize 2, SampleRate 10
+//         (80-bit float); AIFC adds the compression type 4 and its name
+//   SSND  Offset 4, BlockSize 4, then the samples
+//   MARK  NumMarkers 2, then markers: Id 2, Position 4, Name (pstring)
+//   INST  BaseNote, Detune, LowNote, HighNote, LowVelocity, HighVelocity
+//         1 each, Gain 2, then the sustain and release loops: PlayMode 2,
+//         BeginLoop 2, EndLoop 2 (marker ids)
+use crate::riff::ChunkInfo;
+use crate::smpl::{LoopType, SampleLoop, Sampler};
+use crate::{CompressionCode, CueEntry, DataChunkId, Header, ParseMode, WavCueError, WaveFileInfo};
+use std::io::{Read, Seek, SeekFrom};
+
+pub fn is_aiff_signature(signature: &[u8; 4]) -> bool {
+    signature == b"FORM"
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes(*array_ref!(data, offset, 2))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(*array_ref!(data, offset, 4))
+}
+
+// 80-bit IEEE 754 extended precision, as the sample rate is stored
+fn extended(data: &[u8; 10]) -> f64 {
+    let exponent = (u16::from_be_bytes(*array_ref!(data, 0, 2)) & 0x7fff) as i32;
+    let mantissa = u64::from_be_bytes(*array_ref!(data, 2, 8));
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+    mantissa as f64 * 2f64.powi(exponent - 16383 - 63)
+}
+
+// Pascal string, with the count byte and padded to an even size; None if
+// it runs past the end
+fn pstring(data: &[u8], offset: usize) -> Option<(String, usize)> {
+    let count = *data.get(offset)? as usize;
+    let text = data.get(offset + 1..offset + 1 + count)?;
+    let text = String::from_utf8_lossy(text)
+        .trim_end_matches(char::from(0))
+        .to_string();
+    Some((text, offset + 1 + count + (count + 1) % 2))
+}
+
+fn parse_comm(payload: &[u8], aifc: bool) -> Result<Header, WavCueError> {
+    if payload.len() < 18 || (aifc && payload.len() < 22) {
+        return Err(WavCueError::invalid(
+            b"COMM",
+            format!("too small ({} bytes)", payload.len()),
+        ));
+    }
+    let number_of_channels = u16_at(payload, 0);
+    let bits = u16_at(payload, 6);
+    let sampling_rate = extended(array_ref!(payload, 8, 10)).round() as u32;
+    if sampling_rate == 0 {
+        return Err(WavCueError::invalid(
+            b"COMM",
+            String::from("sampling rate is 0"),
+        ));
+    }
+    // AIFF is integer PCM; AIFC names its encoding
+    let compression_code = match &payload.get(18..22) {
+        Some(b"fl32") | Some(b"FL32") | Some(b"fl64") | Some(b"FL64") if aifc => {
+            CompressionCode::IeeeFloat
+        }
+        Some(b"NONE") | Some(b"twos") | Some(b"sowt") | None => CompressionCode::Pcm,
+        Some(_) if !aifc => CompressionCode::Pcm,
+        Some(_) => CompressionCode::Other(0),
+    };
+    let block_align = number_of_channels * bits.div_ceil(8);
+    Ok(Header {
+        compression_code,
+        number_of_channels,
+        sampling_rate,
+        average_bytes_per_second: sampling_rate.saturating_mul(block_align as u32),
+        block_align,
+        significant_bits_per_sample: bits,
+    })
+}
+
+fn parse_mark(payload: &[u8]) -> Result<Vec<CueEntry>, WavCueError> {
+    let invalid = |message: String| WavCueError::invalid(b"MARK", message);
+    if payload.len() < 2 {
+        return Err(invalid(String::from("too small")));
+    }
+    let count = u16_at(payload, 0);
+    let mut cues = Vec::new();
+    let mut ofs = 2;
+    for _ in 0..count {
+        if ofs + 6 > payload.len() {
+            return Err(invalid(format!("cannot hold {count} markers")));
+        }
+        let cue_id = u16_at(payload, ofs) as u32;
+        let position = u32_at(payload, ofs + 2);
+        let (name, next) = pstring(payload, ofs + 6)
+            .ok_or_else(|| invalid(format!("name of marker {cue_id} is cut short")))?;
+        ofs = next;
+        cues.push(CueEntry {
+            cue_id,
+            position,
+            data_chunk_id: DataChunkId::Data,
+            chunk_start: 0,
+            block_start: 0,
+            sample_start: position,
+            label: (!name.is_empty()).then_some(name),
+            note: None,
+            color: None,
+            url: None,
+            image: None,
+            region: None,
+        });
+    }
+    Ok(cues)
+}
+
+// The sustain and release loops, between the positions of their markers
+fn parse_inst(payload: &[u8], cues: &[CueEntry], rate: u32) -> Result<Sampler, WavCueError> {
+    if payload.len() < 20 {
+        return Err(WavCueError::invalid(
+            b"INST",
+            format!("too small ({} bytes)", payload.len()),
+        ));
+    }
+    let position = |id: u16| {
+        cues.iter()
+            .find(|cue| cue.cue_id == id as u32)
+            .map(|cue| cue.sample_start)
+    };
+    let mut loops = Vec::new();
+    for ofs in [8, 14] {
+        let loop_type = match u16_at(payload, ofs) {
+            0 => continue,
+            1 => LoopType::Forward,
+            2 => LoopType::Alternating,
+            mode => LoopType::Other(mode as u32),
+        };
+        let begin = u16_at(payload, ofs + 2);
+        if let (Some(start), Some(end)) = (position(begin), position(u16_at(payload, ofs + 4))) {
+            loops.push(SampleLoop {
+                cue_id: begin as u32,
+                loop_type,
+                start,
+                // the end marker comes after the last frame of the loop
+                end: end.saturating_sub(1).max(start),
+                fraction: 0,
+                play_count: 0,
+            });
+        }
+    }
+    // the detune is in cents, up to 50 either way
+    let (base_note, detune) = (payload[0] as u32, payload[1] as i8 as i64);
+    let (midi_unity_note, cents) = if detune < 0 {
+        (base_note.saturating_sub(1), 100 + detune)
+    } else {
+        (base_note, detune)
+    };
+    Ok(Sampler {
+        manufacturer: 0,
+        product: 0,
+        // nanoseconds
+        sample_period: 1_000_000_000 / rate.max(1),
+        midi_unity_note,
+        midi_pitch_fraction: ((cents << 32) / 100) as u32,
+        smpte_format: 0,
+        smpte_offset: 0,
+        loops,
+    })
+}
+
+// Reads the file after the FORM signature
+pub(crate) fn read<R: Read + Seek>(
+    mut reader: R,
+    mode: ParseMode,
+) -> Result<WaveFileInfo, WavCueError> {
+    let mut buf_form = [0u8; 8];
+    reader
+        .read_exact(&mut buf_form)
+        .map_err(WavCueError::truncated(*b"FORM"))?;
+    let form_size = u32::from_be_bytes(*array_ref!(buf_form, 0, 4)) as u64;
+    let aifc = match array_ref!(buf_form, 4, 4) {
+        b"AIFF" => false,
+        b"AIFC" => true,
+        found => {
+            return Err(WavCueError::UnexpectedFourcc {
+                expected: "AIFF",
+                found: *found,
+            })
+        }
+    };
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    let mut offset = 12u64;
+    let mut header = None;
+    let mut data = None;
+    let mut little_endian = false;
+    let mut cues = Vec::new();
+    let mut inst = None;
+    let mut warnings = Vec::new();
+    let mut buf_chunk = [0u8; 8];
+    // whatever follows the FORM chunk isn't part of the file
+    while offset + 8 <= file_size.min(form_size + 8) {
+        reader.seek(SeekFrom::Start(offset))?;
+        reader.read_exact(&mut buf_chunk)?;
+        let id = *array_ref!(buf_chunk, 0, 4);
+        let size = u32::from_be_bytes(*array_ref!(buf_chunk, 4, 4)) as u64;
+        let start = offset + 8;
+        offset = start + size + (size & 1);
+        if start + size > file_size {
+            let error = WavCueError::TruncatedChunk { id };
+            match mode {
+                ParseMode::Strict => return Err(error),
+                // the audio of an interrupted recording is cut short
+                _ if &id == b"SSND" => (),
+                ParseMode::Lenient => {
+                    warnings.push(error);
+                    break;
+                }
+                ParseMode::Normal => return Err(error),
+            }
+        }
+        let result = match &id {
+            b"COMM" | b"MARK" | b"INST" => {
+                let mut payload = vec![0u8; size as usize];
+                reader
+                    .read_exact(&mut payload)
+                    .map_err(WavCueError::truncated(id))?;
+                match &id {
+                    b"COMM" => parse_comm(&payload, aifc).map(|parsed| {
+                        little_endian = payload.get(18..22) == Some(b"sowt");
+                        header = Some(parsed);
+                    }),
+                    b"MARK" => parse_mark(&payload).map(|parsed| cues.extend(parsed)),
+                    _ => {
+                        inst = Some(payload);
+                        Ok(())
+                    }
+                }
+            }
+            b"SSND" if size >= 8 => {
+                let mut buf_ssnd = [0u8; 4];
+                reader
+                    .read_exact(&mut buf_ssnd)
+                    .map_err(WavCueError::truncated(id))?;
+                let samples = start + 8 + u32::from_be_bytes(buf_ssnd) as u64;
+                // the samples as far as they are in the file
+                data = Some(ChunkInfo {
+                    id,
+                    offset: samples,
+                    size: (start + size).min(file_size).saturating_sub(samples),
+                });
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if let Err(error) = result {
+            match error {
+                WavCueError::Io(_) => return Err(error),
+                error if mode == ParseMode::Lenient => warnings.push(error),
+                error => return Err(error),
+            }
+        }
+    }
+    if mode == ParseMode::Strict && offset != form_size + 8 {
+        return Err(WavCueError::invalid(
+            b"FORM",
+            format!(
+                "size is {form_size} bytes, but the chunks add up to {}",
+                offset - 8
+            ),
+        ));
+    }
+    let header = header
+        .ok_or_else(|| WavCueError::invalid(b"FORM", String::from("File has no COMM chunk")))?;
+    let sampler = match inst.map(|payload| parse_inst(&payload, &cues, header.sampling_rate)) {
+        Some(Ok(sampler)) => Some(sampler),
+        Some(Err(error)) if mode == ParseMode::Lenient => {
+            warnings.push(error);
+            None
+        }
+        Some(Err(error)) => return Err(error),
+        None => None,
+    };
+    let mut wave = WaveFileInfo::new(header, cues, None, data, None, sampler, warnings);
+    wave.big_endian = !little_endian;
+    Ok(wave)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn chunk(id: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = id.to_vec();
+        data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        data.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            data.push(0);
+        }
+        data
+    }
+
+    // stereo 16 bit at 44.1 kHz, 80-bit float 0x400EAC44000000000000
+    fn comm(frames: u32) -> Vec<u8> {
+        let mut comm = vec![0, 2];
+        comm.extend_from_slice(&frames.to_be_bytes());
+        comm.extend_from_slice(&[0, 16, 0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+        comm
+    }
+
+    fn marker(id: u16, position: u32, name: &str) -> Vec<u8> {
+        let mut marker = id.to_be_bytes().to_vec();
+        marker.extend_from_slice(&position.to_be_bytes());
+        marker.push(name.len() as u8);
+        marker.extend_from_slice(name.as_bytes());
+        // the count byte and the text are padded to an even size
+        if name.len().is_multiple_of(2) {
+            marker.push(0);
+        }
+        marker
+    }
+
+    fn form(kind: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = chunks.concat();
+        let mut file = b"FORM".to_vec();
+        file.extend_from_slice(&(body.len() as u32 + 4).to_be_bytes());
+        file.extend_from_slice(kind);
+        file.extend(body);
+        file
+    }
+
+    #[test]
+    fn reads_the_sample_rate() {
+        let comm = comm(0);
+        assert_eq!(extended(array_ref!(comm, 8, 10)), 44100.0);
+        assert_eq!(
+            extended(&[0x40, 0x0e, 0xbb, 0x80, 0, 0, 0, 0, 0, 0]),
+            48000.0
+        );
+        assert_eq!(extended(&[0; 10]), 0.0);
+    }
+
+    #[test]
+    fn reads_markers_and_loops() {
+        let mut mark = 3u16.to_be_bytes().to_vec();
+        mark.extend(marker(1, 0, "Start"));
+        mark.extend(marker(2, 100, "Loop end"));
+        mark.extend(marker(3, 50, ""));
+        // base note 60, detune -10 cents, a forward sustain loop from
+        // marker 1 to 2
+        let mut inst = vec![60, 0xf6, 0, 127, 1, 127, 0, 0, 0, 1, 0, 1, 0, 2];
+        inst.extend_from_slice(&[0; 6]);
+        let mut ssnd = vec![0; 8];
+        ssnd.extend_from_slice(&[0; 16]);
+        let file = form(
+            b"AIFF",
+            &[
+                chunk(b"COMM", &comm(4)),
+                chunk(b"MARK", &mark),
+                chunk(b"INST", &inst),
+                chunk(b"SSND", &ssnd),
+            ],
+        );
+        let wave = crate::read_wave_with(Cursor::new(file), ParseMode::Strict).unwrap();
+        assert!(wave.big_endian);
+        assert_eq!(wave.header.sampling_rate, 44100);
+        assert_eq!(wave.header.block_align, 4);
+        assert_eq!(wave.frames(), Some(4));
+        let labels: Vec<_> = wave
+            .sorted_cues()
+            .map(|cue| (cue.cue_id, cue.sample_start, cue.label.clone()))
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                (1, 0, Some(String::from("Start"))),
+                (3, 50, None),
+                (2, 100, Some(String::from("Loop end")))
+            ]
+        );
+        let sampler = wave.sampler.unwrap();
+        assert_eq!(sampler.midi_unity_note, 59);
+        assert_eq!(sampler.midi_pitch_fraction, ((90i64 << 32) / 100) as u32);
+        assert_eq!(sampler.loops.len(), 1);
+        assert_eq!((sampler.loops[0].start, sampler.loops[0].end), (0, 99));
+    }
+
+    #[test]
+    fn reads_little_endian_aifc() {
+        let mut comm = comm(0);
+        comm.extend_from_slice(b"sowt");
+        comm.extend_from_slice(&[0, 0]);
+        let file = form(b"AIFC", &[chunk(b"COMM", &comm)]);
+        let wave = crate::read_wave(Cursor::new(file)).unwrap();
+        assert!(!wave.big_endian);
+        assert_eq!(wave.header.compression_code, CompressionCode::Pcm);
+    }
+
+    #[test]
+    fn rejects_cut_short_markers() {
+        let mut mark = 2u16.to_be_bytes().to_vec();
+        mark.extend(marker(1, 0, "Start"));
+        assert!(parse_mark(&mark).is_err());
+        let file = form(b"AIFF", &[chunk(b"COMM", &comm(0)), chunk(b"MARK", &mark)]);
+        assert!(crate::read_wave(Cursor::new(file.clone())).is_err());
+        let wave = crate::read_wave_with(Cursor::new(file), ParseMode::Lenient).unwrap();
+        assert!(wave.cues.is_empty());
+        assert_eq!(wave.warnings.len(), 1);
+    }
+}
@@ -0,0 +1,389 @@
+use crate::config::OrganizationProfile;
+use crate::datetime::DateTime;
+use crate::riff::Chunk;
+use crate::text;
+use crate::umid::{self, Umid};
+use crate::{CompressionCode, Error, Header};
+use std::io::{Read, Seek};
+
+// bext: https://web.archive.org/web/20091229093941/http://tech.ebu.ch/docs/tech/tech3285.pdf page 7
+// typedef struct broadcast_audio_extension {
+//   CHAR Description[256]; /* ASCII : «Description of the sound sequence» */
+//   CHAR Originator[32]; /* ASCII : «Name of the originator» */
+//   CHAR OriginatorReference[32]; /* ASCII : «Reference of the originator» */
+//   CHAR OriginationDate[10]; /* ASCII : «yyyy-mm-dd» */
+//   CHAR OriginationTime[8]; /* ASCII : «hh-mm-ss» */
+//   DWORD TimeReferenceLow; /* First sample count since midnight low word */
+//   DWORD TimeReferenceHigh; /* First sample count since midnight, high word */
+//   WORD Version; /* Version of the BWF; unsigned binary number */
+//   BYTE UMID_0 /* Binary byte 0 of SMPTE UMID */
+//   ....
+//   BYTE UMID_63 /* Binary byte 63 of SMPTE UMID */
+//   WORD LoudnessValue; /* Integrated loudness in LUFS, multiplied by 100 */
+//   WORD LoudnessRange; /* Loudness range in LU, multiplied by 100 */
+//   WORD MaxTruePeakLevel; /* in dBTP, multiplied by 100 */
+//   WORD MaxMomentaryLoudness; /* in LUFS, multiplied by 100 */
+//   WORD MaxShortTermLoudness; /* in LUFS, multiplied by 100 */
+//   BYTE Reserved[180] ; /* 180 bytes, reserved for future use, set to “NULL” */
+//   CHAR CodingHistory[]; /* ASCII : « History coding » */
+// } BROADCAST_EXT
+#[allow(dead_code)]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BroadcastAudioExtension {
+    pub description: String, /* ASCII : «Description of the sound sequence» */
+    pub originator: String,  /* ASCII : «Name of the originator» */
+    pub originator_reference: String, /* ASCII : «Reference of the originator» */
+    pub origination_date: String, /* ASCII : «yyyy-mm-dd» */
+    pub origination_time: String, /* ASCII : «hh-mm-ss» */
+    pub time_reference: u64, /* First sample count since midnight */
+    pub version: u16,        /* Version of the BWF; unsigned binary number */
+    pub umid: Option<Umid>,  /* None if blank */
+    pub loudness: Option<Loudness>, /* from version 2 */
+    pub coding_history: String, /* ASCII : « History coding » */
+}
+
+// EBU R128 loudness metadata of version 2, in LUFS, LU and dBTP; values
+// set to 0x7FFF are not given
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Loudness {
+    pub integrated: Option<f64>,
+    pub range: Option<f64>,
+    pub max_true_peak: Option<f64>,
+    pub max_momentary: Option<f64>,
+    pub max_short_term: Option<f64>,
+}
+
+// Smallest bext chunk read, up to the version
+pub const BEXT_HEADER_SIZE: usize = 348;
+pub const BEXT_DESCRIPTION: (usize, usize) = (0, 256);
+pub const BEXT_ORIGINATOR: (usize, usize) = (256, 32);
+pub const BEXT_ORIGINATOR_REFERENCE: (usize, usize) = (288, 32);
+pub const BEXT_ORIGINATION_DATE: (usize, usize) = (320, 10);
+pub const BEXT_ORIGINATION_TIME: (usize, usize) = (330, 8);
+pub const BEXT_TIME_REFERENCE_OFFSET: usize = 338;
+pub const BEXT_VERSION_OFFSET: usize = 346;
+pub const BEXT_UMID_OFFSET: usize = 348;
+pub const BEXT_UMID_SIZE: usize = 64;
+pub const BEXT_LOUDNESS_OFFSET: usize = 412;
+const LOUDNESS_NOT_GIVEN: i16 = 0x7fff;
+// Size of the chunk without CodingHistory
+pub const BEXT_FIXED_SIZE: usize = 602;
+
+fn text(raw: &[u8]) -> String {
+    String::from_utf8_lossy(raw)
+        .trim_end_matches(char::from(0))
+        .to_string()
+}
+
+fn parse_loudness(raw: &[u8]) -> Loudness {
+    let value = |index: usize| {
+        let value = i16::from_le_bytes([raw[2 * index], raw[2 * index + 1]]);
+        (value != LOUDNESS_NOT_GIVEN).then(|| value as f64 / 100.0)
+    };
+    Loudness {
+        integrated: value(0),
+        range: value(1),
+        max_true_peak: value(2),
+        max_momentary: value(3),
+        max_short_term: value(4),
+    }
+}
+
+// Parses a bext chunk of at least BEXT_HEADER_SIZE bytes; the fields
+// after the version are read as far as the chunk has them
+pub fn parse(raw: &[u8]) -> BroadcastAudioExtension {
+    let buf_bext = array_ref!(raw, 0, BEXT_HEADER_SIZE);
+    let mut ofs = 0;
+    let description = text(array_ref!(buf_bext, ofs, 256));
+    ofs += 256;
+    let originator = text(array_ref!(buf_bext, ofs, 32));
+    ofs += 32;
+    let originator_reference = text(array_ref!(buf_bext, ofs, 32));
+    ofs += 32;
+    let origination_date = text(array_ref!(buf_bext, ofs, 10));
+    ofs += 10;
+    let origination_time = text(array_ref!(buf_bext, ofs, 8));
+    ofs += 8;
+    let time_reference_low = u32::from_le_bytes(*array_ref!(buf_bext, ofs, 4));
+    ofs += 4;
+    let time_reference_high = u32::from_le_bytes(*array_ref!(buf_bext, ofs, 4));
+    ofs += 4;
+    let version = u16::from_le_bytes(*array_ref!(buf_bext, ofs, 2));
+    let umid = raw
+        .get(BEXT_UMID_OFFSET..BEXT_UMID_OFFSET + BEXT_UMID_SIZE)
+        .and_then(umid::parse);
+    // reserved, so zero, before version 2
+    let loudness = raw
+        .get(BEXT_LOUDNESS_OFFSET..BEXT_LOUDNESS_OFFSET + 10)
+        .filter(|_| version >= 2)
+        .map(parse_loudness);
+    let coding_history = raw.get(BEXT_FIXED_SIZE..).map(text).unwrap_or_default();
+    BroadcastAudioExtension {
+        description,
+        originator,
+        originator_reference,
+        origination_date,
+        origination_time,
+        time_reference: time_reference_low as u64 | ((time_reference_high as u64) << 32),
+        version,
+        umid,
+        loudness,
+        coding_history,
+    }
+}
+
+// Makes sure the raw chunk has room for all the fixed fields, so they can be
+// patched in place
+pub fn ensure_fixed_size(raw: &mut Vec<u8>) {
+    if raw.len() < BEXT_FIXED_SIZE {
+        raw.resize(BEXT_FIXED_SIZE, 0);
+    }
+}
+
+// Stores the UMID into a raw bext chunk; a basic UMID leaves the latter 32
+// bytes of the field zero. UMIDs were introduced in version 1.
+pub fn set_umid(raw: &mut Vec<u8>, umid: &[u8]) {
+    ensure_fixed_size(raw);
+    let field = &mut raw[BEXT_UMID_OFFSET..BEXT_UMID_OFFSET + BEXT_UMID_SIZE];
+    field.fill(0);
+    field[..umid.len()].copy_from_slice(umid);
+    let version = u16::from_le_bytes(*array_ref!(raw, BEXT_VERSION_OFFSET, 2));
+    if version < 1 {
+        raw[BEXT_VERSION_OFFSET..BEXT_VERSION_OFFSET + 2].copy_from_slice(&1u16.to_le_bytes());
+    }
+}
+
+// Stores an ASCII field, transliterating the text to ASCII and truncating
+// or NUL-padding it to the field size; returns whether it was truncated.
+// A field filled up to its size has no terminating NUL.
+pub fn set_string(raw: &mut Vec<u8>, field: (usize, usize), value: &str) -> bool {
+    ensure_fixed_size(raw);
+    let (offset, size) = field;
+    let value = text::to_ascii(value);
+    let dst = &mut raw[offset..offset + size];
+    dst.fill(0);
+    for (dst, src) in dst.iter_mut().zip(value.bytes()) {
+        *dst = src;
+    }
+    value.len() > size
+}
+
+pub fn is_blank(raw: &[u8], field: (usize, usize)) -> bool {
+    let (offset, size) = field;
+    raw.len() < offset + size || raw[offset..offset + size].iter().all(|byte| *byte == 0)
+}
+
+pub fn set_time_reference(raw: &mut Vec<u8>, time_reference: u64) {
+    ensure_fixed_size(raw);
+    raw[BEXT_TIME_REFERENCE_OFFSET..BEXT_TIME_REFERENCE_OFFSET + 4]
+        .copy_from_slice(&(time_reference as u32).to_le_bytes());
+    raw[BEXT_TIME_REFERENCE_OFFSET + 4..BEXT_TIME_REFERENCE_OFFSET + 8]
+        .copy_from_slice(&((time_reference >> 32) as u32).to_le_bytes());
+}
+
+// A chunk for a file that has none; filled from the organization profile if
+// one is configured
+pub fn new_chunk(profile: Option<&OrganizationProfile>, header: &Header) -> Vec<u8> {
+    let mut raw = vec![0u8; BEXT_FIXED_SIZE];
+    if let Some(profile) = profile {
+        let now = DateTime::now();
+        set_string(&mut raw, BEXT_ORIGINATOR, &profile.originator);
+        set_string(
+            &mut raw,
+            BEXT_ORIGINATOR_REFERENCE,
+            &profile.originator_reference(&now),
+        );
+        set_string(&mut raw, BEXT_ORIGINATION_DATE, &now.date_string());
+        set_string(&mut raw, BEXT_ORIGINATION_TIME, &now.time_string());
+        raw[BEXT_VERSION_OFFSET..BEXT_VERSION_OFFSET + 2].copy_from_slice(&1u16.to_le_bytes());
+        if let Some(coding_history) = profile.coding_history(header) {
+            append_coding_history(&mut raw, &coding_history);
+        }
+    }
+    raw
+}
+
+// EBU R98 mode of the coding history line
+pub fn coding_mode(header: &Header) -> &'static str {
+    match header.number_of_channels {
+        1 => "mono",
+        2 => "stereo",
+        _ => "multitrack",
+    }
+}
+
+// EBU R98 line describing the file as written by wav-cue, e.g.
+// A=PCM,F=48000,W=24,M=stereo,T=wav-cue 0.1.0 join
+// None for formats R98 has no coding algorithm for
+pub fn coding_history_line(header: &Header, operation: &str) -> Option<String> {
+    let algorithm = match header.compression_code {
+        CompressionCode::Pcm | CompressionCode::IeeeFloat | CompressionCode::Extensible => "PCM",
+        CompressionCode::MpegLayer3 => "MPEG1L3",
+        _ => return None,
+    };
+    Some(format!(
+        "A={},F={},W={},M={},T=wav-cue {} {}\r\n",
+        algorithm,
+        header.sampling_rate,
+        header.significant_bits_per_sample,
+        coding_mode(header),
+        env!("CARGO_PKG_VERSION"),
+        operation
+    ))
+}
+
+// Appends a line to CodingHistory, which is terminated with CR/LF
+pub fn append_coding_history(raw: &mut Vec<u8>, line: &str) {
+    ensure_fixed_size(raw);
+    while raw.len() > BEXT_FIXED_SIZE && raw[raw.len() - 1] == 0 {
+        raw.pop();
+    }
+    if raw.len() > BEXT_FIXED_SIZE && !raw.ends_with(b"\r\n") {
+        raw.extend_from_slice(b"\r\n");
+    }
+    raw.extend_from_slice(line.as_bytes());
+    if !line.ends_with("\r\n") {
+        raw.extend_from_slice(b"\r\n");
+    }
+    // keep the chunk size even, as readers may not handle pad bytes
+    if raw.len() % 2 == 1 {
+        raw.push(0);
+    }
+}
+
+// The spec gives OriginationDate as yyyy-mm-dd and OriginationTime as
+// hh:mm:ss, but its struct definition says hh-mm-ss and recorders use
+// various separators, so any of these is accepted as a variant
+const DATE_TIME_SEPARATORS: [char; 6] = ['-', ':', '_', ' ', '.', '/'];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FieldFormat {
+    // as specified, or blank
+    Valid,
+    // valid values with other separators; holds the normalized text
+    Variant(String),
+    Invalid,
+}
+
+// Splits the text into numbers of the given widths, separated by one of
+// the separators, the same one throughout
+fn date_time_fields(text: &str, widths: [usize; 3]) -> Option<(char, [u32; 3])> {
+    let separator = text.chars().find(|ch| !ch.is_ascii_digit())?;
+    if !DATE_TIME_SEPARATORS.contains(&separator) {
+        return None;
+    }
+    let parts: Vec<&str> = text.split(separator).collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let mut fields = [0u32; 3];
+    for ((field, part), width) in fields.iter_mut().zip(parts).zip(widths) {
+        if part.len() != width || !part.chars().all(|ch| ch.is_ascii_digit()) {
+            return None;
+        }
+        *field = part.parse().ok()?;
+    }
+    Some((separator, fields))
+}
+
+fn field_text(raw: &str) -> &str {
+    raw.trim_end_matches(char::from(0))
+}
+
+// Midnight of OriginationDate, in any of the accepted variants
+pub fn origination_date(raw: &str) -> Option<DateTime> {
+    if check_origination_date(raw) == FieldFormat::Invalid {
+        return None;
+    }
+    let (_, [year, month, day]) = date_time_fields(field_text(raw), [4, 2, 2])?;
+    Some(DateTime {
+        year: year as i64,
+        month,
+        day,
+        hour: 0,
+        minute: 0,
+        second: 0,
+    })
+}
+
+// OriginationTime as seconds since midnight
+pub fn origination_seconds(raw: &str) -> Option<u64> {
+    if check_origination_time(raw) == FieldFormat::Invalid {
+        return None;
+    }
+    let (_, [hour, minute, second]) = date_time_fields(field_text(raw), [2, 2, 2])?;
+    Some(hour as u64 * 3600 + minute as u64 * 60 + second as u64)
+}
+
+pub fn check_origination_date(raw: &str) -> FieldFormat {
+    let text = field_text(raw);
+    if text.is_empty() {
+        return FieldFormat::Valid;
+    }
+    let (separator, [year, month, day]) = match date_time_fields(text, [4, 2, 2]) {
+        Some(fields) => fields,
+        None => return FieldFormat::Invalid,
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if !(1..=12).contains(&month) || day < 1 || day > days {
+        FieldFormat::Invalid
+    } else if separator == '-' {
+        FieldFormat::Valid
+    } else {
+        FieldFormat::Variant(format!("{year:04}-{month:02}-{day:02}"))
+    }
+}
+
+pub fn check_origination_time(raw: &str) -> FieldFormat {
+    let text = field_text(raw);
+    if text.is_empty() {
+        return FieldFormat::Valid;
+    }
+    let (separator, [hour, minute, second]) = match date_time_fields(text, [2, 2, 2]) {
+        Some(fields) => fields,
+        None => return FieldFormat::Invalid,
+    };
+    if hour > 23 || minute > 59 || second > 59 {
+        FieldFormat::Invalid
+    } else if separator == ':' {
+        FieldFormat::Valid
+    } else {
+        FieldFormat::Variant(format!("{hour:02}:{minute:02}:{second:02}"))
+    }
+}
+
+// Loads the raw bext chunk (or new_chunk() if the file has none) for editing
+// and stores the result back; a new chunk is placed first, ahead of fmt
+pub fn edit_chunk<R, N, F>(
+    reader: &mut R,
+    chunks: &mut Vec<Chunk>,
+    new_chunk: N,
+    edit: F,
+) -> Result<(), Error>
+where
+    R: Read + Seek,
+    N: FnOnce() -> Vec<u8>,
+    F: FnOnce(&mut Vec<u8>) -> Result<(), Error>,
+{
+    match chunks.iter().position(|chunk| &chunk.id == b"bext") {
+        Some(index) => {
+            let mut raw = chunks[index].payload(reader)?;
+            edit(&mut raw)?;
+            chunks[index] = Chunk::new(b"bext", raw);
+        }
+        None => {
+            let mut raw = new_chunk();
+            edit(&mut raw)?;
+            chunks.insert(0, Chunk::new(b"bext", raw));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,425 @@
+// Core Audio Format files, as iOS recording apps write them, read into the
+// model of a WAV file: the markers of the mark chunk as cue points, named
+// from the strg chunk, and the recording date and title of the info chunk
+// as a bext.
+// https://developer.apple.com/library/archive/documentation/MusicAudio/Reference/CAFSpec/
+//   caff  version 2, flags 2, then chunks: type 4, size 8 (signed, -1 for
+//         a data chunk running to the end), no padding; all big-endian
+//   desc  SampleRate 8 (double), FormatID 4, FormatFlags 4,
+//         BytesPerPacket 4, FramesPerPacket 4, ChannelsPerFrame 4,
+//         BitsPerChannel 4
+//   data  EditCount 4, then the samples
+//   mark  SMPTETimeType 4, NumberMarkers 4, then markers of 28 bytes:
+//         Type 4, FramePosition 8 (double), MarkerID 4, SMPTETime 8,
+//         Channel 4
+//   strg  NumEntries 4, then StringID 4 and offset 8 of each, then the
+//         NUL terminated strings
+//   info  NumEntries 4, then NUL terminated keys and values
+use crate::bext::BroadcastAudioExtension;
+use crate::riff::ChunkInfo;
+use crate::{CompressionCode, CueEntry, DataChunkId, Header, ParseMode, WavCueError, WaveFileInfo};
+use std::io::{Read, Seek, SeekFrom};
+
+const MARKER_SIZE: usize = 28;
+// format flags of linear PCM
+const FLOAT: u32 = 1;
+const LITTLE_ENDIAN: u32 = 2;
+
+pub fn is_caf_signature(signature: &[u8; 4]) -> bool {
+    signature == b"caff"
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(*array_ref!(data, offset, 4))
+}
+
+fn f64_at(data: &[u8], offset: usize) -> f64 {
+    f64::from_be_bytes(*array_ref!(data, offset, 8))
+}
+
+// The NUL terminated string at the start
+fn c_string(data: &[u8]) -> String {
+    let end = data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).to_string()
+}
+
+fn parse_desc(payload: &[u8]) -> Result<(Header, bool), WavCueError> {
+    if payload.len() < 32 {
+        return Err(WavCueError::invalid(
+            b"desc",
+            format!("too small ({} bytes)", payload.len()),
+        ));
+    }
+    let sampling_rate = f64_at(payload, 0).round() as u32;
+    if sampling_rate == 0 {
+        return Err(WavCueError::invalid(
+            b"desc",
+            String::from("sampling rate is 0"),
+        ));
+    }
+    let flags = u32_at(payload, 12);
+    let bytes_per_packet = u32_at(payload, 16);
+    let number_of_channels = u32_at(payload, 24) as u16;
+    let bits = u32_at(payload, 28) as u16;
+    let compression_code = match &payload[8..12] {
+        b"lpcm" if flags & FLOAT != 0 => CompressionCode::IeeeFloat,
+        b"lpcm" => CompressionCode::Pcm,
+        _ => CompressionCode::Other(0),
+    };
+    let header = Header {
+        compression_code,
+        number_of_channels,
+        sampling_rate,
+        average_bytes_per_second: sampling_rate.saturating_mul(bytes_per_packet),
+        block_align: bytes_per_packet as u16,
+        significant_bits_per_sample: bits,
+    };
+    Ok((header, flags & LITTLE_ENDIAN == 0))
+}
+
+// Marker positions with the ids of their names
+fn parse_mark(payload: &[u8]) -> Result<Vec<(u32, u32)>, WavCueError> {
+    if payload.len() < 8 {
+        return Err(WavCueError::invalid(b"mark", String::from("too small")));
+    }
+    let count = u32_at(payload, 4) as usize;
+    if payload.len() < 8 + count * MARKER_SIZE {
+        return Err(WavCueError::invalid(
+            b"mark",
+            format!("{} bytes cannot hold {count} markers", payload.len()),
+        ));
+    }
+    Ok(payload[8..]
+        .chunks_exact(MARKER_SIZE)
+        .take(count)
+        .map(|marker| (f64_at(marker, 4).round() as u32, u32_at(marker, 12)))
+        .collect())
+}
+
+fn parse_strg(payload: &[u8]) -> Vec<(u32, String)> {
+    if payload.len() < 4 {
+        return Vec::new();
+    }
+    let count = u32_at(payload, 0) as usize;
+    let strings = 4 + 12 * count;
+    (0..count)
+        .map(|index| 4 + 12 * index)
+        .take_while(|entry| strings <= payload.len() && entry + 12 <= strings)
+        .filter_map(|entry| {
+            let offset = u64::from_be_bytes(*array_ref!(payload, entry + 4, 8)) as usize;
+            let text = payload.get(strings.checked_add(offset)?..)?;
+            Some((u32_at(payload, entry), c_string(text)))
+        })
+        .collect()
+}
+
+fn parse_info(payload: &[u8]) -> Vec<(String, String)> {
+    let mut texts = payload
+        .get(4..)
+        .unwrap_or_default()
+        .split(|byte| *byte == 0)
+        .map(|text| String::from_utf8_lossy(text).to_string());
+    let mut entries = Vec::new();
+    while let (Some(key), Some(value)) = (texts.next(), texts.next()) {
+        entries.push((key, value));
+    }
+    entries
+}
+
+fn is_date(date: &str) -> bool {
+    date.len() == 10
+        && date.bytes().enumerate().all(|(index, byte)| match index {
+            4 | 7 => byte == b'-',
+            _ => byte.is_ascii_digit(),
+        })
+}
+
+// hh:mm:ss as seconds since midnight
+fn seconds_of_day(time: &str) -> Option<u64> {
+    let parts: Vec<u64> = time
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    match parts[..] {
+        [hour, minute, second] if hour < 24 && minute < 60 && second < 60 => {
+            Some(hour * 3600 + minute * 60 + second)
+        }
+        _ => None,
+    }
+}
+
+// The recording date, such as 2024-05-04T10:22:10-0700, and the title of
+// the info chunk in the fields of a bext, so markers get their time of day
+fn info_bext(info: &[(String, String)], sampling_rate: u32) -> Option<BroadcastAudioExtension> {
+    let value = |key: &str| {
+        info.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    };
+    let recorded = value("recorded date").unwrap_or_default();
+    let date = recorded.get(..10).filter(|date| is_date(date));
+    let time = recorded.get(11..19).and_then(seconds_of_day);
+    let description = value("title").or_else(|| value("comments"));
+    if date.is_none() && description.is_none() {
+        return None;
+    }
+    Some(BroadcastAudioExtension {
+        description: description.unwrap_or_default().to_string(),
+        originator: value("encoding application")
+            .unwrap_or_default()
+            .to_string(),
+        originator_reference: String::new(),
+        origination_date: date.unwrap_or_default().to_string(),
+        origination_time: time
+            .map(|time| format!("{:02}:{:02}:{:02}", time / 3600, time / 60 % 60, time % 60))
+            .unwrap_or_default(),
+        time_reference: time.unwrap_or(0) * sampling_rate as u64,
+        version: 0,
+        umid: None,
+        loudness: None,
+        coding_history: String::new(),
+    })
+}
+
+// Reads the file after the caff signature
+pub(crate) fn read<R: Read + Seek>(
+    mut reader: R,
+    mode: ParseMode,
+) -> Result<WaveFileInfo, WavCueError> {
+    let mut buf_version = [0u8; 4];
+    reader
+        .read_exact(&mut buf_version)
+        .map_err(WavCueError::truncated(*b"caff"))?;
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    let mut offset = 8u64;
+    let mut format = None;
+    let mut data = None;
+    let mut markers = Vec::new();
+    let mut strings = Vec::new();
+    let mut info = Vec::new();
+    let mut warnings = Vec::new();
+    let mut buf_chunk = [0u8; 12];
+    while offset + 12 <= file_size {
+        reader.seek(SeekFrom::Start(offset))?;
+        reader.read_exact(&mut buf_chunk)?;
+        let id = *array_ref!(buf_chunk, 0, 4);
+        let size = i64::from_be_bytes(*array_ref!(buf_chunk, 4, 8));
+        let start = offset + 12;
+        // only the data chunk can leave its size open, being the last
+        let size = match size {
+            size if size >= 0 => size as u64,
+            _ if &id == b"data" => file_size - start,
+            size => return Err(WavCueError::invalid(&id, format!("size is {size}"))),
+        };
+        offset = start.saturating_add(size);
+        if offset > file_size {
+            let error = WavCueError::TruncatedChunk { id };
+            match mode {
+                ParseMode::Strict => return Err(error),
+                // the audio of an interrupted recording is cut short
+                _ if &id == b"data" => (),
+                ParseMode::Lenient => {
+                    warnings.push(error);
+                    break;
+                }
+                ParseMode::Normal => return Err(error),
+            }
+        }
+        let result = match &id {
+            b"desc" | b"mark" | b"strg" | b"info" => {
+                let mut payload = vec![0u8; size as usize];
+                reader
+                    .read_exact(&mut payload)
+                    .map_err(WavCueError::truncated(id))?;
+                match &id {
+                    b"desc" => parse_desc(&payload).map(|parsed| format = Some(parsed)),
+                    b"mark" => parse_mark(&payload).map(|parsed| markers.extend(parsed)),
+                    b"strg" => {
+                        strings.extend(parse_strg(&payload));
+                        Ok(())
+                    }
+                    _ => {
+                        info.extend(parse_info(&payload));
+                        Ok(())
+                    }
+                }
+            }
+            // after the edit count
+            b"data" if size >= 4 => {
+                data = Some(ChunkInfo {
+                    id,
+                    offset: start + 4,
+                    size: (start + size).min(file_size) - start - 4,
+                });
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if let Err(error) = result {
+            match error {
+                WavCueError::Io(_) => return Err(error),
+                error if mode == ParseMode::Lenient => warnings.push(error),
+                error => return Err(error),
+            }
+        }
+    }
+    let (header, big_endian) = format
+        .ok_or_else(|| WavCueError::invalid(b"caff", String::from("File has no desc chunk")))?;
+    // markers are numbered in their order, their ids name them
+    let cues = markers
+        .into_iter()
+        .zip(1..)
+        .map(|((position, string_id), cue_id)| CueEntry {
+            cue_id,
+            position,
+            data_chunk_id: DataChunkId::Data,
+            chunk_start: 0,
+            block_start: 0,
+            sample_start: position,
+            label: strings
+                .iter()
+                .find(|(id, text)| *id == string_id && !text.is_empty())
+                .map(|(_, text)| text.clone()),
+            note: None,
+            color: None,
+            url: None,
+            image: None,
+            region: None,
+        })
+        .collect();
+    let bext = info_bext(&info, header.sampling_rate);
+    let mut wave = WaveFileInfo::new(header, cues, bext, data, None, None, warnings);
+    wave.big_endian = big_endian;
+    Ok(wave)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn chunk(id: &[u8; 4], size: i64, payload: &[u8]) -> Vec<u8> {
+        let mut data = id.to_vec();
+        data.extend_from_slice(&size.to_be_bytes());
+        data.extend_from_slice(payload);
+        data
+    }
+
+    // stereo 16 bit little-endian PCM at 48 kHz
+    fn desc() -> Vec<u8> {
+        let mut desc = 48000f64.to_be_bytes().to_vec();
+        desc.extend_from_slice(b"lpcm");
+        for number in [LITTLE_ENDIAN, 4, 1, 2, 16] {
+            desc.extend_from_slice(&number.to_be_bytes());
+        }
+        desc
+    }
+
+    fn mark(markers: &[(f64, u32)]) -> Vec<u8> {
+        let mut mark = 0u32.to_be_bytes().to_vec();
+        mark.extend_from_slice(&(markers.len() as u32).to_be_bytes());
+        for (position, string_id) in markers {
+            mark.extend_from_slice(&0u32.to_be_bytes());
+            mark.extend_from_slice(&position.to_be_bytes());
+            mark.extend_from_slice(&string_id.to_be_bytes());
+            mark.extend_from_slice(&[0; 12]);
+        }
+        mark
+    }
+
+    fn strg(strings: &[(u32, &str)]) -> Vec<u8> {
+        let mut strg = (strings.len() as u32).to_be_bytes().to_vec();
+        let mut texts = Vec::new();
+        for (id, text) in strings {
+            strg.extend_from_slice(&id.to_be_bytes());
+            strg.extend_from_slice(&(texts.len() as u64).to_be_bytes());
+            texts.extend_from_slice(text.as_bytes());
+            texts.push(0);
+        }
+        strg.extend(texts);
+        strg
+    }
+
+    fn file(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = b"caff".to_vec();
+        file.extend_from_slice(&[0, 1, 0, 0]);
+        file.extend(chunks.concat());
+        file
+    }
+
+    #[test]
+    fn reads_markers_with_their_names() {
+        let mut info = 2u32.to_be_bytes().to_vec();
+        info.extend_from_slice(b"recorded date\x002024-05-04T10:22:10-0700\0title\0Interview\0");
+        let desc = desc();
+        let mark = mark(&[(48000.0, 7), (96000.4, 8), (12.0, 9)]);
+        let strg = strg(&[(7, "One"), (8, "Two"), (9, "")]);
+        let file = file(&[
+            chunk(b"desc", desc.len() as i64, &desc),
+            chunk(b"mark", mark.len() as i64, &mark),
+            chunk(b"strg", strg.len() as i64, &strg),
+            chunk(b"info", info.len() as i64, &info),
+            // runs to the end of the file
+            chunk(b"data", -1, &[0; 12]),
+        ]);
+        let wave = crate::read_wave_with(Cursor::new(file), ParseMode::Strict).unwrap();
+        assert!(!wave.big_endian);
+        assert_eq!(wave.header.sampling_rate, 48000);
+        assert_eq!(wave.header.block_align, 4);
+        assert_eq!(wave.frames(), Some(2));
+        let cues: Vec<_> = wave
+            .sorted_cues()
+            .map(|cue| (cue.cue_id, cue.sample_start, cue.label.clone()))
+            .collect();
+        assert_eq!(
+            cues,
+            [
+                (3, 12, None),
+                (1, 48000, Some(String::from("One"))),
+                (2, 96000, Some(String::from("Two")))
+            ]
+        );
+        let bext = wave.bext.unwrap();
+        assert_eq!(bext.description, "Interview");
+        assert_eq!(bext.origination_date, "2024-05-04");
+        assert_eq!(bext.origination_time, "10:22:10");
+        assert_eq!(bext.time_reference, (10 * 3600 + 22 * 60 + 10) * 48000);
+    }
+
+    #[test]
+    fn rejects_a_short_mark_chunk() {
+        let mut mark = mark(&[(0.0, 1)]);
+        mark[7] = 2;
+        assert!(parse_mark(&mark).is_err());
+        let desc = desc();
+        let file = file(&[
+            chunk(b"desc", desc.len() as i64, &desc),
+            chunk(b"mark", mark.len() as i64, &mark),
+        ]);
+        assert!(crate::read_wave(Cursor::new(file.clone())).is_err());
+        let wave = crate::read_wave_with(Cursor::new(file), ParseMode::Lenient).unwrap();
+        assert!(wave.cues.is_empty());
+        assert_eq!(wave.warnings.len(), 1);
+    }
+
+    #[test]
+    fn rejects_open_sizes_before_the_data() {
+        let desc = desc();
+        let file = file(&[
+            chunk(b"desc", desc.len() as i64, &desc),
+            chunk(b"mark", -1, &[]),
+        ]);
+        assert!(crate::read_wave(Cursor::new(file)).is_err());
+    }
+
+    #[test]
+    fn ignores_strings_past_the_end() {
+        let mut strg = strg(&[(1, "One")]);
+        strg[11] = 200;
+        assert!(parse_strg(&strg).is_empty());
+        assert!(parse_strg(&[0, 0, 0, 9]).is_empty());
+    }
+}
@@ -0,0 +1,182 @@
+// cart chunk (AES46) used by radio automation systems:
+// https://www.aes.org/publications/standards/search.cfm?docID=41
+//   0    Version     4 ("0101")
+//   4    Title, Artist, CutID, ClientID, Category, Classification, OutCue
+//                    64 each
+//   452  StartDate 10, StartTime 8, EndDate 10, EndTime 8
+//   488  ProducerAppID, ProducerAppVersion, UserDef  64 each
+//   680  LevelReference  4
+//   684  PostTimer   8 timers of a usage id (e.g. INTs, SEC1, EOD) and a
+//                    sample position, 8 bytes each
+//   748  Reserved    276
+//   1024 URL         1024
+//   2048 TagText     variable
+use crate::text;
+use crate::{Error, WaveError};
+
+pub const CART_TITLE: (usize, usize) = (4, 64);
+pub const CART_ARTIST: (usize, usize) = (68, 64);
+pub const CART_CUT_ID: (usize, usize) = (132, 64);
+pub const CART_CLIENT_ID: (usize, usize) = (196, 64);
+pub const CART_CATEGORY: (usize, usize) = (260, 64);
+pub const CART_CLASSIFICATION: (usize, usize) = (324, 64);
+pub const CART_OUT_CUE: (usize, usize) = (388, 64);
+pub const CART_START_DATE: (usize, usize) = (452, 10);
+pub const CART_START_TIME: (usize, usize) = (462, 8);
+pub const CART_END_DATE: (usize, usize) = (470, 10);
+pub const CART_END_TIME: (usize, usize) = (480, 8);
+pub const CART_PRODUCER_APP_ID: (usize, usize) = (488, 64);
+pub const CART_PRODUCER_APP_VERSION: (usize, usize) = (552, 64);
+pub const CART_USER_DEF: (usize, usize) = (616, 64);
+pub const CART_URL: (usize, usize) = (1024, 1024);
+const LEVEL_REFERENCE_OFFSET: usize = 680;
+const POST_TIMER_OFFSET: usize = 684;
+pub const TIMER_COUNT: usize = 8;
+const FIXED_SIZE: usize = 2048;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timer {
+    pub usage: [u8; 4],
+    pub value: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cart {
+    pub version: String,
+    pub title: String,
+    pub artist: String,
+    pub cut_id: String,
+    pub client_id: String,
+    pub category: String,
+    pub classification: String,
+    pub out_cue: String,
+    pub start_date: String,
+    pub start_time: String,
+    pub end_date: String,
+    pub end_time: String,
+    pub producer_app_id: String,
+    pub producer_app_version: String,
+    pub user_def: String,
+    // sample value of 0 dB reference level
+    pub level_reference: i32,
+    pub timers: Vec<Timer>,
+    pub url: String,
+    pub tag_text: String,
+}
+
+impl Timer {
+    pub fn usage(&self) -> String {
+        String::from_utf8_lossy(&self.usage)
+            .trim_end_matches(['\0', ' '])
+            .to_string()
+    }
+}
+
+// Usage id from text such as INTs or MRK, padded with spaces
+pub fn parse_usage(text: &str) -> Option<[u8; 4]> {
+    if text.is_empty() || text.len() > 4 || !text.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let mut usage = *b"    ";
+    usage[..text.len()].copy_from_slice(text.as_bytes());
+    Some(usage)
+}
+
+// Timers in use; slots with an empty usage id are left out
+pub fn timers(data: &[u8]) -> Result<Vec<Timer>, Error> {
+    if data.len() < POST_TIMER_OFFSET + 8 * TIMER_COUNT {
+        return Err(Error::from(WaveError {
+            message: format!("cart chunk is too small: {}", data.len()),
+        }));
+    }
+    Ok((0..TIMER_COUNT)
+        .map(|index| {
+            let ofs = POST_TIMER_OFFSET + 8 * index;
+            Timer {
+                usage: *array_ref!(data, ofs, 4),
+                value: u32::from_le_bytes(*array_ref!(data, ofs + 4, 4)),
+            }
+        })
+        .filter(|timer| timer.usage.iter().any(|byte| *byte != 0 && *byte != b' '))
+        .collect())
+}
+
+pub fn new_chunk() -> Vec<u8> {
+    let mut data = vec![0u8; FIXED_SIZE];
+    data[0..4].copy_from_slice(b"0101");
+    data
+}
+
+// Replaces all the timers; unused slots are cleared
+pub fn set_timers(data: &mut Vec<u8>, timers: &[Timer]) {
+    if data.len() < FIXED_SIZE {
+        data.resize(FIXED_SIZE, 0);
+    }
+    for index in 0..TIMER_COUNT {
+        let ofs = POST_TIMER_OFFSET + 8 * index;
+        let (usage, value) = match timers.get(index) {
+            Some(timer) => (timer.usage, timer.value),
+            None => ([0; 4], 0),
+        };
+        data[ofs..ofs + 4].copy_from_slice(&usage);
+        data[ofs + 4..ofs + 8].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+// A text field, up to its first NUL
+fn field(data: &[u8], field: (usize, usize)) -> String {
+    let (offset, size) = field;
+    let raw = data.get(offset..offset + size).unwrap_or_default();
+    let end = raw.iter().position(|byte| *byte == 0).unwrap_or(raw.len());
+    String::from_utf8_lossy(&raw[..end]).trim_end().to_string()
+}
+
+pub fn parse(data: &[u8]) -> Result<Cart, Error> {
+    let timers = timers(data)?;
+    Ok(Cart {
+        version: field(data, (0, 4)),
+        title: field(data, CART_TITLE),
+        artist: field(data, CART_ARTIST),
+        cut_id: field(data, CART_CUT_ID),
+        client_id: field(data, CART_CLIENT_ID),
+        category: field(data, CART_CATEGORY),
+        classification: field(data, CART_CLASSIFICATION),
+        out_cue: field(data, CART_OUT_CUE),
+        start_date: field(data, CART_START_DATE),
+        start_time: field(data, CART_START_TIME),
+        end_date: field(data, CART_END_DATE),
+        end_time: field(data, CART_END_TIME),
+        producer_app_id: field(data, CART_PRODUCER_APP_ID),
+        producer_app_version: field(data, CART_PRODUCER_APP_VERSION),
+        user_def: field(data, CART_USER_DEF),
+        level_reference: i32::from_le_bytes(*array_ref!(data, LEVEL_REFERENCE_OFFSET, 4)),
+        timers,
+        url: field(data, CART_URL),
+        tag_text: data
+            .get(FIXED_SIZE..)
+            .map(|tag_text| {
+                String::from_utf8_lossy(tag_text)
+                    .trim_end_matches(char::from(0))
+                    .to_string()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+// Stores an ASCII field, NUL-padded to the field size as AES46 asks;
+// returns whether the text was cut to fit
+pub fn set_string(data: &mut Vec<u8>, field: (usize, usize), value: &str) -> bool {
+    if data.len() < FIXED_SIZE {
+        data.resize(FIXED_SIZE, 0);
+    }
+    let (offset, size) = field;
+    let value = text::to_ascii(value);
+    let dst = &mut data[offset..offset + size];
+    dst.fill(0);
+    for (dst, src) in dst.iter_mut().zip(value.bytes()) {
+        *dst = src;
+    }
+    value.len() > size
+}
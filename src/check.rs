@@ -0,0 +1,68 @@
+// Compares the markers of a file against a reference list, e.g. to verify
+// that a transcode kept them in place
+use crate::import;
+use crate::{Error, WaveError};
+use std::fs;
+
+// Marker times from a CSV in the shape the export writes: seconds,label,
+// or with a header line naming the columns, such as seconds,end,label.
+// Other lines that don't start with a number are skipped.
+pub fn read_reference(filename: &str) -> Result<Vec<(f64, String)>, Error> {
+    let content = fs::read_to_string(filename)?;
+    let mut markers = Vec::new();
+    let (mut columns, mut label) = (2, 1);
+    for mut fields in import::csv_records(&content, ',') {
+        if fields[0].trim() == "seconds" && fields.len() > 1 {
+            columns = fields.len();
+            label = fields
+                .iter()
+                .position(|name| name.trim() == "label")
+                .unwrap_or(columns - 1);
+            continue;
+        }
+        // unquoted labels of older files run on past the last column
+        if label == columns - 1 && fields.len() > columns {
+            let rest = fields.split_off(label).join(",");
+            fields.push(rest);
+        }
+        if let Ok(seconds) = fields[0].trim().parse::<f64>() {
+            let label = fields.get(label).map_or("", |label| label.trim());
+            markers.push((seconds, label.to_string()));
+        }
+    }
+    if markers.is_empty() && !content.trim().is_empty() {
+        return Err(Error::from(WaveError {
+            message: format!("No markers found in {filename}"),
+        }));
+    }
+    markers.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(markers)
+}
+
+// Pairs the markers in time order; returns a description of each difference
+// larger than the tolerance and of each missing or extra marker
+pub fn compare(
+    actual: &[(f64, String)],
+    expected: &[(f64, String)],
+    tolerance: f64,
+) -> Vec<String> {
+    let mut differences = Vec::new();
+    for (index, pair) in actual.iter().zip(expected).enumerate() {
+        let ((actual, actual_label), (expected, expected_label)) = pair;
+        let difference = actual - expected;
+        if difference.abs() > tolerance {
+            differences.push(format!(
+                "marker {}: {actual_label} at {actual:.6}s, expected {expected_label} at {expected:.6}s ({:+.3} ms)",
+                index + 1,
+                difference * 1000.0
+            ));
+        }
+    }
+    for (seconds, label) in expected.iter().skip(actual.len()) {
+        differences.push(format!("missing marker {label} at {seconds:.6}s"));
+    }
+    for (seconds, label) in actual.iter().skip(expected.len()) {
+        differences.push(format!("unexpected marker {label} at {seconds:.6}s"));
+    }
+    differences
+}
@@ -0,0 +1,96 @@
+// chna chunk of BW64 files (ITU-R BS.2088), mapping the tracks of the
+// audio to the audioTrackUIDs of the ADM metadata in the axml chunk:
+//   0   NumTracks 2, NumUIDs 2
+//   4   audio ids of 40 bytes: TrackIndex 2 (from 1, 0 for unused slots),
+//       UID 12 (ATU_00000001), TrackRef 14 (AT_00010001_01),
+//       PackRef 11 (AP_00010002), pad 1
+// A track can carry several UIDs, e.g. when objects take turns on it.
+use crate::{Error, WaveError};
+
+const AUDIO_ID_SIZE: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AudioId {
+    pub track_index: u16,
+    pub uid: String,
+    pub track_ref: String,
+    pub pack_ref: String,
+}
+
+fn text(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches(['\0', ' '])
+        .to_string()
+}
+
+// The text padded with NULs to the size of its field
+fn put_text(data: &mut Vec<u8>, text: &str, size: usize) {
+    let bytes = text.as_bytes();
+    let count = bytes.len().min(size);
+    data.extend_from_slice(&bytes[..count]);
+    data.resize(data.len() + size - count, 0);
+}
+
+pub fn parse(data: &[u8]) -> Result<Vec<AudioId>, Error> {
+    if data.len() < 4 {
+        return Err(Error::from(WaveError {
+            message: format!("chna chunk too small ({} bytes)", data.len()),
+        }));
+    }
+    let count = u16::from_le_bytes(*array_ref!(data, 2, 2)) as usize;
+    if data.len() < 4 + count * AUDIO_ID_SIZE {
+        return Err(Error::from(WaveError {
+            message: format!(
+                "chna chunk of {} bytes cannot hold {count} audio ids",
+                data.len()
+            ),
+        }));
+    }
+    Ok(data[4..]
+        .chunks_exact(AUDIO_ID_SIZE)
+        .map(|entry| AudioId {
+            track_index: u16::from_le_bytes(*array_ref!(entry, 0, 2)),
+            uid: text(&entry[2..14]),
+            track_ref: text(&entry[14..28]),
+            pack_ref: text(&entry[28..39]),
+        })
+        // writers reserve slots ahead, left zeroed
+        .filter(|id| id.track_index != 0)
+        .collect())
+}
+
+pub fn build(ids: &[AudioId]) -> Vec<u8> {
+    let mut tracks: Vec<u16> = ids.iter().map(|id| id.track_index).collect();
+    tracks.sort_unstable();
+    tracks.dedup();
+    let mut data = Vec::with_capacity(4 + ids.len() * AUDIO_ID_SIZE);
+    data.extend_from_slice(&(tracks.len() as u16).to_le_bytes());
+    data.extend_from_slice(&(ids.len() as u16).to_le_bytes());
+    for id in ids {
+        data.extend_from_slice(&id.track_index.to_le_bytes());
+        put_text(&mut data, &id.uid, 12);
+        put_text(&mut data, &id.track_ref, 14);
+        put_text(&mut data, &id.pack_ref, 11);
+        data.push(0);
+    }
+    data
+}
+
+// The ids after the tracks have been reordered or split off: track n of
+// the new file is track tracks[n - 1] of the old one. The ids of tracks
+// that aren't listed are dropped.
+pub fn remap(ids: &[AudioId], tracks: &[u16]) -> Vec<AudioId> {
+    tracks
+        .iter()
+        .zip(1..)
+        .flat_map(|(old, new)| {
+            ids.iter()
+                .filter(move |id| id.track_index == *old)
+                .map(move |id| AudioId {
+                    track_index: new,
+                    ..id.clone()
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,60 @@
+use crate::adtl;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Color {
+    pub const fn new(red: u8, green: u8, blue: u8) -> Color {
+        Color { red, green, blue }
+    }
+
+    // #RRGGBB, with or without the hash
+    pub fn parse(text: &str) -> Option<Color> {
+        let hex = text.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return None;
+        }
+        let component = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+        Some(Color {
+            red: component(0)?,
+            green: component(2)?,
+            blue: component(4)?,
+        })
+    }
+
+    // Color given in a cue note as color=#RRGGBB
+    pub fn from_note(note: &str) -> Option<Color> {
+        Color::parse(&adtl::note_attribute(note, &["color", "colour"])?)
+    }
+
+    // Name of the closest color of a palette, for formats that only have
+    // a few named colors; None if the palette is empty
+    pub fn nearest<'a>(&self, palette: &[(&'a str, Color)]) -> Option<&'a str> {
+        let distance = |other: &Color| {
+            [
+                (self.red, other.red),
+                (self.green, other.green),
+                (self.blue, other.blue),
+            ]
+            .iter()
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+            .sum::<i32>()
+        };
+        palette
+            .iter()
+            .min_by_key(|(_, color)| distance(color))
+            .map(|(name, _)| *name)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
@@ -0,0 +1,176 @@
+// Configuration is read from $WAVCUE_CONFIG, or config.toml in
+// $XDG_CONFIG_HOME/wav-cue (~/.config/wav-cue). Only the subset of TOML that
+// is needed is supported: [sections] and key = "string" pairs.
+//
+// [organization]
+// originator = "Example Broadcasting"
+// country = "FI"
+// organization_code = "EXB"
+// serial_number = "H1N000012345"
+// coding_history = "A=PCM,F={sampling_rate},W={bits_per_sample},M={mode},T=wav-cue"
+use crate::bext;
+use crate::datetime::DateTime;
+use crate::umid;
+use crate::{Error, Header};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Default)]
+pub struct Config {
+    pub organization: Option<OrganizationProfile>,
+}
+
+#[derive(Debug, Default)]
+pub struct OrganizationProfile {
+    pub originator: String,
+    pub country: String,
+    pub organization_code: String,
+    pub serial_number: String,
+    pub coding_history: Option<String>,
+}
+
+impl OrganizationProfile {
+    // EBU R99: CCOOOSSSSSSSSSSSSHHMMSSRRRRRRRRR
+    //   country code (ISO 3166-1), organization code, serial number,
+    //   origination time and a random number
+    pub fn originator_reference(&self, time: &DateTime) -> String {
+        let random: String = umid::random_bytes(9)
+            .iter()
+            .map(|byte| char::from(b'0' + byte % 10))
+            .collect();
+        format!(
+            "{:<2.2}{:<3.3}{:0>12.12}{:02}{:02}{:02}{}",
+            self.country.to_uppercase(),
+            self.organization_code.to_uppercase(),
+            self.serial_number,
+            time.hour,
+            time.minute,
+            time.second,
+            random
+        )
+    }
+
+    // Expands the template; EBU R98 lines are terminated with CR/LF
+    pub fn coding_history(&self, header: &Header) -> Option<String> {
+        let template = self.coding_history.as_ref()?;
+        let line = template
+            .replace("{sampling_rate}", &header.sampling_rate.to_string())
+            .replace(
+                "{bits_per_sample}",
+                &header.significant_bits_per_sample.to_string(),
+            )
+            .replace("{mode}", bext::coding_mode(header));
+        Some(format!("{}\r\n", line.trim_end()))
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("WAVCUE_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("wav-cue").join("config.toml"))
+}
+
+fn parse_value(value: &str, line_number: usize) -> Result<String, Error> {
+    let value = value.trim();
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut result = String::new();
+        let mut chars = quoted.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '"' => {
+                    let rest: String = chars.collect();
+                    let rest = rest.trim();
+                    if !rest.is_empty() && !rest.starts_with('#') {
+                        break;
+                    }
+                    return Ok(result);
+                }
+                '\\' => match chars.next() {
+                    Some('n') => result.push('\n'),
+                    Some('r') => result.push('\r'),
+                    Some('t') => result.push('\t'),
+                    Some('"') => result.push('"'),
+                    Some('\\') => result.push('\\'),
+                    _ => break,
+                },
+                ch => result.push(ch),
+            }
+        }
+        Err(Error::ConfigError(format!(
+            "line {line_number}: malformed string"
+        )))
+    } else {
+        Ok(value
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string())
+    }
+}
+
+fn parse(text: &str) -> Result<HashMap<String, HashMap<String, String>>, Error> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut section = String::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            match name
+                .split('#')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .strip_suffix(']')
+            {
+                Some(name) => section = name.trim().to_string(),
+                None => {
+                    return Err(Error::ConfigError(format!(
+                        "line {}: malformed section header",
+                        index + 1
+                    )))
+                }
+            }
+        } else if let Some((key, value)) = line.split_once('=') {
+            let value = parse_value(value, index + 1)?;
+            sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value);
+        } else {
+            return Err(Error::ConfigError(format!(
+                "line {}: expected key = value",
+                index + 1
+            )));
+        }
+    }
+    Ok(sections)
+}
+
+pub fn load() -> Result<Config, Error> {
+    let path = match config_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(Config::default()),
+    };
+    let sections = parse(&fs::read_to_string(&path)?)?;
+    let organization = sections.get("organization").map(|values| {
+        let value = |key: &str| values.get(key).cloned().unwrap_or_default();
+        OrganizationProfile {
+            originator: value("originator"),
+            country: value("country"),
+            organization_code: value("organization_code"),
+            serial_number: value("serial_number"),
+            coding_history: values.get("coding_history").cloned(),
+        }
+    });
+    Ok(Config { organization })
+}
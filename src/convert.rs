@@ -0,0 +1,69 @@
+// Rescales the sample positions in the metadata after the audio has been
+// resampled by a tool that kept the chunks but not their meaning
+use crate::bext::BEXT_TIME_REFERENCE_OFFSET;
+use crate::riff::{self, Chunk};
+use crate::{Error, WaveError};
+
+fn scale(value: u64, from: u32, to: u32) -> u64 {
+    ((value as u128 * to as u128 * 2 + from as u128) / (from as u128 * 2)) as u64
+}
+
+fn scale_u32(data: &mut [u8], offset: usize, from: u32, to: u32) -> Result<(), Error> {
+    let value = u32::from_le_bytes(*array_ref!(data, offset, 4));
+    let scaled = scale(value as u64, from, to);
+    if scaled > u32::MAX as u64 {
+        return Err(Error::from(WaveError {
+            message: format!("Position {value} does not fit in 32 bits at the new rate"),
+        }));
+    }
+    data[offset..offset + 4].copy_from_slice(&(scaled as u32).to_le_bytes());
+    Ok(())
+}
+
+// Returns the number of rescaled cue points and regions
+pub fn convert_cues(filename: &str, from: u32, to: u32) -> Result<(usize, usize), Error> {
+    let mut cue_count = 0;
+    let mut region_count = 0;
+    riff::rewrite_file(filename, |reader, chunks| {
+        for chunk in chunks.iter_mut() {
+            match &chunk.id {
+                b"cue " => {
+                    let mut data = chunk.payload(reader)?;
+                    // position and sample offset of each 24 byte cue point
+                    for ofs in (4..data.len().saturating_sub(23)).step_by(24) {
+                        scale_u32(&mut data, ofs + 4, from, to)?;
+                        scale_u32(&mut data, ofs + 20, from, to)?;
+                        cue_count += 1;
+                    }
+                    *chunk = Chunk::new(b"cue ", data);
+                }
+                b"LIST" => {
+                    let (list_type, mut items) = riff::parse_list(&chunk.payload(reader)?)?;
+                    if &list_type != b"adtl" {
+                        continue;
+                    }
+                    for (id, data) in items.iter_mut() {
+                        // cue id followed by the sample length of the region
+                        if id == b"ltxt" && data.len() >= 8 {
+                            scale_u32(data, 4, from, to)?;
+                            region_count += 1;
+                        }
+                    }
+                    *chunk = Chunk::new(b"LIST", riff::build_list(&list_type, &items));
+                }
+                b"bext" => {
+                    let mut data = chunk.payload(reader)?;
+                    if data.len() >= BEXT_TIME_REFERENCE_OFFSET + 8 {
+                        let field = array_mut_ref!(data, BEXT_TIME_REFERENCE_OFFSET, 8);
+                        let time_reference = u64::from_le_bytes(*field);
+                        *field = scale(time_reference, from, to).to_le_bytes();
+                    }
+                    *chunk = Chunk::new(b"bext", data);
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    })?;
+    Ok((cue_count, region_count))
+}
@@ -0,0 +1,188 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Calendar date and time of day, always UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// Parses +HH:MM, -HH:MM, +HHMM or +HH into seconds
+pub fn parse_utc_offset(offset: &str) -> Option<i64> {
+    let (sign, rest) = match offset.chars().next()? {
+        '+' => (1, &offset[1..]),
+        '-' => (-1, &offset[1..]),
+        _ => (1, offset),
+    };
+    let digits: String = rest.chars().filter(|ch| *ch != ':').collect();
+    if !digits.chars().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.parse::<i64>().ok()?, 0),
+        4 => (
+            digits[0..2].parse::<i64>().ok()?,
+            digits[2..4].parse::<i64>().ok()?,
+        ),
+        _ => return None,
+    };
+    if hours > 14 || minutes > 59 {
+        return None;
+    }
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn valid(date_time: &DateTime) -> bool {
+    (1..=12).contains(&date_time.month)
+        && (1..=31).contains(&date_time.day)
+        && date_time.hour < 24
+        && date_time.minute < 60
+        && date_time.second < 60
+}
+
+impl DateTime {
+    pub fn from_unix_seconds(seconds: i64) -> DateTime {
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+        let time_of_day = seconds.rem_euclid(86400) as u32;
+        DateTime {
+            year,
+            month,
+            day,
+            hour: time_of_day / 3600,
+            minute: time_of_day / 60 % 60,
+            second: time_of_day % 60,
+        }
+    }
+
+    pub fn from_system_time(time: SystemTime) -> DateTime {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(error) => -(error.duration().as_secs() as i64),
+        };
+        DateTime::from_unix_seconds(seconds)
+    }
+
+    pub fn unix_seconds(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * 86400
+            + self.seconds_since_midnight() as i64
+    }
+
+    pub fn offset_by(&self, seconds: i64) -> DateTime {
+        DateTime::from_unix_seconds(self.unix_seconds() + seconds)
+    }
+
+    pub fn seconds_since_midnight(&self) -> u64 {
+        self.hour as u64 * 3600 + self.minute as u64 * 60 + self.second as u64
+    }
+
+    // Timestamps in recorder file names, such as ZOOM0012_230415_1342.wav,
+    // 20230415_134200.wav or 2023-04-15 13-42-00.wav
+    pub fn from_filename(filename: &str) -> Option<DateTime> {
+        let stem = Path::new(filename).file_stem()?.to_string_lossy();
+        let runs: Vec<&str> = stem
+            .split(|ch: char| !ch.is_ascii_digit())
+            .filter(|run| !run.is_empty())
+            .collect();
+        let number = |run: &str| run.parse::<u32>().unwrap_or(u32::MAX);
+
+        // a date run followed by a time run
+        for pair in runs.windows(2) {
+            let (date, time) = (pair[0], pair[1]);
+            let year = match date.len() {
+                8 => number(&date[0..4]) as i64,
+                6 => 2000 + number(&date[0..2]) as i64,
+                _ => continue,
+            };
+            let date = &date[date.len() - 4..];
+            if time.len() != 4 && time.len() != 6 {
+                continue;
+            }
+            let date_time = DateTime {
+                year,
+                month: number(&date[0..2]),
+                day: number(&date[2..4]),
+                hour: number(&time[0..2]),
+                minute: number(&time[2..4]),
+                second: if time.len() == 6 {
+                    number(&time[4..6])
+                } else {
+                    0
+                },
+            };
+            if valid(&date_time) {
+                return Some(date_time);
+            }
+        }
+
+        // separate runs for each field, seconds optional
+        for (index, run) in runs.iter().enumerate() {
+            let fields = &runs[index..];
+            if run.len() != 4
+                || fields.len() < 5
+                || fields[1..5].iter().any(|field| field.len() != 2)
+            {
+                continue;
+            }
+            let date_time = DateTime {
+                year: number(run) as i64,
+                month: number(fields[1]),
+                day: number(fields[2]),
+                hour: number(fields[3]),
+                minute: number(fields[4]),
+                second: match fields.get(5) {
+                    Some(field) if field.len() == 2 => number(field),
+                    _ => 0,
+                },
+            };
+            if valid(&date_time) {
+                return Some(date_time);
+            }
+        }
+        None
+    }
+
+    pub fn now() -> DateTime {
+        DateTime::from_system_time(SystemTime::now())
+    }
+
+    // yyyy-mm-dd as used in bext OriginationDate
+    pub fn date_string(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+    // hh:mm:ss as used in bext OriginationTime
+    pub fn time_string(&self) -> String {
+        format!("{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+}
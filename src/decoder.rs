@@ -0,0 +1,360 @@
+// Decoders for chunks that WavCue doesn't know about, keyed by chunk id.
+// The structured output of each decoder ends up under "chunks" in the JSON
+// and YAML reports, so vendor specific chunks can be supported without
+// changing the report code.
+use crate::adm;
+use crate::bext;
+use crate::cart;
+use crate::id3;
+use crate::levl;
+use crate::pcm;
+use crate::umid::{self, Umid};
+use crate::value::{ObjectBuilder, Value};
+use crate::{Error, KeptChunk};
+
+pub trait ChunkDecoder {
+    fn decode(&self, data: &[u8]) -> Result<Value, Error>;
+}
+
+impl<F> ChunkDecoder for F
+where
+    F: Fn(&[u8]) -> Result<Value, Error>,
+{
+    fn decode(&self, data: &[u8]) -> Result<Value, Error> {
+        self(data)
+    }
+}
+
+#[derive(Default)]
+pub struct Registry {
+    decoders: Vec<([u8; 4], Box<dyn ChunkDecoder>)>,
+}
+
+// fact: number of samples per channel, required for compressed formats
+fn decode_fact(data: &[u8]) -> Result<Value, Error> {
+    match data.get(0..4) {
+        Some(length) => Ok(Value::Object(vec![(
+            String::from("sample_length"),
+            Value::from(u32::from_le_bytes(*array_ref!(length, 0, 4))),
+        )])),
+        None => Ok(Value::Null),
+    }
+}
+
+fn umid_value(umid: &Umid) -> Value {
+    let source_pack = umid.source_pack.as_ref().map(|pack| {
+        ObjectBuilder::new()
+            .field(
+                "time_date",
+                pack.time_date
+                    .map(|time| format!("{}T{}Z", time.date_string(), time.time_string())),
+            )
+            .field(
+                "spatial_coordinates",
+                umid::to_hex(&pack.spatial_coordinates),
+            )
+            .field("country", pack.country.as_str())
+            .field("organization", pack.organization.as_str())
+            .field("user", pack.user.as_str())
+            .build()
+    });
+    ObjectBuilder::new()
+        .field("universal_label", umid::to_hex(&umid.universal_label))
+        .field("material_type", umid.material_type())
+        .field("material_number_method", umid.material_number_method())
+        .field("instance_number_method", umid.instance_number_method())
+        .field("extended", umid.is_extended())
+        .field("instance_number", umid.instance_number)
+        .field("material_number", umid::to_hex(&umid.material_number))
+        .field("source_pack", source_pack)
+        .build()
+}
+
+// bext: the UMID, decoded into its SMPTE ST 330 fields
+fn decode_bext(data: &[u8]) -> Result<Value, Error> {
+    let field = data
+        .get(bext::BEXT_UMID_OFFSET..bext::BEXT_UMID_OFFSET + bext::BEXT_UMID_SIZE)
+        .unwrap_or_default();
+    Ok(ObjectBuilder::new()
+        .field("umid", umid::parse(field).as_ref().map(umid_value))
+        .build())
+}
+
+fn strings(values: &[String]) -> Value {
+    Value::Array(
+        values
+            .iter()
+            .map(|value| Value::from(value.as_str()))
+            .collect(),
+    )
+}
+
+// axml: the programmes, objects and formats of the ADM metadata
+fn decode_axml(data: &[u8]) -> Result<Value, Error> {
+    let adm = adm::parse(data)?;
+    Ok(ObjectBuilder::new()
+        .field(
+            "programmes",
+            Value::Array(
+                adm.programmes
+                    .iter()
+                    .map(|programme| {
+                        ObjectBuilder::new()
+                            .field("id", programme.id.as_str())
+                            .field("name", programme.name.as_str())
+                            .field("contents", strings(&programme.content_ids))
+                            .build()
+                    })
+                    .collect(),
+            ),
+        )
+        .field(
+            "contents",
+            Value::Array(
+                adm.contents
+                    .iter()
+                    .map(|content| {
+                        ObjectBuilder::new()
+                            .field("id", content.id.as_str())
+                            .field("name", content.name.as_str())
+                            .field("objects", strings(&content.object_ids))
+                            .build()
+                    })
+                    .collect(),
+            ),
+        )
+        .field(
+            "objects",
+            Value::Array(
+                adm.objects
+                    .iter()
+                    .map(|object| {
+                        ObjectBuilder::new()
+                            .field("id", object.id.as_str())
+                            .field("name", object.name.as_str())
+                            .field("pack_formats", strings(&object.pack_format_ids))
+                            .field("track_uids", strings(&object.track_uids))
+                            .field("objects", strings(&object.object_ids))
+                            .build()
+                    })
+                    .collect(),
+            ),
+        )
+        .field(
+            "pack_formats",
+            Value::Array(
+                adm.pack_formats
+                    .iter()
+                    .map(|pack| {
+                        ObjectBuilder::new()
+                            .field("id", pack.id.as_str())
+                            .field("name", pack.name.as_str())
+                            .field("type", pack.type_definition.as_str())
+                            .field("channel_formats", strings(&pack.channel_format_ids))
+                            .build()
+                    })
+                    .collect(),
+            ),
+        )
+        .field(
+            "channel_formats",
+            Value::Array(
+                adm.channel_formats
+                    .iter()
+                    .map(|channel| {
+                        ObjectBuilder::new()
+                            .field("id", channel.id.as_str())
+                            .field("name", channel.name.as_str())
+                            .field("type", channel.type_definition.as_str())
+                            .build()
+                    })
+                    .collect(),
+            ),
+        )
+        .field(
+            "track_uids",
+            Value::Array(
+                adm.track_uids
+                    .iter()
+                    .map(|track| {
+                        ObjectBuilder::new()
+                            .field("uid", track.uid.as_str())
+                            .field("channel_format", track.channel_format_id.as_deref())
+                            .field("track_format", track.track_format_id.as_deref())
+                            .field("pack_format", track.pack_format_id.as_deref())
+                            .build()
+                    })
+                    .collect(),
+            ),
+        )
+        .build())
+}
+
+// cart: the AES46 fields of radio automation systems and their timers
+fn decode_cart(data: &[u8]) -> Result<Value, Error> {
+    let cart = cart::parse(data)?;
+    Ok(ObjectBuilder::new()
+        .field("version", cart.version.as_str())
+        .field("title", cart.title.as_str())
+        .field("artist", cart.artist.as_str())
+        .field("cut_id", cart.cut_id.as_str())
+        .field("client_id", cart.client_id.as_str())
+        .field("category", cart.category.as_str())
+        .field("classification", cart.classification.as_str())
+        .field("out_cue", cart.out_cue.as_str())
+        .field("start_date", cart.start_date.as_str())
+        .field("start_time", cart.start_time.as_str())
+        .field("end_date", cart.end_date.as_str())
+        .field("end_time", cart.end_time.as_str())
+        .field("producer_app_id", cart.producer_app_id.as_str())
+        .field("producer_app_version", cart.producer_app_version.as_str())
+        .field("user_def", cart.user_def.as_str())
+        .field("level_reference", cart.level_reference as f64)
+        .field(
+            "timers",
+            Value::Array(
+                cart.timers
+                    .iter()
+                    .map(|timer| {
+                        ObjectBuilder::new()
+                            .field("usage", timer.usage())
+                            .field("value", timer.value)
+                            .build()
+                    })
+                    .collect(),
+            ),
+        )
+        .field("url", cart.url.as_str())
+        .field("tag_text", cart.tag_text.as_str())
+        .build())
+}
+
+// id3: the text frames and chapters of an ID3v2 tag
+fn decode_id3(data: &[u8]) -> Result<Value, Error> {
+    let tag = id3::parse(data)?;
+    Ok(ObjectBuilder::new()
+        .field("version", format!("2.{}", tag.version))
+        .field("title", tag.text("TIT2"))
+        .field("artist", tag.text("TPE1"))
+        .field("album", tag.text("TALB"))
+        .field(
+            "frames",
+            Value::Object(
+                tag.texts
+                    .iter()
+                    .map(|(id, text)| (id.clone(), Value::from(text.as_str())))
+                    .collect(),
+            ),
+        )
+        .field(
+            "chapters",
+            Value::Array(
+                tag.chapters
+                    .iter()
+                    .map(|chapter| {
+                        ObjectBuilder::new()
+                            .field("id", chapter.element_id.as_str())
+                            .field("start_ms", chapter.start_ms)
+                            .field("end_ms", chapter.end_ms)
+                            .field("title", chapter.title.as_deref())
+                            .field("url", chapter.url.as_deref())
+                            .field("image", chapter.image.as_deref())
+                            .build()
+                    })
+                    .collect(),
+            ),
+        )
+        .build())
+}
+
+// levl: the peak envelope header with the highest point of each channel,
+// leaving the points out
+fn decode_levl(data: &[u8]) -> Result<Value, Error> {
+    let levl = levl::parse(data)?;
+    Ok(ObjectBuilder::new()
+        .field("version", levl.version)
+        .field(
+            "format",
+            match levl.format {
+                levl::FORMAT_8_BIT => "8 bit",
+                _ => "16 bit",
+            },
+        )
+        .field("points_per_value", levl.points_per_value)
+        .field("block_size", levl.block_size)
+        .field("channels", levl.channels)
+        .field("peak_frames", levl.peak_frames)
+        .field("peak_of_peaks", levl.peak_of_peaks)
+        .field("timestamp", levl.timestamp.as_str())
+        .field(
+            "channel_peaks_dbfs",
+            Value::Array(
+                levl.channel_peaks()
+                    .iter()
+                    .map(|peak| {
+                        Value::from(Some(pcm::to_dbfs(*peak)).filter(|peak| peak.is_finite()))
+                    })
+                    .collect(),
+            ),
+        )
+        .build())
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    // Registry with the decoders shipped with WavCue
+    pub fn with_builtin() -> Registry {
+        let mut registry = Registry::new();
+        registry.register(b"fact", decode_fact);
+        registry.register(b"bext", decode_bext);
+        registry.register(b"axml", decode_axml);
+        registry.register(b"cart", decode_cart);
+        registry.register(b"levl", decode_levl);
+        // both spellings are in use
+        registry.register(b"id3 ", decode_id3);
+        registry.register(b"ID3 ", decode_id3);
+        registry
+    }
+
+    // A later registration for the same id replaces the earlier one
+    pub fn register<D: ChunkDecoder + 'static>(&mut self, id: &[u8; 4], decoder: D) {
+        self.decoders.retain(|(registered, _)| registered != id);
+        self.decoders.push((*id, Box::new(decoder)));
+    }
+
+    fn find(&self, id: &[u8; 4]) -> Option<&dyn ChunkDecoder> {
+        self.decoders
+            .iter()
+            .find(|(registered, _)| registered == id)
+            .map(|(_, decoder)| decoder.as_ref())
+    }
+
+    // Whether a decoder is registered for the chunk, for keeping its
+    // payload while the file is read
+    pub fn decodes(&self, id: &[u8; 4]) -> bool {
+        self.find(id).is_some()
+    }
+
+    // Decoded chunks by chunk id, from the payloads kept while reading
+    // the file. A decoder failing doesn't fail the report; its error
+    // message is reported instead.
+    pub fn decode_chunks(&self, chunks: &[KeptChunk]) -> Vec<(String, Value)> {
+        chunks
+            .iter()
+            .filter_map(|(id, data)| {
+                let decoder = self.find(id)?;
+                let value = match decoder.decode(data) {
+                    Ok(value) => value,
+                    Err(error) => Value::Object(vec![(
+                        String::from("error"),
+                        Value::from(error.to_string()),
+                    )]),
+                };
+                Some((String::from_utf8_lossy(id).to_string(), value))
+            })
+            .collect()
+    }
+}
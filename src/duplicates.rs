@@ -0,0 +1,103 @@
+// Finds files with the same audio, typically copies of a recording that
+// went through different metadata edits
+use crate::riff;
+use crate::Error;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub struct Group {
+    // files with the same audio, with an index telling apart the distinct
+    // sets of metadata among them
+    pub files: Vec<(PathBuf, usize)>,
+}
+
+impl Group {
+    pub fn metadata_differs(&self) -> bool {
+        self.files.iter().any(|(_, variant)| *variant != 0)
+    }
+}
+
+fn hash_range<R: Read + Seek>(
+    reader: &mut R,
+    hasher: &mut DefaultHasher,
+    offset: u64,
+    size: u64,
+) -> Result<(), Error> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut reader = reader.take(size);
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        hasher.write(&buffer[..read]);
+    }
+}
+
+// Hashes of the audio (format and sample data) and of all other chunks
+fn hashes(filename: &Path) -> Result<(u64, u64), Error> {
+    let mut reader = BufReader::new(File::open(filename)?);
+    let mut audio = DefaultHasher::new();
+    let mut metadata = DefaultHasher::new();
+    for info in riff::walk_chunks(&mut reader)? {
+        let hasher = match &info.id {
+            b"fmt " | b"data" => &mut audio,
+            _ => &mut metadata,
+        };
+        hasher.write(&info.id);
+        hasher.write_u64(info.size);
+        hash_range(&mut reader, hasher, info.offset, info.size)?;
+    }
+    Ok((audio.finish(), metadata.finish()))
+}
+
+// Groups of two or more files with the same audio. Files that can't be read
+// are returned separately with the error.
+pub fn find_duplicates(files: &[PathBuf]) -> (Vec<Group>, Vec<(PathBuf, Error)>) {
+    let mut by_audio: HashMap<u64, Vec<(PathBuf, u64)>> = HashMap::new();
+    let mut order = Vec::new();
+    let mut errors = Vec::new();
+    for file in files {
+        match hashes(file) {
+            Ok((audio, metadata)) => {
+                let entry = by_audio.entry(audio).or_default();
+                if entry.is_empty() {
+                    order.push(audio);
+                }
+                entry.push((file.clone(), metadata));
+            }
+            Err(error) => errors.push((file.clone(), error)),
+        }
+    }
+    let groups = order
+        .iter()
+        .filter_map(|audio| {
+            let entries = &by_audio[audio];
+            if entries.len() < 2 {
+                return None;
+            }
+            let mut variants: Vec<u64> = Vec::new();
+            let files = entries
+                .iter()
+                .map(|(file, metadata)| {
+                    let variant = match variants.iter().position(|known| known == metadata) {
+                        Some(variant) => variant,
+                        None => {
+                            variants.push(*metadata);
+                            variants.len() - 1
+                        }
+                    };
+                    (file.clone(), variant)
+                })
+                .collect();
+            Some(Group { files })
+        })
+        .collect();
+    (groups, errors)
+}
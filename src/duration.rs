@@ -0,0 +1,133 @@
+// Time values given on the command line: 1.5s, 500ms, 2m, 1h, 48000smp,
+// plain seconds, or hh:mm:ss(.fff), optionally with a sign
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeValue {
+    Seconds(f64),
+    Samples(i64),
+}
+
+impl TimeValue {
+    pub fn parse(text: &str) -> Option<TimeValue> {
+        let text = text.trim();
+        let (sign, rest) = match text.strip_prefix('-') {
+            Some(rest) => (-1.0, rest),
+            None => (1.0, text.strip_prefix('+').unwrap_or(text)),
+        };
+        if let Some(samples) = rest.strip_suffix("smp") {
+            return Some(TimeValue::Samples(
+                sign as i64 * samples.trim().parse::<i64>().ok()?,
+            ));
+        }
+        let number = |text: &str| -> Option<f64> {
+            let text = text.trim();
+            if text.is_empty() || !text.chars().all(|ch| ch.is_ascii_digit() || ch == '.') {
+                return None;
+            }
+            text.parse::<f64>().ok()
+        };
+        let seconds = if rest.contains(':') {
+            let fields: Vec<&str> = rest.split(':').collect();
+            if fields.len() > 3 {
+                return None;
+            }
+            let mut seconds = 0.0;
+            for field in fields {
+                seconds = seconds * 60.0 + number(field)?;
+            }
+            seconds
+        } else if let Some(value) = rest.strip_suffix("ms") {
+            number(value)? / 1000.0
+        } else if let Some(value) = rest.strip_suffix('s') {
+            number(value)?
+        } else if let Some(value) = rest.strip_suffix('m') {
+            number(value)? * 60.0
+        } else if let Some(value) = rest.strip_suffix('h') {
+            number(value)? * 3600.0
+        } else {
+            number(rest)?
+        };
+        Some(TimeValue::Seconds(sign * seconds))
+    }
+
+    pub fn to_samples(self, sampling_rate: u32) -> i64 {
+        match self {
+            TimeValue::Seconds(seconds) => (seconds * sampling_rate as f64).round() as i64,
+            TimeValue::Samples(samples) => samples,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Round,
+    Truncate,
+}
+
+// Seconds with the given number of decimals, computed exactly from the
+// sample count so that the last digit doesn't depend on float formatting
+pub fn format_seconds(
+    samples: u64,
+    sampling_rate: u32,
+    precision: u32,
+    rounding: Rounding,
+) -> String {
+    let (seconds, fraction) = split_seconds(samples, sampling_rate, precision, rounding);
+    format!("{seconds}{fraction}")
+}
+
+// hh:mm:ss with the given number of decimals
+pub fn format_clock(
+    samples: u64,
+    sampling_rate: u32,
+    precision: u32,
+    rounding: Rounding,
+) -> String {
+    let (seconds, fraction) = split_seconds(samples, sampling_rate, precision, rounding);
+    format!(
+        "{:02}:{:02}:{:02}{fraction}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// m:ss with the given number of decimals, and h:mm:ss from an hour on, as
+// in the decimal time display of Adobe Audition
+pub fn format_minutes(
+    samples: u64,
+    sampling_rate: u32,
+    precision: u32,
+    rounding: Rounding,
+) -> String {
+    let (seconds, fraction) = split_seconds(samples, sampling_rate, precision, rounding);
+    match seconds / 3600 {
+        0 => format!("{}:{:02}{fraction}", seconds / 60, seconds % 60),
+        hours => format!(
+            "{hours}:{:02}:{:02}{fraction}",
+            seconds / 60 % 60,
+            seconds % 60
+        ),
+    }
+}
+
+// Whole seconds and the decimals, including the point
+fn split_seconds(
+    samples: u64,
+    sampling_rate: u32,
+    precision: u32,
+    rounding: Rounding,
+) -> (u128, String) {
+    let scale = 10u128.pow(precision);
+    let numerator = samples as u128 * scale;
+    let rate = sampling_rate.max(1) as u128;
+    let units = match rounding {
+        Rounding::Round => (numerator * 2 + rate) / (rate * 2),
+        Rounding::Truncate => numerator / rate,
+    };
+    let fraction = if precision == 0 {
+        String::new()
+    } else {
+        format!(".{:0width$}", units % scale, width = precision as usize)
+    };
+    (units / scale, fraction)
+}
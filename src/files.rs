@@ -0,0 +1,146 @@
+// Wave files named on the command line: directories searched recursively,
+// and glob patterns for shells that don't expand them
+use crate::{Error, WaveError};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+fn is_wave(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| {
+            let extension = extension.to_string_lossy().to_lowercase();
+            ["wav", "bwf", "aif", "aiff", "aifc", "caf"].contains(&extension.as_str())
+        })
+        .unwrap_or(false)
+}
+
+// Wave files in the directories, recursively, in a stable order
+pub fn find_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<_, _>>()?;
+            entries.sort();
+            let (directories, others): (Vec<PathBuf>, Vec<PathBuf>) =
+                entries.into_iter().partition(|entry| entry.is_dir());
+            files.extend(others.into_iter().filter(|entry| is_wave(entry)));
+            files.extend(find_files(&directories)?);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn is_pattern(text: &str) -> bool {
+    text.contains(['*', '?'])
+}
+
+// Glob match of a file name, with * for any run of characters and ? for
+// any one character
+fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // position after the last * and where its match would continue
+    let mut star = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(ch) if *ch == '?' || *ch == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|ch| *ch == '*')
+}
+
+// Paths matching a pattern with wildcards in any of its components, in a
+// stable order. Hidden files only match patterns starting with a dot.
+fn glob(pattern: &str) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = vec![PathBuf::new()];
+    for component in Path::new(pattern).components() {
+        let name = match component {
+            Component::Normal(name) => name.to_string_lossy(),
+            _ => {
+                paths.iter_mut().for_each(|path| path.push(component));
+                continue;
+            }
+        };
+        if !is_pattern(&name) {
+            paths.iter_mut().for_each(|path| path.push(&*name));
+            continue;
+        }
+        let mut matched = Vec::new();
+        for path in &paths {
+            let directory = if path.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                path.as_path()
+            };
+            let entries = match fs::read_dir(directory) {
+                Ok(entries) => entries,
+                // only directories can have matching entries
+                Err(_) => continue,
+            };
+            for entry in entries {
+                let entry_name = entry?.file_name().to_string_lossy().to_string();
+                if (!entry_name.starts_with('.') || name.starts_with('.'))
+                    && matches(&name, &entry_name)
+                {
+                    matched.push(path.join(entry_name));
+                }
+            }
+        }
+        matched.sort();
+        paths = matched;
+    }
+    Ok(paths)
+}
+
+// The files given as arguments, expanding glob patterns and, with
+// recursive, the wave files in directories. Paths that don't exist are
+// kept as they are, as they can name files in ZIP archives.
+pub fn expand(arguments: &[String], recursive: bool) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    for argument in arguments {
+        let path = PathBuf::from(argument);
+        let paths = if !path.exists() && is_pattern(argument) {
+            let paths = glob(argument)?;
+            if paths.is_empty() {
+                return Err(Error::from(WaveError {
+                    message: format!("No files match {argument}"),
+                }));
+            }
+            paths
+        } else if path.is_dir() && !recursive {
+            return Err(Error::from(WaveError {
+                message: format!("{argument} is a directory, use --recursive"),
+            }));
+        } else {
+            vec![path]
+        };
+        for path in paths {
+            // directories matched by a pattern are only searched with recursive
+            if !path.is_dir() {
+                files.push(path);
+            } else if recursive {
+                files.extend(find_files(&[path])?);
+            }
+        }
+    }
+    Ok(files)
+}
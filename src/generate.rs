@@ -0,0 +1,134 @@
+// Writes a small synthetic WAV file with known markers and metadata, for
+// testing tools that consume them
+use crate::bext::{self, BEXT_FIXED_SIZE};
+use crate::datetime::DateTime;
+use crate::duration::TimeValue;
+use crate::riff::{self, Chunk};
+use crate::{
+    adtl, cue_chunk, fmt_chunk, CompressionCode, CueEntry, DataChunkId, Error, Header, WaveError,
+};
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
+
+pub struct Options {
+    pub duration: TimeValue,
+    pub sampling_rate: u32,
+    pub number_of_channels: u16,
+    pub bits_per_sample: u16,
+    pub cues: u32,
+    pub bext: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            duration: TimeValue::Seconds(10.0),
+            sampling_rate: 48000,
+            number_of_channels: 2,
+            bits_per_sample: 16,
+            cues: 0,
+            bext: false,
+        }
+    }
+}
+
+// A 1 kHz sine at -20 dBFS on every channel
+fn audio(header: &Header, frames: u64) -> Vec<u8> {
+    let bytes_per_sample = header.block_align as usize / header.number_of_channels as usize;
+    let mut data = Vec::with_capacity(frames as usize * header.block_align as usize);
+    for frame in 0..frames {
+        let value = 0.1 * (2.0 * PI * 1000.0 * frame as f64 / header.sampling_rate as f64).sin();
+        let sample = (value * 2147483647.0) as i32;
+        let bytes = match bytes_per_sample {
+            // 8 bit samples are unsigned
+            1 => vec![((sample >> 24) + 128) as u8],
+            _ => sample.to_le_bytes()[4 - bytes_per_sample..].to_vec(),
+        };
+        for _ in 0..header.number_of_channels {
+            data.extend_from_slice(&bytes);
+        }
+    }
+    data
+}
+
+fn bext_chunk(header: &Header) -> Vec<u8> {
+    let now = DateTime::now();
+    let mut raw = vec![0u8; BEXT_FIXED_SIZE];
+    bext::set_string(&mut raw, bext::BEXT_DESCRIPTION, "wav-cue test signal");
+    bext::set_string(&mut raw, bext::BEXT_ORIGINATOR, "wav-cue");
+    bext::set_string(&mut raw, bext::BEXT_ORIGINATION_DATE, &now.date_string());
+    bext::set_string(&mut raw, bext::BEXT_ORIGINATION_TIME, &now.time_string());
+    bext::set_time_reference(
+        &mut raw,
+        now.seconds_since_midnight() * header.sampling_rate as u64,
+    );
+    raw[bext::BEXT_VERSION_OFFSET..bext::BEXT_VERSION_OFFSET + 2]
+        .copy_from_slice(&1u16.to_le_bytes());
+    if let Some(line) = bext::coding_history_line(header, "generate") {
+        bext::append_coding_history(&mut raw, &line);
+    }
+    raw
+}
+
+pub fn generate(filename: &str, options: &Options) -> Result<(), Error> {
+    if options.sampling_rate == 0
+        || options.number_of_channels == 0
+        || !matches!(options.bits_per_sample, 8 | 16 | 24 | 32)
+    {
+        return Err(Error::from(WaveError {
+            message: String::from("Unsupported audio format"),
+        }));
+    }
+    let frames = options.duration.to_samples(options.sampling_rate).max(0) as u64;
+    let block_align = options.number_of_channels * options.bits_per_sample / 8;
+    let header = Header {
+        compression_code: CompressionCode::Pcm,
+        number_of_channels: options.number_of_channels,
+        sampling_rate: options.sampling_rate,
+        average_bytes_per_second: options.sampling_rate * block_align as u32,
+        block_align,
+        significant_bits_per_sample: options.bits_per_sample,
+    };
+
+    // evenly spaced, the first and last ones away from the file edges
+    let cues: Vec<CueEntry> = (1..=options.cues)
+        .map(|cue_id| {
+            let sample_start = (frames * cue_id as u64 / (options.cues as u64 + 1)) as u32;
+            CueEntry {
+                cue_id,
+                position: sample_start,
+                data_chunk_id: DataChunkId::Data,
+                chunk_start: 0,
+                block_start: 0,
+                sample_start,
+                label: None,
+                note: None,
+                color: None,
+                url: None,
+                image: None,
+                region: None,
+            }
+        })
+        .collect();
+
+    let mut chunks = Vec::new();
+    if options.bext {
+        chunks.push(Chunk::new(b"bext", bext_chunk(&header)));
+    }
+    chunks.push(Chunk::new(b"fmt ", fmt_chunk(&header)));
+    chunks.push(Chunk::new(b"data", audio(&header, frames)));
+    if !cues.is_empty() {
+        let labels: Vec<(u32, String)> = cues
+            .iter()
+            .map(|cue| (cue.cue_id, format!("Marker {}", cue.cue_id)))
+            .collect();
+        chunks.push(Chunk::new(b"cue ", cue_chunk(&cues)));
+        chunks.push(Chunk::new(b"LIST", adtl::build_labels(&labels)));
+    }
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    riff::write_riff(&mut Cursor::new(Vec::new()), &mut writer, &chunks)?;
+    writer.flush()?;
+    Ok(())
+}
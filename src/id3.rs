@@ -0,0 +1,372 @@
+// ID3v2 tag that DJ and conversion tools embed in an id3 chunk, with the
+// text frames (TIT2 title, TPE1 artist, TALB album...) and CHAP chapters.
+// https://id3.org/id3v2.4.0-structure, https://id3.org/id3v2-chapters-1.0
+//   0    "ID3", version 1, revision 1, flags 1, size 4 (syncsafe)
+//   10   extended header if flagged, then frames: id 4, size 4 (syncsafe
+//        from version 4), flags 2
+// Text frames start with the encoding: 0 ISO-8859-1, 1 UTF-16 with BOM,
+// 2 UTF-16BE, 3 UTF-8. CHAP holds an element id, the start and end time
+// in milliseconds, byte offsets and sub-frames such as TIT2, WXXX for a
+// link and APIC for an image. CTOC lists the element ids of the chapters
+// in order.
+use crate::{Error, WaveError};
+
+const HEADER_SIZE: usize = 10;
+const UNSYNCHRONISATION: u8 = 0x80;
+const EXTENDED_HEADER: u8 = 0x40;
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Chapter {
+    pub element_id: String,
+    pub start_ms: u32,
+    pub end_ms: u32,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    // an image linked with the "-->" MIME type, not an embedded one
+    pub image: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tag {
+    // 3 or 4
+    pub version: u8,
+    // text frames by id, TXXX left out
+    pub texts: Vec<(String, String)>,
+    pub chapters: Vec<Chapter>,
+}
+
+impl Tag {
+    pub fn text(&self, id: &str) -> Option<&str> {
+        self.texts
+            .iter()
+            .find(|(frame, _)| frame == id)
+            .map(|(_, text)| text.as_str())
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::from(WaveError {
+        message: format!("id3 chunk: {message}"),
+    })
+}
+
+fn syncsafe(bytes: &[u8; 4]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |size, byte| (size << 7) | (*byte & 0x7f) as usize)
+}
+
+// Drops the zero byte stuffed after each 0xFF
+fn resynchronise(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    for (index, byte) in data.iter().enumerate() {
+        if *byte == 0 && index > 0 && data[index - 1] == 0xff {
+            continue;
+        }
+        output.push(*byte);
+    }
+    output
+}
+
+fn utf16(data: &[u8], big_endian: bool) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|unit| match big_endian {
+            true => u16::from_be_bytes([unit[0], unit[1]]),
+            false => u16::from_le_bytes([unit[0], unit[1]]),
+        })
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+// The text of a text frame; the values of a list are separated with /
+fn text(data: &[u8]) -> String {
+    let (encoding, data) = match data.split_first() {
+        Some((encoding, data)) => (*encoding, data),
+        None => return String::new(),
+    };
+    let text = match encoding {
+        0 => data.iter().map(|byte| *byte as char).collect(),
+        1 => match data {
+            [0xfe, 0xff, rest @ ..] => utf16(rest, true),
+            [0xff, 0xfe, rest @ ..] => utf16(rest, false),
+            _ => utf16(data, false),
+        },
+        2 => utf16(data, true),
+        _ => String::from_utf8_lossy(data).to_string(),
+    };
+    text.trim_end_matches('\0')
+        .replace('\0', "/")
+        .replace(['\u{feff}', '\u{fffe}'], "")
+}
+
+// Frames of the tag body as id and payload
+fn frames(data: &[u8], version: u8) -> Vec<([u8; 4], Vec<u8>)> {
+    let mut frames = Vec::new();
+    let mut ofs = 0;
+    while ofs + HEADER_SIZE <= data.len() {
+        let id = *array_ref!(data, ofs, 4);
+        // padding after the last frame
+        if id[0] == 0 {
+            break;
+        }
+        let size = match version {
+            4 => syncsafe(array_ref!(data, ofs + 4, 4)),
+            _ => u32::from_be_bytes(*array_ref!(data, ofs + 4, 4)) as usize,
+        };
+        let flags = data[ofs + 9];
+        let start = ofs + HEADER_SIZE;
+        let end = match start.checked_add(size) {
+            Some(end) if end <= data.len() => end,
+            _ => break,
+        };
+        let mut payload = data[start..end].to_vec();
+        if version == 4 {
+            // a data length indicator goes before the payload
+            if flags & 0x01 != 0 && payload.len() >= 4 {
+                payload.drain(..4);
+            }
+            if flags & 0x02 != 0 {
+                payload = resynchronise(&payload);
+            }
+        }
+        frames.push((id, payload));
+        ofs = end;
+    }
+    frames
+}
+
+// The string up to the terminator of the encoding, and the rest
+fn terminated(data: &[u8], encoding: u8) -> (&[u8], &[u8]) {
+    let end = match encoding {
+        1 | 2 => (0..data.len() / 2)
+            .map(|unit| unit * 2)
+            .find(|ofs| data[*ofs] == 0 && data[ofs + 1] == 0)
+            .map(|ofs| (ofs, ofs + 2)),
+        _ => data
+            .iter()
+            .position(|byte| *byte == 0)
+            .map(|ofs| (ofs, ofs + 1)),
+    };
+    match end {
+        Some((end, rest)) => (&data[..end], &data[rest..]),
+        None => (data, &[]),
+    }
+}
+
+// WXXX: encoding, description, then the URL in ISO-8859-1
+fn link(payload: &[u8]) -> Option<String> {
+    let (encoding, data) = payload.split_first()?;
+    let (_, url) = terminated(data, *encoding);
+    Some(url.iter().map(|byte| *byte as char).collect())
+}
+
+// APIC: encoding, MIME type, picture type, description, then the picture,
+// or its URL when the MIME type is "-->"
+fn linked_image(payload: &[u8]) -> Option<String> {
+    let (encoding, data) = payload.split_first()?;
+    let (mime_type, data) = terminated(data, 0);
+    if mime_type != b"-->" {
+        return None;
+    }
+    let (_, url) = terminated(data.get(1..)?, *encoding);
+    Some(url.iter().map(|byte| *byte as char).collect())
+}
+
+fn chapter(payload: &[u8], version: u8) -> Option<Chapter> {
+    let end = payload.iter().position(|byte| *byte == 0)?;
+    let times = payload.get(end + 1..end + 17)?;
+    let mut chapter = Chapter {
+        element_id: String::from_utf8_lossy(&payload[..end]).to_string(),
+        start_ms: u32::from_be_bytes(*array_ref!(times, 0, 4)),
+        end_ms: u32::from_be_bytes(*array_ref!(times, 4, 4)),
+        ..Chapter::default()
+    };
+    for (id, payload) in frames(&payload[end + 17..], version) {
+        match &id {
+            b"TIT2" => chapter.title = Some(text(&payload)),
+            b"WXXX" => chapter.url = link(&payload),
+            b"APIC" => chapter.image = linked_image(&payload),
+            _ => (),
+        }
+    }
+    Some(chapter)
+}
+
+pub fn parse(data: &[u8]) -> Result<Tag, Error> {
+    if data.len() < HEADER_SIZE || &data[0..3] != b"ID3" {
+        return Err(invalid(String::from("no ID3v2 header")));
+    }
+    let version = data[3];
+    if version != 3 && version != 4 {
+        return Err(invalid(format!("ID3v2.{version} is not supported")));
+    }
+    let flags = data[5];
+    let size = syncsafe(array_ref!(data, 6, 4));
+    let body = &data[HEADER_SIZE..data.len().min(HEADER_SIZE + size)];
+    let mut body = match version {
+        3 if flags & UNSYNCHRONISATION != 0 => resynchronise(body),
+        _ => body.to_vec(),
+    };
+    if flags & EXTENDED_HEADER != 0 && body.len() >= 4 {
+        // the size counts itself in version 4, not in version 3
+        let extended = match version {
+            4 => syncsafe(array_ref!(body, 0, 4)),
+            _ => u32::from_be_bytes(*array_ref!(body, 0, 4)) as usize + 4,
+        };
+        body.drain(..extended.min(body.len()));
+    }
+    let mut tag = Tag {
+        version,
+        ..Tag::default()
+    };
+    for (id, payload) in frames(&body, version) {
+        match &id {
+            b"TXXX" => (),
+            b"CHAP" => tag.chapters.extend(chapter(&payload, version)),
+            [b'T', ..] => tag
+                .texts
+                .push((String::from_utf8_lossy(&id).to_string(), text(&payload))),
+            _ => (),
+        }
+    }
+    Ok(tag)
+}
+
+fn to_syncsafe(size: usize) -> [u8; 4] {
+    let size = size.min(0x0fff_ffff);
+    [21, 14, 7, 0].map(|shift| (size >> shift) as u8 & 0x7f)
+}
+
+// An ID3v2.4 frame without flags
+fn frame(id: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut data = id.to_vec();
+    data.extend_from_slice(&to_syncsafe(payload.len()));
+    data.extend_from_slice(&[0, 0]);
+    data.extend_from_slice(payload);
+    data
+}
+
+fn text_frame(id: &[u8; 4], text: &str) -> Vec<u8> {
+    let mut payload = vec![3];
+    payload.extend_from_slice(text.as_bytes());
+    frame(id, &payload)
+}
+
+// URLs are ISO-8859-1, so other characters are left as ?
+fn latin1(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|char| match char as u32 {
+            0..=0xff => char as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+// An ID3v2.4 tag with the title, a table of contents and a chapter frame
+// for each chapter. Images are linked rather than embedded. The table of
+// contents can list 255 chapters, so chapters past those are left out of
+// it but kept as frames.
+pub fn chapters_tag(title: Option<&str>, chapters: &[Chapter]) -> Vec<u8> {
+    let mut body = Vec::new();
+    if let Some(title) = title {
+        body.extend(text_frame(b"TIT2", title));
+    }
+    // top level and ordered, then the entry count
+    let listed = &chapters[..chapters.len().min(255)];
+    let mut toc = b"toc\0".to_vec();
+    toc.extend_from_slice(&[0x03, listed.len() as u8]);
+    for chapter in listed {
+        toc.extend_from_slice(chapter.element_id.as_bytes());
+        toc.push(0);
+    }
+    body.extend(frame(b"CTOC", &toc));
+    for chapter in chapters {
+        let mut payload = chapter.element_id.as_bytes().to_vec();
+        payload.push(0);
+        payload.extend_from_slice(&chapter.start_ms.to_be_bytes());
+        payload.extend_from_slice(&chapter.end_ms.to_be_bytes());
+        // no byte offsets
+        payload.extend_from_slice(&[0xff; 8]);
+        if let Some(title) = &chapter.title {
+            payload.extend(text_frame(b"TIT2", title));
+        }
+        if let Some(url) = &chapter.url {
+            let mut link = vec![0, 0];
+            link.extend(latin1(url));
+            payload.extend(frame(b"WXXX", &link));
+        }
+        if let Some(image) = &chapter.image {
+            // the "other" picture type and no description
+            let mut picture = b"\0-->\0\0\0".to_vec();
+            picture.extend(latin1(image));
+            payload.extend(frame(b"APIC", &picture));
+        }
+        body.extend(frame(b"CHAP", &payload));
+    }
+    let mut tag = b"ID3\x04\0\0".to_vec();
+    tag.extend_from_slice(&to_syncsafe(body.len()));
+    tag.extend(body);
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(element_id: &str, start_ms: u32, end_ms: u32) -> Chapter {
+        Chapter {
+            element_id: element_id.to_string(),
+            start_ms,
+            end_ms,
+            ..Chapter::default()
+        }
+    }
+
+    #[test]
+    fn writes_syncsafe_sizes() {
+        assert_eq!(to_syncsafe(0x7f), [0, 0, 0, 0x7f]);
+        assert_eq!(to_syncsafe(0x80), [0, 0, 1, 0]);
+        assert_eq!(syncsafe(&to_syncsafe(0x0abc_def0)), 0x0abc_def0);
+    }
+
+    #[test]
+    fn round_trips_chapters() {
+        let chapters = vec![
+            Chapter {
+                title: Some(String::from("Intro – Café")),
+                url: Some(String::from("https://example.com/intro")),
+                image: Some(String::from("https://example.com/intro.jpg")),
+                ..chapter("chp0", 0, 1500)
+            },
+            chapter("chp1", 1500, 90_000),
+        ];
+        let data = chapters_tag(Some("Episode 1"), &chapters);
+        let tag = parse(&data).unwrap();
+        assert_eq!(tag.version, 4);
+        assert_eq!(tag.text("TIT2"), Some("Episode 1"));
+        assert_eq!(tag.chapters.len(), 2);
+        let first = &tag.chapters[0];
+        assert_eq!(first.element_id, "chp0");
+        assert_eq!((first.start_ms, first.end_ms), (0, 1500));
+        assert_eq!(first.title.as_deref(), Some("Intro – Café"));
+        assert_eq!(first.url.as_deref(), Some("https://example.com/intro"));
+        assert_eq!(
+            first.image.as_deref(),
+            Some("https://example.com/intro.jpg")
+        );
+        let second = &tag.chapters[1];
+        assert_eq!((second.start_ms, second.end_ms), (1500, 90_000));
+        assert!(second.title.is_none() && second.url.is_none() && second.image.is_none());
+    }
+
+    #[test]
+    fn lists_the_chapters_in_the_table_of_contents() {
+        let data = chapters_tag(None, &[chapter("chp0", 0, 1), chapter("chp1", 1, 2)]);
+        let (id, toc) = &frames(&data[HEADER_SIZE..], 4)[0];
+        assert_eq!(id, b"CTOC");
+        assert_eq!(toc, b"toc\0\x03\x02chp0\0chp1\0");
+    }
+}
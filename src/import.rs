@@ -0,0 +1,409 @@
+// Markers read from the files of other applications, to be written into
+// the cue and adtl chunks
+use crate::timecode::{FrameRate, Timecode};
+use crate::xml::{self, Element};
+use crate::{Error, WaveError};
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Marker {
+    pub sample_start: u64,
+    pub sample_length: u64,
+    pub name: String,
+    pub comment: String,
+}
+
+fn error(message: String) -> Error {
+    Error::from(WaveError { message })
+}
+
+// Exports are often UTF-16 with a byte order mark
+pub fn decode_text(data: &[u8]) -> String {
+    if let Some(data) = data.strip_prefix(&[0xff, 0xfe]) {
+        let units: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else if let Some(data) = data.strip_prefix(&[0xfe, 0xff]) {
+        let units: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        let data = data.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(data);
+        String::from_utf8_lossy(data).to_string()
+    }
+}
+
+// Records of RFC 4180 CSV: fields in double quotes can hold the
+// separator, line breaks and quotes doubled. Blank lines are skipped.
+pub fn csv_records(text: &str, separator: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            // a quote inside an unquoted field is taken as it is
+            '"' if field.is_empty() => quoted = true,
+            _ if quoted => field.push(ch),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\r' | '\n' => {
+                record.push(std::mem::take(&mut field));
+                if record.len() > 1 || !record[0].trim().is_empty() {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+            }
+            _ if ch == separator => record.push(std::mem::take(&mut field)),
+            _ => field.push(ch),
+        }
+    }
+    record.push(field);
+    if record.len() > 1 || !record[0].trim().is_empty() {
+        records.push(record);
+    }
+    records
+}
+
+fn timecode_samples(text: &str, frame_rate: &FrameRate, sampling_rate: u32) -> Result<u64, Error> {
+    match Timecode::parse(text.trim()) {
+        Some(timecode) if frame_rate.is_valid(&timecode) => {
+            Ok(frame_rate.timecode_to_samples(&timecode, sampling_rate))
+        }
+        _ => Err(error(format!("Invalid timecode: {text}"))),
+    }
+}
+
+// Marker list exported from Premiere Pro (File > Export > Markers as CSV):
+// tab separated columns Marker Name, Description, In, Out, Duration and
+// Marker Type, with the times as timecode of the sequence
+pub fn premiere_csv(
+    text: &str,
+    frame_rate: &FrameRate,
+    sampling_rate: u32,
+) -> Result<Vec<Marker>, Error> {
+    let first_line = text.lines().find(|line| !line.trim().is_empty());
+    let separator = match first_line {
+        Some(line) if !line.contains('\t') => ',',
+        _ => '\t',
+    };
+    let mut records = csv_records(text, separator).into_iter();
+    let columns: Vec<String> = match records.next() {
+        Some(header) => header
+            .iter()
+            .map(|column| column.trim().to_lowercase())
+            .collect(),
+        None => return Ok(Vec::new()),
+    };
+    let column = |name: &str| columns.iter().position(|column| column == name);
+    let (name, description, start, duration) = match (
+        column("marker name"),
+        column("description"),
+        column("in"),
+        column("duration"),
+    ) {
+        (Some(name), description, Some(start), duration) => (name, description, start, duration),
+        _ => {
+            return Err(error(String::from(
+                "Not a Premiere marker list (no Marker Name and In columns)",
+            )))
+        }
+    };
+    let mut markers = Vec::new();
+    for fields in records {
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|index| fields.get(index))
+                .map_or("", |field| field.trim())
+        };
+        let sample_length = match field(duration) {
+            "" => 0,
+            duration => timecode_samples(duration, frame_rate, sampling_rate)?,
+        };
+        markers.push(Marker {
+            sample_start: timecode_samples(field(Some(start)), frame_rate, sampling_rate)?,
+            sample_length,
+            name: field(Some(name)).to_string(),
+            comment: field(description).to_string(),
+        });
+    }
+    Ok(markers)
+}
+
+fn find<'a>(element: &'a Element, name: &str) -> Option<&'a Element> {
+    if element.name == name {
+        return Some(element);
+    }
+    element.children.iter().find_map(|child| find(child, name))
+}
+
+// Frame rate of a rate element: timebase 30 with ntsc TRUE is 29.97
+fn xml_frame_rate(rate: &Element) -> Option<FrameRate> {
+    let timebase: u64 = rate.child_text("timebase")?.parse().ok()?;
+    let ntsc = rate
+        .child_text("ntsc")
+        .map(|ntsc| ntsc.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    Some(FrameRate {
+        numerator: if ntsc { timebase * 1000 } else { timebase },
+        denominator: if ntsc { 1001 } else { 1 },
+        drop_frame: false,
+    })
+}
+
+// Sequence markers of a Final Cut Pro 7 XML (xmeml) export from Premiere;
+// in and out are frame counts at the rate of the sequence, out is -1 for
+// markers without a duration
+pub fn premiere_xml(text: &str, sampling_rate: u32) -> Result<Vec<Marker>, Error> {
+    let root = xml::parse(text)?;
+    let sequence = match find(&root, "sequence") {
+        Some(sequence) => sequence,
+        None => return Err(error(String::from("No sequence found in the XML"))),
+    };
+    let frame_rate = match sequence.child("rate").and_then(xml_frame_rate) {
+        Some(frame_rate) => frame_rate,
+        None => return Err(error(String::from("Sequence has no frame rate"))),
+    };
+    let mut markers = Vec::new();
+    for marker in sequence.children_named("marker") {
+        let frames = |name: &str| -> Option<i64> { marker.child_text(name)?.parse().ok() };
+        let start = match frames("in") {
+            Some(start) if start >= 0 => start as u64,
+            _ => return Err(error(String::from("Marker without a valid in point"))),
+        };
+        let end = frames("out").filter(|end| *end > start as i64);
+        let sample_start = frame_rate.frames_to_samples(start, sampling_rate);
+        markers.push(Marker {
+            sample_start,
+            sample_length: end.map_or(0, |end| {
+                frame_rate.frames_to_samples(end as u64, sampling_rate) - sample_start
+            }),
+            name: marker.child_text("name").unwrap_or("").to_string(),
+            comment: marker.child_text("comment").unwrap_or("").to_string(),
+        });
+    }
+    Ok(markers)
+}
+
+fn seconds_samples(text: &str, sampling_rate: u32) -> Result<u64, Error> {
+    match text.trim().parse::<f64>() {
+        Ok(seconds) if seconds >= 0.0 && seconds.is_finite() => {
+            Ok((seconds * sampling_rate as f64).round() as u64)
+        }
+        _ => Err(error(format!("Invalid time in seconds: {text}"))),
+    }
+}
+
+// The time of day wav-cue appends to the labels in its CSV, such as
+// " 12:23:42" or " 1:32:24", the hours counting on past a day
+fn strip_time_of_day(label: &str) -> &str {
+    let (rest, time) = match label.rsplit_once(' ') {
+        Some(split) => split,
+        None => return label,
+    };
+    let fields: Vec<&str> = time.split(':').collect();
+    let is_time = match fields[..] {
+        [hours, minutes, seconds] => {
+            !hours.is_empty()
+                && minutes.len() == 2
+                && seconds.len() == 2
+                && fields
+                    .iter()
+                    .all(|field| field.bytes().all(|byte| byte.is_ascii_digit()))
+        }
+        _ => false,
+    };
+    if is_time {
+        rest
+    } else {
+        label
+    }
+}
+
+// The CSV written by wav-cue: seconds and label, or a header line naming
+// the columns, such as seconds,end,label. Unless there is a wall_clock
+// column, the time of day is appended to the label. Labels are quoted as
+// RFC 4180 has it; in older files they weren't, so the fields past the
+// last column belong to the label. An end column gives the end of a
+// region, and columns named note, url and image make up the note of the
+// marker.
+pub fn csv(text: &str, sampling_rate: u32) -> Result<Vec<Marker>, Error> {
+    let mut records = csv_records(text, ',').into_iter().peekable();
+    let mut columns = vec![String::from("seconds"), String::from("label")];
+    if let Some(header) = records.peek() {
+        if header.len() > 1 && header[0].trim() == "seconds" {
+            columns = header
+                .iter()
+                .map(|column| column.trim().to_lowercase())
+                .collect();
+            records.next();
+        }
+    }
+    let column = |name: &str| columns.iter().position(|column| column == name);
+    let (note, url, image) = (column("note"), column("url"), column("image"));
+    let label_column = column("label").unwrap_or(columns.len() - 1);
+    let has_time_of_day = column("wall_clock").is_none();
+    let mut markers = Vec::new();
+    for mut fields in records {
+        if label_column == columns.len() - 1 && fields.len() > columns.len() {
+            let label = fields.split_off(label_column).join(",");
+            fields.push(label);
+        }
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|index| fields.get(index))
+                .map_or("", |field| field.trim())
+        };
+        let mut comment = vec![field(note).to_string()];
+        if !field(url).is_empty() {
+            comment.push(format!("url={}", field(url)));
+        }
+        if !field(image).is_empty() {
+            comment.push(format!("image={}", field(image)));
+        }
+        let label = field(Some(label_column));
+        let sample_start = seconds_samples(&fields[0], sampling_rate)?;
+        let sample_end = match field(column("end")) {
+            "" => sample_start,
+            end => seconds_samples(end, sampling_rate)?,
+        };
+        markers.push(Marker {
+            sample_start,
+            sample_length: sample_end.saturating_sub(sample_start),
+            name: match has_time_of_day {
+                true => strip_time_of_day(label).to_string(),
+                false => label.to_string(),
+            },
+            comment: comment.join(" ").trim().to_string(),
+        });
+    }
+    Ok(markers)
+}
+
+// Audacity label track export: tab separated start, end and label in
+// seconds, with point labels having the same start and end. Lines
+// starting with a backslash carry the frequency range of the label.
+pub fn audacity_labels(text: &str, sampling_rate: u32) -> Result<Vec<Marker>, Error> {
+    let mut markers = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() || line.starts_with('\\') {
+            continue;
+        }
+        let fields: Vec<&str> = line.splitn(3, '\t').collect();
+        if fields.len() < 2 {
+            return Err(error(format!("Not an Audacity label: {line}")));
+        }
+        let sample_start = seconds_samples(fields[0], sampling_rate)?;
+        let sample_end = seconds_samples(fields[1], sampling_rate)?;
+        markers.push(Marker {
+            sample_start,
+            sample_length: sample_end.saturating_sub(sample_start),
+            name: fields.get(2).unwrap_or(&"").trim().to_string(),
+            comment: String::new(),
+        });
+    }
+    Ok(markers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(markers: &[Marker]) -> Vec<&str> {
+        markers.iter().map(|marker| marker.name.as_str()).collect()
+    }
+
+    #[test]
+    fn reads_quoted_fields() {
+        let text = "a,\"b, c\",\"say \"\"hi\"\"\"\r\n\r\n1,\"two\nlines\",\n";
+        assert_eq!(
+            csv_records(text, ','),
+            [vec!["a", "b, c", "say \"hi\""], vec!["1", "two\nlines", ""]]
+        );
+        assert_eq!(csv_records("x\ty\t\"z\"", '\t'), [vec!["x", "y", "z"]]);
+        // a quote that doesn't start the field is kept
+        assert_eq!(csv_records("5\" floppy,b", ','), [vec!["5\" floppy", "b"]]);
+        assert!(csv_records("\n \n", ',').is_empty());
+    }
+
+    #[test]
+    fn strips_the_time_of_day() {
+        assert_eq!(strip_time_of_day("Marker 1 12:23:42"), "Marker 1");
+        assert_eq!(strip_time_of_day("Marker 1 1:32:24"), "Marker 1");
+        assert_eq!(strip_time_of_day("Marker 1 25:00:01"), "Marker 1");
+        assert_eq!(strip_time_of_day("Take 1:2:03"), "Take 1:2:03");
+        assert_eq!(strip_time_of_day("12:23:42"), "12:23:42");
+        assert_eq!(strip_time_of_day("Intro"), "Intro");
+    }
+
+    #[test]
+    fn reads_the_exported_csv() {
+        let text =
+            "1.5,Marker 1 1:32:24\n2.000,\"Verse, part 2 1:32:25\"\n3,Old, unquoted 1:32:26\n";
+        let markers = csv(text, 1000).unwrap();
+        assert_eq!(
+            names(&markers),
+            ["Marker 1", "Verse, part 2", "Old, unquoted"]
+        );
+        assert_eq!(markers[0].sample_start, 1500);
+        assert_eq!(markers[0].sample_length, 0);
+    }
+
+    #[test]
+    fn reads_the_columns_of_the_header() {
+        let text = "seconds,end,label\n1,2.5,\"Intro \"\"live\"\" 10:00:01\"\n3,,Outro\n";
+        let markers = csv(text, 100).unwrap();
+        assert_eq!(names(&markers), ["Intro \"live\"", "Outro"]);
+        assert_eq!(markers[0].sample_length, 150);
+        assert_eq!(markers[1].sample_length, 0);
+        // the time of day has its own column
+        let text = "seconds,wall_clock,timecode,label\n1,10:00:01,,Take 10:00:01\n";
+        assert_eq!(names(&csv(text, 100).unwrap()), ["Take 10:00:01"]);
+        let text = "seconds,label,note,url,image\n1,Intro,First,https://example.com,a.png\n";
+        assert_eq!(
+            csv(text, 100).unwrap()[0].comment,
+            "First url=https://example.com image=a.png"
+        );
+        assert!(csv("x,Intro\n", 100).is_err());
+    }
+
+    #[test]
+    fn reads_premiere_marker_lists() {
+        let frame_rate = FrameRate::parse("25").unwrap();
+        let text = "Marker Name\tDescription\tIn\tOut\tDuration\tMarker Type\n\
+                    Intro\tStart\t00:00:01:00\t00:00:02:00\t00:00:01:00\tComment\n";
+        let markers = premiere_csv(text, &frame_rate, 48000).unwrap();
+        assert_eq!(names(&markers), ["Intro"]);
+        assert_eq!(markers[0].comment, "Start");
+        assert_eq!(
+            (markers[0].sample_start, markers[0].sample_length),
+            (48000, 48000)
+        );
+        let text = "\"Marker Name\",\"Description\",\"In\"\n\"Verse, 2\",\"\",\"00:00:00:12\"\n";
+        let markers = premiere_csv(text, &frame_rate, 48000).unwrap();
+        assert_eq!(names(&markers), ["Verse, 2"]);
+        assert_eq!(markers[0].sample_start, 23040);
+        assert!(premiere_csv("Name,Start\n", &frame_rate, 48000).is_err());
+    }
+
+    #[test]
+    fn reads_audacity_labels() {
+        let text = "1.000000\t2.500000\tIntro\n\\\t100.0\t2000.0\n3\t3\tPoint\n";
+        let markers = audacity_labels(text, 10).unwrap();
+        assert_eq!(names(&markers), ["Intro", "Point"]);
+        assert_eq!(markers[0].sample_length, 15);
+        assert_eq!(markers[1].sample_length, 0);
+    }
+}
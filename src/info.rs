@@ -0,0 +1,74 @@
+// INFO list of RIFF files, where consumer tools look for the title and
+// artist: sub-chunks with a NUL terminated text each, keyed by their id.
+// https://www.recordingblogs.com/wiki/list-chunk-of-a-wave-file
+use crate::riff::{self, ListItem};
+use crate::Error;
+
+// The common ids with what they hold
+pub const INFO_FIELDS: [(&[u8; 4], &str); 18] = [
+    (b"INAM", "title"),
+    (b"IART", "artist"),
+    (b"IPRD", "album"),
+    (b"ITRK", "track number"),
+    (b"IGNR", "genre"),
+    (b"ICMT", "comment"),
+    (b"ICRD", "creation date"),
+    (b"ICOP", "copyright"),
+    (b"IENG", "engineer"),
+    (b"ITCH", "technician"),
+    (b"ISFT", "software"),
+    (b"ISRC", "source"),
+    (b"ISBJ", "subject"),
+    (b"IKEY", "keywords"),
+    (b"IMED", "medium"),
+    (b"ILNG", "language"),
+    (b"ICMS", "commissioned"),
+    (b"ISRF", "source form"),
+];
+
+// What the field with this id holds, for the common ones
+pub fn field_name(id: &[u8; 4]) -> Option<&'static str> {
+    INFO_FIELDS
+        .iter()
+        .find(|(field, _)| *field == id)
+        .map(|(_, name)| *name)
+}
+
+// A sub-chunk id given as text, such as IART: four ASCII letters or digits
+pub fn parse_id(text: &str) -> Option<[u8; 4]> {
+    let bytes = text.as_bytes();
+    if bytes.len() != 4 || !bytes.iter().all(u8::is_ascii_alphanumeric) {
+        return None;
+    }
+    Some(*array_ref!(bytes, 0, 4))
+}
+
+pub fn text(payload: &[u8]) -> String {
+    let end = payload
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(payload.len());
+    String::from_utf8_lossy(&payload[..end]).to_string()
+}
+
+// The fields of an INFO list payload in file order, by id
+pub fn parse(data: &[u8]) -> Result<Vec<(String, String)>, Error> {
+    let (_, items) = riff::parse_list(data)?;
+    Ok(items
+        .iter()
+        .map(|(id, payload)| (String::from_utf8_lossy(id).to_string(), text(payload)))
+        .collect())
+}
+
+// Sets a field, replacing the first one with the id and dropping any
+// others; an empty value removes the field
+pub fn set(items: &mut Vec<ListItem>, id: &[u8; 4], value: &str) {
+    let position = items.iter().position(|(item, _)| item == id);
+    items.retain(|(item, _)| item != id);
+    if value.is_empty() {
+        return;
+    }
+    let mut payload = value.as_bytes().to_vec();
+    payload.push(0);
+    items.insert(position.unwrap_or(items.len()), (*id, payload));
+}
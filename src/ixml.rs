@@ -0,0 +1,94 @@
+// iXML: http://www.gallery.co.uk/ixml/
+// <BWFXML>
+//   <TRACK_LIST>
+//     <TRACK_COUNT>2</TRACK_COUNT>
+//     <TRACK>
+//       <CHANNEL_INDEX>1</CHANNEL_INDEX>
+//       <INTERLEAVE_INDEX>1</INTERLEAVE_INDEX>
+//       <NAME>Boom</NAME>
+//     </TRACK>
+//   ...
+//   </TRACK_LIST>
+//   <SPEED>
+//     <TIMECODE_RATE>30000/1001</TIMECODE_RATE>
+//     <TIMECODE_FLAG>DF</TIMECODE_FLAG>
+//   </SPEED>
+use crate::timecode::FrameRate;
+use crate::xml;
+use crate::Error;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Track {
+    pub channel_index: u32,
+    // Position of the track in the interleaved audio, 0 if not recorded
+    pub interleave_index: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IXml {
+    pub tracks: Vec<Track>,
+    pub frame_rate: Option<FrameRate>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Channel {
+    pub index: u32,
+    pub name: String,
+    pub armed: bool,
+}
+
+pub fn parse(data: &[u8]) -> Result<IXml, Error> {
+    let root = xml::parse(&String::from_utf8_lossy(data))?;
+    let mut ixml = IXml::default();
+    if let Some(track_list) = root.child("TRACK_LIST") {
+        for track in track_list.children_named("TRACK") {
+            let number = |name: &str| {
+                track
+                    .child_text(name)
+                    .and_then(|text| text.parse().ok())
+                    .unwrap_or(0)
+            };
+            ixml.tracks.push(Track {
+                channel_index: number("CHANNEL_INDEX"),
+                interleave_index: number("INTERLEAVE_INDEX"),
+                name: track.child_text("NAME").unwrap_or_default().to_string(),
+            });
+        }
+    }
+    if let Some(speed) = root.child("SPEED") {
+        let drop_frame = speed.child_text("TIMECODE_FLAG") == Some("DF");
+        ixml.frame_rate = speed
+            .child_text("TIMECODE_RATE")
+            .and_then(|rate| FrameRate::from_ratio(rate, drop_frame));
+    }
+    Ok(ixml)
+}
+
+impl IXml {
+    // Tracks by the channel they're interleaved to. Recorders such as the
+    // Zoom F-series list disarmed tracks too, without a channel in the file.
+    pub fn channels(&self, number_of_channels: u16) -> Vec<Channel> {
+        let mut channels: Vec<Channel> = (1..=number_of_channels as u32)
+            .map(|index| Channel {
+                index,
+                name: String::new(),
+                armed: true,
+            })
+            .collect();
+        for track in &self.tracks {
+            match channels.get_mut((track.interleave_index as usize).wrapping_sub(1)) {
+                Some(channel) => channel.name = track.name.clone(),
+                None => channels.push(Channel {
+                    index: track.channel_index,
+                    name: track.name.clone(),
+                    armed: false,
+                }),
+            }
+        }
+        channels
+    }
+}
@@ -0,0 +1,262 @@
+// Joins files one after the other into one file, such as the parts a
+// recorder splits a long take into, keeping the cue points of all of them
+use crate::adtl::Region;
+use crate::bext;
+use crate::metadata::WaveMetadata;
+use crate::riff::{self, ChunkInfo};
+use crate::{cue_chunk, CueEntry, Error, WaveError};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// A file to join: its metadata, the raw fmt chunk and where its audio is
+struct Part {
+    filename: String,
+    metadata: WaveMetadata,
+    fmt: Vec<u8>,
+    data: ChunkInfo,
+}
+
+fn read_part(filename: &str) -> Result<Part, Error> {
+    let metadata = WaveMetadata::read_from(Path::new(filename))?;
+    let mut reader = BufReader::new(File::open(filename)?);
+    let infos = riff::walk_chunks(&mut reader)?;
+    let fmt = match infos.iter().find(|info| &info.id == b"fmt ") {
+        Some(info) => riff::read_payload(&mut reader, info)?,
+        None => Vec::new(),
+    };
+    let data = match infos.into_iter().find(|info| &info.id == b"data") {
+        Some(data) => data,
+        None => {
+            return Err(Error::from(WaveError {
+                message: format!("{filename} has no data chunk"),
+            }))
+        }
+    };
+    Ok(Part {
+        filename: filename.to_string(),
+        metadata,
+        fmt,
+        data,
+    })
+}
+
+// The audio can only be appended as it is if it's in the same format
+fn check_compatible(first: &Part, part: &Part) -> Result<(), Error> {
+    let header = &part.metadata.header;
+    if !header.compression_code.is_linear() {
+        return Err(Error::from(WaveError {
+            message: format!("{} is not PCM audio", part.filename),
+        }));
+    }
+    if part.fmt != first.fmt {
+        let describe = |part: &Part| {
+            let header = &part.metadata.header;
+            format!(
+                "{} Hz, {} bits, {} channels",
+                header.sampling_rate, header.significant_bits_per_sample, header.number_of_channels
+            )
+        };
+        return Err(Error::from(WaveError {
+            message: format!(
+                "{} ({}) doesn't have the format of {} ({})",
+                part.filename,
+                describe(part),
+                first.filename,
+                describe(first)
+            ),
+        }));
+    }
+    Ok(())
+}
+
+fn write_chunk<W: Write>(writer: &mut W, id: &[u8; 4], payload: &[u8]) -> Result<(), Error> {
+    writer.write_all(id)?;
+    writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+    writer.write_all(payload)?;
+    if payload.len() % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+    Ok(())
+}
+
+// Joins the files into output. The cue points of each file are moved by
+// the length of the files before it and numbered anew, with their labels,
+// notes and regions; bext, INFO and iXML are those of the first file.
+// Returns the metadata written.
+pub fn join(filenames: &[String], output: &Path) -> Result<WaveMetadata, Error> {
+    let parts = filenames
+        .iter()
+        .map(|filename| read_part(filename))
+        .collect::<Result<Vec<Part>, Error>>()?;
+    let first = match parts.first() {
+        Some(first) => first,
+        None => {
+            return Err(Error::from(WaveError {
+                message: String::from("No files to join"),
+            }))
+        }
+    };
+    for part in &parts {
+        check_compatible(first, part)?;
+        // the output is written while the parts are read
+        if output.exists() && output.canonicalize()? == Path::new(&part.filename).canonicalize()? {
+            return Err(Error::from(WaveError {
+                message: format!("{} is one of the files to join", output.display()),
+            }));
+        }
+    }
+    let block_align = first.metadata.header.block_align.max(1) as u64;
+    let data_size: u64 = parts.iter().map(|part| part.data.size).sum();
+    // cue points are 32 bit sample positions
+    if data_size / block_align > u32::MAX as u64 {
+        return Err(Error::from(WaveError {
+            message: String::from("The joined audio is too long for cue points"),
+        }));
+    }
+    let fmt = first.fmt.clone();
+    let sources: Vec<(String, ChunkInfo)> = parts
+        .iter()
+        .map(|part| (part.filename.clone(), part.data.clone()))
+        .collect();
+
+    let mut joined: Option<WaveMetadata> = None;
+    let mut offset = 0u64;
+    let mut next_id = 1;
+    for part in parts {
+        let metadata = part.metadata;
+        let filename = &part.filename;
+        // a cue point can be past the end of its file
+        let too_late = || {
+            Error::from(WaveError {
+                message: format!("A cue point of {filename} is too late for the joined file"),
+            })
+        };
+        let part_offset = u32::try_from(offset).map_err(|_| too_late())?;
+        let mut ids = Vec::new();
+        let mut cues = Vec::new();
+        for cue in metadata.cues {
+            ids.push((cue.cue_id, next_id));
+            cues.push(CueEntry {
+                cue_id: next_id,
+                position: cue.position.checked_add(part_offset).ok_or_else(too_late)?,
+                sample_start: cue
+                    .sample_start
+                    .checked_add(part_offset)
+                    .ok_or_else(too_late)?,
+                ..cue
+            });
+            next_id += 1;
+        }
+        let new_id = |cue_id: u32| {
+            ids.iter()
+                .find(|(old, _)| *old == cue_id)
+                .map(|(_, new)| *new)
+        };
+        // labels of cue points the file doesn't have are left out
+        let renumber = |texts: Vec<(u32, String)>| -> Vec<(u32, String)> {
+            texts
+                .into_iter()
+                .filter_map(|(cue_id, text)| new_id(cue_id).map(|cue_id| (cue_id, text)))
+                .collect()
+        };
+        let labels = renumber(metadata.labels);
+        let notes = renumber(metadata.notes);
+        let regions: Vec<Region> = metadata
+            .regions
+            .into_iter()
+            .filter_map(|region| new_id(region.cue_id).map(|cue_id| Region { cue_id, ..region }))
+            .collect();
+        match &mut joined {
+            None => {
+                joined = Some(WaveMetadata {
+                    cues,
+                    labels,
+                    notes,
+                    regions,
+                    ..metadata
+                })
+            }
+            Some(joined) => {
+                joined.cues.extend(cues);
+                joined.labels.extend(labels);
+                joined.notes.extend(notes);
+                joined.regions.extend(regions);
+            }
+        }
+        offset += part.data.size / block_align;
+    }
+    let mut joined = match joined {
+        Some(joined) => joined,
+        None => unreachable!("there is a first file"),
+    };
+    if let (Some(raw), Some(line)) = (
+        &mut joined.bext,
+        bext::coding_history_line(&joined.header, "join"),
+    ) {
+        bext::append_coding_history(raw, &line);
+    }
+
+    // bext first and the other metadata after the audio, as write_to does
+    let adtl = joined.associated_data();
+    let mut before = Vec::new();
+    if let Some(raw) = &joined.bext {
+        before.push((*b"bext", raw.clone()));
+    }
+    before.push((*b"fmt ", fmt));
+    let mut after = Vec::new();
+    if !joined.cues.is_empty() {
+        after.push((*b"cue ", cue_chunk(&joined.cues)));
+    }
+    if !adtl.is_empty() {
+        after.push((*b"LIST", riff::build_list(b"adtl", &adtl)));
+    }
+    if !joined.info.is_empty() {
+        after.push((*b"LIST", riff::build_list(b"INFO", &joined.info)));
+    }
+    if let Some(ixml) = &joined.ixml {
+        after.push((*b"iXML", ixml.as_bytes().to_vec()));
+    }
+    let chunk_size = |size: u64| 8 + size + (size & 1);
+    let riff_size = 4
+        + before
+            .iter()
+            .chain(&after)
+            .map(|(_, payload)| chunk_size(payload.len() as u64))
+            .sum::<u64>()
+        + chunk_size(data_size);
+    if riff_size > u32::MAX as u64 {
+        return Err(Error::from(WaveError {
+            message: format!("Resulting file is too large for RIFF ({riff_size} bytes)"),
+        }));
+    }
+
+    riff::write_file(&output.to_string_lossy(), |writer| {
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(riff_size as u32).to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+        for (id, payload) in &before {
+            write_chunk(writer, id, payload)?;
+        }
+        writer.write_all(b"data")?;
+        writer.write_all(&(data_size as u32).to_le_bytes())?;
+        for (filename, data) in &sources {
+            let mut reader = BufReader::new(File::open(filename)?);
+            reader.seek(SeekFrom::Start(data.offset))?;
+            if io::copy(&mut reader.take(data.size), writer)? != data.size {
+                return Err(Error::from(WaveError {
+                    message: format!("The audio of {filename} is truncated"),
+                }));
+            }
+        }
+        if data_size & 1 == 1 {
+            writer.write_all(&[0])?;
+        }
+        for (id, payload) in &after {
+            write_chunk(writer, id, payload)?;
+        }
+        Ok(())
+    })?;
+    Ok(joined)
+}
@@ -0,0 +1,226 @@
+// levl chunk of EBU Tech 3285 supplement 3, the peak envelope that
+// recorders write so that editors can draw the waveform overview without
+// reading the audio:
+//   0    Version 4, Format 4 (1: 8 bit, 2: 16 bit points), PointsPerValue 4
+//        (1: positive peaks, 2: positive and negative), BlockSize 4 (frames
+//        per peak), PeakChannels 4, NumPeakFrames 4, PosPeakOfPeaks 4 (frame
+//        of the highest sample, 0xFFFFFFFF if unknown), OffsetToPeaks 4
+//        (from the start of the chunk header, 128)
+//   32   Timestamp 28 ("YYYY:MM:DD:hh:mm:ss:uuu"), reserved 60
+//   120  the points: per peak frame, per channel, the positive peak and
+//        the negative one, both as magnitudes
+use crate::datetime::DateTime;
+use crate::pcm;
+use crate::{Error, Header, WaveError};
+use std::io::{Read, Seek};
+
+const HEADER_SIZE: usize = 120;
+const TIMESTAMP_OFFSET: usize = 32;
+const TIMESTAMP_SIZE: usize = 28;
+// the chunk header is counted in OffsetToPeaks
+const OFFSET_TO_PEAKS: u32 = HEADER_SIZE as u32 + 8;
+const UNKNOWN: u32 = 0xffff_ffff;
+pub const FORMAT_8_BIT: u32 = 1;
+pub const FORMAT_16_BIT: u32 = 2;
+pub const DEFAULT_BLOCK_SIZE: u32 = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Levl {
+    pub version: u32,
+    pub format: u32,
+    pub points_per_value: u32,
+    pub block_size: u32,
+    pub channels: u32,
+    pub peak_frames: u32,
+    pub peak_of_peaks: Option<u32>,
+    pub timestamp: String,
+    // peak frame after peak frame, points_per_value per channel
+    pub points: Vec<u16>,
+}
+
+impl Levl {
+    // The point value of a full scale sample: the magnitudes are scaled
+    // like 8 and 16 bit samples
+    pub fn full_scale(&self) -> f64 {
+        match self.format {
+            FORMAT_8_BIT => 127.0,
+            _ => 32767.0,
+        }
+    }
+
+    // Positive peak and, if the chunk has them, negative peak of a channel
+    // in the peak frame, from 0.0 to 1.0
+    pub fn peak(&self, frame: usize, channel: usize) -> Option<(f64, Option<f64>)> {
+        let points = self.points_per_value.max(1) as usize;
+        let index = (frame * self.channels as usize + channel) * points;
+        let values = self.points.get(index..index + points)?;
+        let scale = self.full_scale();
+        Some((
+            values[0] as f64 / scale,
+            values.get(1).map(|value| *value as f64 / scale),
+        ))
+    }
+
+    // Highest point of each channel, from 0.0 to 1.0
+    pub fn channel_peaks(&self) -> Vec<f64> {
+        let mut peaks = vec![0f64; self.channels as usize];
+        let points = self.points_per_value.max(1) as usize;
+        for (index, value) in self.points.iter().enumerate() {
+            if let Some(peak) = peaks.get_mut(index / points % self.channels.max(1) as usize) {
+                *peak = peak.max(*value as f64 / self.full_scale());
+            }
+        }
+        peaks
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::from(WaveError {
+        message: format!("levl chunk: {message}"),
+    })
+}
+
+pub fn parse(data: &[u8]) -> Result<Levl, Error> {
+    if data.len() < HEADER_SIZE {
+        return Err(invalid(format!("too small: {}", data.len())));
+    }
+    let number = |offset: usize| u32::from_le_bytes(*array_ref!(data, offset, 4));
+    let format = number(4);
+    if format != FORMAT_8_BIT && format != FORMAT_16_BIT {
+        return Err(invalid(format!("unknown format {format}")));
+    }
+    let points_per_value = number(8);
+    if points_per_value != 1 && points_per_value != 2 {
+        return Err(invalid(format!("{points_per_value} points per value")));
+    }
+    let channels = number(16);
+    let peak_frames = number(20);
+    let start = (number(28) as usize).saturating_sub(8).max(HEADER_SIZE);
+    let count = peak_frames as usize * channels as usize * points_per_value as usize;
+    let data_points = data.get(start..).unwrap_or_default();
+    // a recorder that stopped early may leave the chunk short
+    let points = match format {
+        FORMAT_8_BIT => data_points
+            .iter()
+            .take(count)
+            .map(|value| *value as u16)
+            .collect(),
+        _ => data_points
+            .chunks_exact(2)
+            .take(count)
+            .map(|value| u16::from_le_bytes([value[0], value[1]]))
+            .collect(),
+    };
+    let timestamp = &data[TIMESTAMP_OFFSET..TIMESTAMP_OFFSET + TIMESTAMP_SIZE];
+    let end = timestamp
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(TIMESTAMP_SIZE);
+    Ok(Levl {
+        version: number(0),
+        format,
+        points_per_value,
+        block_size: number(12),
+        channels,
+        peak_frames,
+        peak_of_peaks: Some(number(24)).filter(|frame| *frame != UNKNOWN),
+        timestamp: String::from_utf8_lossy(&timestamp[..end]).to_string(),
+        points,
+    })
+}
+
+pub fn build(levl: &Levl) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_SIZE + 2 * levl.points.len());
+    for number in [
+        levl.version,
+        levl.format,
+        levl.points_per_value,
+        levl.block_size,
+        levl.channels,
+        levl.peak_frames,
+        levl.peak_of_peaks.unwrap_or(UNKNOWN),
+        OFFSET_TO_PEAKS,
+    ] {
+        data.extend_from_slice(&number.to_le_bytes());
+    }
+    let mut timestamp = levl.timestamp.as_bytes().to_vec();
+    timestamp.resize(TIMESTAMP_SIZE, 0);
+    data.extend_from_slice(&timestamp);
+    data.resize(HEADER_SIZE, 0);
+    for point in &levl.points {
+        match levl.format {
+            FORMAT_8_BIT => data.push(*point as u8),
+            _ => data.extend_from_slice(&point.to_le_bytes()),
+        }
+    }
+    data
+}
+
+// Timestamp field of the time, with 000 milliseconds
+pub fn timestamp(time: &DateTime) -> String {
+    format!(
+        "{:04}:{:02}:{:02}:{:02}:{:02}:{:02}:000",
+        time.year, time.month, time.day, time.hour, time.minute, time.second
+    )
+}
+
+// 16 bit envelope of the data chunk with the positive and negative peaks
+// of each block of frames
+pub fn generate<R: Read + Seek>(
+    reader: &mut R,
+    header: &Header,
+    data_offset: u64,
+    data_size: u64,
+    block_size: u32,
+    time: &DateTime,
+) -> Result<Levl, Error> {
+    let channels = header.number_of_channels as usize;
+    let mut points = Vec::new();
+    // positive and negative peak of each channel in the current block
+    let mut block = vec![(0f64, 0f64); channels];
+    let mut frames_in_block = 0;
+    let mut frame_index = 0u64;
+    let mut peak_of_peaks = (0f64, None);
+    let flush = |block: &mut Vec<(f64, f64)>, points: &mut Vec<u16>| {
+        for (positive, negative) in block.iter_mut() {
+            for value in [*positive, *negative] {
+                points.push((value * 32767.0).round().min(32767.0) as u16);
+            }
+            *positive = 0.0;
+            *negative = 0.0;
+        }
+    };
+    pcm::for_each_frame(reader, header, data_offset, data_size, |frame| {
+        for ((positive, negative), sample) in block.iter_mut().zip(frame) {
+            *positive = positive.max(*sample);
+            *negative = negative.max(-sample);
+            if sample.abs() > peak_of_peaks.0 {
+                peak_of_peaks = (sample.abs(), Some(frame_index));
+            }
+        }
+        frame_index += 1;
+        frames_in_block += 1;
+        if frames_in_block == block_size {
+            flush(&mut block, &mut points);
+            frames_in_block = 0;
+        }
+    })?;
+    if frames_in_block > 0 {
+        flush(&mut block, &mut points);
+    }
+    Ok(Levl {
+        version: 1,
+        format: FORMAT_16_BIT,
+        points_per_value: 2,
+        block_size,
+        channels: channels as u32,
+        peak_frames: (points.len() / (2 * channels.max(1))) as u32,
+        peak_of_peaks: peak_of_peaks
+            .1
+            .filter(|frame| *frame < UNKNOWN as u64)
+            .map(|frame| frame as u32),
+        timestamp: timestamp(time),
+        points,
+    })
+}
@@ -0,0 +1,1356 @@
+#[macro_use]
+extern crate arrayref;
+
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use thiserror::Error;
+
+pub mod acid;
+pub mod adm;
+pub mod adtl;
+pub mod aiff;
+pub mod bext;
+pub mod caf;
+pub mod cart;
+pub mod check;
+pub mod chna;
+pub mod color;
+pub mod config;
+pub mod convert;
+pub mod datetime;
+pub mod decoder;
+pub mod duplicates;
+pub mod duration;
+pub mod files;
+pub mod generate;
+pub mod id3;
+pub mod import;
+mod inflate;
+pub mod info;
+pub mod ixml;
+pub mod join;
+pub mod levl;
+pub mod lint;
+pub mod live;
+mod loudness;
+pub mod mapped;
+pub mod merge;
+pub mod metadata;
+pub mod pcm;
+pub mod plst;
+mod png;
+pub mod project;
+pub mod redact;
+pub mod riff;
+pub mod rifx;
+pub mod sha256;
+pub mod silence;
+pub mod smpl;
+pub mod split;
+pub mod stream;
+pub mod text;
+pub mod thumbnail;
+pub mod timecode;
+pub mod umid;
+pub mod validate;
+pub mod value;
+pub mod xlsx;
+pub mod xml;
+pub mod xmp;
+pub mod zip;
+
+pub use bext::BroadcastAudioExtension;
+
+use bext::BEXT_HEADER_SIZE;
+use color::Color;
+use ixml::IXml;
+use riff::ChunkInfo;
+
+#[derive(Error, Debug)]
+pub struct WaveError {
+    pub message: String,
+}
+
+impl std::fmt::Display for WaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to process: {}", self.message)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    WaveError(#[from] WaveError),
+
+    // #[error(transparent)]
+    // TomlDeError(#[from] toml::de::Error),
+
+    // #[error(transparent)]
+    // TomlSerError(#[from] toml::ser::Error),
+    #[error(transparent)]
+    WavCueError(#[from] WavCueError),
+
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+
+    #[error("Invalid configuration: {}", .0)]
+    ConfigError(String),
+
+    #[error("{}", .0)]
+    UsageError(String),
+    // #[error(transparent)]
+    // AtomicIOError(#[from] atomicwrites::Error<io::Error>),
+
+    // #[error("Cannot determine default host: {}", .0)]
+    // DefaultHostError(String),
+
+    // #[error(transparent)]
+    // RumaIdentifierError(#[from] ruma_identifiers::Error),
+}
+
+// What can be wrong in the structure of a WAV file, for telling apart a
+// broken file from a failing disk
+#[derive(Error, Debug)]
+pub enum WavCueError {
+    #[error("Not a WAV file: expected {}, found {:?}", .expected, String::from_utf8_lossy(.found))]
+    UnexpectedFourcc {
+        expected: &'static str,
+        found: [u8; 4],
+    },
+
+    #[error("{:?} chunk is cut short by the end of the file", String::from_utf8_lossy(.id))]
+    TruncatedChunk { id: [u8; 4] },
+
+    #[error("File has no fmt chunk")]
+    MissingFmt,
+
+    #[error("cue chunk of {size} bytes cannot hold {count} cue points")]
+    BadCueCount { count: u32, size: u64 },
+
+    #[error("Invalid {:?} chunk: {}", String::from_utf8_lossy(.id), .message)]
+    InvalidChunk { id: [u8; 4], message: String },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl WavCueError {
+    // For reads inside a chunk: running out of file means the chunk is
+    // truncated
+    fn truncated(id: [u8; 4]) -> impl FnOnce(io::Error) -> WavCueError {
+        move |error| match error.kind() {
+            io::ErrorKind::UnexpectedEof => WavCueError::TruncatedChunk { id },
+            _ => WavCueError::Io(error),
+        }
+    }
+
+    fn invalid(id: &[u8; 4], message: String) -> WavCueError {
+        WavCueError::InvalidChunk { id: *id, message }
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DataChunkId {
+    Data,
+    Sint,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CueEntry {
+    pub cue_id: u32,
+    pub position: u32,
+    pub data_chunk_id: DataChunkId,
+    pub chunk_start: u32,
+    pub block_start: u32,
+    pub sample_start: u32,
+    // text of the labl and note sub-chunks of the adtl LIST
+    pub label: Option<String>,
+    pub note: Option<String>,
+    pub color: Option<Color>,
+    pub url: Option<String>,
+    pub image: Option<String>,
+    pub region: Option<adtl::Region>,
+}
+
+// The format tag of the fmt chunk, serialized as its number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "u16", into = "u16")
+)]
+pub enum CompressionCode {
+    Pcm,
+    MsAdpcm,
+    IeeeFloat,
+    ALaw,
+    MuLaw,
+    ImaAdpcm,
+    Gsm610,
+    Mpeg,
+    MpegLayer3,
+    Extensible,
+    Other(u16),
+}
+
+impl CompressionCode {
+    pub fn code(&self) -> u16 {
+        match self {
+            CompressionCode::Pcm => 1,
+            CompressionCode::MsAdpcm => 2,
+            CompressionCode::IeeeFloat => 3,
+            CompressionCode::ALaw => 6,
+            CompressionCode::MuLaw => 7,
+            CompressionCode::ImaAdpcm => 0x11,
+            CompressionCode::Gsm610 => 0x31,
+            CompressionCode::Mpeg => 0x50,
+            CompressionCode::MpegLayer3 => 0x55,
+            CompressionCode::Extensible => 0xfffe,
+            CompressionCode::Other(code) => *code,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            CompressionCode::Pcm => String::from("PCM"),
+            CompressionCode::MsAdpcm => String::from("Microsoft ADPCM"),
+            CompressionCode::IeeeFloat => String::from("IEEE float"),
+            CompressionCode::ALaw => String::from("A-law"),
+            CompressionCode::MuLaw => String::from("mu-law"),
+            CompressionCode::ImaAdpcm => String::from("IMA ADPCM"),
+            CompressionCode::Gsm610 => String::from("GSM 6.10"),
+            CompressionCode::Mpeg => String::from("MPEG"),
+            CompressionCode::MpegLayer3 => String::from("MPEG Layer 3"),
+            CompressionCode::Extensible => String::from("extensible"),
+            CompressionCode::Other(0) => String::from("unknown"),
+            CompressionCode::Other(code) => format!("compression code {code}"),
+        }
+    }
+
+    // PCM, IEEE float and WAVE_FORMAT_EXTENSIBLE (taken to be PCM): samples
+    // wav-cue can read, with a whole number of frames in the data chunk
+    pub fn is_linear(&self) -> bool {
+        matches!(
+            self,
+            CompressionCode::Pcm | CompressionCode::IeeeFloat | CompressionCode::Extensible
+        )
+    }
+}
+
+impl From<u16> for CompressionCode {
+    fn from(code: u16) -> CompressionCode {
+        match code {
+            1 => CompressionCode::Pcm,
+            2 => CompressionCode::MsAdpcm,
+            3 => CompressionCode::IeeeFloat,
+            6 => CompressionCode::ALaw,
+            7 => CompressionCode::MuLaw,
+            0x11 => CompressionCode::ImaAdpcm,
+            0x31 => CompressionCode::Gsm610,
+            0x50 => CompressionCode::Mpeg,
+            0x55 => CompressionCode::MpegLayer3,
+            0xfffe => CompressionCode::Extensible,
+            code => CompressionCode::Other(code),
+        }
+    }
+}
+
+impl From<CompressionCode> for u16 {
+    fn from(code: CompressionCode) -> u16 {
+        code.code()
+    }
+}
+
+impl std::fmt::Display for CompressionCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header {
+    pub compression_code: CompressionCode,
+    pub number_of_channels: u16,
+    pub sampling_rate: u32,
+    pub average_bytes_per_second: u32,
+    pub block_align: u16,
+    pub significant_bits_per_sample: u16,
+    // number of extra format bytes	2	16
+    //	extra format bytes	various	0x1A
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WaveFileInfo {
+    pub header: Header,
+    pub cues: Vec<CueEntry>,
+    // indices of the cues ordered by position, for the lookups below
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cue_order: Vec<usize>,
+    pub bext: Option<BroadcastAudioExtension>,
+    pub data: Option<ChunkInfo>,
+    pub ixml: Option<IXml>,
+    pub sampler: Option<smpl::Sampler>,
+    // tempo and meter of loops
+    pub acid: Option<acid::Acid>,
+    // fields of the INFO list by id, such as IART and INAM
+    pub info: Vec<(String, String)>,
+    // display title of the DISP chunk
+    pub title: Option<String>,
+    // segments of the plst chunk in playback order
+    pub playlist: Vec<plst::Segment>,
+    // frames per channel of the fact chunk, which compressed formats need,
+    // or of the ds64 chunk of RF64 files
+    pub sample_length: Option<u64>,
+    // the samples are big-endian, as in RIFX and most AIFF files
+    pub big_endian: bool,
+    // problems ParseMode::Lenient read past, and metadata too broken to
+    // use in either mode
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub warnings: Vec<WavCueError>,
+}
+
+// The serialized fields, with the cue order derived from the cues again
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for WaveFileInfo {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Fields {
+            header: Header,
+            cues: Vec<CueEntry>,
+            bext: Option<BroadcastAudioExtension>,
+            data: Option<ChunkInfo>,
+            ixml: Option<IXml>,
+            sampler: Option<smpl::Sampler>,
+            #[serde(default)]
+            acid: Option<acid::Acid>,
+            #[serde(default)]
+            info: Vec<(String, String)>,
+            #[serde(default)]
+            title: Option<String>,
+            #[serde(default)]
+            playlist: Vec<plst::Segment>,
+            #[serde(default)]
+            sample_length: Option<u64>,
+            #[serde(default)]
+            big_endian: bool,
+        }
+        let fields = Fields::deserialize(deserializer)?;
+        let mut wave = WaveFileInfo::new(
+            fields.header,
+            fields.cues,
+            fields.bext,
+            fields.data,
+            fields.ixml,
+            fields.sampler,
+            Vec::new(),
+        );
+        wave.acid = fields.acid;
+        wave.info = fields.info;
+        wave.title = fields.title;
+        wave.playlist = fields.playlist;
+        wave.sample_length = fields.sample_length;
+        wave.big_endian = fields.big_endian;
+        Ok(wave)
+    }
+}
+
+impl CueEntry {
+    // The label of the cue, or a name made from its id for cues without one
+    pub fn title(&self) -> String {
+        match &self.label {
+            Some(label) if !label.is_empty() => label.clone(),
+            _ => format!("Mark {}", self.cue_id),
+        }
+    }
+
+    // Where the region starting at the cue ends, for cues with a region
+    // that has a length
+    pub fn sample_end(&self) -> Option<u64> {
+        match &self.region {
+            Some(region) if region.sample_length > 0 => {
+                Some(self.sample_start as u64 + region.sample_length as u64)
+            }
+            _ => None,
+        }
+    }
+}
+
+// Lookups by sample position, binary searched over the cues in position
+// order; cues at the same position are returned in file order
+impl WaveFileInfo {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        header: Header,
+        cues: Vec<CueEntry>,
+        bext: Option<BroadcastAudioExtension>,
+        data: Option<ChunkInfo>,
+        ixml: Option<IXml>,
+        sampler: Option<smpl::Sampler>,
+        warnings: Vec<WavCueError>,
+    ) -> WaveFileInfo {
+        let mut cue_order: Vec<usize> = (0..cues.len()).collect();
+        cue_order.sort_by_key(|index| cues[*index].sample_start);
+        WaveFileInfo {
+            header,
+            cues,
+            cue_order,
+            bext,
+            data,
+            ixml,
+            sampler,
+            acid: None,
+            info: Vec::new(),
+            title: None,
+            playlist: Vec::new(),
+            sample_length: None,
+            big_endian: false,
+            warnings,
+        }
+    }
+
+    // Length of the audio in frames. The data chunk of compressed formats
+    // like ADPCM doesn't hold whole frames, so their fact chunk gives it.
+    pub fn frames(&self) -> Option<u64> {
+        let data = self.data.as_ref()?;
+        match self.sample_length {
+            Some(sample_length) if !self.header.compression_code.is_linear() => Some(sample_length),
+            _ => Some(data.size / self.header.block_align.max(1) as u64),
+        }
+    }
+
+    // The data chunk, for reading the samples
+    pub fn audio(&self) -> Result<&ChunkInfo, Error> {
+        match &self.data {
+            Some(_) if self.big_endian => Err(Error::from(WaveError {
+                message: String::from("Big-endian samples can't be read"),
+            })),
+            Some(data) => Ok(data),
+            None => Err(Error::from(WaveError {
+                message: String::from("File has no data chunk"),
+            })),
+        }
+    }
+
+    pub fn sorted_cues(&self) -> impl Iterator<Item = &CueEntry> + '_ {
+        self.cue_order.iter().map(move |index| &self.cues[*index])
+    }
+
+    // Number of cues positioned before the sample
+    pub fn cues_before(&self, sample: u64) -> usize {
+        self.cue_order
+            .partition_point(|index| (self.cues[*index].sample_start as u64) < sample)
+    }
+
+    pub fn cue_at_or_before(&self, sample: u64) -> Option<&CueEntry> {
+        let end = self
+            .cue_order
+            .partition_point(|index| self.cues[*index].sample_start as u64 <= sample);
+        end.checked_sub(1)
+            .map(|last| &self.cues[self.cue_order[last]])
+    }
+
+    // Cues in start..end, end exclusive
+    pub fn cues_in_range(&self, start: u64, end: u64) -> Vec<&CueEntry> {
+        let first = self.cues_before(start);
+        let count = self.cues_before(end).saturating_sub(first);
+        self.sorted_cues().skip(first).take(count).collect()
+    }
+
+    // The closest cue, the earlier one of two equally close
+    pub fn nearest_cue(&self, sample: u64) -> Option<&CueEntry> {
+        let distance = |cue: &CueEntry| (cue.sample_start as u64).abs_diff(sample);
+        let after = self.cues_before(sample);
+        let candidates = [after.checked_sub(1), Some(after)];
+        candidates
+            .iter()
+            .flatten()
+            .filter_map(|position| self.cue_order.get(*position))
+            .map(|index| &self.cues[*index])
+            .min_by_key(|cue| distance(cue))
+    }
+}
+
+pub fn fmt_chunk(header: &Header) -> Vec<u8> {
+    let mut data = Vec::with_capacity(16);
+    data.extend_from_slice(&header.compression_code.code().to_le_bytes());
+    data.extend_from_slice(&header.number_of_channels.to_le_bytes());
+    data.extend_from_slice(&header.sampling_rate.to_le_bytes());
+    data.extend_from_slice(&header.average_bytes_per_second.to_le_bytes());
+    data.extend_from_slice(&header.block_align.to_le_bytes());
+    data.extend_from_slice(&header.significant_bits_per_sample.to_le_bytes());
+    data
+}
+
+pub fn cue_chunk(cues: &[CueEntry]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(4 + 24 * cues.len());
+    data.extend_from_slice(&(cues.len() as u32).to_le_bytes());
+    for cue in cues {
+        data.extend_from_slice(&cue.cue_id.to_le_bytes());
+        data.extend_from_slice(&cue.position.to_le_bytes());
+        data.extend_from_slice(match cue.data_chunk_id {
+            DataChunkId::Data => b"data",
+            DataChunkId::Sint => b"sint",
+        });
+        data.extend_from_slice(&cue.chunk_start.to_le_bytes());
+        data.extend_from_slice(&cue.block_start.to_le_bytes());
+        data.extend_from_slice(&cue.sample_start.to_le_bytes());
+    }
+    data
+}
+
+// The id after the highest one, or the lowest unused one once that would
+// pass u32::MAX
+pub fn next_cue_id(ids: &[u32]) -> Option<u32> {
+    match ids.iter().max() {
+        Some(max) => max
+            .checked_add(1)
+            .or_else(|| (1..u32::MAX).find(|id| !ids.contains(id))),
+        None => Some(1),
+    }
+}
+
+// Replaces the cue chunk, or adds one after the other chunks
+pub fn set_cue_chunk(chunks: &mut Vec<riff::Chunk>, cues: &[CueEntry]) {
+    let cue = riff::Chunk::new(b"cue ", cue_chunk(cues));
+    match chunks.iter().position(|chunk| &chunk.id == b"cue ") {
+        Some(index) => chunks[index] = cue,
+        None => chunks.push(cue),
+    }
+}
+
+// Writes the cue points into the file, keeping the other chunks as they
+// are, so the cues of read_wave can be edited and written back
+pub fn write_cues(filename: &str, cues: &[CueEntry]) -> Result<(), Error> {
+    riff::rewrite_file(filename, |_, chunks| {
+        set_cue_chunk(chunks, cues);
+        Ok(())
+    })
+}
+
+// Reads the metadata of a WAV file, or of a file in a ZIP archive
+pub fn read_wave_file(filename: &str) -> Result<WaveFileInfo, Error> {
+    Ok(read_wave(zip::open(filename)?)?)
+}
+
+pub fn read_wave_file_with(filename: &str, mode: ParseMode) -> Result<WaveFileInfo, Error> {
+    Ok(read_wave_with(zip::open(filename)?, mode)?)
+}
+
+pub fn read_wave_file_needing(
+    filename: &str,
+    mode: ParseMode,
+    need: &[Need],
+) -> Result<WaveFileInfo, Error> {
+    Ok(read_wave_needing(zip::open(filename)?, mode, need)?)
+}
+
+pub fn read_wave_file_keeping(
+    filename: &str,
+    mode: ParseMode,
+    need: Option<&[Need]>,
+    keep: &dyn Fn(&[u8; 4]) -> bool,
+) -> Result<(WaveFileInfo, Vec<KeptChunk>), Error> {
+    Ok(read_wave_keeping(zip::open(filename)?, mode, need, keep)?)
+}
+
+// How read_wave deals with files that don't follow the specification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    // fails on any violation: chunks out of order, sizes that don't add
+    // up, chunks running past the end of the file, duplicate fmt, data,
+    // cue or bext chunks
+    Strict,
+    // fails on chunks that can't be read
+    Normal,
+    // skips the chunks that can't be read, keeping the cue points read
+    // before the problem, and lists the problems in the warnings
+    Lenient,
+}
+
+// What read_wave_needing has to find before it stops reading the file.
+// The fmt chunk is always needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Need {
+    // the cue chunk and the labels, notes and regions of the adtl list
+    Cues,
+    Bext,
+    // where the audio is, for the length of the file
+    Data,
+    Ixml,
+    Sampler,
+}
+
+impl Need {
+    pub fn parse(name: &str) -> Option<Need> {
+        match name {
+            "cue" | "cues" => Some(Need::Cues),
+            "bext" => Some(Need::Bext),
+            "data" => Some(Need::Data),
+            "ixml" | "iXML" => Some(Need::Ixml),
+            "smpl" | "sampler" => Some(Need::Sampler),
+            _ => None,
+        }
+    }
+}
+
+// The chunks of a file as they are read
+#[derive(Default)]
+struct Parsed {
+    cues: Vec<CueEntry>,
+    bext: Option<BroadcastAudioExtension>,
+    header: Option<Header>,
+    data: Option<ChunkInfo>,
+    ixml: Option<IXml>,
+    sampler: Option<smpl::Sampler>,
+    acid: Option<acid::Acid>,
+    info: Vec<(String, String)>,
+    title: Option<String>,
+    playlist: Vec<plst::Segment>,
+    sample_length: Option<u64>,
+    // the _PMX packet, for its markers once the sampling rate is known
+    xmp: Option<Vec<u8>>,
+    labels: Vec<(u32, String)>,
+    notes: Vec<(u32, String)>,
+    regions: Vec<adtl::Region>,
+    ds64: Option<riff::Ds64>,
+    adtl: bool,
+    warnings: Vec<WavCueError>,
+    kept: Vec<KeptChunk>,
+}
+
+// The id and payload of a chunk read_wave_keeping was asked to keep
+pub type KeptChunk = ([u8; 4], Vec<u8>);
+
+impl Parsed {
+    fn has(&self, need: &[Need], seen: &[[u8; 4]]) -> bool {
+        self.header.is_some()
+            && need.iter().all(|need| match need {
+                Need::Cues => seen.contains(b"cue ") && self.adtl,
+                Need::Bext => seen.contains(b"bext"),
+                Need::Data => self.data.is_some(),
+                Need::Ixml => seen.contains(b"iXML"),
+                Need::Sampler => seen.contains(b"smpl"),
+            })
+    }
+}
+
+// Checks of ParseMode::Strict on a chunk before reading it
+fn check_strict(
+    parsed: &Parsed,
+    seen: &[[u8; 4]],
+    id: [u8; 4],
+    past_end: bool,
+) -> Result<(), WavCueError> {
+    if past_end {
+        return Err(WavCueError::TruncatedChunk { id });
+    }
+    if [b"fmt ", b"data", b"cue ", b"bext"].contains(&&id) && seen.contains(&id) {
+        return Err(WavCueError::invalid(
+            &id,
+            String::from("File cannot have two of these chunks"),
+        ));
+    }
+    if &id == b"data" && parsed.header.is_none() {
+        return Err(WavCueError::invalid(
+            &id,
+            String::from("data chunk comes before the fmt chunk"),
+        ));
+    }
+    if &id == b"ds64" && !seen.is_empty() {
+        return Err(WavCueError::invalid(
+            &id,
+            String::from("ds64 has to be the first chunk"),
+        ));
+    }
+    Ok(())
+}
+
+// Clipboard format of a DISP chunk holding text
+const CF_TEXT: u32 = 1;
+
+// Reads a chunk into parsed, leaving the reader at the end of the chunk
+fn read_chunk<R: Read + Seek>(
+    reader: &mut R,
+    id: [u8; 4],
+    size: u64,
+    first: bool,
+    mode: ParseMode,
+    parsed: &mut Parsed,
+) -> Result<(), WavCueError> {
+    if size == 0 {
+        return Err(WavCueError::invalid(
+            &id,
+            String::from("Cannot process empty chunk"),
+        ));
+    }
+    if &id == b"ds64" && first {
+        let mut buf_ds64 = vec![0u8; size as usize];
+        reader
+            .read_exact(&mut buf_ds64)
+            .map_err(WavCueError::truncated(id))?;
+        parsed.ds64 = riff::Ds64::parse(&buf_ds64);
+        if parsed.ds64.is_none() {
+            return Err(WavCueError::invalid(
+                &id,
+                format!("too small ({size} bytes)"),
+            ));
+        }
+    } else if &id == b"bext" {
+        if (size as usize) < BEXT_HEADER_SIZE {
+            return Err(WavCueError::invalid(&id, format!("unexpected size {size}")));
+        }
+        let mut buf_bext = vec![0u8; size as usize];
+        reader
+            .read_exact(&mut buf_bext)
+            .map_err(WavCueError::truncated(id))?;
+        parsed.bext = Some(bext::parse(&buf_bext));
+    } else if &id == b"fmt " {
+        let mut buf_fmt: [u8; 16] = [0; 16];
+        if size < 16 {
+            return Err(WavCueError::invalid(
+                &id,
+                format!("too small ({size} bytes)"),
+            ));
+        }
+        if parsed.header.is_some() {
+            return Err(WavCueError::invalid(
+                &id,
+                String::from("File cannot have two fmt headers"),
+            ));
+        }
+        reader
+            .read_exact(&mut buf_fmt)
+            .map_err(WavCueError::truncated(id))?;
+        reader.seek_relative(size as i64 - buf_fmt.len() as i64)?;
+        let compression_code =
+            CompressionCode::from(u16::from_le_bytes(*array_ref!(buf_fmt, 0, 2)));
+        let number_of_channels = u16::from_le_bytes(*array_ref!(buf_fmt, 2, 2));
+        let sampling_rate = u32::from_le_bytes(*array_ref!(buf_fmt, 4, 4));
+        let average_bytes_per_second = u32::from_le_bytes(*array_ref!(buf_fmt, 8, 4));
+        let block_align = u16::from_le_bytes(*array_ref!(buf_fmt, 12, 2));
+        let significant_bits_per_sample = u16::from_le_bytes(*array_ref!(buf_fmt, 14, 2));
+        // every time and frame count divides by these
+        if sampling_rate == 0 {
+            return Err(WavCueError::invalid(
+                &id,
+                String::from("sampling rate is 0"),
+            ));
+        }
+        if block_align == 0 {
+            return Err(WavCueError::invalid(&id, String::from("block align is 0")));
+        }
+        parsed.header = Some(Header {
+            compression_code,
+            number_of_channels,
+            sampling_rate,
+            average_bytes_per_second,
+            block_align,
+            significant_bits_per_sample,
+        });
+    } else if &id == b"cue " {
+        // https://www.recordingblogs.com/wiki/cue-chunk-of-a-wave-file
+        let mut buf_num_cue_points: [u8; 4] = [0; 4];
+        reader
+            .read_exact(&mut buf_num_cue_points)
+            .map_err(WavCueError::truncated(id))?;
+        let num_cue_points = u32::from_le_bytes(buf_num_cue_points);
+        let mut count = num_cue_points;
+        if size != 4 + 24 * num_cue_points as u64 {
+            let error = WavCueError::BadCueCount {
+                count: num_cue_points,
+                size,
+            };
+            if mode != ParseMode::Lenient {
+                return Err(error);
+            }
+            // the cue points that fit in the chunk
+            count = count.min((size.saturating_sub(4) / 24) as u32);
+            parsed.warnings.push(error);
+        }
+        for _ in 0..count {
+            let mut buf_cue: [u8; 24] = [0; 24];
+            reader
+                .read_exact(&mut buf_cue)
+                .map_err(WavCueError::truncated(id))?;
+
+            let cue_id = u32::from_le_bytes(*array_ref!(buf_cue, 0, 4));
+            let position = u32::from_le_bytes(*array_ref!(buf_cue, 4, 4));
+            let data_chunk_id = {
+                let data_id = *array_ref!(buf_cue, 8, 4);
+                if &data_id == b"data" {
+                    DataChunkId::Data
+                } else if &data_id == b"sint" {
+                    DataChunkId::Sint
+                } else {
+                    let error = WavCueError::invalid(
+                        &id,
+                        format!(
+                            "Data chunk id should be either 'data' or 'sint', not {:?}",
+                            String::from_utf8_lossy(&data_id)
+                        ),
+                    );
+                    if mode != ParseMode::Lenient {
+                        return Err(error);
+                    }
+                    // the other cue points can still be good
+                    parsed.warnings.push(error);
+                    continue;
+                }
+            };
+
+            let chunk_start = u32::from_le_bytes(*array_ref!(buf_cue, 12, 4));
+
+            let block_start = u32::from_le_bytes(*array_ref!(buf_cue, 16, 4));
+
+            let sample_start = u32::from_le_bytes(*array_ref!(buf_cue, 20, 4));
+
+            let entry = CueEntry {
+                cue_id,
+                position,
+                data_chunk_id,
+                chunk_start,
+                block_start,
+                sample_start,
+                label: None,
+                note: None,
+                color: None,
+                url: None,
+                image: None,
+                region: None,
+            };
+
+            parsed.cues.push(entry);
+        }
+        // past the cue points that didn't fit
+        reader.seek_relative(size as i64 - 4 - 24 * count as i64)?;
+    } else if &id == b"LIST" {
+        let mut buf_list = vec![0u8; size as usize];
+        reader
+            .read_exact(&mut buf_list)
+            .map_err(WavCueError::truncated(id))?;
+        if buf_list.starts_with(b"adtl") {
+            let adtl = adtl::parse(&buf_list).map_err(|error| match error {
+                Error::WaveError(error) => WavCueError::invalid(&id, error.message),
+                error => WavCueError::invalid(&id, error.to_string()),
+            })?;
+            parsed.adtl = true;
+            parsed.labels.extend(adtl.labels);
+            parsed.notes.extend(adtl.notes);
+            parsed.regions.extend(adtl.regions);
+        } else if buf_list.starts_with(b"INFO") {
+            let fields = info::parse(&buf_list).map_err(|error| match error {
+                Error::WaveError(error) => WavCueError::invalid(&id, error.message),
+                error => WavCueError::invalid(&id, error.to_string()),
+            })?;
+            parsed.info.extend(fields);
+        }
+    } else if &id == b"smpl" {
+        let mut buf_smpl = vec![0u8; size as usize];
+        reader
+            .read_exact(&mut buf_smpl)
+            .map_err(WavCueError::truncated(id))?;
+        parsed.sampler = Some(smpl::parse(&buf_smpl).map_err(|error| match error {
+            Error::WaveError(error) => WavCueError::invalid(&id, error.message),
+            error => WavCueError::invalid(&id, error.to_string()),
+        })?);
+    } else if &id == b"acid" {
+        let mut buf_acid = vec![0u8; size as usize];
+        reader
+            .read_exact(&mut buf_acid)
+            .map_err(WavCueError::truncated(id))?;
+        parsed.acid = Some(acid::parse(&buf_acid).map_err(|error| match error {
+            Error::WaveError(error) => WavCueError::invalid(&id, error.message),
+            error => WavCueError::invalid(&id, error.to_string()),
+        })?);
+    } else if &id == b"fact" {
+        let mut buf_fact = vec![0u8; size as usize];
+        reader
+            .read_exact(&mut buf_fact)
+            .map_err(WavCueError::truncated(id))?;
+        if buf_fact.len() < 4 {
+            return Err(WavCueError::invalid(
+                &id,
+                format!("too small ({size} bytes)"),
+            ));
+        }
+        parsed.sample_length = Some(u32::from_le_bytes(*array_ref!(buf_fact, 0, 4)) as u64);
+    } else if &id == b"DISP" {
+        // a clipboard format, then the data; only CF_TEXT is a title
+        let mut buf_disp = vec![0u8; size as usize];
+        reader
+            .read_exact(&mut buf_disp)
+            .map_err(WavCueError::truncated(id))?;
+        if buf_disp.len() > 4 && buf_disp[0..4] == CF_TEXT.to_le_bytes() {
+            let text = &buf_disp[4..];
+            let end = text
+                .iter()
+                .position(|byte| *byte == 0)
+                .unwrap_or(text.len());
+            let title = String::from_utf8_lossy(&text[..end]).trim().to_string();
+            if !title.is_empty() {
+                parsed.title = Some(title);
+            }
+        }
+    } else if &id == b"plst" {
+        let mut buf_plst = vec![0u8; size as usize];
+        reader
+            .read_exact(&mut buf_plst)
+            .map_err(WavCueError::truncated(id))?;
+        parsed.playlist = plst::parse(&buf_plst).map_err(|error| match error {
+            Error::WaveError(error) => WavCueError::invalid(&id, error.message),
+            error => WavCueError::invalid(&id, error.to_string()),
+        })?;
+    } else if &id == b"_PMX" {
+        let mut buf_xmp = vec![0u8; size as usize];
+        reader
+            .read_exact(&mut buf_xmp)
+            .map_err(WavCueError::truncated(id))?;
+        parsed.xmp = Some(buf_xmp);
+    } else if &id == b"iXML" {
+        let mut buf_ixml = vec![0u8; size as usize];
+        reader
+            .read_exact(&mut buf_ixml)
+            .map_err(WavCueError::truncated(id))?;
+        // metadata is not essential, so a broken iXML is only a warning
+        match ixml::parse(&buf_ixml) {
+            Ok(ixml) => parsed.ixml = Some(ixml),
+            Err(error) => parsed
+                .warnings
+                .push(WavCueError::invalid(&id, error.to_string())),
+        }
+    } else {
+        if &id == b"data" {
+            parsed.data = Some(ChunkInfo {
+                id,
+                offset: reader.stream_position()?,
+                size,
+            });
+        }
+        reader.seek_relative(size as i64)?;
+    }
+    Ok(())
+}
+
+// Reads a chunk of a RIFX file, swapped into the RIFF layout
+fn read_swapped<R: Read>(
+    reader: &mut R,
+    id: [u8; 4],
+    size: u64,
+    mode: ParseMode,
+    parsed: &mut Parsed,
+) -> Result<(), WavCueError> {
+    let mut payload = vec![0u8; size as usize];
+    reader
+        .read_exact(&mut payload)
+        .map_err(WavCueError::truncated(id))?;
+    rifx::to_little_endian(&id, &mut payload);
+    read_chunk(&mut Cursor::new(payload), id, size, false, mode, parsed)
+}
+
+// Reads a chunk from its payload, which is kept, with the numbers of a
+// RIFX chunk little-endian
+fn read_kept<R: Read>(
+    reader: &mut R,
+    id: [u8; 4],
+    size: u64,
+    first: bool,
+    big_endian: bool,
+    mode: ParseMode,
+    parsed: &mut Parsed,
+) -> Result<(), WavCueError> {
+    let mut payload = vec![0u8; size as usize];
+    reader
+        .read_exact(&mut payload)
+        .map_err(WavCueError::truncated(id))?;
+    if big_endian {
+        rifx::to_little_endian(&id, &mut payload);
+    }
+    let result = read_chunk(&mut Cursor::new(&payload), id, size, first, mode, parsed);
+    parsed.kept.push((id, payload));
+    result
+}
+
+// Decoders of the known chunks found with a ChunkReader, parsing only the
+// chunk asked for, the way read_wave does
+impl<R: Read + Seek> riff::ChunkReader<R> {
+    fn decode(&mut self, info: &ChunkInfo, expected: &[u8; 4]) -> Result<Parsed, WavCueError> {
+        if &info.id != expected {
+            return Err(WavCueError::invalid(
+                &info.id,
+                format!(
+                    "not a {} chunk",
+                    String::from_utf8_lossy(expected).trim_end()
+                ),
+            ));
+        }
+        let big_endian = self.is_big_endian();
+        let reader = self.get_mut();
+        reader.seek(SeekFrom::Start(info.offset))?;
+        let mut parsed = Parsed::default();
+        if big_endian {
+            read_swapped(reader, info.id, info.size, ParseMode::Normal, &mut parsed)?;
+        } else {
+            read_chunk(
+                reader,
+                info.id,
+                info.size,
+                false,
+                ParseMode::Normal,
+                &mut parsed,
+            )?;
+        }
+        Ok(parsed)
+    }
+
+    pub fn header(&mut self, info: &ChunkInfo) -> Result<Header, WavCueError> {
+        self.decode(info, b"fmt ")?
+            .header
+            .ok_or(WavCueError::MissingFmt)
+    }
+
+    // The cue points without their labels and notes, which are in the
+    // adtl LIST
+    pub fn cues(&mut self, info: &ChunkInfo) -> Result<Vec<CueEntry>, WavCueError> {
+        Ok(self.decode(info, b"cue ")?.cues)
+    }
+
+    pub fn bext(&mut self, info: &ChunkInfo) -> Result<BroadcastAudioExtension, WavCueError> {
+        match self.decode(info, b"bext")?.bext {
+            Some(bext) => Ok(bext),
+            None => Err(WavCueError::invalid(&info.id, String::from("empty"))),
+        }
+    }
+
+    // Labels, notes and regions of an adtl LIST; empty for other lists
+    pub fn associated_data(
+        &mut self,
+        info: &ChunkInfo,
+    ) -> Result<adtl::AssociatedData, WavCueError> {
+        let parsed = self.decode(info, b"LIST")?;
+        Ok(adtl::AssociatedData {
+            labels: parsed.labels,
+            notes: parsed.notes,
+            regions: parsed.regions,
+        })
+    }
+
+    pub fn ixml(&mut self, info: &ChunkInfo) -> Result<IXml, WavCueError> {
+        match self.decode(info, b"iXML")?.ixml {
+            Some(ixml) => Ok(ixml),
+            None => Err(WavCueError::invalid(
+                &info.id,
+                String::from("not valid XML"),
+            )),
+        }
+    }
+
+    pub fn sampler(&mut self, info: &ChunkInfo) -> Result<smpl::Sampler, WavCueError> {
+        match self.decode(info, b"smpl")?.sampler {
+            Some(sampler) => Ok(sampler),
+            None => Err(WavCueError::invalid(&info.id, String::from("empty"))),
+        }
+    }
+}
+
+// Cue point of an XMP marker, a region if it has a duration
+fn xmp_cue((marker, cue_id): (xmp::Marker, u32)) -> CueEntry {
+    let position = marker.start_time.min(u32::MAX as u64) as u32;
+    let length = marker.duration.min(u32::MAX as u64) as u32;
+    CueEntry {
+        cue_id,
+        position,
+        data_chunk_id: DataChunkId::Data,
+        chunk_start: 0,
+        block_start: 0,
+        sample_start: position,
+        label: (!marker.name.is_empty()).then_some(marker.name),
+        note: None,
+        color: None,
+        url: None,
+        image: None,
+        region: (length > 0).then(|| adtl::Region {
+            sample_length: length,
+            ..adtl::Region::new(cue_id)
+        }),
+    }
+}
+
+pub fn read_wave<R: Read + Seek>(reader: R) -> Result<WaveFileInfo, WavCueError> {
+    read_wave_with(reader, ParseMode::Normal)
+}
+
+pub fn read_wave_with<R: Read + Seek>(
+    reader: R,
+    mode: ParseMode,
+) -> Result<WaveFileInfo, WavCueError> {
+    read_wave_until(reader, mode, None, &|_| false).map(|(wave, _)| wave)
+}
+
+// Reads the chunks up to the last of the ones needed, leaving the rest of
+// the file unread; the audio is skipped with a seek either way. With
+// ParseMode::Strict only the chunks read are checked.
+pub fn read_wave_needing<R: Read + Seek>(
+    reader: R,
+    mode: ParseMode,
+    need: &[Need],
+) -> Result<WaveFileInfo, WavCueError> {
+    read_wave_until(reader, mode, Some(need), &|_| false).map(|(wave, _)| wave)
+}
+
+// Reads the file like read_wave_needing, or all of it without need,
+// keeping the payloads of the chunks keep asks for along the way, as a
+// stream can't be read a second time for them. AIFF and CAF files keep
+// none.
+pub fn read_wave_keeping<R: Read + Seek>(
+    reader: R,
+    mode: ParseMode,
+    need: Option<&[Need]>,
+    keep: &dyn Fn(&[u8; 4]) -> bool,
+) -> Result<(WaveFileInfo, Vec<KeptChunk>), WavCueError> {
+    read_wave_until(reader, mode, need, keep)
+}
+
+fn read_wave_until<R: Read + Seek>(
+    mut reader: R,
+    mode: ParseMode,
+    need: Option<&[Need]>,
+    keep: &dyn Fn(&[u8; 4]) -> bool,
+) -> Result<(WaveFileInfo, Vec<KeptChunk>), WavCueError> {
+    let mut parsed = Parsed::default();
+
+    let mut buf_riff: [u8; 4] = [0; 4];
+    reader
+        .read_exact(&mut buf_riff)
+        .map_err(WavCueError::truncated(*b"RIFF"))?;
+    // the chunks of AIFF and CAF files are few, so they are all read
+    if aiff::is_aiff_signature(&buf_riff) {
+        return aiff::read(reader, mode).map(|wave| (wave, Vec::new()));
+    }
+    if caf::is_caf_signature(&buf_riff) {
+        return caf::read(reader, mode).map(|wave| (wave, Vec::new()));
+    }
+
+    // https://www.recordingblogs.com/wiki/format-chunk-of-a-wave-file
+    let big_endian = rifx::is_rifx_signature(&buf_riff);
+    if riff::is_riff_signature(&buf_riff) || big_endian {
+        let mut buf_size: [u8; 4] = [0; 4];
+        reader
+            .read_exact(&mut buf_size)
+            .map_err(WavCueError::truncated(*b"RIFF"))?;
+        let size = if big_endian {
+            u32::from_be_bytes(buf_size)
+        } else {
+            u32::from_le_bytes(buf_size)
+        };
+        // the WAVE id counts towards the RIFF size
+        let mut bytes_processed = 4u64;
+        // Read.
+        let mut buf_wave: [u8; 4] = [0; 4];
+        reader
+            .read_exact(&mut buf_wave)
+            .map_err(WavCueError::truncated(*b"RIFF"))?;
+        if &buf_wave == b"WAVE" {
+            // a stream's size isn't known before reading it
+            let file_size = match reader.seek(SeekFrom::End(0)) {
+                Ok(file_size) => file_size,
+                Err(error) if error.kind() == io::ErrorKind::Unsupported => u64::MAX,
+                Err(error) => return Err(error.into()),
+            };
+            reader.seek(SeekFrom::Start(12))?;
+            let mut buf_tag: [u8; 4] = [0; 4];
+            let mut buf_chunk32_size: [u8; 4] = [0; 4];
+            let mut seen: Vec<[u8; 4]> = Vec::new();
+            let mut stopped = false;
+            // walk chunks
+            while let Ok(()) = reader.read_exact(&mut buf_tag) {
+                if let Err(error) = reader
+                    .read_exact(&mut buf_chunk32_size)
+                    .map_err(WavCueError::truncated(buf_tag))
+                {
+                    match mode {
+                        ParseMode::Lenient => {
+                            parsed.warnings.push(error);
+                            break;
+                        }
+                        _ => return Err(error),
+                    }
+                }
+                let chunk_size = if big_endian {
+                    u32::from_be_bytes(buf_chunk32_size)
+                } else {
+                    u32::from_le_bytes(buf_chunk32_size)
+                };
+                let chunk_size = match &parsed.ds64 {
+                    Some(ds64) => ds64.chunk_size(&buf_tag, chunk_size),
+                    None => chunk_size as u64,
+                };
+                let start = reader.stream_position()?;
+                // sizes from ds64 can be anything, up to past any file
+                let padded_end = match start
+                    .checked_add(chunk_size)
+                    .and_then(|end| end.checked_add(chunk_size & 1))
+                {
+                    Some(padded_end) => padded_end,
+                    None if mode == ParseMode::Lenient => {
+                        parsed
+                            .warnings
+                            .push(WavCueError::TruncatedChunk { id: buf_tag });
+                        break;
+                    }
+                    None => return Err(WavCueError::TruncatedChunk { id: buf_tag }),
+                };
+                if mode == ParseMode::Strict {
+                    check_strict(&parsed, &seen, buf_tag, start + chunk_size > file_size)?;
+                }
+                seen.push(buf_tag);
+                let first = bytes_processed == 4;
+                // a chunk running past the end isn't kept
+                let result = if keep(&buf_tag) && start + chunk_size <= file_size {
+                    read_kept(
+                        &mut reader,
+                        buf_tag,
+                        chunk_size,
+                        first,
+                        big_endian,
+                        mode,
+                        &mut parsed,
+                    )
+                } else if big_endian && rifx::has_numbers(&buf_tag) {
+                    read_swapped(&mut reader, buf_tag, chunk_size, mode, &mut parsed)
+                } else {
+                    read_chunk(&mut reader, buf_tag, chunk_size, first, mode, &mut parsed)
+                };
+                match result {
+                    Ok(()) => (),
+                    Err(error @ WavCueError::Io(_)) => return Err(error),
+                    Err(error) if mode == ParseMode::Lenient => {
+                        parsed.warnings.push(error);
+                        // nothing after a chunk running past the end is left
+                        if start + chunk_size > file_size {
+                            break;
+                        }
+                    }
+                    Err(error) => return Err(error),
+                }
+                // chunks are word aligned, with a pad byte after odd sizes
+                reader.seek(SeekFrom::Start(padded_end))?;
+                bytes_processed = bytes_processed.saturating_add(8 + padded_end - start);
+                if need.is_some_and(|need| parsed.has(need, &seen)) {
+                    stopped = true;
+                    break;
+                }
+            }
+            let size = parsed
+                .ds64
+                .as_ref()
+                .map_or(size as u64, |ds64| ds64.riff_size);
+            if mode == ParseMode::Strict && !stopped && size != bytes_processed {
+                return Err(WavCueError::invalid(
+                    b"RIFF",
+                    format!("size is {size} bytes, but the chunks add up to {bytes_processed}"),
+                ));
+            }
+        } else {
+            return Err(WavCueError::UnexpectedFourcc {
+                expected: "WAVE",
+                found: buf_wave,
+            });
+        }
+    } else {
+        return Err(WavCueError::UnexpectedFourcc {
+            expected: "RIFF",
+            found: buf_riff,
+        });
+    }
+
+    let Parsed {
+        mut cues,
+        bext,
+        header,
+        data,
+        ixml,
+        sampler,
+        acid,
+        info,
+        title,
+        playlist,
+        sample_length,
+        xmp,
+        labels,
+        notes,
+        regions,
+        ds64,
+        mut warnings,
+        kept,
+        ..
+    } = parsed;
+
+    // the fact chunk of an RF64 file can't hold the length, ds64 does
+    let sample_length = match (ds64, sample_length) {
+        (Some(ds64), None | Some(0xffff_ffff)) if ds64.sample_count > 0 => Some(ds64.sample_count),
+        (_, sample_length) => sample_length,
+    };
+
+    // adtl and cue may come in either order
+    for (cue_id, label) in labels {
+        if let Some(cue) = cues.iter_mut().find(|cue| cue.cue_id == cue_id) {
+            cue.label = Some(label);
+        }
+    }
+
+    for (cue_id, note) in notes {
+        if let Some(cue) = cues.iter_mut().find(|cue| cue.cue_id == cue_id) {
+            if let Some(color) = Color::from_note(&note) {
+                cue.color = Some(color);
+            }
+            if let Some(url) = adtl::note_attribute(&note, &["url"]) {
+                cue.url = Some(url);
+            }
+            if let Some(image) = adtl::note_attribute(&note, &["image", "img"]) {
+                cue.image = Some(image);
+            }
+            cue.note = Some(note);
+        }
+    }
+
+    for region in regions {
+        if let Some(cue) = cues.iter_mut().find(|cue| cue.cue_id == region.cue_id) {
+            cue.region = Some(region);
+        }
+    }
+
+    // segments of the playlist give the length of cues that have no
+    // region of their own
+    for segment in playlist.iter().filter(|segment| segment.sample_length > 0) {
+        if let Some(cue) = cues.iter_mut().find(|cue| cue.cue_id == segment.cue_id) {
+            let region = cue
+                .region
+                .get_or_insert_with(|| adtl::Region::new(segment.cue_id));
+            if region.sample_length == 0 {
+                region.sample_length = segment.sample_length;
+            }
+        }
+    }
+
+    let header = header.ok_or(WavCueError::MissingFmt)?;
+
+    // Adobe applications keep their markers in XMP; they stand in for the
+    // cue points of files that have none
+    if let (true, Some(xmp)) = (cues.is_empty(), xmp) {
+        match xmp::markers(&xmp, header.sampling_rate) {
+            Ok(markers) => cues.extend(markers.into_iter().zip(1..).map(xmp_cue)),
+            Err(error) => warnings.push(WavCueError::invalid(b"_PMX", error.to_string())),
+        }
+    }
+
+    let mut wave = WaveFileInfo::new(header, cues, bext, data, ixml, sampler, warnings);
+    wave.acid = acid;
+    wave.info = info;
+    wave.title = title;
+    wave.playlist = playlist;
+    wave.sample_length = sample_length;
+    wave.big_endian = big_endian;
+    Ok((wave, kept))
+}
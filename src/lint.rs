@@ -0,0 +1,83 @@
+// Checks for metadata that doesn't follow the specifications, with fixes
+// for what can be fixed without guessing
+use crate::bext::{self, FieldFormat};
+use crate::riff;
+use crate::{read_wave_file, Error};
+
+#[derive(Debug)]
+pub struct Finding {
+    pub message: String,
+    pub fixable: bool,
+}
+
+pub fn lint(filename: &str) -> Result<Vec<Finding>, Error> {
+    let wave = read_wave_file(filename)?;
+    let mut findings = Vec::new();
+    if let Some(bext) = &wave.bext {
+        for (name, value, format, expected) in [
+            (
+                "OriginationDate",
+                &bext.origination_date,
+                bext::check_origination_date(&bext.origination_date),
+                "yyyy-mm-dd",
+            ),
+            (
+                "OriginationTime",
+                &bext.origination_time,
+                bext::check_origination_time(&bext.origination_time),
+                "hh:mm:ss",
+            ),
+        ] {
+            let value = value.trim_end_matches(char::from(0));
+            match format {
+                FieldFormat::Valid => (),
+                FieldFormat::Variant(normalized) => findings.push(Finding {
+                    message: format!(
+                        "bext {name} {value:?} should be written as {expected}: {normalized}"
+                    ),
+                    fixable: true,
+                }),
+                FieldFormat::Invalid => findings.push(Finding {
+                    message: format!("bext {name} {value:?} is not a valid {expected}"),
+                    fixable: false,
+                }),
+            }
+        }
+    }
+    Ok(findings)
+}
+
+// Applies the fixes for the fixable findings; returns what was changed
+pub fn fix(filename: &str) -> Result<Vec<String>, Error> {
+    let mut fixed = Vec::new();
+    riff::rewrite_file(filename, |reader, chunks| {
+        let index = match chunks.iter().position(|chunk| &chunk.id == b"bext") {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        let mut raw = chunks[index].payload(reader)?;
+        bext::ensure_fixed_size(&mut raw);
+        for (name, field, check) in [
+            (
+                "OriginationDate",
+                bext::BEXT_ORIGINATION_DATE,
+                bext::check_origination_date as fn(&str) -> FieldFormat,
+            ),
+            (
+                "OriginationTime",
+                bext::BEXT_ORIGINATION_TIME,
+                bext::check_origination_time,
+            ),
+        ] {
+            let (offset, size) = field;
+            let value = String::from_utf8_lossy(&raw[offset..offset + size]).to_string();
+            if let FieldFormat::Variant(normalized) = check(&value) {
+                bext::set_string(&mut raw, field, &normalized);
+                fixed.push(format!("bext {name} set to {normalized}"));
+            }
+        }
+        chunks[index] = riff::Chunk::new(b"bext", raw);
+        Ok(())
+    })?;
+    Ok(fixed)
+}
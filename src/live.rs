@@ -0,0 +1,163 @@
+// Adding cue points to a file that is still being recorded. The file can't
+// be rewritten and the size of the data is not known yet, so the cue chunk
+// grows in place into a JUNK chunk reserved right after it (or a JUNK chunk
+// is turned into a cue chunk); only the bytes of those two chunks change.
+use crate::riff::{self, Chunk, ChunkInfo};
+use crate::{Error, WaveError};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+
+const CUE_POINT_SIZE: u32 = 24;
+
+fn error(message: &str) -> Error {
+    Error::from(WaveError {
+        message: message.to_string(),
+    })
+}
+
+fn format_info(file: &mut File, chunks: &[ChunkInfo]) -> Result<(u32, u16), Error> {
+    match chunks.iter().find(|chunk| &chunk.id == b"fmt ") {
+        Some(info) if info.size >= 16 => {
+            let fmt = riff::read_payload(file, info)?;
+            Ok((
+                u32::from_le_bytes(*array_ref!(fmt, 4, 4)),
+                u16::from_le_bytes(*array_ref!(fmt, 12, 2)).max(1),
+            ))
+        }
+        _ => Err(error("File has no fmt chunk")),
+    }
+}
+
+// Chunks preceding the data; the size of the data chunk of a file being
+// written is not reliable, so anything after it is ignored
+fn leading_chunks(file: &mut File) -> Result<Vec<ChunkInfo>, Error> {
+    let mut chunks = riff::walk_chunks(file)?;
+    if let Some(index) = chunks.iter().position(|chunk| &chunk.id == b"data") {
+        chunks.truncate(index + 1);
+    }
+    Ok(chunks)
+}
+
+// Sample rate and the number of frames recorded so far
+pub fn position(filename: &str) -> Result<(u32, u64), Error> {
+    let mut file = File::open(filename)?;
+    let chunks = leading_chunks(&mut file)?;
+    let (sampling_rate, block_align) = format_info(&mut file, &chunks)?;
+    let data = match chunks.iter().find(|chunk| &chunk.id == b"data") {
+        Some(data) => data,
+        None => return Err(error("File has no data chunk")),
+    };
+    let length = file.metadata()?.len();
+    Ok((
+        sampling_rate,
+        length.saturating_sub(data.offset) / block_align as u64,
+    ))
+}
+
+fn write_at(file: &mut File, offset: u64, data: &[u8]) -> Result<(), Error> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(data)?;
+    Ok(())
+}
+
+// Adds a cue point without changing the size of the file; returns its id
+pub fn append_cue(filename: &str, sample_start: u32) -> Result<u32, Error> {
+    let mut file = OpenOptions::new().read(true).write(true).open(filename)?;
+    let chunks = leading_chunks(&mut file)?;
+    let cue = chunks.iter().position(|chunk| &chunk.id == b"cue ");
+    // a JUNK chunk at the start is kept for upgrading to RF64
+    let junk = match cue {
+        Some(index) => chunks.get(index + 1).filter(|chunk| &chunk.id == b"JUNK"),
+        None => chunks.iter().skip(1).find(|chunk| {
+            &chunk.id == b"JUNK" && chunk.size >= (4 + CUE_POINT_SIZE + 8 + 2) as u64
+        }),
+    };
+    // empty chunks are not accepted by the reader, so some JUNK is left over
+    let junk = match junk {
+        Some(junk) if junk.size >= (CUE_POINT_SIZE + 2) as u64 => junk.clone(),
+        _ => {
+            return Err(error(
+                "No room for cue points; reserve it with wav-cue reserve-cues before recording",
+            ))
+        }
+    };
+
+    let (cue_offset, mut points) = match cue {
+        Some(index) => {
+            let info = &chunks[index];
+            let payload = riff::read_payload(&mut file, info)?;
+            (info.offset, payload)
+        }
+        // the cue chunk takes the place of the JUNK header
+        None => (junk.offset, 0u32.to_le_bytes().to_vec()),
+    };
+    let count = u32::from_le_bytes(*array_ref!(points, 0, 4));
+    if points.len() as u64 != 4 + count as u64 * CUE_POINT_SIZE as u64 {
+        return Err(error("cue chunk has unexpected size"));
+    }
+    let ids: Vec<u32> = points[4..]
+        .chunks_exact(CUE_POINT_SIZE as usize)
+        .map(|point| u32::from_le_bytes(*array_ref!(point, 0, 4)))
+        .collect();
+    let cue_id = match crate::next_cue_id(&ids) {
+        Some(cue_id) => cue_id,
+        None => return Err(error("No unused cue id left")),
+    };
+    let mut point = Vec::with_capacity(CUE_POINT_SIZE as usize);
+    point.extend_from_slice(&cue_id.to_le_bytes());
+    point.extend_from_slice(&sample_start.to_le_bytes());
+    point.extend_from_slice(b"data");
+    point.extend_from_slice(&[0; 8]);
+    point.extend_from_slice(&sample_start.to_le_bytes());
+    points.extend_from_slice(&point);
+    points[0..4].copy_from_slice(&(count + 1).to_le_bytes());
+
+    // the new JUNK header goes first, into what was JUNK payload, and the
+    // cue chunk header last, so a reader sees either the old or the new cue
+    // chunk apart from a window of a few writes
+    let new_cue_end = cue_offset + points.len() as u64;
+    let junk_end = junk.offset + junk.size;
+    let mut junk_header = b"JUNK".to_vec();
+    junk_header.extend_from_slice(&((junk_end - new_cue_end - 8) as u32).to_le_bytes());
+    write_at(&mut file, new_cue_end, &junk_header)?;
+    write_at(&mut file, cue_offset, &points)?;
+    let mut cue_header = b"cue ".to_vec();
+    cue_header.extend_from_slice(&(points.len() as u32).to_le_bytes());
+    write_at(&mut file, cue_offset - 8, &cue_header)?;
+    file.sync_all()?;
+    Ok(cue_id)
+}
+
+// Makes room for the given number of cue points by adding a JUNK chunk
+// after the cue chunk, which is moved before the data if it comes after it
+pub fn reserve(filename: &str, count: u32) -> Result<(), Error> {
+    let size = match count
+        .checked_mul(CUE_POINT_SIZE)
+        .and_then(|size| size.checked_add(4 + 8 + 2))
+    {
+        Some(size) => size,
+        None => {
+            return Err(Error::UsageError(format!(
+                "--count {} needs more than 4 GiB of JUNK",
+                count
+            )))
+        }
+    };
+    riff::rewrite_file(filename, |_, chunks| {
+        let data = match chunks.iter().position(|chunk| &chunk.id == b"data") {
+            Some(index) => index,
+            None => return Err(error("File has no data chunk")),
+        };
+        let index = match chunks.iter().position(|chunk| &chunk.id == b"cue ") {
+            Some(index) if index < data => index + 1,
+            Some(index) => {
+                let cue = chunks.remove(index);
+                chunks.insert(data, cue);
+                data + 1
+            }
+            None => data,
+        };
+        chunks.insert(index, Chunk::new(b"JUNK", vec![0; size as usize]));
+        Ok(())
+    })
+}
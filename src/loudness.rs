@@ -0,0 +1,111 @@
+// Integrated loudness per ITU-R BS.1770-4: K-weighting, 400 ms blocks
+// with 75% overlap, absolute gate at -70 LUFS and relative gate 10 LU
+// below the ungated level. All channels are weighted equally.
+
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.state[0];
+        self.state[0] = self.b[1] * input - self.a[0] * output + self.state[1];
+        self.state[1] = self.b[2] * input - self.a[1] * output;
+        output
+    }
+}
+
+// The two K-weighting stages, a high shelf and a high pass, with the
+// coefficients derived for the sampling rate
+fn k_weighting(sampling_rate: u32) -> [Biquad; 2] {
+    let rate = sampling_rate.max(1) as f64;
+    let k = (std::f64::consts::PI * 1681.974450955533 / rate).tan();
+    let q = 0.7071752369554196;
+    let vh = 10f64.powf(3.999843853973347 / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        state: [0.0; 2],
+    };
+    let k = (std::f64::consts::PI * 38.13547087602444 / rate).tan();
+    let q = 0.5003270373238773;
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        state: [0.0; 2],
+    };
+    [shelf, high_pass]
+}
+
+pub struct Meter {
+    filters: Vec<[Biquad; 2]>,
+    // frames in a 100 ms step
+    step: u64,
+    frames: u64,
+    sum: f64,
+    // mean square of each complete step
+    steps: Vec<f64>,
+}
+
+impl Meter {
+    pub fn new(number_of_channels: u16, sampling_rate: u32) -> Meter {
+        Meter {
+            filters: vec![k_weighting(sampling_rate); number_of_channels as usize],
+            step: (sampling_rate as u64 / 10).max(1),
+            frames: 0,
+            sum: 0.0,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn add_frame(&mut self, frame: &[f64]) {
+        for (filters, sample) in self.filters.iter_mut().zip(frame) {
+            let shelved = filters[0].process(*sample);
+            let weighted = filters[1].process(shelved);
+            self.sum += weighted * weighted;
+        }
+        self.frames += 1;
+        if self.frames == self.step {
+            self.steps.push(self.sum / self.step as f64);
+            self.frames = 0;
+            self.sum = 0.0;
+        }
+    }
+
+    // None if the audio is shorter than a block or entirely below the gate
+    pub fn integrated(&self) -> Option<f64> {
+        let blocks: Vec<f64> = self
+            .steps
+            .windows(4)
+            .map(|steps| steps.iter().sum::<f64>() / 4.0)
+            .filter(|power| block_loudness(*power) > -70.0)
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+        let relative_gate = block_loudness(mean(&blocks)) - 10.0;
+        let gated: Vec<f64> = blocks
+            .into_iter()
+            .filter(|power| block_loudness(*power) > relative_gate)
+            .collect();
+        Some(block_loudness(mean(&gated)))
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn block_loudness(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
@@ -1,325 +1,4676 @@
-#[macro_use]
-extern crate arrayref;
-
 use std::env;
-use std::fs::File;
-use std::io::BufReader;
-use std::io::Read;
-use thiserror::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+use wav_cue::bext::{self, BroadcastAudioExtension};
+use wav_cue::color::Color;
+use wav_cue::datetime::{self, DateTime};
+use wav_cue::duration::{self, Rounding, TimeValue};
+use wav_cue::ixml::{self, IXml};
+use wav_cue::riff;
+use wav_cue::timecode::{FrameRate, Timecode};
+use wav_cue::umid::{self, SourcePack};
+use wav_cue::value::{ObjectBuilder, Value};
+use wav_cue::{
+    acid, adm, adtl, cart, check, chna, config, convert, decoder, duplicates, files, generate, id3,
+    import, info, join, levl, lint, live, merge, metadata, pcm, project, redact, sha256, silence,
+    smpl, split, text, thumbnail, validate, xml, xmp, zip,
+};
+use wav_cue::{
+    next_cue_id, read_wave_file, read_wave_file_keeping, read_wave_file_needing,
+    read_wave_file_with, set_cue_chunk, CueEntry, DataChunkId, Error, Header, KeptChunk, Need,
+    ParseMode, WaveError, WaveFileInfo,
+};
 
-#[derive(Error, Debug)]
-pub struct WaveError {
-    pub message: String,
+#[derive(Debug, Clone, Copy)]
+enum OutputFormat {
+    Csv,
+    Json,
+    Yaml,
+    Toml,
+    Xlsx,
+    Podcast,
+    ChapterTool,
+    Id3,
+    Cubase,
+    Reaper,
+    Audition,
+    ProTools,
+    Fcpxml,
+    Premiere,
+    Edl,
+    Resolve,
+    Audacity,
+    CueSheet,
+    WebVtt,
+    Srt,
 }
 
-impl std::fmt::Display for WaveError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Failed to process: {}", self.message)
+impl OutputFormat {
+    // file name extension of the files written with export --output
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Toml => "toml",
+            OutputFormat::Xlsx => "xlsx",
+            OutputFormat::Podcast => "chapters.json",
+            OutputFormat::ChapterTool => "chapters.xml",
+            OutputFormat::Id3 => "id3",
+            OutputFormat::Cubase => "cubase.csv",
+            OutputFormat::Reaper => "reaper.csv",
+            OutputFormat::Audition => "audition.csv",
+            OutputFormat::ProTools => "protools.txt",
+            OutputFormat::Fcpxml => "fcpxml",
+            OutputFormat::Premiere => "premiere.xml",
+            OutputFormat::Edl => "edl",
+            OutputFormat::Resolve => "resolve.edl",
+            OutputFormat::Audacity => "txt",
+            OutputFormat::CueSheet => "cue",
+            OutputFormat::WebVtt => "vtt",
+            OutputFormat::Srt => "srt",
+        }
+    }
+
+    // formats of the whole report, with bext, channels and decoded chunks
+    fn is_report(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Json | OutputFormat::Yaml | OutputFormat::Toml | OutputFormat::Xlsx
+        )
+    }
+
+    // formats that are in timecode already, at the rate of --fps or iXML
+    fn is_timecode(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::ProTools
+                | OutputFormat::Premiere
+                | OutputFormat::Edl
+                | OutputFormat::Resolve
+        )
+    }
+
+    // formats that can hold the markers of several files in one document
+    fn is_combinable(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Csv | OutputFormat::Json | OutputFormat::Yaml | OutputFormat::Toml
+        )
     }
 }
 
-#[derive(Error, Debug)]
-pub enum Error {
-    #[error(transparent)]
-    WaveError(#[from] WaveError),
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Times {
+    // seconds from the start of the file
+    Seconds,
+    // timecode anchored to the bext TimeReference instead
+    RelativeToTimecode,
+    // timecode from the start of the file, 00:00:00:00 at the first sample
+    Timecode,
+    // seconds, wall clock time and timecode (when the frame rate is known)
+    // side by side
+    Both,
+    // bar:beat from the tempo and meter of the acid chunk
+    Bars,
+}
+
+struct ExportOptions {
+    format: OutputFormat,
+    sanitize: text::Sanitize,
+    // decimals of the seconds; the CSV defaults to 3, the other formats
+    // give full precision unless set
+    precision: Option<u32>,
+    rounding: Rounding,
+    // times as timecode anchored to the bext TimeReference, at the given
+    // frame rate or the one in iXML
+    times: Times,
+    frame_rate: Option<FrameRate>,
+    // list every chunk with its offset, size and payload hash
+    checksums: bool,
+    // measure the peak level of each channel, reading all of the audio
+    peaks: bool,
+    parsing: ParseMode,
+    // the chunks to read, stopping there instead of reading the whole file
+    need: Option<Vec<Need>>,
+}
 
-    // #[error(transparent)]
-    // TomlDeError(#[from] toml::de::Error),
+impl Default for ExportOptions {
+    fn default() -> ExportOptions {
+        ExportOptions {
+            format: OutputFormat::Csv,
+            sanitize: text::Sanitize::default(),
+            precision: None,
+            rounding: Rounding::Round,
+            times: Times::Seconds,
+            frame_rate: None,
+            checksums: false,
+            peaks: false,
+            parsing: ParseMode::Normal,
+            need: None,
+        }
+    }
+}
 
-    // #[error(transparent)]
-    // TomlSerError(#[from] toml::ser::Error),
-    #[error(transparent)]
-    IOError(#[from] std::io::Error),
-    // #[error(transparent)]
-    // AtomicIOError(#[from] atomicwrites::Error<io::Error>),
+impl ExportOptions {
+    fn read(&self, filename: &str) -> Result<WaveFileInfo, Error> {
+        read_wave_needing(filename, self.parsing, self.need.as_deref())
+    }
 
-    // #[error("Cannot determine default host: {}", .0)]
-    // DefaultHostError(String),
+    // The file and, for the reports, the payloads of the chunks they
+    // decode, kept in the one pass that standard input allows
+    fn read_export(
+        &self,
+        filename: &str,
+        decoders: &decoder::Registry,
+    ) -> Result<(WaveFileInfo, Vec<KeptChunk>), Error> {
+        if !self.format.is_report() {
+            return Ok((self.read(filename)?, Vec::new()));
+        }
+        let keep = |id: &[u8; 4]| decoders.decodes(id) || id == b"chna" || id == b"axml";
+        let (wave, chunks) =
+            read_wave_file_keeping(filename, self.parsing, self.need.as_deref(), &keep)?;
+        for warning in &wave.warnings {
+            eprintln!("{filename}: {warning}");
+        }
+        Ok((wave, chunks))
+    }
 
-    // #[error(transparent)]
-    // RumaIdentifierError(#[from] ruma_identifiers::Error),
+    fn seconds(&self, samples: u32, sampling_rate: u32) -> f64 {
+        match self.precision {
+            Some(precision) => {
+                duration::format_seconds(samples as u64, sampling_rate, precision, self.rounding)
+                    .parse()
+                    .unwrap_or_default()
+            }
+            None => samples as f64 / sampling_rate as f64,
+        }
+    }
 }
 
-#[derive(Debug)]
-enum DataChunkId {
-    Data,
-    Sint,
+// Peaks below this are considered silence
+const SILENCE_THRESHOLD_DBFS: f64 = -60.0;
+
+fn time_of_day(wave: &WaveFileInfo, sample_start: u32) -> Option<String> {
+    match wave.bext {
+        None => None,
+        Some(BroadcastAudioExtension { time_reference, .. }) => {
+            let time =
+                (time_reference + sample_start as u64) / wave.header.sampling_rate.max(1) as u64;
+            let hour = time / 3600;
+            let min = time / 60 % 60;
+            let sec = time % 60;
+            Some(format!("{}:{:02}:{:02}", hour, min, sec))
+        }
+    }
 }
 
-#[derive(Debug)]
-struct CueEntry {
-    cue_id: u32,
-    position: u32,
-    data_chunk_id: DataChunkId,
-    chunk_start: u32,
-    block_start: u32,
-    sample_start: u32,
+// Frame rate for timecode output, or None if times are output in seconds
+fn timecode_rate(wave: &WaveFileInfo, options: &ExportOptions) -> Result<Option<FrameRate>, Error> {
+    let ixml_rate = wave.ixml.as_ref().and_then(|ixml| ixml.frame_rate);
+    match options.times {
+        Times::Seconds | Times::Bars => return Ok(None),
+        // the timecode column is left empty when it can't be computed
+        Times::Both if wave.bext.is_none() => return Ok(None),
+        Times::Both => return Ok(options.frame_rate.or(ixml_rate)),
+        Times::RelativeToTimecode => (),
+        Times::Timecode => {
+            return options.frame_rate.or(ixml_rate).map(Some).ok_or_else(|| {
+                Error::UsageError(String::from("No frame rate in iXML, give one with --fps"))
+            })
+        }
+    }
+    if wave.bext.is_none() {
+        return Err(Error::from(WaveError {
+            message: String::from("No bext TimeReference to anchor the timecode to"),
+        }));
+    }
+    match options.frame_rate.or(ixml_rate) {
+        Some(frame_rate) => Ok(Some(frame_rate)),
+        None => Err(Error::UsageError(String::from(
+            "No frame rate in iXML, give one with --fps",
+        ))),
+    }
 }
 
-// bext: https://web.archive.org/web/20091229093941/http://tech.ebu.ch/docs/tech/tech3285.pdf page 7
-// typedef struct broadcast_audio_extension {
-//   CHAR Description[256]; /* ASCII : «Description of the sound sequence» */
-//   CHAR Originator[32]; /* ASCII : «Name of the originator» */
-//   CHAR OriginatorReference[32]; /* ASCII : «Reference of the originator» */
-//   CHAR OriginationDate[10]; /* ASCII : «yyyy-mm-dd» */
-//   CHAR OriginationTime[8]; /* ASCII : «hh-mm-ss» */
-//   DWORD TimeReferenceLow; /* First sample count since midnight low word */
-//   DWORD TimeReferenceHigh; /* First sample count since midnight, high word */
-//   WORD Version; /* Version of the BWF; unsigned binary number */
-//   BYTE UMID_0 /* Binary byte 0 of SMPTE UMID */
-//   ....
-//   BYTE UMID_63 /* Binary byte 63 of SMPTE UMID */
-//   BYTE Reserved[190] ; /* 190 bytes, reserved for future use, set to “NULL” */
-//   CHAR CodingHistory[]; /* ASCII : « History coding » */
-// } BROADCAST_EXT
-#[derive(Debug)]
-struct BroadcastAudioExtension {
-    description: String,          /* ASCII : «Description of the sound sequence» */
-    originator: String,           /* ASCII : «Name of the originator» */
-    originator_reference: String, /* ASCII : «Reference of the originator» */
-    origination_date: String,     /* ASCII : «yyyy-mm-dd» */
-    origination_time: String,     /* ASCII : «hh-mm-ss» */
-    time_reference: u64,          /* First sample count since midnight */
-    version: u16,                 /* Version of the BWF; unsigned binary number */
-}
-
-#[derive(Debug)]
-struct Header {
-    compression_code: u16,
-    number_of_channels: u16,
-    sampling_rate: u32,
-    average_bytes_per_second: u32,
-    block_align: u16,
-    significant_bits_per_sample: u16,
-    // number of extra format bytes	2	16
-    //	extra format bytes	various	0x1A
-}
-
-struct WaveFileInfo {
-    header: Header,
-    cues: Vec<CueEntry>,
-    bext: Option<BroadcastAudioExtension>,
-}
-
-fn read_wave(filename: &str) -> Result<WaveFileInfo, Error> {
-    let file = File::open(filename)?;
-    let mut reader = BufReader::new(file);
-    let mut cues = Vec::new();
-    let mut bext: Option<BroadcastAudioExtension> = None;
-    let mut header: Option<Header> = None;
-
-    let mut buf_riff: [u8; 4] = [0; 4];
-    reader.read_exact(&mut buf_riff)?;
-
-    // https://www.recordingblogs.com/wiki/format-chunk-of-a-wave-file
-    if &buf_riff == b"RIFF" {
-        let mut buf_size: [u8; 4] = [0; 4];
-        reader.read_exact(&mut buf_size)?;
-        let size = u32::from_le_bytes(buf_size);
-        let mut bytes_processed = 0u32;
-        eprintln!("Audio data size: {size}");
-        // Read.
-        let mut buf_wave: [u8; 4] = [0; 4];
-        reader.read_exact(&mut buf_wave)?;
-        if &buf_wave == b"WAVE" {
-            let mut buf_tag: [u8; 4] = [0; 4];
-            let mut buf_chunk32_size: [u8; 4] = [0; 4];
-            // walk chunks
-            while let Ok(()) = reader.read_exact(&mut buf_tag) {
-                reader.read_exact(&mut buf_chunk32_size)?;
-                let chunk_size = u32::from_le_bytes(buf_chunk32_size);
-                if chunk_size == 0 {
-                    return Err(Error::from(WaveError {
-                        message: String::from("Cannot process empty chunk"),
-                    }));
-                }
-                if &buf_tag == b"bext" {
-                    let mut buf_bext: [u8; 348] = [0; 348];
-                    if (chunk_size as usize) < buf_bext.len() {
-                        return Err(Error::from(WaveError {
-                            message: format!(
-                                "BroadcastAudioExtension chunk has unexpected size: {chunk_size}"
-                            ),
-                        }));
-                    }
-                    reader.read_exact(&mut buf_bext)?;
-                    reader.seek_relative(chunk_size as i64 - buf_bext.len() as i64)?;
-                    let mut ofs = 0;
-                    let description = String::from_utf8_lossy(array_ref!(buf_bext, ofs, 256))
-                        .trim_end_matches(char::from(0))
-                        .to_string();
-                    ofs += 256;
-                    let originator = String::from_utf8_lossy(array_ref!(buf_bext, ofs, 32))
-                        .trim_end_matches(char::from(0))
-                        .to_string();
-                    ofs += 32;
-                    let originator_reference =
-                        String::from_utf8_lossy(array_ref!(buf_bext, ofs, 32))
-                            .trim_end_matches(char::from(0))
-                            .to_string();
-                    ofs += 32;
-                    let origination_date =
-                        String::from_utf8_lossy(array_ref!(buf_bext, ofs, 10)).to_string();
-                    ofs += 10;
-                    let origination_time =
-                        String::from_utf8_lossy(array_ref!(buf_bext, ofs, 8)).to_string();
-                    ofs += 8;
-                    let time_reference_low = u32::from_le_bytes(*array_ref!(buf_bext, ofs, 4));
-                    ofs += 4;
-                    let time_reference_high = u32::from_le_bytes(*array_ref!(buf_bext, ofs, 4));
-                    ofs += 4;
-                    let version = u16::from_le_bytes(*array_ref!(buf_bext, ofs, 2));
-                    bext = Some(BroadcastAudioExtension {
-                        description,
-                        originator,
-                        originator_reference,
-                        origination_date,
-                        origination_time,
-                        time_reference: time_reference_low as u64
-                            | ((time_reference_high as u64) << 32),
-                        version,
-                    });
-                    eprintln!("{bext:?}");
-                } else if &buf_tag == b"fmt " {
-                    let mut buf_fmt: [u8; 16] = [0; 16];
-                    if chunk_size < 16 {
-                        return Err(Error::from(WaveError {
-                            message: format!("fmt chunk is too small ({chunk_size} bytes)"),
-                        }));
-                    }
-                    if header.is_some() {
-                        return Err(Error::from(WaveError {
-                            message: format!("File cannot have two fmt headers"),
-                        }));
-                    }
-                    reader.read_exact(&mut buf_fmt)?;
-                    reader.seek_relative(chunk_size as i64 - buf_fmt.len() as i64)?;
-                    let compression_code = u16::from_le_bytes(*array_ref!(buf_fmt, 0, 2));
-                    let number_of_channels = u16::from_le_bytes(*array_ref!(buf_fmt, 2, 2));
-                    let sampling_rate = u32::from_le_bytes(*array_ref!(buf_fmt, 4, 4));
-                    let average_bytes_per_second = u32::from_le_bytes(*array_ref!(buf_fmt, 8, 4));
-                    let block_align = u16::from_le_bytes(*array_ref!(buf_fmt, 12, 2));
-                    let significant_bits_per_sample =
-                        u16::from_le_bytes(*array_ref!(buf_fmt, 14, 2));
-                    header = Some(Header {
-                        compression_code,
-                        number_of_channels,
-                        sampling_rate,
-                        average_bytes_per_second,
-                        block_align,
-                        significant_bits_per_sample,
-                    });
-                    eprintln!("{header:?}");
-                } else if &buf_tag == b"cue " {
-                    // https://www.recordingblogs.com/wiki/cue-chunk-of-a-wave-file
-                    let mut buf_num_cue_points: [u8; 4] = [0; 4];
-                    reader.read_exact(&mut buf_num_cue_points)?;
-                    let num_cue_points = u32::from_le_bytes(buf_num_cue_points);
-                    if chunk_size != 4 + 24 * num_cue_points {
-                        return Err(Error::from(WaveError {
-                            message: format!("Incorrect chunk size for cue: {chunk_size}"),
-                        }));
-                    }
-                    for _ in 0..num_cue_points {
-                        let mut buf_cue: [u8; 24] = [0; 24];
-                        reader.read_exact(&mut buf_cue)?;
-
-                        let cue_id = u32::from_le_bytes(*array_ref!(buf_cue, 0, 4));
-                        let position = u32::from_le_bytes(*array_ref!(buf_cue, 4, 4));
-                        let data_chunk_id = {
-                            let id = array_ref!(buf_cue, 8, 4).clone();
-                            if &id == b"data" {
-                                DataChunkId::Data
-                            } else if &id == b"sint" {
-                                DataChunkId::Sint
-                            } else {
-                                return Err(Error::from(WaveError {
-				    message: format!("Data chunk id should be either 'data' or 'sint', not {id:?}"),
-				}));
-                            }
-                        };
+// Timecode of the position, counted from the TimeReference
+fn source_timecode(wave: &WaveFileInfo, frame_rate: &FrameRate, sample_start: u32) -> Timecode {
+    let time_reference = wave.bext.as_ref().map_or(0, |bext| bext.time_reference);
+    frame_rate.samples_to_time_of_day(
+        time_reference + sample_start as u64,
+        wave.header.sampling_rate,
+    )
+}
 
-                        let chunk_start = u32::from_le_bytes(*array_ref!(buf_cue, 12, 4));
+// Timecode of a marker: from the start of the file with --times
+// timecode, otherwise from the TimeReference
+fn marker_timecode(
+    wave: &WaveFileInfo,
+    frame_rate: &FrameRate,
+    sample_start: u32,
+    options: &ExportOptions,
+) -> Timecode {
+    match options.times {
+        Times::Timecode => {
+            frame_rate.samples_to_timecode(sample_start as u64, wave.header.sampling_rate)
+        }
+        _ => source_timecode(wave, frame_rate, sample_start),
+    }
+}
 
-                        let block_start = u32::from_le_bytes(*array_ref!(buf_cue, 16, 4));
+// The tempo and meter for --times bars
+fn bars_acid(wave: &WaveFileInfo) -> Result<&acid::Acid, Error> {
+    match &wave.acid {
+        Some(acid) if acid.has_tempo() => Ok(acid),
+        _ => Err(Error::from(WaveError {
+            message: String::from("No tempo in an acid chunk to count bars and beats with"),
+        })),
+    }
+}
 
-                        let sample_start = u32::from_le_bytes(*array_ref!(buf_cue, 20, 4));
+// Date and time of day of the position as yyyy-mm-ddThh:mm:ss.fff, from
+// the TimeReference (or OriginationTime if the TimeReference is zero) and
+// OriginationDate; without a valid date only the time of day is given
+fn wall_clock(wave: &WaveFileInfo, sample_start: u32, options: &ExportOptions) -> Option<String> {
+    let bext = wave.bext.as_ref()?;
+    let rate = wave.header.sampling_rate as u64;
+    let start = match bext.time_reference {
+        0 => bext::origination_seconds(&bext.origination_time).unwrap_or(0) * rate,
+        time_reference => time_reference,
+    };
+    let position = start + sample_start as u64;
+    let samples_per_day = rate * 86400;
+    let time = duration::format_clock(
+        position % samples_per_day.max(1),
+        wave.header.sampling_rate,
+        options.precision.unwrap_or(3),
+        options.rounding,
+    );
+    match bext::origination_date(&bext.origination_date) {
+        Some(date) => {
+            let days = position / samples_per_day.max(1);
+            let date = date.offset_by(days as i64 * 86400);
+            Some(format!("{}T{time}", date.date_string()))
+        }
+        None => Some(time),
+    }
+}
 
-                        let entry = CueEntry {
-                            cue_id,
-                            position,
-                            data_chunk_id,
-                            chunk_start,
-                            block_start,
-                            sample_start,
-                        };
+fn channels(wave: &WaveFileInfo) -> Vec<ixml::Channel> {
+    match &wave.ixml {
+        Some(ixml) => ixml.channels(wave.header.number_of_channels),
+        None => IXml::default().channels(wave.header.number_of_channels),
+    }
+}
 
-                        eprintln!("{entry:?}");
+// Names of the ADM objects playing each track, from the chna and axml
+// chunks; RIFX, AIFF and CAF files have none
+fn adm_objects(chunks: &[KeptChunk]) -> Result<Vec<(u16, String)>, Error> {
+    let payload = |id: &[u8; 4]| {
+        chunks
+            .iter()
+            .find(|(kept, _)| kept == id)
+            .map(|(_, data)| data)
+    };
+    let (ids, adm) = match (payload(b"chna"), payload(b"axml")) {
+        (Some(chna), Some(axml)) => (chna::parse(chna)?, adm::parse(axml)?),
+        _ => return Ok(Vec::new()),
+    };
+    Ok(ids
+        .iter()
+        .filter_map(|id| {
+            let object = adm.object_of_track(&id.uid)?;
+            Some((id.track_index, object.name.clone()))
+        })
+        .collect())
+}
 
-                        cues.push(entry);
-                    }
-                } else {
-                    eprintln!("skipping {}", String::from_utf8_lossy(&buf_tag));
-                    reader.seek_relative(chunk_size as i64)?;
+// The channels with their names; with measure_peaks also their peak
+// levels, which takes reading the audio
+fn channel_table(
+    filename: &str,
+    wave: &WaveFileInfo,
+    chunks: &[KeptChunk],
+    measure_peaks: bool,
+) -> Result<Value, Error> {
+    let channels = channels(wave);
+    let objects = adm_objects(chunks)?;
+    let peaks = match &wave.data {
+        // the samples of RIFX and AIFF files are big-endian, and those of
+        // compressed formats can't be read
+        Some(data)
+            if measure_peaks && !wave.big_endian && wave.header.compression_code.is_linear() =>
+        {
+            let mut reader = zip::open(filename)?;
+            Some(pcm::channel_peaks(
+                &mut reader,
+                &wave.header,
+                data.offset,
+                data.size,
+            )?)
+        }
+        _ => None,
+    };
+    Ok(Value::Array(
+        channels
+            .iter()
+            .map(|channel| {
+                let peak = peaks
+                    .as_ref()
+                    .filter(|_| channel.armed)
+                    .and_then(|peaks| peaks.get(channel.index as usize - 1))
+                    .map(|peak| pcm::to_dbfs(*peak));
+                let entry = ObjectBuilder::new()
+                    .field("index", channel.index)
+                    .field("name", channel.name.as_str())
+                    .field("armed", channel.armed)
+                    .field(
+                        "adm_object",
+                        objects
+                            .iter()
+                            .find(|(track, _)| *track as u32 == channel.index)
+                            .map(|(_, name)| name.as_str()),
+                    );
+                match measure_peaks {
+                    true => entry
+                        .field("peak_dbfs", peak.filter(|peak| peak.is_finite()))
+                        .field("silent", peak.map(|peak| peak < SILENCE_THRESHOLD_DBFS))
+                        .build(),
+                    false => entry.build(),
                 }
-                bytes_processed += chunk_size as u32;
-                // TODO: implement alingment per https://www.recordingblogs.com/wiki/format-chunk-of-a-wave-file
+            })
+            .collect(),
+    ))
+}
+
+// Raw text is kept next to the sanitized one
+fn region(region: &adtl::Region, sanitize: &text::Sanitize) -> Value {
+    let mut object = ObjectBuilder::new()
+        .field("sample_length", region.sample_length)
+        .field(
+            "purpose",
+            String::from_utf8_lossy(&region.purpose).trim_end(),
+        )
+        .field("country", region.country)
+        .field("language", region.language)
+        .field("dialect", region.dialect)
+        .field("code_page", region.code_page)
+        .field("text", sanitize.apply(&region.text));
+    if sanitize.is_active() {
+        object = object.field("text_raw", region.text.as_str());
+    }
+    object.build()
+}
+
+fn header(header: &Header) -> Value {
+    ObjectBuilder::new()
+        .field("compression_code", header.compression_code.code())
+        .field("compression", header.compression_code.name())
+        .field("number_of_channels", header.number_of_channels)
+        .field("sampling_rate", header.sampling_rate)
+        .field("average_bytes_per_second", header.average_bytes_per_second)
+        .field("block_align", header.block_align)
+        .field(
+            "significant_bits_per_sample",
+            header.significant_bits_per_sample,
+        )
+        .build()
+}
+
+// With a frame rate, the TimeReference is also given as timecode
+fn bext(
+    bext: &BroadcastAudioExtension,
+    sanitize: &text::Sanitize,
+    timecode: Option<Timecode>,
+) -> Value {
+    ObjectBuilder::new()
+        .field("description", sanitize.apply(&bext.description))
+        .field("originator", sanitize.apply(&bext.originator))
+        .field(
+            "originator_reference",
+            sanitize.apply(&bext.originator_reference),
+        )
+        .field("origination_date", bext.origination_date.as_str())
+        .field("origination_time", bext.origination_time.as_str())
+        .field("time_reference", bext.time_reference)
+        .field(
+            "time_reference_timecode",
+            timecode.map(|timecode| timecode.to_string()),
+        )
+        .field("version", bext.version)
+        .field(
+            "loudness",
+            bext.loudness.as_ref().map(|loudness| {
+                ObjectBuilder::new()
+                    .field("integrated", loudness.integrated)
+                    .field("range", loudness.range)
+                    .field("max_true_peak", loudness.max_true_peak)
+                    .field("max_momentary", loudness.max_momentary)
+                    .field("max_short_term", loudness.max_short_term)
+                    .build()
+            }),
+        )
+        .field("coding_history", sanitize.apply(&bext.coding_history))
+        .build()
+}
+
+// Sampler settings and loops of the smpl chunk
+fn sampler(sampler: &smpl::Sampler, sampling_rate: u32, options: &ExportOptions) -> Value {
+    let loops = sampler
+        .loops
+        .iter()
+        .map(|sample_loop| {
+            ObjectBuilder::new()
+                .field("cue_id", sample_loop.cue_id)
+                .field("type", sample_loop.loop_type.to_string())
+                .field("start", sample_loop.start)
+                .field("end", sample_loop.end)
+                .field(
+                    "start_seconds",
+                    options.seconds(sample_loop.start, sampling_rate),
+                )
+                .field(
+                    "end_seconds",
+                    options.seconds(sample_loop.end, sampling_rate),
+                )
+                .field("fraction", sample_loop.fraction)
+                .field("play_count", sample_loop.play_count)
+                .build()
+        })
+        .collect();
+    ObjectBuilder::new()
+        .field("manufacturer", sampler.manufacturer)
+        .field("product", sampler.product)
+        .field("sample_period", sampler.sample_period)
+        .field("midi_unity_note", sampler.midi_unity_note)
+        .field("midi_pitch_fraction", sampler.midi_pitch_fraction)
+        .field("pitch_fraction_cents", sampler.pitch_fraction_cents())
+        .field("smpte_format", sampler.smpte_format)
+        .field("smpte_offset", sampler.smpte_offset)
+        .field("loops", Value::Array(loops))
+        .build()
+}
+
+fn report(
+    filename: &str,
+    wave: &WaveFileInfo,
+    chunks: &[KeptChunk],
+    options: &ExportOptions,
+    decoders: &decoder::Registry,
+) -> Result<Value, Error> {
+    let frame_rate = timecode_rate(wave, options)?;
+    let acid = match options.times {
+        Times::Bars => Some(bars_acid(wave)?),
+        _ => None,
+    };
+    let frames = wave.frames();
+    let markers = wave
+        .cues
+        .iter()
+        .map(|cue| {
+            let mut marker = ObjectBuilder::new()
+                .field("id", cue.cue_id)
+                .field(
+                    "label",
+                    cue.label
+                        .as_deref()
+                        .map(|label| options.sanitize.apply(label)),
+                )
+                .field(
+                    "note",
+                    cue.note.as_deref().map(|note| options.sanitize.apply(note)),
+                )
+                .field("sample_start", cue.sample_start)
+                .field(
+                    "seconds",
+                    options.seconds(cue.sample_start, wave.header.sampling_rate),
+                )
+                .field("time_of_day", time_of_day(wave, cue.sample_start))
+                .field(
+                    "percent",
+                    frames
+                        .filter(|frames| *frames > 0)
+                        .map(|frames| cue.sample_start as f64 * 100.0 / frames as f64),
+                );
+            if options.times == Times::Both {
+                marker = marker.field("wall_clock", wall_clock(wave, cue.sample_start, options));
             }
-            eprintln!("bytes left: {}", size as i64 - bytes_processed as i64);
-        } else {
-            return Err(Error::from(WaveError {
-                message: format!("Not a wav file (no WAVE found)"),
-            }));
+            if let Some(acid) = acid {
+                marker = marker.field(
+                    "bars_beats",
+                    acid.bars_beats(
+                        cue.sample_start as u64,
+                        wave.header.sampling_rate,
+                        options.precision.unwrap_or(3),
+                    ),
+                );
+            }
+            if let Some(frame_rate) = &frame_rate {
+                marker = marker.field(
+                    "timecode",
+                    marker_timecode(wave, frame_rate, cue.sample_start, options).to_string(),
+                );
+            }
+            marker
+                .field("color", cue.color.map(|color| color.to_string()))
+                .field("url", cue.url.clone())
+                .field("image", cue.image.clone())
+                .field(
+                    "region",
+                    cue.region
+                        .as_ref()
+                        .map(|region| self::region(region, &options.sanitize)),
+                )
+                .build()
+        })
+        .collect();
+    let mut document = ObjectBuilder::new()
+        .field("filename", filename)
+        .field(
+            "title",
+            wave.title
+                .as_deref()
+                .map(|title| options.sanitize.apply(title)),
+        )
+        .field("header", header(&wave.header))
+        .field(
+            "bext",
+            wave.bext.as_ref().map(|bext| {
+                let timecode = frame_rate.as_ref().map(|frame_rate| {
+                    frame_rate
+                        .samples_to_time_of_day(bext.time_reference, wave.header.sampling_rate)
+                });
+                self::bext(bext, &options.sanitize, timecode)
+            }),
+        )
+        .field("markers", Value::Array(markers));
+    if options.parsing == ParseMode::Lenient {
+        let warnings = wave
+            .warnings
+            .iter()
+            .map(|warning| Value::from(warning.to_string()));
+        document = document.field("warnings", Value::Array(warnings.collect()));
+    }
+    if let Some(smpl) = &wave.sampler {
+        document = document.field("sampler", sampler(smpl, wave.header.sampling_rate, options));
+    }
+    if !wave.info.is_empty() {
+        let fields = wave
+            .info
+            .iter()
+            .map(|(id, value)| (id.clone(), Value::from(options.sanitize.apply(value))));
+        document = document.field("info", Value::Object(fields.collect()));
+    }
+    if !wave.playlist.is_empty() {
+        let segments = wave.playlist.iter().map(|segment| {
+            let label = wave
+                .cues
+                .iter()
+                .find(|cue| cue.cue_id == segment.cue_id)
+                .map(|cue| options.sanitize.apply(&cue.title()));
+            ObjectBuilder::new()
+                .field("cue_id", segment.cue_id)
+                .field("label", label)
+                .field("sample_length", segment.sample_length)
+                .field(
+                    "seconds",
+                    options.seconds(segment.sample_length, wave.header.sampling_rate),
+                )
+                .field("repeats", segment.repeats)
+                .build()
+        });
+        document = document.field("playlist", Value::Array(segments.collect()));
+    }
+    if let Some(acid) = &wave.acid {
+        document = document.field(
+            "acid",
+            ObjectBuilder::new()
+                .field("one_shot", acid.one_shot)
+                .field("stretch", acid.stretch)
+                .field("disk_based", acid.disk_based)
+                .field("root_note", acid.root_note.map(u32::from))
+                .field("beats", acid.beats)
+                .field(
+                    "meter",
+                    format!("{}/{}", acid.meter_numerator, acid.meter_denominator),
+                )
+                .field("tempo", acid.tempo as f64)
+                .build(),
+        );
+    }
+    if wave.header.number_of_channels > 1 {
+        // --need leaves the audio unread
+        let measure_peaks = options.peaks && options.need.is_none();
+        document = document.field(
+            "channels",
+            channel_table(filename, wave, chunks, measure_peaks)?,
+        );
+    }
+    let decoded = decoders.decode_chunks(chunks);
+    if !decoded.is_empty() {
+        document = document.field("chunks", Value::Object(decoded));
+    }
+    if options.checksums {
+        document = document.field("layout", chunk_checksums(filename)?);
+    }
+    Ok(document.build())
+}
+
+// Every chunk of the file in order, with the SHA-256 of its payload so that
+// changes between versions of a file can be pinned down to chunks
+fn chunk_checksums(filename: &str) -> Result<Value, Error> {
+    let mut reader = zip::open(filename)?;
+    let mut layout = Vec::new();
+    for info in riff::walk_chunks(&mut reader)? {
+        reader.seek(SeekFrom::Start(info.offset))?;
+        let sha256 = sha256::hex_digest(&mut (&mut reader).take(info.size))?;
+        layout.push(
+            ObjectBuilder::new()
+                .field("id", String::from_utf8_lossy(&info.id).to_string())
+                .field("offset", info.offset)
+                .field("size", info.size)
+                .field("sha256", sha256)
+                .build(),
+        );
+    }
+    Ok(Value::Array(layout))
+}
+
+// Podcasting 2.0 chapters: https://github.com/Podcastindex-org/podcast-namespace/blob/main/chapters/jsonChapters.md
+fn podcast_chapters(wave: &WaveFileInfo, options: &ExportOptions) -> Value {
+    let mut cues: Vec<&CueEntry> = wave.cues.iter().collect();
+    cues.sort_by_key(|cue| cue.sample_start);
+    let chapters = cues
+        .iter()
+        .map(|cue| {
+            let mut chapter = ObjectBuilder::new()
+                .field(
+                    "startTime",
+                    options.seconds(cue.sample_start, wave.header.sampling_rate),
+                )
+                .field("title", options.sanitize.apply(&cue.title()));
+            if let Some(end) = cue.sample_end() {
+                let end = end.min(u32::MAX as u64) as u32;
+                chapter = chapter.field("endTime", options.seconds(end, wave.header.sampling_rate));
+            }
+            if let Some(image) = &cue.image {
+                chapter = chapter.field("img", image.as_str());
+            }
+            if let Some(url) = &cue.url {
+                chapter = chapter.field("url", url.as_str());
+            }
+            chapter.build()
+        })
+        .collect();
+    ObjectBuilder::new()
+        .field("version", "1.2.0")
+        .field("chapters", Value::Array(chapters))
+        .build()
+}
+
+// Apple Chapter Tool XML, used for enhanced podcasts and audiobooks
+fn chaptertool_chapters(wave: &WaveFileInfo, options: &ExportOptions) -> String {
+    let mut cues: Vec<&CueEntry> = wave.cues.iter().collect();
+    cues.sort_by_key(|cue| cue.sample_start);
+    let mut output =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<chapters version=\"1\">\n");
+    for cue in cues {
+        let start_time = duration::format_clock(
+            cue.sample_start as u64,
+            wave.header.sampling_rate,
+            options.precision.unwrap_or(3),
+            options.rounding,
+        );
+        let title = options.sanitize.apply(&cue.title());
+        output.push_str(&format!("  <chapter starttime=\"{start_time}\">\n"));
+        output.push_str(&format!("    <title>{}</title>\n", xml::escape(&title)));
+        if let Some(image) = &cue.image {
+            output.push_str(&format!("    <picture>{}</picture>\n", xml::escape(image)));
+        }
+        if let Some(url) = &cue.url {
+            let url = xml::escape(url);
+            output.push_str(&format!("    <link href=\"{url}\">{url}</link>\n"));
+        }
+        output.push_str("  </chapter>\n");
+    }
+    output.push_str("</chapters>\n");
+    output
+}
+
+// ID3v2 chapters to put in front of the encoded MP3. Chapters without a
+// region of their own end where the next one starts, the last at the end
+// of the file.
+fn id3_chapters(wave: &WaveFileInfo, options: &ExportOptions) -> Vec<u8> {
+    let mut cues: Vec<&CueEntry> = wave.cues.iter().collect();
+    cues.sort_by_key(|cue| cue.sample_start);
+    let sampling_rate = wave.header.sampling_rate.max(1) as u64;
+    let ms = |samples: u64| (samples * 1000 / sampling_rate).min(u32::MAX as u64) as u32;
+    let file_end = wave.frames().unwrap_or(0);
+    let chapters: Vec<id3::Chapter> = cues
+        .iter()
+        .enumerate()
+        .map(|(index, cue)| {
+            let start = cue.sample_start as u64;
+            let end = cue.sample_end().unwrap_or_else(|| {
+                cues.get(index + 1)
+                    .map_or(file_end, |next| next.sample_start as u64)
+            });
+            id3::Chapter {
+                element_id: format!("chp{index}"),
+                start_ms: ms(start),
+                end_ms: ms(end.max(start)),
+                title: Some(options.sanitize.apply(&cue.title())),
+                url: cue.url.clone(),
+                image: cue.image.clone(),
+            }
+        })
+        .collect();
+    let title = wave
+        .title
+        .as_deref()
+        .map(|title| options.sanitize.apply(title));
+    id3::chapters_tag(title.as_deref(), &chapters)
+}
+
+// CSV marker list as imported into a marker track by Cubase and Nuendo;
+// cues with a length, from an ltxt region or the playlist, become cycle
+// markers
+fn cubase_markers(wave: &WaveFileInfo, options: &ExportOptions) -> String {
+    let clock = |samples: u64| {
+        duration::format_clock(
+            samples,
+            wave.header.sampling_rate,
+            options.precision.unwrap_or(3),
+            options.rounding,
+        )
+    };
+    let mut output = String::from("#,Name,Start,End,Length\n");
+    for (index, cue) in wave.sorted_cues().enumerate() {
+        let name = match &cue.region {
+            Some(region) if !region.text.is_empty() => region.text.clone(),
+            _ => cue.title(),
+        };
+        // quotes are doubled inside a quoted field
+        let name = options.sanitize.apply(&name).replace('"', "\"\"");
+        let start = cue.sample_start as u64;
+        let (end, length) = match cue.sample_end() {
+            Some(end) => (clock(end), clock(end - start)),
+            None => (String::new(), String::new()),
+        };
+        output.push_str(&format!(
+            "{},\"{name}\",{},{end},{length}\n",
+            index + 1,
+            clock(start)
+        ));
+    }
+    output
+}
+
+// Region/Marker Manager list of Reaper: cues with an ltxt region are
+// regions numbered R1, R2..., the others markers numbered M1, M2..., with
+// the marker color as RRGGBB
+fn reaper_markers(wave: &WaveFileInfo, options: &ExportOptions) -> String {
+    let clock = |samples: u64| {
+        duration::format_clock(
+            samples,
+            wave.header.sampling_rate,
+            options.precision.unwrap_or(3),
+            options.rounding,
+        )
+    };
+    let mut output = String::from("#,Name,Start,End,Length,Color\n");
+    let (mut markers, mut regions) = (0, 0);
+    for cue in wave.sorted_cues() {
+        let name = match &cue.region {
+            Some(region) if !region.text.is_empty() => region.text.clone(),
+            _ => cue.title(),
+        };
+        // quotes are doubled inside a quoted field
+        let name = options.sanitize.apply(&name).replace('"', "\"\"");
+        let start = cue.sample_start as u64;
+        let (number, end, length) = match cue.sample_end() {
+            Some(end) => {
+                regions += 1;
+                (format!("R{regions}"), clock(end), clock(end - start))
+            }
+            None => {
+                markers += 1;
+                (format!("M{markers}"), String::new(), String::new())
+            }
+        };
+        let color = cue.color.map_or(String::new(), |color| {
+            format!("{:02X}{:02X}{:02X}", color.red, color.green, color.blue)
+        });
+        output.push_str(&format!(
+            "{number},\"{name}\",{},{end},{length},{color}\n",
+            clock(start)
+        ));
+    }
+    output
+}
+
+// Marker list of Audition's Markers panel: tab separated name, start,
+// duration, time format, type and description, in the decimal time
+// format. Cues with a region have its length as duration.
+fn audition_markers(wave: &WaveFileInfo, options: &ExportOptions) -> String {
+    let time = |samples: u64| {
+        duration::format_minutes(
+            samples,
+            wave.header.sampling_rate,
+            options.precision.unwrap_or(3),
+            options.rounding,
+        )
+    };
+    // a tab or a line break would end the field
+    let field = |text: &str| {
+        options
+            .sanitize
+            .apply(text)
+            .replace(['\t', '\r', '\n'], " ")
+    };
+    let mut output = String::from("Name\tStart\tDuration\tTime Format\tType\tDescription\n");
+    for cue in wave.sorted_cues() {
+        let start = cue.sample_start as u64;
+        let length = cue.sample_end().map_or(0, |end| end - start);
+        output.push_str(&format!(
+            "{}\t{}\t{}\tdecimal\tCue\t{}\n",
+            field(&cue.title()),
+            time(start),
+            time(length),
+            field(cue.note.as_deref().unwrap_or(""))
+        ));
+    }
+    output
+}
+
+// Timecode format as Pro Tools names it, e.g. 29.97 Drop Frame
+fn protools_timecode_format(frame_rate: &FrameRate) -> String {
+    let fps = match frame_rate.denominator {
+        1 => frame_rate.numerator.to_string(),
+        _ => {
+            let fps = format!(
+                "{:.3}",
+                frame_rate.numerator as f64 / frame_rate.denominator as f64
+            );
+            fps.trim_end_matches('0').to_string()
+        }
+    };
+    match frame_rate.drop_frame {
+        true => format!("{fps} Drop Frame"),
+        false => format!("{fps} Frame"),
+    }
+}
+
+// The session header and markers section of Pro Tools' Export Session
+// Info as Text, a memory location per cue. The session starts at the
+// bext TimeReference, and the locations are counted from the start of the
+// timeline: as timecode with a frame rate from --fps or iXML, otherwise
+// as min:sec and samples.
+fn protools_markers(filename: &str, wave: &WaveFileInfo, options: &ExportOptions) -> String {
+    let rate = wave.header.sampling_rate;
+    let time_reference = wave.bext.as_ref().map_or(0, |bext| bext.time_reference);
+    let frame_rate = options
+        .frame_rate
+        .or(wave.ixml.as_ref().and_then(|ixml| ixml.frame_rate));
+    let min_sec = |samples: u64| {
+        duration::format_minutes(
+            samples,
+            rate,
+            options.precision.unwrap_or(3),
+            options.rounding,
+        )
+    };
+    let location = |sample: u32| match &frame_rate {
+        Some(frame_rate) => source_timecode(wave, frame_rate, sample).to_string(),
+        None => min_sec(time_reference + sample as u64),
+    };
+    let session = Path::new(filename)
+        .file_stem()
+        .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+    let mut output = format!(
+        "SESSION NAME:\t{session}\nSAMPLE RATE:\t{rate}.000000\nBIT DEPTH:\t{}-bit\n",
+        wave.header.significant_bits_per_sample
+    );
+    match &frame_rate {
+        Some(frame_rate) => output.push_str(&format!(
+            "SESSION START:\t{}\nTIMECODE FORMAT:\t{}\n",
+            location(0),
+            protools_timecode_format(frame_rate)
+        )),
+        None => output.push_str(&format!("SESSION START:\t{}\n", location(0))),
+    }
+    output.push_str("# OF AUDIO TRACKS:\t1\n# OF AUDIO CLIPS:\t1\n# OF AUDIO FILES:\t1\n\n\n");
+    output.push_str("M A R K E R S  L I S T I N G\n");
+    output.push_str(&format!(
+        "{:<4}\t{:<13}\t{:<18}\t{:<9}\t{:<33}\tCOMMENTS\n",
+        "#", "LOCATION", "TIME REFERENCE", "UNITS", "NAME"
+    ));
+    // a tab or a line break would end the field
+    let field = |text: &str| {
+        options
+            .sanitize
+            .apply(text)
+            .replace(['\t', '\r', '\n'], " ")
+    };
+    for (index, cue) in wave.sorted_cues().enumerate() {
+        output.push_str(&format!(
+            "{:<4}\t{:<13}\t{:<18}\t{:<9}\t{:<33}\t{}\n",
+            index + 1,
+            location(cue.sample_start),
+            time_reference + cue.sample_start as u64,
+            "Samples",
+            field(&cue.title()),
+            field(cue.note.as_deref().unwrap_or(""))
+        ));
+    }
+    output
+}
+
+// file: URL of the file, with the bytes other than letters, digits and
+// -._~/ percent encoded
+fn file_url(filename: &str) -> String {
+    let path = fs::canonicalize(filename).unwrap_or_else(|_| PathBuf::from(filename));
+    let mut url = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{byte:02X}")),
         }
+    }
+    url
+}
+
+// Final Cut Pro XML with the file as an audio asset and a clip of it in an
+// event, with a chapter marker at each cue. Times are rational seconds in
+// samples; the clip starts at the bext TimeReference, so that it lines up
+// with the source timecode.
+fn fcpxml(filename: &str, wave: &WaveFileInfo, options: &ExportOptions) -> String {
+    let rate = wave.header.sampling_rate;
+    let time = |samples: u64| match samples {
+        0 => String::from("0s"),
+        _ => format!("{samples}/{rate}s"),
+    };
+    let start = wave.bext.as_ref().map_or(0, |bext| bext.time_reference);
+    let duration = time(wave.frames().unwrap_or(0));
+    let name = xml::escape(
+        &Path::new(filename)
+            .file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().to_string()),
+    );
+    let mut output = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE fcpxml>\n<fcpxml version=\"1.9\">\n",
+    );
+    output.push_str("  <resources>\n");
+    output.push_str(&format!(
+        "    <asset id=\"r1\" name=\"{name}\" start=\"{}\" duration=\"{duration}\" hasAudio=\"1\" audioSources=\"1\" audioChannels=\"{}\" audioRate=\"{rate}\">\n",
+        time(start),
+        wave.header.number_of_channels
+    ));
+    output.push_str(&format!(
+        "      <media-rep kind=\"original-media\" src=\"{}\"/>\n",
+        xml::escape(&file_url(filename))
+    ));
+    output.push_str("    </asset>\n  </resources>\n  <library>\n");
+    output.push_str(&format!("    <event name=\"{name}\">\n"));
+    output.push_str(&format!(
+        "      <asset-clip ref=\"r1\" name=\"{name}\" start=\"{}\" duration=\"{duration}\">\n",
+        time(start)
+    ));
+    for cue in wave.sorted_cues() {
+        output.push_str(&format!(
+            "        <chapter-marker start=\"{}\" value=\"{}\" posterOffset=\"0s\"/>\n",
+            time(start + cue.sample_start as u64),
+            xml::escape(&options.sanitize.apply(&cue.title()))
+        ));
+    }
+    output.push_str("      </asset-clip>\n    </event>\n  </library>\n</fcpxml>\n");
+    output
+}
+
+// Final Cut Pro 7 XML (xmeml) as Premiere imports it: a sequence with the
+// file on an audio track and a sequence marker at each cue, counted in
+// frames of the sequence rate. The sequence timecode starts at the bext
+// TimeReference. import --from premiere reads it back.
+fn premiere_xml(
+    filename: &str,
+    wave: &WaveFileInfo,
+    options: &ExportOptions,
+) -> Result<String, Error> {
+    let frame_rate = export_frame_rate(wave, options)?;
+    let sampling_rate = wave.header.sampling_rate;
+    let frames = |samples: u64| frame_rate.samples_to_frames(samples, sampling_rate);
+    let number = |name: &str, value: u64| xml::Element::new(name).with_text(&value.to_string());
+    // timebase 30 with ntsc TRUE is 29.97
+    let rate = || {
+        let ntsc = if frame_rate.denominator == 1001 {
+            "TRUE"
+        } else {
+            "FALSE"
+        };
+        xml::Element::new("rate")
+            .with_child(number("timebase", frame_rate.nominal()))
+            .with_child(xml::Element::new("ntsc").with_text(ntsc))
+    };
+    let duration = frames(wave.frames().unwrap_or(0));
+    let name = Path::new(filename)
+        .file_stem()
+        .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+    let file_name = Path::new(filename)
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().to_string());
+    let start = wave.bext.as_ref().map_or(0, |bext| bext.time_reference);
+    let timecode = xml::Element::new("timecode")
+        .with_child(rate())
+        .with_child(
+            xml::Element::new("string")
+                .with_text(&source_timecode(wave, &frame_rate, 0).to_string()),
+        )
+        .with_child(number("frame", frames(start)))
+        .with_child(
+            xml::Element::new("displayformat").with_text(if frame_rate.drop_frame {
+                "DF"
+            } else {
+                "NDF"
+            }),
+        );
+    let file = xml::Element::new("file")
+        .with_attribute("id", "file-1")
+        .with_child(xml::Element::new("name").with_text(&file_name))
+        .with_child(xml::Element::new("pathurl").with_text(&file_url(filename)))
+        .with_child(rate())
+        .with_child(number("duration", duration))
+        .with_child(
+            xml::Element::new("media").with_child(
+                xml::Element::new("audio")
+                    .with_child(
+                        xml::Element::new("samplecharacteristics")
+                            .with_child(number(
+                                "depth",
+                                wave.header.significant_bits_per_sample as u64,
+                            ))
+                            .with_child(number("samplerate", sampling_rate as u64)),
+                    )
+                    .with_child(number(
+                        "channelcount",
+                        wave.header.number_of_channels as u64,
+                    )),
+            ),
+        );
+    let clip = xml::Element::new("clipitem")
+        .with_attribute("id", "clipitem-1")
+        .with_child(xml::Element::new("name").with_text(&name))
+        .with_child(number("duration", duration))
+        .with_child(rate())
+        .with_child(number("start", 0))
+        .with_child(number("end", duration))
+        .with_child(number("in", 0))
+        .with_child(number("out", duration))
+        .with_child(file);
+    let mut sequence = xml::Element::new("sequence")
+        .with_attribute("id", "sequence-1")
+        .with_child(xml::Element::new("name").with_text(&name))
+        .with_child(number("duration", duration))
+        .with_child(rate())
+        .with_child(timecode)
+        .with_child(xml::Element::new("media").with_child(
+            xml::Element::new("audio").with_child(xml::Element::new("track").with_child(clip)),
+        ));
+    for cue in wave.sorted_cues() {
+        // out is -1 for markers without a duration
+        let out = match cue.sample_end() {
+            Some(end) => frames(end).to_string(),
+            None => String::from("-1"),
+        };
+        sequence = sequence.with_child(
+            xml::Element::new("marker")
+                .with_child(
+                    xml::Element::new("comment")
+                        .with_text(&options.sanitize.apply(cue.note.as_deref().unwrap_or(""))),
+                )
+                .with_child(
+                    xml::Element::new("name").with_text(&options.sanitize.apply(&cue.title())),
+                )
+                .with_child(number("in", frames(cue.sample_start as u64)))
+                .with_child(xml::Element::new("out").with_text(&out)),
+        );
+    }
+    let root = xml::Element::new("xmeml")
+        .with_attribute("version", "4")
+        .with_child(sequence);
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE xmeml>\n{}",
+        root.to_xml()
+    ))
+}
+
+// The locator colors of Avid, which EDL LOC comments name
+const LOCATOR_COLORS: [(&str, Color); 8] = [
+    ("WHITE", Color::new(255, 255, 255)),
+    ("RED", Color::new(255, 0, 0)),
+    ("GREEN", Color::new(0, 255, 0)),
+    ("BLUE", Color::new(0, 0, 255)),
+    ("CYAN", Color::new(0, 255, 255)),
+    ("MAGENTA", Color::new(255, 0, 255)),
+    ("YELLOW", Color::new(255, 255, 0)),
+    ("BLACK", Color::new(0, 0, 0)),
+];
+
+// Frame rate of the timecode formats, from --fps or iXML
+fn export_frame_rate(wave: &WaveFileInfo, options: &ExportOptions) -> Result<FrameRate, Error> {
+    match options
+        .frame_rate
+        .or(wave.ixml.as_ref().and_then(|ixml| ixml.frame_rate))
+    {
+        Some(frame_rate) => Ok(frame_rate),
+        None => Err(Error::UsageError(String::from(
+            "No frame rate in iXML, give one with --fps",
+        ))),
+    }
+}
+
+// CMX3600 EDL with the file as one event at its source timecode, counted
+// from the bext TimeReference, and a * LOC: comment at each cue with the
+// closest locator color
+fn edl(filename: &str, wave: &WaveFileInfo, options: &ExportOptions) -> Result<String, Error> {
+    let frame_rate = export_frame_rate(wave, options)?;
+    let timecode = |sample: u64| {
+        let sample = sample.min(u32::MAX as u64) as u32;
+        source_timecode(wave, &frame_rate, sample).to_string()
+    };
+    let path = Path::new(filename);
+    let title = path
+        .file_stem()
+        .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+    let mut output = format!("TITLE: {}\n", options.sanitize.apply(&title));
+    output.push_str(match frame_rate.drop_frame {
+        true => "FCM: DROP FRAME\n\n",
+        false => "FCM: NON-DROP FRAME\n\n",
+    });
+    // A1 for mono files, A1 and A2 for the others
+    let track = if wave.header.number_of_channels == 1 {
+        "A"
     } else {
+        "AA"
+    };
+    let (start, end) = (timecode(0), timecode(wave.frames().unwrap_or(0)));
+    output.push_str(&format!(
+        "001  AX       {track:<5} C        {start} {end} {start} {end}\n"
+    ));
+    if let Some(name) = path.file_name() {
+        output.push_str(&format!("* FROM CLIP NAME: {}\n", name.to_string_lossy()));
+    }
+    for cue in wave.sorted_cues() {
+        let color = cue
+            .color
+            .and_then(|color| color.nearest(&LOCATOR_COLORS))
+            .unwrap_or("WHITE");
+        // a line break would end the comment
+        let name = options
+            .sanitize
+            .apply(&cue.title())
+            .replace(['\r', '\n'], " ");
+        output.push_str(&format!(
+            "* LOC: {} {color:<7} {name}\n",
+            timecode(cue.sample_start as u64)
+        ));
+    }
+    Ok(output)
+}
+
+// The marker colors of DaVinci Resolve, Blue being its default
+const RESOLVE_COLORS: [(&str, Color); 16] = [
+    ("Blue", Color::new(65, 105, 225)),
+    ("Cyan", Color::new(0, 206, 209)),
+    ("Green", Color::new(0, 170, 0)),
+    ("Yellow", Color::new(240, 200, 0)),
+    ("Red", Color::new(220, 20, 20)),
+    ("Pink", Color::new(255, 105, 180)),
+    ("Purple", Color::new(128, 0, 160)),
+    ("Fuchsia", Color::new(210, 0, 210)),
+    ("Rose", Color::new(255, 160, 170)),
+    ("Lavender", Color::new(180, 160, 230)),
+    ("Sky", Color::new(135, 206, 235)),
+    ("Mint", Color::new(150, 230, 180)),
+    ("Lemon", Color::new(250, 250, 120)),
+    ("Sand", Color::new(200, 170, 120)),
+    ("Cocoa", Color::new(110, 70, 40)),
+    ("Cream", Color::new(250, 240, 210)),
+];
+
+// The marker EDL DaVinci Resolve imports onto a timeline: an event per
+// cue at its source timecode, a frame long or as long as its region, with
+// the color, name and duration in frames in a |C: |M: |D: comment
+fn resolve_markers(
+    filename: &str,
+    wave: &WaveFileInfo,
+    options: &ExportOptions,
+) -> Result<String, Error> {
+    let frame_rate = export_frame_rate(wave, options)?;
+    let sampling_rate = wave.header.sampling_rate;
+    let time_reference = wave.bext.as_ref().map_or(0, |bext| bext.time_reference);
+    let timecode = |frames: u64| {
+        let samples = frame_rate.frames_to_samples(frames, sampling_rate);
+        frame_rate
+            .samples_to_time_of_day(samples, sampling_rate)
+            .to_string()
+    };
+    let title = Path::new(filename)
+        .file_stem()
+        .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+    let mut output = format!("TITLE: {}\n", options.sanitize.apply(&title));
+    output.push_str(match frame_rate.drop_frame {
+        true => "FCM: DROP FRAME\n\n",
+        false => "FCM: NON-DROP FRAME\n\n",
+    });
+    for (index, cue) in wave.sorted_cues().enumerate() {
+        let start =
+            frame_rate.samples_to_frames(time_reference + cue.sample_start as u64, sampling_rate);
+        let length = cue.sample_end().map_or(1, |end| {
+            let end = frame_rate.samples_to_frames(time_reference + end, sampling_rate);
+            end.saturating_sub(start).max(1)
+        });
+        let (start, end) = (timecode(start), timecode(start + length));
+        let color = cue
+            .color
+            .and_then(|color| color.nearest(&RESOLVE_COLORS))
+            .unwrap_or("Blue");
+        // a line break would end the comment, a bar the field
+        let name = options
+            .sanitize
+            .apply(&cue.title())
+            .replace(['\r', '\n', '|'], " ");
+        output.push_str(&format!(
+            "{:03}  001      V     C        {start} {end} {start} {end}  \n |C:ResolveColor{color} |M:{name} |D:{length}\n\n",
+            index + 1
+        ));
+    }
+    Ok(output)
+}
+
+// Label track for Audacity's Import Labels: start, end and label separated
+// by tabs, with the regions spanning their length and other cues as points
+fn audacity_labels(wave: &WaveFileInfo, options: &ExportOptions) -> String {
+    let seconds = |samples: u64| {
+        duration::format_seconds(
+            samples,
+            wave.header.sampling_rate,
+            options.precision.unwrap_or(6),
+            options.rounding,
+        )
+    };
+    let mut output = String::new();
+    for cue in wave.sorted_cues() {
+        let start = cue.sample_start as u64;
+        let end = start
+            + cue
+                .region
+                .as_ref()
+                .map_or(0, |region| region.sample_length as u64);
+        // a tab or a line break would end the label
+        let label = options
+            .sanitize
+            .apply(&cue.title())
+            .replace(['\t', '\r', '\n'], " ");
+        output.push_str(&format!("{}\t{}\t{label}\n", seconds(start), seconds(end)));
+    }
+    output
+}
+
+// Audio CD track count limit
+const MAX_TRACKS: usize = 99;
+
+// CUE sheet with a track starting at each cue point, for CD burning and
+// track splitting tools. Times are MM:SS:FF in CD frames of 1/75 s; the
+// audio before the first cue becomes a track of its own.
+fn cue_sheet(
+    filename: &str,
+    wave: &WaveFileInfo,
+    options: &ExportOptions,
+) -> Result<String, Error> {
+    // quotes can't be escaped in a CUE sheet
+    let quoted = |text: &str| format!("\"{}\"", options.sanitize.apply(text).replace('"', "'"));
+    let mut starts: Vec<(u64, Option<String>)> = wave
+        .sorted_cues()
+        .map(|cue| (cue.sample_start as u64, Some(cue.title())))
+        .collect();
+    starts.dedup_by_key(|(start, _)| *start);
+    if starts.first().is_none_or(|(start, _)| *start > 0) {
+        starts.insert(0, (0, None));
+    }
+    if starts.len() > MAX_TRACKS {
         return Err(Error::from(WaveError {
-            message: format!("Not a wav file (no RIFF found)"),
+            message: format!(
+                "{} tracks is more than the {MAX_TRACKS} of an audio CD",
+                starts.len()
+            ),
         }));
     }
-
-    let header = match header {
-        Some(header) => header,
-        None => {
-            return Err(Error::from(WaveError {
-                message: format!("File did not have header"),
-            }))
+    let mut output = String::new();
+    if let Some(bext) = &wave.bext {
+        if !bext.description.is_empty() {
+            output.push_str(&format!("TITLE {}\n", quoted(&bext.description)));
+        }
+        if !bext.originator.is_empty() {
+            output.push_str(&format!("PERFORMER {}\n", quoted(&bext.originator)));
+        }
+    }
+    let file_name = Path::new(filename).file_name().map_or_else(
+        || filename.to_string(),
+        |name| name.to_string_lossy().to_string(),
+    );
+    output.push_str(&format!("FILE {} WAVE\n", quoted(&file_name)));
+    for (index, (start, title)) in starts.iter().enumerate() {
+        let frames = start * 75 / wave.header.sampling_rate.max(1) as u64;
+        output.push_str(&format!("  TRACK {:02} AUDIO\n", index + 1));
+        if let Some(title) = title {
+            output.push_str(&format!("    TITLE {}\n", quoted(title)));
         }
+        output.push_str(&format!(
+            "    INDEX 01 {:02}:{:02}:{:02}\n",
+            frames / 75 / 60,
+            frames / 75 % 60,
+            frames % 75
+        ));
+    }
+    Ok(output)
+}
+
+// The cues with where they end: with their region, otherwise at the
+// next cue further on, and the last one at the end of the file
+fn cue_spans(wave: &WaveFileInfo) -> Vec<(&CueEntry, u64, u64)> {
+    let cues: Vec<&CueEntry> = wave.sorted_cues().collect();
+    let file_end = wave.frames().unwrap_or(0);
+    cues.iter()
+        .enumerate()
+        .map(|(index, cue)| {
+            let start = cue.sample_start as u64;
+            let end = cue.sample_end().unwrap_or_else(|| {
+                cues[index + 1..]
+                    .iter()
+                    .map(|next| next.sample_start as u64)
+                    .find(|next| *next > start)
+                    .unwrap_or(file_end)
+            });
+            (*cue, start, end.max(start))
+        })
+        .collect()
+}
+
+// WebVTT chapters for HTML5 media players and podcast apps: a cue per
+// chapter, numbered, with its label as the text
+fn webvtt_chapters(wave: &WaveFileInfo, options: &ExportOptions) -> String {
+    // WebVTT timestamps always have milliseconds
+    let timestamp = |samples: u64| {
+        duration::format_clock(samples, wave.header.sampling_rate, 3, options.rounding)
+    };
+    let mut output = String::from("WEBVTT\n");
+    for (index, (cue, start, end)) in cue_spans(wave).into_iter().enumerate() {
+        // a line break would end the cue text
+        let text = options
+            .sanitize
+            .apply(&cue.title())
+            .replace(['\r', '\n'], " ")
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        output.push_str(&format!(
+            "\n{}\n{} --> {}\n{text}\n",
+            index + 1,
+            timestamp(start),
+            timestamp(end)
+        ));
+    }
+    output
+}
+
+// SubRip subtitles with an entry per cue, to follow the markers of a
+// recording in a video player
+fn srt_subtitles(wave: &WaveFileInfo, options: &ExportOptions) -> String {
+    // hh:mm:ss,mmm
+    let timestamp = |samples: u64| {
+        duration::format_clock(samples, wave.header.sampling_rate, 3, options.rounding)
+            .replace('.', ",")
     };
+    let mut output = String::new();
+    for (index, (cue, start, end)) in cue_spans(wave).into_iter().enumerate() {
+        // a blank line would end the entry
+        let text = options
+            .sanitize
+            .apply(&cue.title())
+            .replace(['\r', '\n'], " ");
+        output.push_str(&format!(
+            "{}\n{} --> {}\n{text}\n\n",
+            index + 1,
+            timestamp(start),
+            timestamp(end)
+        ));
+    }
+    output
+}
+
+// Whether a cue of the file has a region with a length, for the end
+// column of the CSV
+fn has_regions(wave: &WaveFileInfo) -> bool {
+    wave.cues.iter().any(|cue| cue.sample_end().is_some())
+}
+
+// A CSV field, quoted as RFC 4180 has it if it holds a comma, a quote or
+// a line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
 
-    Ok(WaveFileInfo { header, bext, cues })
+// The markers as CSV: seconds and label, or with a header line naming
+// the columns when there is more, the end of the regions or the wall
+// clock and timecode of --times both
+fn csv(wave: &WaveFileInfo, options: &ExportOptions, end_column: bool) -> Result<String, Error> {
+    let (header, rows) = csv_rows(wave, options, end_column)?;
+    let mut output = header.map_or(String::new(), |header| format!("{header}\n"));
+    for row in rows {
+        output.push_str(&format!("{row}\n"));
+    }
+    Ok(output)
 }
 
-fn process(filename: &str) -> Result<(), Error> {
-    let wave = read_wave(filename)?;
-    for cue in wave.cues {
+// The header line, if the columns need one, and a row per cue
+fn csv_rows(
+    wave: &WaveFileInfo,
+    options: &ExportOptions,
+    end_column: bool,
+) -> Result<(Option<String>, Vec<String>), Error> {
+    let mut rows = Vec::new();
+    let end_header = if end_column { "end," } else { "" };
+    let seconds = |samples: u64| {
+        duration::format_seconds(
+            samples,
+            wave.header.sampling_rate,
+            options.precision.unwrap_or(3),
+            options.rounding,
+        )
+    };
+    if options.times == Times::Both {
+        let frame_rate = timecode_rate(wave, options)?;
+        let header = format!("seconds,{end_header}wall_clock,timecode,label");
+        for cue in &wave.cues {
+            let timecode = frame_rate
+                .as_ref()
+                .map(|frame_rate| source_timecode(wave, frame_rate, cue.sample_start))
+                .map(|timecode| timecode.to_string())
+                .unwrap_or_default();
+            let wall_clock = wall_clock(wave, cue.sample_start, options).unwrap_or_default();
+            let end = match end_column {
+                true => format!("{},", cue.sample_end().map(seconds).unwrap_or_default()),
+                false => String::new(),
+            };
+            let title = csv_field(&options.sanitize.apply(&cue.title()));
+            rows.push(format!(
+                "{},{end}{wall_clock},{timecode},{title}",
+                seconds(cue.sample_start as u64)
+            ));
+        }
+        return Ok((Some(header), rows));
+    }
+    let frame_rate = timecode_rate(wave, options)?;
+    let acid = match options.times {
+        Times::Bars => Some(bars_acid(wave)?),
+        _ => None,
+    };
+    // positions as bars and beats, timecode or seconds as asked for
+    let time = |sample: u64| {
+        let bars = acid.and_then(|acid| {
+            acid.bars_beats(
+                sample,
+                wave.header.sampling_rate,
+                options.precision.unwrap_or(3),
+            )
+        });
+        match (&frame_rate, bars) {
+            (_, Some(bars)) => bars,
+            (Some(frame_rate), None) => {
+                let sample = sample.min(u32::MAX as u64) as u32;
+                marker_timecode(wave, frame_rate, sample, options).to_string()
+            }
+            (None, None) => seconds(sample),
+        }
+    };
+    let header = end_column.then(|| String::from("seconds,end,label"));
+    for cue in &wave.cues {
         let sample_start = cue.sample_start;
-        let seconds = sample_start as f64 / wave.header.sampling_rate as f64;
-        let time_label = match wave.bext {
+        let time_label = match time_of_day(wave, sample_start) {
             None => String::from(""),
-            Some(BroadcastAudioExtension { time_reference, .. }) => {
-                let time = (time_reference as f64 + sample_start as f64)
-                    / wave.header.sampling_rate as f64;
-                let hour = (time / 3600f64) as u32;
-                let min = (time / 60f64) as u32 % 60u32;
-                let sec = time as u32 % 60u32;
-                let time_fmt = format!(" {}:{:02}:{:02}", hour, min, sec);
-                time_fmt
-            }
+            Some(time) => format!(" {time}"),
         };
-
-        println!("{:.3},Mark {}{}", seconds, cue.cue_id, time_label);
+        let title = csv_field(
+            &options
+                .sanitize
+                .apply(&format!("{}{}", cue.title(), time_label)),
+        );
+        let end = match end_column {
+            true => format!("{},", cue.sample_end().map(time).unwrap_or_default()),
+            false => String::new(),
+        };
+        rows.push(format!("{},{end}{title}", time(sample_start as u64)));
     }
+    Ok((header, rows))
+}
+
+fn process(filename: &str, options: &ExportOptions) -> Result<(), Error> {
+    io::stdout().write_all(&render(filename, options)?)?;
     Ok(())
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
-        let filename = &args[1];
-        if let Err(error) = process(filename) {
+// The markers of the file in the output format, which for ID3 is binary
+fn render(filename: &str, options: &ExportOptions) -> Result<Vec<u8>, Error> {
+    let decoders = decoder::Registry::with_builtin();
+    let (wave, chunks) = options.read_export(filename, &decoders)?;
+    let timecode = options.times == Times::Timecode && options.format.is_timecode();
+    if options.times != Times::Seconds
+        && !options.format.is_combinable()
+        && !options.format.is_report()
+        && !timecode
+    {
+        return Err(Error::UsageError(String::from(
+            "--times other than seconds is only supported with csv, json, yaml, toml and xlsx",
+        )));
+    }
+    let output = match options.format {
+        OutputFormat::Id3 => return Ok(id3_chapters(&wave, options)),
+        OutputFormat::Xlsx => {
+            return Ok(report(filename, &wave, &chunks, options, &decoders)?.to_xlsx())
+        }
+        OutputFormat::Csv => csv(&wave, options, has_regions(&wave))?,
+        OutputFormat::Json => report(filename, &wave, &chunks, options, &decoders)?.to_json(),
+        OutputFormat::Yaml => report(filename, &wave, &chunks, options, &decoders)?.to_yaml(),
+        OutputFormat::Toml => report(filename, &wave, &chunks, options, &decoders)?.to_toml(),
+        OutputFormat::Podcast => podcast_chapters(&wave, options).to_json(),
+        OutputFormat::ChapterTool => chaptertool_chapters(&wave, options),
+        OutputFormat::Cubase => cubase_markers(&wave, options),
+        OutputFormat::Reaper => reaper_markers(&wave, options),
+        OutputFormat::Audition => audition_markers(&wave, options),
+        OutputFormat::ProTools => protools_markers(filename, &wave, options),
+        OutputFormat::Fcpxml => fcpxml(filename, &wave, options),
+        OutputFormat::Premiere => premiere_xml(filename, &wave, options)?,
+        OutputFormat::Edl => edl(filename, &wave, options)?,
+        OutputFormat::Resolve => resolve_markers(filename, &wave, options)?,
+        OutputFormat::WebVtt => webvtt_chapters(&wave, options),
+        OutputFormat::Srt => srt_subtitles(&wave, options),
+        OutputFormat::Audacity => audacity_labels(&wave, options),
+        OutputFormat::CueSheet => cue_sheet(filename, &wave, options)?,
+    };
+    Ok(output.into_bytes())
+}
+
+// The markers of several files in one document: CSV with a filename
+// column in front, or the reports of the files in an array. Files that
+// can't be read are reported and left out. The CSV has an end column for
+// all the files if one of them has regions.
+fn render_combined(files: &[String], options: &ExportOptions) -> Result<(String, bool), Error> {
+    let decoders = decoder::Registry::with_builtin();
+    let mut passed = true;
+    let mut combined = String::new();
+    let mut reports = Vec::new();
+    let waves: Vec<_> = files
+        .iter()
+        .map(|filename| (filename, options.read_export(filename, &decoders)))
+        .collect();
+    let end_column = waves
+        .iter()
+        .any(|(_, read)| read.as_ref().is_ok_and(|(wave, _)| has_regions(wave)));
+    for (filename, wave) in waves {
+        let result = wave.and_then(|(wave, chunks)| match options.format {
+            OutputFormat::Csv => csv_rows(&wave, options, end_column).map(|(header, rows)| {
+                if let Some(header) = header {
+                    if combined.is_empty() {
+                        combined.push_str(&format!("filename,{header}\n"));
+                    }
+                }
+                let column = csv_field(filename);
+                for row in rows {
+                    combined.push_str(&format!("{column},{row}\n"));
+                }
+            }),
+            _ => report(filename, &wave, &chunks, options, &decoders)
+                .map(|report| reports.push(report)),
+        });
+        if let Err(error) = result {
             eprintln!("{filename}: {error}");
+            passed = false;
+        }
+    }
+    let output = match options.format {
+        OutputFormat::Csv => combined,
+        OutputFormat::Json => Value::Array(reports).to_json(),
+        OutputFormat::Yaml => Value::Array(reports).to_yaml(),
+        _ => ObjectBuilder::new()
+            .field("files", Value::Array(reports))
+            .build()
+            .to_toml(),
+    };
+    Ok((output, passed))
+}
+
+fn export_command(args: &[String]) -> Result<bool, Error> {
+    let usage = "usage: wav-cue [--format csv|json|yaml|toml|xlsx|podcast|chaptertool|id3|cubase|reaper|audition|protools|fcpxml|premiere|edl|resolve|audacity|cuesheet|webvtt|srt] [--json] [--precision DIGITS] [--truncate|--round] [--times seconds|relative-to-tc|timecode|both|bars] [--fps RATE] [--timecode-fps RATE] [--nfc] [--strip-control] [--ascii] [--checksums] [--peaks] [--strict|--lenient] [--need CHUNKS] [--recursive] [--output DIR] filename.wav...";
+    let mut options = ExportOptions::default();
+    let mut inputs = Vec::new();
+    let mut recursive = false;
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                options.format = match option_value(arg, args.next())?.as_str() {
+                    "csv" => OutputFormat::Csv,
+                    "json" => OutputFormat::Json,
+                    "yaml" => OutputFormat::Yaml,
+                    "toml" => OutputFormat::Toml,
+                    "xlsx" => OutputFormat::Xlsx,
+                    "podcast" => OutputFormat::Podcast,
+                    "chaptertool" => OutputFormat::ChapterTool,
+                    "id3" => OutputFormat::Id3,
+                    "cubase" => OutputFormat::Cubase,
+                    "reaper" => OutputFormat::Reaper,
+                    "audition" => OutputFormat::Audition,
+                    "protools" => OutputFormat::ProTools,
+                    "fcpxml" => OutputFormat::Fcpxml,
+                    "premiere" => OutputFormat::Premiere,
+                    "edl" => OutputFormat::Edl,
+                    "resolve" => OutputFormat::Resolve,
+                    "webvtt" => OutputFormat::WebVtt,
+                    "srt" => OutputFormat::Srt,
+                    "audacity" => OutputFormat::Audacity,
+                    "cuesheet" => OutputFormat::CueSheet,
+                    _ => return Err(Error::UsageError(String::from(usage))),
+                }
+            }
+            "--json" => options.format = OutputFormat::Json,
+            "--precision" => {
+                let value = option_value(arg, args.next())?;
+                options.precision = match value.parse() {
+                    Ok(precision) if precision <= 9 => Some(precision),
+                    _ => return Err(Error::UsageError(format!("Invalid precision: {value}"))),
+                }
+            }
+            "--truncate" => options.rounding = Rounding::Truncate,
+            "--round" => options.rounding = Rounding::Round,
+            "--times" => {
+                options.times = match option_value(arg, args.next())?.as_str() {
+                    "seconds" => Times::Seconds,
+                    "relative-to-tc" => Times::RelativeToTimecode,
+                    "timecode" => Times::Timecode,
+                    "both" => Times::Both,
+                    "bars" => Times::Bars,
+                    _ => return Err(Error::UsageError(String::from(usage))),
+                }
+            }
+            "--fps" => {
+                let rate = option_value(arg, args.next())?;
+                options.frame_rate = match FrameRate::parse(&rate) {
+                    Some(frame_rate) => Some(frame_rate),
+                    None => return Err(Error::UsageError(format!("Invalid frame rate: {rate}"))),
+                }
+            }
+            "--timecode-fps" => {
+                let rate = option_value(arg, args.next())?;
+                options.frame_rate = match FrameRate::parse(&rate) {
+                    Some(frame_rate) => Some(frame_rate),
+                    None => return Err(Error::UsageError(format!("Invalid frame rate: {rate}"))),
+                };
+                options.times = Times::Timecode;
+            }
+            "--nfc" => options.sanitize.nfc = true,
+            "--strip-control" => options.sanitize.strip_control = true,
+            "--ascii" => options.sanitize.ascii = true,
+            "--checksums" => options.checksums = true,
+            "--peaks" => options.peaks = true,
+            "--strict" => options.parsing = ParseMode::Strict,
+            "--lenient" => options.parsing = ParseMode::Lenient,
+            "--need" => options.need = Some(parse_need(&option_value(arg, args.next())?)?),
+            "--recursive" => recursive = true,
+            "--output" => output = Some(option_value(arg, args.next())?),
+            _ if !arg.starts_with("--") => inputs.push(arg.clone()),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    if inputs.is_empty() {
+        return Err(Error::UsageError(String::from(usage)));
+    }
+    let files: Vec<String> = files::expand(&inputs, recursive)?
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    match (output, &files[..]) {
+        (Some(directory), _) => write_outputs(&files, &directory, &options),
+        (None, [filename]) if !recursive => {
+            process(filename, &options)
+                .map_err(|error| Error::UsageError(format!("{filename}: {error}")))?;
+            Ok(true)
+        }
+        (None, _) if !options.format.is_combinable() => Err(Error::UsageError(String::from(
+            "Only csv, json, yaml and toml combine several files, use --output DIR for the others",
+        ))),
+        (None, _) => {
+            let (output, passed) = render_combined(&files, &options)?;
+            print!("{output}");
+            Ok(passed)
+        }
+    }
+}
+
+// Writes the output for each file to the directory, named after the file
+fn write_outputs(
+    files: &[String],
+    directory: &str,
+    options: &ExportOptions,
+) -> Result<bool, Error> {
+    fs::create_dir_all(directory)?;
+    let mut written: Vec<PathBuf> = Vec::new();
+    let mut passed = true;
+    for filename in files {
+        let stem = Path::new(filename).file_stem().map_or_else(
+            || filename.clone(),
+            |stem| stem.to_string_lossy().to_string(),
+        );
+        let path = Path::new(directory).join(format!("{stem}.{}", options.format.extension()));
+        // recordings in different folders often have the same names
+        if written.contains(&path) {
+            eprintln!(
+                "{filename}: {} is already written for another file",
+                path.display()
+            );
+            passed = false;
+            continue;
+        }
+        match render(filename, options).and_then(|output| Ok(fs::write(&path, output)?)) {
+            Ok(()) => {
+                println!("{}", path.display());
+                written.push(path);
+            }
+            Err(error) => {
+                eprintln!("{filename}: {error}");
+                passed = false;
+            }
+        }
+    }
+    Ok(passed)
+}
+
+fn tracks_command(args: &[String]) -> Result<(), Error> {
+    let filename = match args {
+        [filename] => filename,
+        _ => {
+            return Err(Error::UsageError(String::from(
+                "usage: wav-cue tracks filename.wav",
+            )))
+        }
+    };
+    let wave = read_wave_file(filename)?;
+    for channel in channels(&wave) {
+        let state = if channel.armed { "armed" } else { "disarmed" };
+        println!("{}\t{}\t{}", channel.index, channel.name, state);
+    }
+    Ok(())
+}
+
+// Reads the file, reporting what the lenient parser skipped
+// With need, reading stops once the chunks needed are found
+fn read_wave_needing(
+    filename: &str,
+    mode: ParseMode,
+    need: Option<&[Need]>,
+) -> Result<WaveFileInfo, Error> {
+    let wave = match need {
+        Some(need) => read_wave_file_needing(filename, mode, need)?,
+        None => read_wave_file_with(filename, mode)?,
+    };
+    for warning in &wave.warnings {
+        eprintln!("{filename}: {warning}");
+    }
+    Ok(wave)
+}
+
+// Chunk names for --need: cue,bext,data,ixml,smpl
+fn parse_need(value: &str) -> Result<Vec<Need>, Error> {
+    value
+        .split(',')
+        .map(|name| {
+            Need::parse(name.trim())
+                .ok_or_else(|| Error::UsageError(format!("Unknown chunk for --need: {name}")))
+        })
+        .collect()
+}
+
+fn option_value(option: &str, value: Option<&String>) -> Result<String, Error> {
+    match value {
+        Some(value) => Ok(value.clone()),
+        None => Err(Error::UsageError(format!("{option} requires a value"))),
+    }
+}
+
+fn default_to(value: String, default: &str) -> String {
+    if value.is_empty() {
+        default.to_string()
+    } else {
+        value
+    }
+}
+
+fn set_umid(filename: &str, source_pack: Option<SourcePack>) -> Result<(), Error> {
+    let config = config::load()?;
+    let profile = config.organization.as_ref();
+    let wave = read_wave_file(filename)?;
+    // codes not given on the command line default to the organization profile
+    let source_pack = source_pack.map(|source_pack| match profile {
+        Some(profile) => SourcePack {
+            country: default_to(source_pack.country, &profile.country),
+            organization: default_to(source_pack.organization, &profile.organization_code),
+            user: source_pack.user,
+        },
+        None => source_pack,
+    });
+    let umid = umid::generate(wave.header.number_of_channels, source_pack.as_ref());
+    riff::rewrite_file(filename, |reader, chunks| {
+        bext::edit_chunk(
+            reader,
+            chunks,
+            || bext::new_chunk(profile, &wave.header),
+            |raw| {
+                bext::set_umid(raw, &umid);
+                Ok(())
+            },
+        )
+    })?;
+    println!("{}", umid::to_hex(&umid));
+    Ok(())
+}
+
+enum TimeSource {
+    Mtime,
+    Filename,
+}
+
+fn set_time_reference(filename: &str, source: TimeSource, utc_offset: i64) -> Result<(), Error> {
+    let config = config::load()?;
+    let wave = read_wave_file(filename)?;
+    let start = match source {
+        TimeSource::Filename => match DateTime::from_filename(filename) {
+            Some(start) => start,
+            None => {
+                return Err(Error::from(WaveError {
+                    message: String::from("No timestamp found in the filename"),
+                }))
+            }
+        },
+        TimeSource::Mtime => {
+            // the modification time is updated while recording, so it marks
+            // the end of the recording
+            let modified = fs::metadata(filename)?.modified()?;
+            let duration = match (&wave.data, wave.header.average_bytes_per_second) {
+                (Some(data), bytes_per_second) if bytes_per_second > 0 => {
+                    Duration::from_secs_f64(data.size as f64 / bytes_per_second as f64)
+                }
+                _ => Duration::ZERO,
+            };
+            let local = DateTime::from_system_time(modified - duration);
+            local.offset_by(utc_offset)
+        }
+    };
+    let time_reference = start.seconds_since_midnight() * wave.header.sampling_rate as u64;
+    riff::rewrite_file(filename, |reader, chunks| {
+        bext::edit_chunk(
+            reader,
+            chunks,
+            || bext::new_chunk(config.organization.as_ref(), &wave.header),
+            |raw| {
+                bext::set_time_reference(raw, time_reference);
+                if wave.bext.is_none() || bext::is_blank(raw, bext::BEXT_ORIGINATION_DATE) {
+                    bext::set_string(raw, bext::BEXT_ORIGINATION_DATE, &start.date_string());
+                    bext::set_string(raw, bext::BEXT_ORIGINATION_TIME, &start.time_string());
+                }
+                Ok(())
+            },
+        )
+    })?;
+    println!(
+        "{} {} ({time_reference} samples since midnight)",
+        start.date_string(),
+        start.time_string()
+    );
+    Ok(())
+}
+
+// Text fields of bext set, by option name
+const BEXT_TEXT_FIELDS: [(&str, &str, (usize, usize)); 5] = [
+    ("--description", "Description", bext::BEXT_DESCRIPTION),
+    ("--originator", "Originator", bext::BEXT_ORIGINATOR),
+    (
+        "--originator-reference",
+        "OriginatorReference",
+        bext::BEXT_ORIGINATOR_REFERENCE,
+    ),
+    (
+        "--origination-date",
+        "OriginationDate",
+        bext::BEXT_ORIGINATION_DATE,
+    ),
+    (
+        "--origination-time",
+        "OriginationTime",
+        bext::BEXT_ORIGINATION_TIME,
+    ),
+];
+
+// Sets the given fields, leaving the others as they are; a file without
+// bext gets one
+fn set_bext_fields(
+    filename: &str,
+    fields: &[(&str, (usize, usize), String)],
+    time_reference: Option<u64>,
+) -> Result<(), Error> {
+    let config = config::load()?;
+    let wave = read_wave_file(filename)?;
+    riff::rewrite_file(filename, |reader, chunks| {
+        bext::edit_chunk(
+            reader,
+            chunks,
+            || bext::new_chunk(config.organization.as_ref(), &wave.header),
+            |raw| {
+                for (name, field, value) in fields {
+                    if bext::set_string(raw, *field, value) {
+                        eprintln!("{filename}: {name} is cut to {} characters", field.1);
+                    }
+                }
+                if let Some(time_reference) = time_reference {
+                    bext::set_time_reference(raw, time_reference);
+                }
+                Ok(())
+            },
+        )
+    })
+}
+
+fn append_history(filename: &str, text: Option<&str>) -> Result<(), Error> {
+    let config = config::load()?;
+    let wave = read_wave_file(filename)?;
+    let line = match text {
+        Some(text) => text.to_string(),
+        None => bext::coding_history_line(&wave.header, "append-history").ok_or_else(|| {
+            Error::UsageError(format!(
+                "{} has no EBU R98 coding algorithm, give the line with --text",
+                filename
+            ))
+        })?,
+    };
+    riff::rewrite_file(filename, |reader, chunks| {
+        bext::edit_chunk(
+            reader,
+            chunks,
+            || bext::new_chunk(config.organization.as_ref(), &wave.header),
+            |raw| {
+                bext::append_coding_history(raw, &line);
+                Ok(())
+            },
+        )
+    })
+}
+
+fn bext_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue bext set [--description TEXT] [--originator TEXT] [--originator-reference TEXT] [--origination-date YYYY-MM-DD] [--origination-time HH:MM:SS] [--time-reference SAMPLES] filename.wav
+       wav-cue bext set-umid [--extended] [--country CODE] [--organization CODE] [--user CODE] filename.wav
+       wav-cue bext set-time-reference --from mtime|filename [--utc-offset +HH:MM] filename.wav
+       wav-cue bext append-history [--text LINE] filename.wav";
+    match args.first().map(String::as_str) {
+        Some("set") => {
+            let mut fields = Vec::new();
+            let mut time_reference = None;
+            let mut filename = None;
+            let mut args = args[1..].iter();
+            while let Some(arg) = args.next() {
+                let text_field = BEXT_TEXT_FIELDS.iter().find(|(option, _, _)| option == arg);
+                match (arg.as_str(), text_field) {
+                    ("--time-reference", _) => {
+                        let value = option_value(arg, args.next())?;
+                        time_reference = match value.parse() {
+                            Ok(samples) => Some(samples),
+                            Err(_) => {
+                                return Err(Error::UsageError(format!(
+                                    "Invalid time reference: {value}"
+                                )))
+                            }
+                        }
+                    }
+                    (_, Some((_, name, field))) => {
+                        let mut value = option_value(arg, args.next())?;
+                        // dates and times as the spec gives them, other
+                        // separators are normalized
+                        let format = match *name {
+                            "OriginationDate" => Some(bext::check_origination_date(&value)),
+                            "OriginationTime" => Some(bext::check_origination_time(&value)),
+                            _ => None,
+                        };
+                        match format {
+                            Some(bext::FieldFormat::Invalid) => {
+                                return Err(Error::UsageError(format!("Invalid {name}: {value}")))
+                            }
+                            Some(bext::FieldFormat::Variant(normalized)) => value = normalized,
+                            _ => (),
+                        }
+                        fields.push((*name, *field, value));
+                    }
+                    _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+                    _ => return Err(Error::UsageError(String::from(usage))),
+                }
+            }
+            match filename {
+                Some(filename) if !fields.is_empty() || time_reference.is_some() => {
+                    set_bext_fields(filename, &fields, time_reference)
+                }
+                _ => Err(Error::UsageError(String::from(usage))),
+            }
+        }
+        Some("set-umid") => {
+            let mut extended = false;
+            let mut source_pack = SourcePack::default();
+            let mut filename = None;
+            let mut args = args[1..].iter();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--extended" => extended = true,
+                    "--country" => {
+                        source_pack.country = option_value(arg, args.next())?;
+                        extended = true;
+                    }
+                    "--organization" => {
+                        source_pack.organization = option_value(arg, args.next())?;
+                        extended = true;
+                    }
+                    "--user" => {
+                        source_pack.user = option_value(arg, args.next())?;
+                        extended = true;
+                    }
+                    _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+                    _ => return Err(Error::UsageError(String::from(usage))),
+                }
+            }
+            match filename {
+                Some(filename) => set_umid(filename, extended.then_some(source_pack)),
+                None => Err(Error::UsageError(String::from(usage))),
+            }
+        }
+        Some("set-time-reference") => {
+            let mut source = None;
+            let mut utc_offset = 0;
+            let mut filename = None;
+            let mut args = args[1..].iter();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--from" => {
+                        source = match option_value(arg, args.next())?.as_str() {
+                            "mtime" => Some(TimeSource::Mtime),
+                            "filename" => Some(TimeSource::Filename),
+                            _ => return Err(Error::UsageError(String::from(usage))),
+                        }
+                    }
+                    "--utc-offset" => {
+                        let value = option_value(arg, args.next())?;
+                        utc_offset = match datetime::parse_utc_offset(&value) {
+                            Some(offset) => offset,
+                            None => {
+                                return Err(Error::UsageError(format!(
+                                    "Invalid UTC offset: {value}"
+                                )))
+                            }
+                        }
+                    }
+                    _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+                    _ => return Err(Error::UsageError(String::from(usage))),
+                }
+            }
+            match (filename, source) {
+                (Some(filename), Some(source)) => set_time_reference(filename, source, utc_offset),
+                _ => Err(Error::UsageError(String::from(usage))),
+            }
+        }
+        Some("append-history") => {
+            let mut text = None;
+            let mut filename = None;
+            let mut args = args[1..].iter();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--text" => text = Some(option_value(arg, args.next())?),
+                    _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+                    _ => return Err(Error::UsageError(String::from(usage))),
+                }
+            }
+            match filename {
+                Some(filename) => append_history(filename, text.as_deref()),
+                None => Err(Error::UsageError(String::from(usage))),
+            }
+        }
+        _ => Err(Error::UsageError(String::from(usage))),
+    }
+}
+
+// Converts between sample counts and timecode, e.g.
+// wav-cue timecode --fps 29.97df --rate 48000 01:00:00;00
+fn timecode_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue timecode --fps RATE [--rate SAMPLING_RATE] SAMPLES|HH:MM:SS:FF";
+    let mut frame_rate = None;
+    let mut sampling_rate = 48000;
+    let mut value = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fps" => {
+                let rate = option_value(arg, args.next())?;
+                frame_rate = match FrameRate::parse(&rate) {
+                    Some(frame_rate) => Some(frame_rate),
+                    None => return Err(Error::UsageError(format!("Invalid frame rate: {rate}"))),
+                }
+            }
+            "--rate" => {
+                let rate = option_value(arg, args.next())?;
+                sampling_rate = match rate.parse() {
+                    Ok(rate) if rate > 0 => rate,
+                    _ => return Err(Error::UsageError(format!("Invalid sampling rate: {rate}"))),
+                }
+            }
+            _ if value.is_none() && !arg.starts_with("--") => value = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let (frame_rate, value) = match (frame_rate, value) {
+        (Some(frame_rate), Some(value)) => (frame_rate, value),
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let samples = if let Ok(samples) = value.parse::<u64>() {
+        samples
+    } else {
+        match Timecode::parse(value) {
+            Some(timecode) if frame_rate.is_valid(&timecode) => {
+                frame_rate.timecode_to_samples(&timecode, sampling_rate)
+            }
+            _ => return Err(Error::UsageError(format!("Invalid timecode: {value}"))),
+        }
+    };
+    println!(
+        "{} samples, frame {}, {}",
+        samples,
+        frame_rate.samples_to_frames(samples, sampling_rate),
+        frame_rate.samples_to_timecode(samples, sampling_rate)
+    );
+    Ok(())
+}
+
+fn generate_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue generate [--duration 10s] [--sample-rate 48000] [--channels 2] [--bits 16] [--cues N] [--bext] filename.wav";
+    let mut options = generate::Options::default();
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let number = |value: String| -> Result<u32, Error> {
+            value
+                .parse()
+                .map_err(|_| Error::UsageError(format!("Invalid value for {arg}: {value}")))
+        };
+        match arg.as_str() {
+            "--duration" => {
+                let value = option_value(arg, args.next())?;
+                options.duration = match TimeValue::parse(&value) {
+                    Some(duration) => duration,
+                    None => return Err(Error::UsageError(format!("Invalid duration: {value}"))),
+                }
+            }
+            "--sample-rate" => options.sampling_rate = number(option_value(arg, args.next())?)?,
+            "--channels" => {
+                options.number_of_channels = number(option_value(arg, args.next())?)? as u16
+            }
+            "--bits" => options.bits_per_sample = number(option_value(arg, args.next())?)? as u16,
+            "--cues" => options.cues = number(option_value(arg, args.next())?)?,
+            "--bext" => options.bext = true,
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    match filename {
+        Some(filename) => generate::generate(filename, &options),
+        None => Err(Error::UsageError(String::from(usage))),
+    }
+}
+
+fn redact_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue redact [--replace TEXT] filename.wav";
+    let mut replacement = String::new();
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--replace" => replacement = option_value(arg, args.next())?,
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    match filename {
+        Some(filename) => {
+            for field in redact::redact(filename, &replacement)? {
+                println!("{filename}: redacted {field}");
+            }
+            Ok(())
+        }
+        None => Err(Error::UsageError(String::from(usage))),
+    }
+}
+
+fn region_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue region --cue ID [--length DURATION] [--purpose rgn] [--country N] [--language N] [--dialect N] [--code-page N] [--text TEXT] filename.wav";
+    let mut cue_id = None;
+    let mut length = None;
+    let mut purpose = None;
+    let mut country = None;
+    let mut language = None;
+    let mut dialect = None;
+    let mut code_page = None;
+    let mut text = None;
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let number = |value: String| -> Result<u16, Error> {
+            value
+                .parse()
+                .map_err(|_| Error::UsageError(format!("Invalid value for {arg}: {value}")))
+        };
+        match arg.as_str() {
+            "--cue" => {
+                let value = option_value(arg, args.next())?;
+                cue_id = match value.parse::<u32>() {
+                    Ok(cue_id) => Some(cue_id),
+                    Err(_) => return Err(Error::UsageError(format!("Invalid cue id: {value}"))),
+                }
+            }
+            "--length" => {
+                let value = option_value(arg, args.next())?;
+                length = match TimeValue::parse(&value) {
+                    Some(length) => Some(length),
+                    None => return Err(Error::UsageError(format!("Invalid duration: {value}"))),
+                }
+            }
+            "--purpose" => {
+                let value = option_value(arg, args.next())?;
+                if value.is_empty() || value.len() > 4 || !value.is_ascii() {
+                    return Err(Error::UsageError(format!("Invalid purpose: {value}")));
+                }
+                let mut id = *b"    ";
+                id[..value.len()].copy_from_slice(value.as_bytes());
+                purpose = Some(id);
+            }
+            "--country" => country = Some(number(option_value(arg, args.next())?)?),
+            "--language" => language = Some(number(option_value(arg, args.next())?)?),
+            "--dialect" => dialect = Some(number(option_value(arg, args.next())?)?),
+            "--code-page" => code_page = Some(number(option_value(arg, args.next())?)?),
+            "--text" => text = Some(option_value(arg, args.next())?),
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let (filename, cue_id) = match (filename, cue_id) {
+        (Some(filename), Some(cue_id)) => (filename, cue_id),
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let wave = read_wave_file(filename)?;
+    let cue = match wave.cues.iter().find(|cue| cue.cue_id == cue_id) {
+        Some(cue) => cue,
+        None => {
+            return Err(Error::from(WaveError {
+                message: format!("No cue point with id {cue_id}"),
+            }))
+        }
+    };
+    // fields not given keep their current values
+    let mut region = cue
+        .region
+        .clone()
+        .unwrap_or_else(|| adtl::Region::new(cue_id));
+    if let Some(length) = length {
+        region.sample_length = length
+            .to_samples(wave.header.sampling_rate)
+            .clamp(0, u32::MAX as i64) as u32;
+    }
+    region.purpose = purpose.unwrap_or(region.purpose);
+    region.country = country.unwrap_or(region.country);
+    region.language = language.unwrap_or(region.language);
+    region.dialect = dialect.unwrap_or(region.dialect);
+    region.code_page = code_page.unwrap_or(region.code_page);
+    region.text = text.unwrap_or(region.text);
+    riff::rewrite_file(filename, |reader, chunks| {
+        adtl::edit_list(reader, chunks, |items| {
+            adtl::set_region(items, &region);
+            Ok(())
+        })
+    })
+}
+
+// Returns whether the markers matched
+fn check_command(args: &[String]) -> Result<bool, Error> {
+    let usage = "usage: wav-cue check --against expected.csv [--tolerance 1ms] filename.wav";
+    let mut against = None;
+    let mut tolerance = TimeValue::Seconds(0.001);
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--against" => against = Some(option_value(arg, args.next())?),
+            "--tolerance" => {
+                let value = option_value(arg, args.next())?;
+                tolerance = match TimeValue::parse(&value) {
+                    Some(tolerance) => tolerance,
+                    None => return Err(Error::UsageError(format!("Invalid tolerance: {value}"))),
+                }
+            }
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let (filename, against) = match (filename, against) {
+        (Some(filename), Some(against)) => (filename, against),
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let expected = check::read_reference(&against)?;
+    let wave = read_wave_file(filename)?;
+    let sampling_rate = wave.header.sampling_rate;
+    let mut actual: Vec<(f64, String)> = wave
+        .cues
+        .iter()
+        .map(|cue| (cue.sample_start as f64 / sampling_rate as f64, cue.title()))
+        .collect();
+    actual.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let tolerance = tolerance.to_samples(sampling_rate).abs() as f64 / sampling_rate as f64;
+    let differences = check::compare(&actual, &expected, tolerance);
+    for difference in &differences {
+        println!("{filename}: {difference}");
+    }
+    if differences.is_empty() {
+        println!("{filename}: {} markers match {against}", actual.len());
+    }
+    Ok(differences.is_empty())
+}
+
+fn thumbnails_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue thumbnails [--window 2s] [--width 320] [--height 80] [--output DIR] filename.wav";
+    let mut window = TimeValue::Seconds(2.0);
+    let mut width = 320;
+    let mut height = 80;
+    let mut output = String::from(".");
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let size = |value: String| -> Result<u32, Error> {
+            match value.parse() {
+                Ok(size) if (2..=10000).contains(&size) => Ok(size),
+                _ => Err(Error::UsageError(format!(
+                    "Invalid value for {arg}: {value}"
+                ))),
+            }
+        };
+        match arg.as_str() {
+            "--window" => {
+                let value = option_value(arg, args.next())?;
+                window = match TimeValue::parse(&value) {
+                    Some(window) => window,
+                    None => return Err(Error::UsageError(format!("Invalid duration: {value}"))),
+                }
+            }
+            "--width" => width = size(option_value(arg, args.next())?)?,
+            "--height" => height = size(option_value(arg, args.next())?)?,
+            "--output" => output = option_value(arg, args.next())?,
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return Err(Error::UsageError(String::from(usage))),
+    };
+    let wave = read_wave_file(filename)?;
+    let options = thumbnail::Options {
+        window: window.to_samples(wave.header.sampling_rate).unsigned_abs(),
+        width,
+        height,
+    };
+    fs::create_dir_all(&output)?;
+    for path in thumbnail::write_thumbnails(filename, &wave, Path::new(&output), &options)? {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+// Marks the start of each segment of sound after a silent gap
+fn analyze_command(args: &[String]) -> Result<(), Error> {
+    let usage =
+        "usage: wav-cue analyze [--silence-threshold -50] [--min-gap 2s] [--dry-run] filename.wav";
+    let mut threshold = -50.0;
+    let mut min_gap = TimeValue::Seconds(2.0);
+    let mut dry_run = false;
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--silence-threshold" => {
+                let value = option_value(arg, args.next())?;
+                threshold = match value
+                    .trim_end_matches("dBFS")
+                    .trim_end_matches("dB")
+                    .trim()
+                    .parse()
+                {
+                    Ok(threshold) => threshold,
+                    Err(_) => return Err(Error::UsageError(format!("Invalid level: {value}"))),
+                }
+            }
+            "--min-gap" => {
+                let value = option_value(arg, args.next())?;
+                min_gap = match TimeValue::parse(&value) {
+                    Some(min_gap) => min_gap,
+                    None => return Err(Error::UsageError(format!("Invalid duration: {value}"))),
+                }
+            }
+            "--dry-run" => dry_run = true,
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return Err(Error::UsageError(String::from(usage))),
+    };
+    let wave = read_wave_file(filename)?;
+    let data = wave.audio()?;
+    let rate = wave.header.sampling_rate;
+    let frames = data.size / wave.header.block_align.max(1) as u64;
+    let mut reader = BufReader::new(File::open(filename)?);
+    let gaps = silence::find_gaps(
+        &mut reader,
+        &wave.header,
+        data.offset,
+        data.size,
+        threshold,
+        min_gap.to_samples(rate).unsigned_abs(),
+    )?;
+    let markers: Vec<import::Marker> = silence::segment_starts(&gaps, frames)
+        .into_iter()
+        .enumerate()
+        .map(|(index, sample_start)| import::Marker {
+            sample_start,
+            sample_length: 0,
+            name: format!("Segment {}", index + 1),
+            comment: String::new(),
+        })
+        .collect();
+    let seconds = |samples| duration::format_seconds(samples, rate, 3, Rounding::Round);
+    for gap in &gaps {
+        println!("silence {}-{}", seconds(gap.start), seconds(gap.end));
+    }
+    if dry_run {
+        for marker in &markers {
+            println!("{}\t{}", seconds(marker.sample_start), marker.name);
+        }
+        return Ok(());
+    }
+    write_markers(filename, &markers, ImportMode::Add)?;
+    println!("{filename}: added {} cue points", markers.len());
+    Ok(())
+}
+
+fn append_cue_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue append-cue [--at now|TIME] filename.wav";
+    let mut at = None;
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--at" => {
+                let value = option_value(arg, args.next())?;
+                at = match value.as_str() {
+                    "now" => None,
+                    _ => match TimeValue::parse(&value) {
+                        Some(at) => Some(at),
+                        None => return Err(Error::UsageError(format!("Invalid time: {value}"))),
+                    },
+                }
+            }
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return Err(Error::UsageError(String::from(usage))),
+    };
+    // by default the cue goes at the end of what has been recorded so far
+    let (sampling_rate, recorded) = live::position(filename)?;
+    let sample_start = match at {
+        Some(at) => at.to_samples(sampling_rate).max(0) as u64,
+        None => recorded,
+    };
+    if sample_start > u32::MAX as u64 {
+        return Err(Error::from(WaveError {
+            message: format!("Position {sample_start} does not fit in a cue point"),
+        }));
+    }
+    let cue_id = live::append_cue(filename, sample_start as u32)?;
+    println!("{filename}: added cue {cue_id} at sample {sample_start}");
+    Ok(())
+}
+
+fn reserve_cues_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue reserve-cues [--count 100] filename.wav";
+    let mut count = 100;
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--count" => {
+                let value = option_value(arg, args.next())?;
+                count = match value.parse() {
+                    Ok(count) if count > 0 && count <= 100_000 => count,
+                    _ => return Err(Error::UsageError(format!("Invalid count: {value}"))),
+                }
+            }
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    match filename {
+        Some(filename) => live::reserve(filename, count),
+        None => Err(Error::UsageError(String::from(usage))),
+    }
+}
+
+fn duplicates_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue duplicates [--all] DIRECTORY|FILE...";
+    let mut all = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--all" => all = true,
+            _ if !arg.starts_with("--") => paths.push(PathBuf::from(arg)),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    if paths.is_empty() {
+        return Err(Error::UsageError(String::from(usage)));
+    }
+    let files = files::find_files(&paths)?;
+    let (groups, errors) = duplicates::find_duplicates(&files);
+    for (file, error) in errors {
+        eprintln!("{}: {error}", file.display());
+    }
+    // exact copies are only listed with --all
+    for group in groups
+        .iter()
+        .filter(|group| all || group.metadata_differs())
+    {
+        let state = if group.metadata_differs() {
+            "same audio, different metadata"
+        } else {
+            "identical copies"
+        };
+        println!("{state}:");
+        for (file, variant) in &group.files {
+            println!("  [{}] {}", variant + 1, file.display());
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportMode {
+    // markers become new cue points
+    Add,
+    // a marker at the position of a cue point updates its label, note and
+    // region instead
+    Merge,
+    // markers take the place of the cue points and their labels, notes
+    // and regions
+    Replace,
+}
+
+// Adds the markers to the cue points of the file, with labl, note and ltxt
+// entries for their names, comments and durations
+fn write_markers(
+    filename: &str,
+    markers: &[import::Marker],
+    mode: ImportMode,
+) -> Result<(), Error> {
+    let wave = read_wave_file(filename)?;
+    let mut cues = match mode {
+        ImportMode::Replace => Vec::new(),
+        _ => wave.cues,
+    };
+    let mut next_id = cues.iter().map(|cue| cue.cue_id).max().unwrap_or(0) + 1;
+    let mut items = Vec::new();
+    // the adtl entries the merged markers replace
+    let mut replaced: Vec<([u8; 4], u32)> = Vec::new();
+    for marker in markers {
+        if marker.sample_start > u32::MAX as u64 || marker.sample_length > u32::MAX as u64 {
+            return Err(Error::from(WaveError {
+                message: format!("Marker {} is beyond the reach of a cue point", marker.name),
+            }));
+        }
+        let merged = cues
+            .iter_mut()
+            .filter(|_| mode == ImportMode::Merge)
+            .find(|cue| cue.sample_start as u64 == marker.sample_start);
+        if let Some(cue) = merged {
+            if !marker.name.is_empty() {
+                cue.label = Some(marker.name.clone());
+                replaced.push((*b"labl", cue.cue_id));
+            }
+            if !marker.comment.is_empty() {
+                cue.note = Some(marker.comment.clone());
+                replaced.push((*b"note", cue.cue_id));
+            }
+            if marker.sample_length > 0 {
+                replaced.push((*b"ltxt", cue.cue_id));
+            }
+            add_marker_items(&mut items, cue.cue_id, marker);
+            continue;
+        }
+        let cue_id = next_id;
+        next_id += 1;
+        add_marker_items(&mut items, cue_id, marker);
+        cues.push(CueEntry {
+            cue_id,
+            position: marker.sample_start as u32,
+            data_chunk_id: DataChunkId::Data,
+            chunk_start: 0,
+            block_start: 0,
+            sample_start: marker.sample_start as u32,
+            label: Some(marker.name.clone()).filter(|name| !name.is_empty()),
+            note: Some(marker.comment.clone()).filter(|comment| !comment.is_empty()),
+            color: None,
+            url: None,
+            image: None,
+            region: None,
+        });
+    }
+    riff::rewrite_file(filename, |reader, chunks| {
+        set_cue_chunk(chunks, &cues);
+        adtl::edit_list(reader, chunks, |list| {
+            if mode == ImportMode::Replace {
+                list.clear();
+            }
+            list.retain(|(id, data)| {
+                data.len() < 4
+                    || !replaced.contains(&(
+                        *id,
+                        u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+                    ))
+            });
+            list.extend(items);
+            Ok(())
+        })
+    })
+}
+
+fn add_marker_items(items: &mut Vec<riff::ListItem>, cue_id: u32, marker: &import::Marker) {
+    if !marker.name.is_empty() {
+        items.push(adtl::text_item(b"labl", cue_id, &marker.name));
+    }
+    if !marker.comment.is_empty() {
+        items.push(adtl::text_item(b"note", cue_id, &marker.comment));
+    }
+    if marker.sample_length > 0 {
+        let mut region = adtl::Region::new(cue_id);
+        region.sample_length = marker.sample_length as u32;
+        items.push((*b"ltxt", region.to_bytes()));
+    }
+}
+
+fn import_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue import --from csv|audacity|premiere [--fps RATE] [--merge|--replace] markers filename.wav";
+    let mut source = None;
+    let mut frame_rate = None;
+    let mut mode = ImportMode::Add;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => source = Some(option_value(arg, args.next())?),
+            "--merge" if mode == ImportMode::Add => mode = ImportMode::Merge,
+            "--replace" if mode == ImportMode::Add => mode = ImportMode::Replace,
+            "--fps" => {
+                let rate = option_value(arg, args.next())?;
+                frame_rate = match FrameRate::parse(&rate) {
+                    Some(frame_rate) => Some(frame_rate),
+                    None => return Err(Error::UsageError(format!("Invalid frame rate: {rate}"))),
+                }
+            }
+            _ if files.len() < 2 && !arg.starts_with("--") => files.push(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let (markers_file, filename) = match files[..] {
+        [markers_file, filename] => (markers_file, filename),
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let sampling_rate = read_wave_file(filename)?.header.sampling_rate;
+    let text = import::decode_text(&fs::read(markers_file)?);
+    let markers = match source.as_deref() {
+        Some("csv") => import::csv(&text, sampling_rate)?,
+        Some("audacity") => import::audacity_labels(&text, sampling_rate)?,
+        // the XML carries its frame rate, the CSV doesn't
+        Some("premiere") if text.trim_start().starts_with('<') => {
+            import::premiere_xml(&text, sampling_rate)?
+        }
+        Some("premiere") => match frame_rate {
+            Some(frame_rate) => import::premiere_csv(&text, &frame_rate, sampling_rate)?,
+            None => {
+                return Err(Error::UsageError(String::from(
+                    "--fps is needed for the timecode in Premiere marker lists",
+                )))
+            }
+        },
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    write_markers(filename, &markers, mode)?;
+    println!("{filename}: imported {} markers", markers.len());
+    Ok(())
+}
+
+fn xmp_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue xmp [--title TEXT] [--creator NAME] [--no-markers] filename.wav";
+    let mut update = xmp::Update::default();
+    let mut markers = true;
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--title" => update.title = Some(option_value(arg, args.next())?),
+            "--creator" => update.creator = Some(option_value(arg, args.next())?),
+            "--no-markers" => markers = false,
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return Err(Error::UsageError(String::from(usage))),
+    };
+    let wave = read_wave_file(filename)?;
+    if markers {
+        let mut cues: Vec<&CueEntry> = wave.cues.iter().collect();
+        cues.sort_by_key(|cue| cue.sample_start);
+        update.markers = Some(
+            cues.iter()
+                .map(|cue| xmp::Marker {
+                    name: cue.title(),
+                    start_time: cue.sample_start as u64,
+                    duration: cue
+                        .region
+                        .as_ref()
+                        .map_or(0, |region| region.sample_length as u64),
+                })
+                .collect(),
+        );
+    }
+    riff::rewrite_file(filename, |reader, chunks| {
+        match chunks.iter().position(|chunk| &chunk.id == b"_PMX") {
+            Some(index) => {
+                let existing = chunks[index].payload(reader)?;
+                let packet = xmp::update(Some(&existing), &update, wave.header.sampling_rate)?;
+                chunks[index] = riff::Chunk::new(b"_PMX", packet);
+            }
+            None => {
+                let packet = xmp::update(None, &update, wave.header.sampling_rate)?;
+                chunks.push(riff::Chunk::new(b"_PMX", packet));
+            }
+        }
+        Ok(())
+    })
+}
+
+// Payloads of the chunks with the id, in file order
+fn chunk_payloads(filename: &str, id: &[u8; 4]) -> Result<Vec<Vec<u8>>, Error> {
+    let mut reader = zip::open(filename)?;
+    riff::walk_chunks(&mut reader)?
+        .iter()
+        .filter(|info| &info.id == id)
+        .map(|info| riff::read_payload(&mut reader, info))
+        .collect()
+}
+
+// Adds a cue point for each cart timer, labeled with the usage id and with
+// a cart=ID note so that the mapping survives a round trip. Timers that
+// already have a cue point, e.g. from an earlier run, are skipped.
+fn cart_to_cues(filename: &str) -> Result<(), Error> {
+    let timers = match chunk_payloads(filename, b"cart")?.first() {
+        Some(data) => cart::timers(data)?,
+        None => {
+            return Err(Error::from(WaveError {
+                message: String::from("File has no cart chunk"),
+            }))
+        }
+    };
+    let wave = read_wave_file(filename)?;
+    let markers: Vec<import::Marker> = timers
+        .iter()
+        .filter(|timer| !wave.cues.iter().any(|cue| cue.sample_start == timer.value))
+        .map(|timer| import::Marker {
+            sample_start: timer.value as u64,
+            sample_length: 0,
+            name: timer.usage(),
+            comment: format!("cart={}", timer.usage()),
+        })
+        .collect();
+    write_markers(filename, &markers, ImportMode::Add)?;
+    println!("{filename}: added {} cue points", markers.len());
+    Ok(())
+}
+
+// Replaces the cart timers with the cue points; the usage id comes from a
+// cart=ID note of the cue, an existing timer at the same position, or the
+// default
+fn cart_from_cues(filename: &str, default_usage: [u8; 4]) -> Result<(), Error> {
+    let wave = read_wave_file(filename)?;
+    let mut notes = Vec::new();
+    for data in chunk_payloads(filename, b"LIST")? {
+        if data.starts_with(b"adtl") {
+            notes.extend(adtl::parse(&data)?.notes);
+        }
+    }
+    let existing = match chunk_payloads(filename, b"cart")?.first() {
+        Some(data) => cart::timers(data)?,
+        None => Vec::new(),
+    };
+    let mut cues: Vec<&CueEntry> = wave.cues.iter().collect();
+    cues.sort_by_key(|cue| cue.sample_start);
+    if cues.len() > cart::TIMER_COUNT {
+        return Err(Error::from(WaveError {
+            message: format!(
+                "cart has room for {} timers, the file has {} cue points",
+                cart::TIMER_COUNT,
+                cues.len()
+            ),
+        }));
+    }
+    let timers: Vec<cart::Timer> = cues
+        .iter()
+        .map(|cue| {
+            let usage = notes
+                .iter()
+                .filter(|(cue_id, _)| *cue_id == cue.cue_id)
+                .find_map(|(_, note)| adtl::note_attribute(note, &["cart"]))
+                .and_then(|usage| cart::parse_usage(&usage))
+                .or_else(|| {
+                    existing
+                        .iter()
+                        .find(|timer| timer.value == cue.sample_start)
+                        .map(|timer| timer.usage)
+                })
+                .unwrap_or(default_usage);
+            cart::Timer {
+                usage,
+                value: cue.sample_start,
+            }
+        })
+        .collect();
+    riff::rewrite_file(filename, |reader, chunks| {
+        match chunks.iter().position(|chunk| &chunk.id == b"cart") {
+            Some(index) => {
+                let mut data = chunks[index].payload(reader)?;
+                cart::set_timers(&mut data, &timers);
+                chunks[index] = riff::Chunk::new(b"cart", data);
+            }
+            None => {
+                let mut data = cart::new_chunk();
+                cart::set_timers(&mut data, &timers);
+                chunks.push(riff::Chunk::new(b"cart", data));
+            }
+        }
+        Ok(())
+    })?;
+    for timer in &timers {
+        println!("{filename}: {} at sample {}", timer.usage(), timer.value);
+    }
+    Ok(())
+}
+
+const CART_TEXT_FIELDS: [(&str, &str, (usize, usize)); 14] = [
+    ("--title", "Title", cart::CART_TITLE),
+    ("--artist", "Artist", cart::CART_ARTIST),
+    ("--cut-id", "CutID", cart::CART_CUT_ID),
+    ("--client-id", "ClientID", cart::CART_CLIENT_ID),
+    ("--category", "Category", cart::CART_CATEGORY),
+    (
+        "--classification",
+        "Classification",
+        cart::CART_CLASSIFICATION,
+    ),
+    ("--out-cue", "OutCue", cart::CART_OUT_CUE),
+    ("--start-date", "StartDate", cart::CART_START_DATE),
+    ("--start-time", "StartTime", cart::CART_START_TIME),
+    ("--end-date", "EndDate", cart::CART_END_DATE),
+    ("--end-time", "EndTime", cart::CART_END_TIME),
+    (
+        "--producer-app-id",
+        "ProducerAppID",
+        cart::CART_PRODUCER_APP_ID,
+    ),
+    ("--user-def", "UserDef", cart::CART_USER_DEF),
+    ("--url", "URL", cart::CART_URL),
+];
+
+fn cart_show(filename: &str) -> Result<(), Error> {
+    let cart = match chunk_payloads(filename, b"cart")?.first() {
+        Some(data) => cart::parse(data)?,
+        None => {
+            return Err(Error::from(WaveError {
+                message: String::from("File has no cart chunk"),
+            }))
+        }
+    };
+    let fields = [
+        ("Version", &cart.version),
+        ("Title", &cart.title),
+        ("Artist", &cart.artist),
+        ("CutID", &cart.cut_id),
+        ("ClientID", &cart.client_id),
+        ("Category", &cart.category),
+        ("Classification", &cart.classification),
+        ("OutCue", &cart.out_cue),
+        ("StartDate", &cart.start_date),
+        ("StartTime", &cart.start_time),
+        ("EndDate", &cart.end_date),
+        ("EndTime", &cart.end_time),
+        ("ProducerAppID", &cart.producer_app_id),
+        ("ProducerAppVersion", &cart.producer_app_version),
+        ("UserDef", &cart.user_def),
+        ("URL", &cart.url),
+        ("TagText", &cart.tag_text),
+    ];
+    for (name, value) in fields.iter().filter(|(_, value)| !value.is_empty()) {
+        println!("{name}: {value}");
+    }
+    println!("LevelReference: {}", cart.level_reference);
+    for timer in &cart.timers {
+        println!("Timer {}: sample {}", timer.usage(), timer.value);
+    }
+    Ok(())
+}
+
+// Sets the text fields of the cart chunk, adding one if the file has none
+fn cart_set(filename: &str, fields: &[(&str, (usize, usize), String)]) -> Result<(), Error> {
+    riff::rewrite_file(filename, |reader, chunks| {
+        let (index, mut data) = match chunks.iter().position(|chunk| &chunk.id == b"cart") {
+            Some(index) => (index, chunks[index].payload(reader)?),
+            None => {
+                chunks.push(riff::Chunk::new(b"cart", Vec::new()));
+                (chunks.len() - 1, cart::new_chunk())
+            }
+        };
+        for (name, field, value) in fields {
+            if cart::set_string(&mut data, *field, value) {
+                eprintln!("{filename}: {name} is cut to {} characters", field.1);
+            }
+        }
+        chunks[index] = riff::Chunk::new(b"cart", data);
+        Ok(())
+    })
+}
+
+fn cart_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue cart show filename.wav
+       wav-cue cart set [--title TEXT] [--artist TEXT] [--cut-id TEXT] [--category TEXT] [--start-date YYYY-MM-DD] ... filename.wav
+       wav-cue cart to-cues filename.wav
+       wav-cue cart from-cues [--usage MRK] filename.wav";
+    if args.first().map(String::as_str) == Some("set") {
+        let mut fields = Vec::new();
+        let mut filename = None;
+        let mut rest = args[1..].iter();
+        while let Some(arg) = rest.next() {
+            match CART_TEXT_FIELDS.iter().find(|(option, _, _)| option == arg) {
+                Some((_, name, field)) => {
+                    let value = option_value(arg, rest.next())?;
+                    let format = match *name {
+                        "StartDate" | "EndDate" => bext::check_origination_date(&value),
+                        "StartTime" | "EndTime" => bext::check_origination_time(&value),
+                        _ => bext::FieldFormat::Valid,
+                    };
+                    if let bext::FieldFormat::Invalid = format {
+                        return Err(Error::UsageError(format!("Invalid {name}: {value}")));
+                    }
+                    fields.push((*name, *field, value));
+                }
+                None if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+                None => return Err(Error::UsageError(String::from(usage))),
+            }
+        }
+        return match filename {
+            Some(filename) if !fields.is_empty() => cart_set(filename, &fields),
+            _ => Err(Error::UsageError(String::from(usage))),
+        };
+    }
+    let mut default_usage = *b"MRK ";
+    let mut filename = None;
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--usage" => {
+                let value = option_value(arg, rest.next())?;
+                default_usage = match cart::parse_usage(&value) {
+                    Some(usage) => usage,
+                    None => return Err(Error::UsageError(format!("Invalid usage id: {value}"))),
+                }
+            }
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    match (args.first().map(String::as_str), filename) {
+        (Some("show"), Some(filename)) => cart_show(filename),
+        (Some("to-cues"), Some(filename)) => cart_to_cues(filename),
+        (Some("from-cues"), Some(filename)) => cart_from_cues(filename, default_usage),
+        _ => Err(Error::UsageError(String::from(usage))),
+    }
+}
+
+// Lists the audio ids of the chna chunk with the ADM objects they belong
+// to, or rewrites them for tracks that were reordered or split off
+fn chna_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue chna [--tracks 3,1-2] filename.wav";
+    let mut tracks = None;
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tracks" => {
+                let value = option_value(arg, args.next())?;
+                let mut list = Vec::new();
+                for (first, last) in parse_id_ranges(&value)? {
+                    if first == 0 || last > u16::MAX as u32 {
+                        return Err(Error::UsageError(format!("Invalid tracks: {value}")));
+                    }
+                    list.extend((first..=last).map(|track| track as u16));
+                }
+                tracks = Some(list);
+            }
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return Err(Error::UsageError(String::from(usage))),
+    };
+    let mut ids = match chunk_payloads(filename, b"chna")?.first() {
+        Some(data) => chna::parse(data)?,
+        None => {
+            return Err(Error::from(WaveError {
+                message: String::from("File has no chna chunk"),
+            }))
+        }
+    };
+    if let Some(tracks) = tracks {
+        let channels = read_wave_file(filename)?.header.number_of_channels as usize;
+        if tracks.len() != channels {
+            return Err(Error::from(WaveError {
+                message: format!(
+                    "--tracks lists {} tracks, the file has {channels} channels",
+                    tracks.len()
+                ),
+            }));
+        }
+        ids = chna::remap(&ids, &tracks);
+        let data = chna::build(&ids);
+        riff::rewrite_file(filename, |_, chunks| {
+            if let Some(index) = chunks.iter().position(|chunk| &chunk.id == b"chna") {
+                chunks[index] = riff::Chunk::new(b"chna", data);
+            }
+            Ok(())
+        })?;
+    }
+    let adm = match chunk_payloads(filename, b"axml")?.first() {
+        Some(data) => adm::parse(data)?,
+        None => adm::Adm::default(),
+    };
+    for id in &ids {
+        let object = adm
+            .object_of_track(&id.uid)
+            .map(|object| format!(" ({})", object.name))
+            .unwrap_or_default();
+        println!(
+            "track {}: {} {} {}{object}",
+            id.track_index, id.uid, id.track_ref, id.pack_ref
+        );
+    }
+    Ok(())
+}
+
+// Prints the peak envelope of the levl chunk, with the points of each
+// block if asked
+fn levl_show(filename: &str, points: bool) -> Result<(), Error> {
+    let levl = match chunk_payloads(filename, b"levl")?.first() {
+        Some(data) => levl::parse(data)?,
+        None => {
+            return Err(Error::from(WaveError {
+                message: String::from("File has no levl chunk"),
+            }))
+        }
+    };
+    let rate = read_wave_file(filename)?.header.sampling_rate;
+    let time = |frame: u64| duration::format_seconds(frame, rate, 3, Rounding::Round);
+    println!(
+        "{} bit points, {} per value, {} frames per peak, {} channels, {} peak frames",
+        if levl.format == levl::FORMAT_8_BIT {
+            8
+        } else {
+            16
+        },
+        levl.points_per_value,
+        levl.block_size,
+        levl.channels,
+        levl.peak_frames
+    );
+    if !levl.timestamp.is_empty() {
+        println!("timestamp: {}", levl.timestamp);
+    }
+    if let Some(frame) = levl.peak_of_peaks {
+        println!("peak of peaks: {}", time(frame as u64));
+    }
+    for (index, peak) in levl.channel_peaks().iter().enumerate() {
+        println!("channel {}: {:.1} dBFS", index + 1, pcm::to_dbfs(*peak));
+    }
+    if points {
+        for frame in 0..levl.peak_frames as usize {
+            let mut line = time(frame as u64 * levl.block_size as u64);
+            for channel in 0..levl.channels as usize {
+                match levl.peak(frame, channel) {
+                    Some((positive, Some(negative))) => {
+                        line.push_str(&format!(" {positive:.4}/-{negative:.4}"))
+                    }
+                    Some((positive, None)) => line.push_str(&format!(" {positive:.4}")),
+                    None => (),
+                }
+            }
+            println!("{line}");
+        }
+    }
+    Ok(())
+}
+
+// Writes a levl chunk computed from the samples, replacing the one the
+// file has, e.g. after the audio was edited
+fn levl_generate(filename: &str, block_size: u32) -> Result<(), Error> {
+    let wave = read_wave_file(filename)?;
+    let data = match &wave.data {
+        Some(data) if !wave.big_endian => data,
+        Some(_) => {
+            return Err(Error::from(WaveError {
+                message: String::from("Cannot read the samples of big-endian files"),
+            }))
+        }
+        None => {
+            return Err(Error::from(WaveError {
+                message: String::from("File has no data chunk"),
+            }))
+        }
+    };
+    let mut reader = zip::open(filename)?;
+    let envelope = levl::generate(
+        &mut reader,
+        &wave.header,
+        data.offset,
+        data.size,
+        block_size,
+        &DateTime::now(),
+    )?;
+    let payload = levl::build(&envelope);
+    riff::rewrite_file(filename, |_, chunks| {
+        // the recorders write it ahead of the data chunk
+        let index = chunks
+            .iter()
+            .position(|chunk| &chunk.id == b"levl")
+            .or_else(|| chunks.iter().position(|chunk| &chunk.id == b"data"));
+        match index {
+            Some(index) if &chunks[index].id == b"levl" => {
+                chunks[index] = riff::Chunk::new(b"levl", payload)
+            }
+            Some(index) => chunks.insert(index, riff::Chunk::new(b"levl", payload)),
+            None => chunks.push(riff::Chunk::new(b"levl", payload)),
+        }
+        Ok(())
+    })?;
+    println!(
+        "{filename}: {} peak frames of {block_size} frames",
+        envelope.peak_frames
+    );
+    Ok(())
+}
+
+fn levl_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue levl show [--points] filename.wav
+       wav-cue levl generate [--block-size 256] filename.wav";
+    let mut points = false;
+    let mut block_size = levl::DEFAULT_BLOCK_SIZE;
+    let mut filename = None;
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--points" => points = true,
+            "--block-size" => {
+                let value = option_value(arg, rest.next())?;
+                block_size = match value.parse() {
+                    Ok(size) if size > 0 => size,
+                    _ => return Err(Error::UsageError(format!("Invalid block size: {value}"))),
+                }
+            }
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    match (args.first().map(String::as_str), filename) {
+        (Some("show"), Some(filename)) => levl_show(filename, points),
+        (Some("generate"), Some(filename)) => levl_generate(filename, block_size),
+        _ => Err(Error::UsageError(String::from(usage))),
+    }
+}
+
+fn split_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue split [--pre 0.5s] [--post 1s] [--fade 5ms] [--fade-shape linear|equal-power] [--normalize-peak -1] [--normalize-loudness -23] [--name TEMPLATE] [--output DIR] filename.wav";
+    let mut pre = TimeValue::Samples(0);
+    let mut post = TimeValue::Samples(0);
+    let mut fade = TimeValue::Samples(0);
+    let mut fade_shape = split::FadeShape::Linear;
+    let mut normalize = None;
+    let mut name_template = String::from(split::DEFAULT_NAME_TEMPLATE);
+    let mut output = String::from(".");
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let handle = |value: String| -> Result<TimeValue, Error> {
+            match TimeValue::parse(&value) {
+                Some(handle) => Ok(handle),
+                None => Err(Error::UsageError(format!("Invalid duration: {value}"))),
+            }
+        };
+        match arg.as_str() {
+            "--pre" => pre = handle(option_value(arg, args.next())?)?,
+            "--post" => post = handle(option_value(arg, args.next())?)?,
+            "--fade" => fade = handle(option_value(arg, args.next())?)?,
+            "--normalize-peak" | "--normalize-loudness" => {
+                let value = option_value(arg, args.next())?;
+                let level = match value
+                    .trim_end_matches("dBFS")
+                    .trim_end_matches("LUFS")
+                    .parse()
+                {
+                    Ok(level) => level,
+                    Err(_) => return Err(Error::UsageError(format!("Invalid level: {value}"))),
+                };
+                normalize = Some(if arg == "--normalize-peak" {
+                    split::Normalize::Peak(level)
+                } else {
+                    split::Normalize::Loudness(level)
+                });
+            }
+            "--name" => name_template = option_value(arg, args.next())?,
+            "--fade-shape" => {
+                fade_shape = match option_value(arg, args.next())?.as_str() {
+                    "linear" => split::FadeShape::Linear,
+                    "equal-power" => split::FadeShape::EqualPower,
+                    shape => return Err(Error::UsageError(format!("Unknown fade shape: {shape}"))),
+                }
+            }
+            "--output" => output = option_value(arg, args.next())?,
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return Err(Error::UsageError(String::from(usage))),
+    };
+    let wave = read_wave_file(filename)?;
+    let sampling_rate = wave.header.sampling_rate;
+    let options = split::Options {
+        pre: pre.to_samples(sampling_rate).unsigned_abs(),
+        post: post.to_samples(sampling_rate).unsigned_abs(),
+        fade: fade.to_samples(sampling_rate).unsigned_abs(),
+        fade_shape,
+        normalize,
+    };
+    fs::create_dir_all(&output)?;
+    let (written, manifest) = split::split(
+        filename,
+        &wave,
+        Path::new(&output),
+        &name_template,
+        &options,
+    )?;
+    for (path, segment) in written {
+        println!(
+            "{}: {:.3}-{:.3}",
+            path.display(),
+            segment.start as f64 / sampling_rate as f64,
+            segment.end as f64 / sampling_rate as f64
+        );
+    }
+    println!("{}", manifest.display());
+    Ok(())
+}
+
+fn project_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue project [--format csv|json] [--tolerance 100ms] DIRECTORY|FILE...";
+    let mut json = false;
+    let mut tolerance = TimeValue::Seconds(0.1);
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                json = match option_value(arg, args.next())?.as_str() {
+                    "csv" => false,
+                    "json" => true,
+                    format => return Err(Error::UsageError(format!("Unknown format: {format}"))),
+                }
+            }
+            "--tolerance" => {
+                let value = option_value(arg, args.next())?;
+                tolerance = match TimeValue::parse(&value) {
+                    Some(tolerance) => tolerance,
+                    None => return Err(Error::UsageError(format!("Invalid tolerance: {value}"))),
+                }
+            }
+            _ if !arg.starts_with("--") => paths.push(PathBuf::from(arg)),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    if paths.is_empty() {
+        return Err(Error::UsageError(String::from(usage)));
+    }
+    let files = files::find_files(&paths)?;
+    // the tolerance is converted at the rate of the first file; the takes
+    // of a project share the rate anyway
+    let sampling_rate = files
+        .first()
+        .and_then(|file| read_wave_file(&file.to_string_lossy()).ok())
+        .map_or(48000, |wave| wave.header.sampling_rate);
+    let tolerance = tolerance.to_samples(sampling_rate).unsigned_abs();
+    let (projects, errors) = project::find_projects(&files, tolerance);
+    for (file, error) in errors {
+        eprintln!("{}: {error}", file.display());
+    }
+    let file_name = |path: &Path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    if json {
+        let projects = projects
+            .iter()
+            .map(|project| {
+                let rate = project.sampling_rate();
+                let takes = project
+                    .takes
+                    .iter()
+                    .map(|take| {
+                        ObjectBuilder::new()
+                            .field("file", take.path.to_string_lossy().to_string())
+                            .field("offset", take.offset as f64 / rate as f64)
+                            .field("duration", take.frames as f64 / rate as f64)
+                            .build()
+                    })
+                    .collect();
+                let markers = project
+                    .markers()
+                    .iter()
+                    .map(|marker| {
+                        ObjectBuilder::new()
+                            .field("time", marker.sample_start as f64 / rate as f64)
+                            .field("file", file_name(&project.takes[marker.take].path))
+                            .field("cue_id", marker.cue_id)
+                            .field("label", marker.label.as_str())
+                            .build()
+                    })
+                    .collect();
+                ObjectBuilder::new()
+                    .field("name", project.name.as_str())
+                    .field("sampling_rate", rate)
+                    .field("duration", project.frames() as f64 / rate as f64)
+                    .field("takes", Value::Array(takes))
+                    .field("markers", Value::Array(markers))
+                    .build()
+            })
+            .collect();
+        print!(
+            "{}",
+            ObjectBuilder::new()
+                .field("projects", Value::Array(projects))
+                .build()
+                .to_json()
+        );
+    } else {
+        println!("project,seconds,file,label");
+        for project in &projects {
+            for marker in project.markers() {
+                println!(
+                    "\"{}\",{},\"{}\",\"{}\"",
+                    project.name.replace('"', "\"\""),
+                    duration::format_seconds(
+                        marker.sample_start,
+                        project.sampling_rate(),
+                        3,
+                        Rounding::Round
+                    ),
+                    file_name(&project.takes[marker.take].path).replace('"', "\"\""),
+                    marker.label.replace('"', "\"\"")
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+// Copies all the metadata, or with --cues, --bext or --labels only those
+// parts, keeping the rest of the target as it is. The labels and notes
+// are keyed by cue id, so they go with the cue points they belong to.
+fn copy_metadata_command(args: &[String]) -> Result<(), Error> {
+    let usage =
+        "usage: wav-cue copy-metadata [--cues] [--bext] [--labels] --from source.wav filename.wav";
+    let mut source = None;
+    let mut filename = None;
+    let (mut cues, mut bext, mut labels) = (false, false, false);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => source = Some(option_value(arg, args.next())?),
+            "--cues" => cues = true,
+            "--bext" => bext = true,
+            "--labels" => labels = true,
+            // copy-metadata source.wav filename.wav
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ if source.is_none() && !arg.starts_with("--") => {
+                source = filename.replace(arg).cloned()
+            }
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let (source, filename) = match (source, filename) {
+        (Some(source), Some(filename)) => (source, filename),
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let all = !(cues || bext || labels);
+    let metadata = metadata::WaveMetadata::read_from(Path::new(&source))?;
+    let mut target = metadata::WaveMetadata::read_from(Path::new(filename))?;
+    // cue positions are in samples
+    if (all || cues) && metadata.header.sampling_rate != target.header.sampling_rate {
+        return Err(Error::from(WaveError {
+            message: format!(
+                "{source} is at {} Hz and {filename} at {} Hz; use convert-cues after copying",
+                metadata.header.sampling_rate, target.header.sampling_rate
+            ),
+        }));
+    }
+    if all {
+        return metadata.write_to(Path::new(filename));
+    }
+    if cues {
+        target.cues = metadata.cues;
+        target.regions = metadata.regions;
+    }
+    if cues || labels {
+        target.labels = metadata.labels;
+        target.notes = metadata.notes;
+    }
+    if bext {
+        target.bext = metadata.bext;
+    }
+    target.write_to(Path::new(filename))
+}
+
+fn join_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue join --output joined.wav filename.wav|PATTERN...";
+    let mut output = None;
+    let mut inputs = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" | "-o" => output = Some(option_value(arg, args.next())?),
+            _ if !arg.starts_with('-') => inputs.push(arg.clone()),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let output = match output {
+        Some(output) if !inputs.is_empty() => output,
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let files: Vec<String> = files::expand(&inputs, false)?
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    let joined = join::join(&files, Path::new(&output))?;
+    println!(
+        "{output}: {} files, {} cue points",
+        files.len(),
+        joined.cues.len()
+    );
+    Ok(())
+}
+
+fn find_command(args: &[String]) -> Result<(), Error> {
+    let usage =
+        "usage: wav-cue find --before TIME|--nearest TIME|--from TIME --to TIME filename.wav";
+    let mut before = None;
+    let mut nearest = None;
+    let mut from = None;
+    let mut to = None;
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let time = |value: String| -> Result<Option<TimeValue>, Error> {
+            match TimeValue::parse(&value) {
+                Some(time) => Ok(Some(time)),
+                None => Err(Error::UsageError(format!("Invalid time: {value}"))),
+            }
+        };
+        match arg.as_str() {
+            "--before" => before = time(option_value(arg, args.next())?)?,
+            "--nearest" => nearest = time(option_value(arg, args.next())?)?,
+            "--from" => from = time(option_value(arg, args.next())?)?,
+            "--to" => to = time(option_value(arg, args.next())?)?,
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return Err(Error::UsageError(String::from(usage))),
+    };
+    let wave = read_wave_file(filename)?;
+    let rate = wave.header.sampling_rate;
+    let sample = |time: TimeValue| time.to_samples(rate).max(0) as u64;
+    let found = match (before, nearest, from, to) {
+        (Some(time), None, None, None) => wave.cue_at_or_before(sample(time)).into_iter().collect(),
+        (None, Some(time), None, None) => wave.nearest_cue(sample(time)).into_iter().collect(),
+        (None, None, Some(from), to) => {
+            wave.cues_in_range(sample(from), to.map_or(u64::MAX, sample))
+        }
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    for cue in found {
+        println!(
+            "{}\t{}",
+            cue.cue_id,
+            duration::format_seconds(cue.sample_start as u64, rate, 3, Rounding::Round)
+        );
+    }
+    Ok(())
+}
+
+// Four character chunk id; shorter ones are padded with spaces as in "fmt "
+fn chunk_id(text: &str) -> Result<[u8; 4], Error> {
+    let bytes = text.as_bytes();
+    if bytes.is_empty() || bytes.len() > 4 || !text.is_ascii() {
+        return Err(Error::UsageError(format!("Invalid chunk id: {text}")));
+    }
+    let mut id = *b"    ";
+    id[..bytes.len()].copy_from_slice(bytes);
+    Ok(id)
+}
+
+// Every chunk with the offset of its header and the size of its payload,
+// and the items of LIST chunks indented under them
+fn chunks_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue chunks filename.wav";
+    let filename = match args {
+        [filename] if !filename.starts_with("--") => filename,
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let name = |id: &[u8]| String::from_utf8_lossy(id).to_string();
+    let mut reader = zip::open(filename)?;
+    let mut header = [0u8; 12];
+    reader.read_exact(&mut header)?;
+    let size = [header[4], header[5], header[6], header[7]];
+    println!(
+        "{:>10}  {:<6}  {:>10}  {}",
+        0,
+        name(&header[..4]),
+        if &header[..4] == b"RIFX" {
+            u32::from_be_bytes(size)
+        } else {
+            u32::from_le_bytes(size)
+        },
+        name(&header[8..])
+    );
+    let mut chunks = riff::ChunkReader::new(reader)?;
+    while let Some(info) = chunks.next() {
+        let info = info?;
+        let offset = info.offset - 8;
+        if &info.id != b"LIST" {
+            println!("{offset:>10}  {:<6}  {:>10}", name(&info.id), info.size);
+            continue;
+        }
+        let payload = chunks.read_payload(&info)?;
+        let (list_type, items) = match riff::parse_list(&payload) {
+            Ok(list) => list,
+            Err(error) => {
+                println!("{offset:>10}  LIST    {:>10}  ({error})", info.size);
+                continue;
+            }
+        };
+        println!(
+            "{offset:>10}  LIST    {:>10}  {}",
+            info.size,
+            name(&list_type)
+        );
+        // items follow the list type, word aligned like chunks
+        let mut item_offset = info.offset + 4;
+        for (id, data) in items {
+            println!("{item_offset:>10}    {:<4}  {:>10}", name(&id), data.len());
+            item_offset += 8 + data.len() as u64 + (data.len() as u64 & 1);
+        }
+    }
+    Ok(())
+}
+
+fn extract_chunk_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue extract-chunk --id ID [--index N] [-o OUTPUT] filename.wav";
+    let mut id = None;
+    let mut index = 1;
+    let mut output = None;
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--id" => id = Some(chunk_id(&option_value(arg, args.next())?)?),
+            "--index" => {
+                let value = option_value(arg, args.next())?;
+                index = match value.parse::<usize>() {
+                    Ok(index) if index > 0 => index,
+                    _ => return Err(Error::UsageError(format!("Invalid index: {value}"))),
+                }
+            }
+            "-o" | "--output" => output = Some(option_value(arg, args.next())?),
+            _ if filename.is_none() && !arg.starts_with('-') => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let (id, filename) = match (id, filename) {
+        (Some(id), Some(filename)) => (id, filename),
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let mut reader = zip::open(filename)?;
+    let info = match riff::walk_chunks(&mut reader)?
+        .into_iter()
+        .filter(|info| info.id == id)
+        .nth(index - 1)
+    {
+        Some(info) => info,
+        None => {
+            return Err(Error::from(WaveError {
+                message: format!(
+                    "{filename} has no {} chunk number {index}",
+                    String::from_utf8_lossy(&id).trim_end()
+                ),
+            }))
+        }
+    };
+    // copied without reading the payload into memory, as it may be the audio
+    reader.seek(SeekFrom::Start(info.offset))?;
+    let mut payload = reader.take(info.size as u64);
+    match output {
+        Some(output) => {
+            let mut writer = BufWriter::new(File::create(output)?);
+            io::copy(&mut payload, &mut writer)?;
+            writer.flush()?;
+        }
+        None => {
+            io::copy(&mut payload, &mut io::stdout().lock())?;
+        }
+    }
+    Ok(())
+}
+
+fn inject_chunk_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue inject-chunk --id ID --from FILE [--index N|--add] filename.wav";
+    let mut id = None;
+    let mut index = 1;
+    let mut add = false;
+    let mut from = None;
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--id" => id = Some(chunk_id(&option_value(arg, args.next())?)?),
+            "--index" => {
+                let value = option_value(arg, args.next())?;
+                index = match value.parse::<usize>() {
+                    Ok(index) if index > 0 => index,
+                    _ => return Err(Error::UsageError(format!("Invalid index: {value}"))),
+                }
+            }
+            "--add" => add = true,
+            "--from" => from = Some(option_value(arg, args.next())?),
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let (id, from, filename) = match (id, from, filename) {
+        (Some(id), Some(from), Some(filename)) => (id, from, filename),
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let payload = fs::read(from)?;
+    if payload.len() > u32::MAX as usize {
+        return Err(Error::from(WaveError {
+            message: String::from("Chunk is too large for RIFF"),
+        }));
+    }
+    riff::rewrite_file(filename, |_, chunks| {
+        let existing = chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| chunk.id == id)
+            .map(|(position, _)| position)
+            .nth(index - 1);
+        match existing {
+            Some(position) if !add => chunks[position] = riff::Chunk::new(&id, payload),
+            // new chunks go in front of the audio, where readers look for
+            // metadata first
+            _ => {
+                let position = chunks
+                    .iter()
+                    .position(|chunk| &chunk.id == b"data")
+                    .unwrap_or(chunks.len());
+                chunks.insert(position, riff::Chunk::new(&id, payload));
+            }
+        }
+        Ok(())
+    })
+}
+
+// Returns whether all the files passed, after the fixes if requested
+fn lint_command(args: &[String]) -> Result<bool, Error> {
+    let usage = "usage: wav-cue lint [--fix] filename.wav...";
+    let mut fix = false;
+    let mut filenames = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--fix" => fix = true,
+            _ if !arg.starts_with("--") => filenames.push(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    if filenames.is_empty() {
+        return Err(Error::UsageError(String::from(usage)));
+    }
+    let mut passed = true;
+    for filename in filenames {
+        let findings = lint::lint(filename)?;
+        if fix && findings.iter().any(|finding| finding.fixable) {
+            for change in lint::fix(filename)? {
+                println!("{filename}: fixed: {change}");
+            }
+        }
+        for finding in findings {
+            if !(fix && finding.fixable) {
+                println!("{filename}: {}", finding.message);
+                passed = false;
+            }
+        }
+    }
+    Ok(passed)
+}
+
+// Returns whether the merge was free of conflicts
+fn merge_command(args: &[String]) -> Result<bool, Error> {
+    let usage =
+        "usage: wav-cue merge --base original.wav --ours a.csv --theirs b.csv [--tolerance 1ms]";
+    let mut base = None;
+    let mut ours = None;
+    let mut theirs = None;
+    let mut tolerance = TimeValue::Seconds(0.001);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--base" => base = Some(option_value(arg, args.next())?),
+            "--ours" => ours = Some(option_value(arg, args.next())?),
+            "--theirs" => theirs = Some(option_value(arg, args.next())?),
+            "--tolerance" => {
+                let value = option_value(arg, args.next())?;
+                tolerance = match TimeValue::parse(&value) {
+                    Some(tolerance) => tolerance,
+                    None => return Err(Error::UsageError(format!("Invalid tolerance: {value}"))),
+                }
+            }
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let (base, ours, theirs) = match (base, ours, theirs) {
+        (Some(base), Some(ours), Some(theirs)) => (base, ours, theirs),
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let wave = read_wave_file(&base)?;
+    let sampling_rate = wave.header.sampling_rate;
+    let mut base_markers: Vec<(f64, String)> = wave
+        .cues
+        .iter()
+        .map(|cue| (cue.sample_start as f64 / sampling_rate as f64, cue.title()))
+        .collect();
+    base_markers.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let tolerance = tolerance.to_samples(sampling_rate).abs() as f64 / sampling_rate as f64;
+    let merged = merge::merge(
+        &base_markers,
+        &check::read_reference(&ours)?,
+        &check::read_reference(&theirs)?,
+        tolerance,
+    );
+    for (seconds, label) in &merged.markers {
+        println!("{seconds:.3},{label}");
+    }
+    for conflict in &merged.conflicts {
+        eprintln!("conflict: {conflict}");
+    }
+    Ok(merged.conflicts.is_empty())
+}
+
+fn convert_cues_command(args: &[String]) -> Result<(), Error> {
+    let usage =
+        "usage: wav-cue convert-cues --from RATE|--from-file original.wav [--to RATE] filename.wav";
+    let mut from = None;
+    let mut to = None;
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let rate = |value: String| -> Result<u32, Error> {
+            match value.parse() {
+                Ok(rate) if rate > 0 => Ok(rate),
+                _ => Err(Error::UsageError(format!("Invalid sampling rate: {value}"))),
+            }
+        };
+        match arg.as_str() {
+            "--from" => from = Some(rate(option_value(arg, args.next())?)?),
+            "--from-file" => {
+                from = Some(
+                    read_wave_file(&option_value(arg, args.next())?)?
+                        .header
+                        .sampling_rate,
+                )
+            }
+            "--to" => to = Some(rate(option_value(arg, args.next())?)?),
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let (filename, from) = match (filename, from) {
+        (Some(filename), Some(from)) => (filename, from),
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    // the file itself is already at the new rate
+    let to = match to {
+        Some(to) => to,
+        None => read_wave_file(filename)?.header.sampling_rate,
+    };
+    let (cues, regions) = convert::convert_cues(filename, from, to)?;
+    println!(
+        "{filename}: rescaled {cues} cue points and {regions} regions from {from} Hz to {to} Hz"
+    );
+    Ok(())
+}
+
+// Sets fields of the INFO list, given as pairs of an id and a text; an
+// empty text removes the field
+fn info_set(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue info set ID TEXT [ID TEXT...] filename.wav";
+    let (filename, pairs) = match args.split_last() {
+        Some((filename, pairs)) if !pairs.is_empty() && pairs.len() % 2 == 0 => (filename, pairs),
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let mut fields = Vec::new();
+    for pair in pairs.chunks(2) {
+        match info::parse_id(&pair[0]) {
+            Some(id) => fields.push((id, pair[1].as_str())),
+            None => return Err(Error::UsageError(format!("Invalid INFO id: {}", pair[0]))),
+        }
+    }
+    let path = Path::new(filename);
+    let mut metadata = metadata::WaveMetadata::read_from(path)?;
+    for (id, value) in &fields {
+        info::set(&mut metadata.info, id, value);
+    }
+    metadata.write_to(path)
+}
+
+// Summary of the file for reading at a glance; --json gives all of it
+fn info_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue info [--json] [--strict|--lenient] [--need CHUNKS] filename.wav
+       wav-cue info set ID TEXT [ID TEXT...] filename.wav";
+    if args.first().map(String::as_str) == Some("set") {
+        return info_set(&args[1..]);
+    }
+    let mut json = false;
+    let mut parsing = ParseMode::Normal;
+    let mut need = None;
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--strict" => parsing = ParseMode::Strict,
+            "--lenient" => parsing = ParseMode::Lenient,
+            "--need" => need = Some(parse_need(&option_value(arg, args.next())?)?),
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return Err(Error::UsageError(String::from(usage))),
+    };
+    if json {
+        let options = ExportOptions {
+            format: OutputFormat::Json,
+            parsing,
+            need,
+            ..ExportOptions::default()
+        };
+        return process(filename, &options);
+    }
+    let wave = read_wave_needing(filename, parsing, need.as_deref())?;
+    let header = &wave.header;
+    let rate = header.sampling_rate;
+    let clock = |samples: u64| duration::format_clock(samples, rate, 3, Rounding::Round);
+    println!("{filename}");
+    if let Some(title) = &wave.title {
+        println!("  title: {title}");
+    }
+    println!(
+        "  format: {}, {} bits, {} channels, {rate} Hz",
+        header.compression_code, header.significant_bits_per_sample, header.number_of_channels
+    );
+    if let Some(frames) = wave.frames() {
+        println!("  length: {} ({frames} frames)", clock(frames));
+    }
+    if let Some(bext) = &wave.bext {
+        println!(
+            "  bext: {}, {} {}",
+            bext.originator, bext.origination_date, bext.origination_time
+        );
+        if let Some(integrated) = bext.loudness.and_then(|loudness| loudness.integrated) {
+            println!("  loudness: {integrated:.1} LUFS");
+        }
+    }
+    for (id, value) in &wave.info {
+        match info::parse_id(id).and_then(|id| info::field_name(&id)) {
+            Some(name) => println!("  {id} ({name}): {value}"),
+            None => println!("  {id}: {value}"),
+        }
+    }
+    println!("  cue points: {}", wave.cues.len());
+    for cue in wave.sorted_cues() {
+        println!(
+            "    {}\t{}\t{}",
+            cue.cue_id,
+            clock(cue.sample_start as u64),
+            cue.title()
+        );
+    }
+    if let Some(sampler) = &wave.sampler {
+        println!("  sample loops: {}", sampler.loops.len());
+    }
+    Ok(())
+}
+
+// Changes to the cue points of a file, applied together by apply_edits
+#[derive(Default)]
+struct CueEdits {
+    adds: Vec<(TimeValue, String)>,
+    removes: Vec<u32>,
+    renames: Vec<(u32, String)>,
+    moves: Vec<(u32, TimeValue)>,
+    // added to every cue point, which stops at the start of the file
+    shift: Option<TimeValue>,
+    // cue points to delete by ranges of ids and of times, both inclusive;
+    // unlike removes, ids that don't exist are skipped
+    delete_ids: Vec<(u32, u32)>,
+    delete_between: Vec<(TimeValue, TimeValue)>,
+}
+
+fn parse_time(value: &str) -> Result<TimeValue, Error> {
+    match TimeValue::parse(value) {
+        Some(time) => Ok(time),
+        None => Err(Error::UsageError(format!("Invalid time: {value}"))),
+    }
+}
+
+// Adds, removes, renames, moves and shifts cue points; the labels, notes
+// and regions of removed cue points go with them
+fn edit_command(args: &[String]) -> Result<(), Error> {
+    match args.first().map(String::as_str) {
+        Some("shift") => return edit_shift_command(&args[1..]),
+        Some("delete") => return edit_delete_command(&args[1..]),
+        Some("add") => return edit_add_command(&args[1..]),
+        Some("renumber") => return edit_renumber_command(&args[1..]),
+        _ => (),
+    }
+    let usage = "usage: wav-cue edit [--add TIME[=LABEL]]... [--remove ID]... [--rename ID=LABEL]... [--move ID=TIME]... filename.wav";
+    let mut edits = CueEdits::default();
+    let mut filename = None;
+    let cue_id = |value: &str| {
+        value
+            .parse::<u32>()
+            .map_err(|_| Error::UsageError(format!("Invalid cue id: {value}")))
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--add" => {
+                let value = option_value(arg, args.next())?;
+                let (at, label) = value.split_once('=').unwrap_or((&value, ""));
+                edits.adds.push((parse_time(at)?, label.to_string()));
+            }
+            "--remove" => edits
+                .removes
+                .push(cue_id(&option_value(arg, args.next())?)?),
+            "--rename" => {
+                let value = option_value(arg, args.next())?;
+                match value.split_once('=') {
+                    Some((id, label)) => edits.renames.push((cue_id(id)?, label.to_string())),
+                    None => return Err(Error::UsageError(String::from(usage))),
+                }
+            }
+            "--move" => {
+                let value = option_value(arg, args.next())?;
+                match value.split_once('=') {
+                    Some((id, to)) => edits.moves.push((cue_id(id)?, parse_time(to)?)),
+                    None => return Err(Error::UsageError(String::from(usage))),
+                }
+            }
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    match filename {
+        Some(filename) => apply_edits(filename, edits),
+        None => Err(Error::UsageError(String::from(usage))),
+    }
+}
+
+// Moves all cue points, e.g. after the audio was trimmed in another program
+fn edit_shift_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue edit shift --by [-]TIME filename.wav";
+    let mut edits = CueEdits::default();
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--by" => edits.shift = Some(parse_time(&option_value(arg, args.next())?)?),
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    match (filename, edits.shift) {
+        (Some(filename), Some(_)) => apply_edits(filename, edits),
+        _ => Err(Error::UsageError(String::from(usage))),
+    }
+}
+
+// Cue ids like 3,7-9
+fn parse_id_ranges(value: &str) -> Result<Vec<(u32, u32)>, Error> {
+    let invalid = || Error::UsageError(format!("Invalid cue ids: {value}"));
+    let mut ranges = Vec::new();
+    for range in value.split(',') {
+        let (first, last) = range.split_once('-').unwrap_or((range, range));
+        match (first.trim().parse(), last.trim().parse()) {
+            (Ok(first), Ok(last)) if first <= last => ranges.push((first, last)),
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(ranges)
+}
+
+fn edit_delete_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue edit delete [--id 3,7-9] [--between TIME TIME] filename.wav";
+    let mut edits = CueEdits::default();
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--id" => edits
+                .delete_ids
+                .extend(parse_id_ranges(&option_value(arg, args.next())?)?),
+            "--between" => {
+                let from = parse_time(&option_value(arg, args.next())?)?;
+                let to = parse_time(&option_value(arg, args.next())?)?;
+                edits.delete_between.push((from, to));
+            }
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    match filename {
+        Some(filename) if !edits.delete_ids.is_empty() || !edits.delete_between.is_empty() => {
+            apply_edits(filename, edits)
+        }
+        _ => Err(Error::UsageError(String::from(usage))),
+    }
+}
+
+fn edit_add_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue edit add --at TIME [--label LABEL] filename.wav";
+    let mut at = None;
+    let mut label = String::new();
+    let mut filename = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--at" => at = Some(parse_time(&option_value(arg, args.next())?)?),
+            "--label" => label = option_value(arg, args.next())?,
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    match (filename, at) {
+        (Some(filename), Some(at)) => apply_edits(
+            filename,
+            CueEdits {
+                adds: vec![(at, label)],
+                ..CueEdits::default()
+            },
+        ),
+        _ => Err(Error::UsageError(String::from(usage))),
+    }
+}
+
+// Sorts the cue points by position and numbers them from 1, with their
+// labels, notes and regions; those of cue points that don't exist are
+// dropped, as they would end up on another one
+fn edit_renumber_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue edit renumber filename.wav";
+    let filename = match args {
+        [filename] if !filename.starts_with("--") => filename,
+        _ => return Err(Error::UsageError(String::from(usage))),
+    };
+    let mut cues = read_wave_file(filename)?.cues;
+    cues.sort_by_key(|cue| (cue.sample_start, cue.cue_id));
+    let ids: Vec<(u32, u32)> = (1..)
+        .zip(&mut cues)
+        .map(|(new_id, cue)| (std::mem::replace(&mut cue.cue_id, new_id), new_id))
+        .collect();
+    riff::rewrite_file(filename, |reader, chunks| {
+        set_cue_chunk(chunks, &cues);
+        adtl::edit_list(reader, chunks, |list| {
+            list.retain_mut(|(_, data)| {
+                let new_id = data.get(0..4).and_then(|id| {
+                    let cue_id = u32::from_le_bytes([id[0], id[1], id[2], id[3]]);
+                    ids.iter()
+                        .find(|(old_id, _)| *old_id == cue_id)
+                        .map(|(_, new_id)| *new_id)
+                });
+                match new_id {
+                    Some(new_id) => {
+                        data[0..4].copy_from_slice(&new_id.to_le_bytes());
+                        true
+                    }
+                    None => false,
+                }
+            });
+            Ok(())
+        })
+    })?;
+    let changed = ids
+        .iter()
+        .filter(|(old_id, new_id)| old_id != new_id)
+        .count();
+    println!(
+        "{filename}: renumbered {changed} of {} cue points",
+        cues.len()
+    );
+    Ok(())
+}
+
+fn apply_edits(filename: &str, edits: CueEdits) -> Result<(), Error> {
+    let CueEdits {
+        adds,
+        mut removes,
+        mut renames,
+        moves,
+        shift,
+        delete_ids,
+        delete_between,
+    } = edits;
+    let wave = read_wave_file(filename)?;
+    let rate = wave.header.sampling_rate;
+    let beyond_reach = || {
+        Error::from(WaveError {
+            message: String::from("Time is beyond the reach of a cue point"),
+        })
+    };
+    let sample = |time: TimeValue| -> Result<u32, Error> {
+        match time.to_samples(rate).max(0) {
+            samples if samples <= u32::MAX as i64 => Ok(samples as u32),
+            _ => Err(beyond_reach()),
+        }
+    };
+    let mut cues = wave.cues;
+    let missing = |cue_id: u32| {
+        Error::from(WaveError {
+            message: format!("No cue point with id {cue_id}"),
+        })
+    };
+    for cue_id in &removes {
+        if !cues.iter().any(|cue| cue.cue_id == *cue_id) {
+            return Err(missing(*cue_id));
+        }
+    }
+    if !delete_ids.is_empty() || !delete_between.is_empty() {
+        let mut deleted = Vec::new();
+        for cue in &cues {
+            let sample = cue.sample_start as i64;
+            let by_id = delete_ids
+                .iter()
+                .any(|(first, last)| (*first..=*last).contains(&cue.cue_id));
+            let by_time = delete_between
+                .iter()
+                .any(|(from, to)| (from.to_samples(rate)..=to.to_samples(rate)).contains(&sample));
+            if by_id || by_time {
+                deleted.push(cue.cue_id);
+            }
+        }
+        if deleted.is_empty() {
+            return Err(Error::from(WaveError {
+                message: String::from("No cue points to delete"),
+            }));
+        }
+        removes.extend(deleted);
+    }
+    cues.retain(|cue| !removes.contains(&cue.cue_id));
+    if let Some(shift) = shift {
+        let offset = shift.to_samples(rate);
+        let shifted = |sample: u32| -> Result<u32, Error> {
+            match (sample as i64 + offset).max(0) {
+                sample if sample <= u32::MAX as i64 => Ok(sample as u32),
+                _ => Err(beyond_reach()),
+            }
+        };
+        for cue in &mut cues {
+            cue.sample_start = shifted(cue.sample_start)?;
+            cue.position = shifted(cue.position)?;
+        }
+    }
+    for (cue_id, to) in moves {
+        let cue = cues
+            .iter_mut()
+            .find(|cue| cue.cue_id == cue_id)
+            .ok_or_else(|| missing(cue_id))?;
+        cue.sample_start = sample(to)?;
+        cue.position = cue.sample_start;
+    }
+    for (cue_id, _) in &renames {
+        if !cues.iter().any(|cue| cue.cue_id == *cue_id) {
+            return Err(missing(*cue_id));
+        }
+    }
+    let mut added = Vec::new();
+    for (at, label) in adds {
+        let ids: Vec<u32> = cues.iter().map(|cue| cue.cue_id).collect();
+        let cue_id = next_cue_id(&ids).ok_or_else(|| {
+            Error::from(WaveError {
+                message: String::from("No unused cue id left"),
+            })
+        })?;
+        added.push((cue_id, sample(at)?));
+        cues.push(CueEntry {
+            cue_id,
+            position: sample(at)?,
+            data_chunk_id: DataChunkId::Data,
+            chunk_start: 0,
+            block_start: 0,
+            sample_start: sample(at)?,
+            label: None,
+            note: None,
+            color: None,
+            url: None,
+            image: None,
+            region: None,
+        });
+        if !label.is_empty() {
+            renames.push((cue_id, label));
+        }
+    }
+    riff::rewrite_file(filename, |reader, chunks| {
+        set_cue_chunk(chunks, &cues);
+        adtl::edit_list(reader, chunks, |list| {
+            // every adtl entry starts with the id of its cue point
+            list.retain(|(id, data)| {
+                let cue_id = data
+                    .get(0..4)
+                    .map(|id| u32::from_le_bytes([id[0], id[1], id[2], id[3]]));
+                let renamed =
+                    id == b"labl" && renames.iter().any(|(renamed, _)| Some(*renamed) == cue_id);
+                !renamed && cue_id.is_none_or(|cue_id| !removes.contains(&cue_id))
+            });
+            for (cue_id, label) in &renames {
+                list.push(adtl::text_item(b"labl", *cue_id, label));
+            }
+            // labels and regions that aren't in the list yet, those of
+            // XMP markers, are written along with their cue points
+            let listed = |list: &[riff::ListItem], kind: &[u8; 4], cue_id: u32| {
+                list.iter().any(|(id, data)| {
+                    id == kind && data.get(0..4) == Some(&cue_id.to_le_bytes()[..])
+                })
+            };
+            for cue in &cues {
+                if let Some(label) = &cue.label {
+                    if !listed(list, b"labl", cue.cue_id) {
+                        list.push(adtl::text_item(b"labl", cue.cue_id, label));
+                    }
+                }
+                if let Some(region) = &cue.region {
+                    if !listed(list, b"ltxt", cue.cue_id) {
+                        adtl::set_region(list, region);
+                    }
+                }
+            }
+            Ok(())
+        })
+    })?;
+    for (cue_id, sample_start) in added {
+        println!(
+            "{filename}: added cue {cue_id} at {}",
+            duration::format_seconds(sample_start as u64, rate, 3, Rounding::Round)
+        );
+    }
+    println!("{filename}: {} cue points", cues.len());
+    Ok(())
+}
+
+// Checks the structure of the files, that they parse and that their cue
+// points are consistent, along with the lint checks; returns whether all
+// the files passed without errors
+fn validate_command(args: &[String]) -> Result<bool, Error> {
+    let usage = "usage: wav-cue validate [--strict|--lenient] filename.wav...";
+    let mut parsing = ParseMode::Normal;
+    let mut files = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--strict" => parsing = ParseMode::Strict,
+            "--lenient" => parsing = ParseMode::Lenient,
+            _ if !arg.starts_with("--") => files.push(arg),
+            _ => return Err(Error::UsageError(String::from(usage))),
+        }
+    }
+    if files.is_empty() {
+        return Err(Error::UsageError(String::from(usage)));
+    }
+    let mut passed = true;
+    for filename in files {
+        let findings = validate::validate(filename, parsing)?;
+        for finding in &findings {
+            println!("{filename}: {}: {}", finding.severity, finding.message);
+        }
+        passed &= findings
+            .iter()
+            .all(|finding| finding.severity < validate::Severity::Error);
+    }
+    Ok(passed)
+}
+
+// One line per file with its length and format, the number of markers
+// and which metadata chunks it has, to look a set of takes over
+fn stat_command(args: &[String]) -> Result<(), Error> {
+    let usage = "usage: wav-cue stat filename.wav...";
+    if args.is_empty() || args.iter().any(|arg| arg.starts_with("--")) {
+        return Err(Error::UsageError(String::from(usage)));
+    }
+    for filename in args {
+        let wave = read_wave_file(filename)?;
+        let header = &wave.header;
+        let mut signature = [0u8; 4];
+        zip::open(filename)?.read_exact(&mut signature)?;
+        // where the length comes from
+        let source = match (&signature, wave.frames()) {
+            (_, None) => "no data chunk",
+            (b"RF64" | b"BW64", _) => "ds64",
+            _ if wave.sample_length.is_some() && !header.compression_code.is_linear() => "fact",
+            _ => "data size",
+        };
+        let length = duration::format_clock(
+            wave.frames().unwrap_or(0),
+            header.sampling_rate,
+            3,
+            Rounding::Round,
+        );
+        let regions = wave.cues.iter().filter(|cue| cue.region.is_some()).count();
+        let has = |present: bool, name: &str| match present {
+            true => name.to_string(),
+            false => format!("no {name}"),
+        };
+        println!(
+            "{filename}: {length} ({source}), {} channels, {} Hz, {} bits, {}, {} cues, {regions} regions, {}, {}",
+            header.number_of_channels,
+            header.sampling_rate,
+            header.significant_bits_per_sample,
+            header.compression_code,
+            wave.cues.len(),
+            has(wave.bext.is_some(), "bext"),
+            has(wave.ixml.is_some(), "iXML"),
+        );
+    }
+    Ok(())
+}
+
+type Command = fn(&[String]) -> Result<bool, Error>;
+
+// Subcommands, with the usage lines shown when none is given. A command
+// returning false, like a check that doesn't pass, exits with 1, and one
+// failing with an error exits with 2.
+const COMMANDS: &[(&str, &[&str], Command)] = &[
+    (
+        "info",
+        &[
+            "info [--json] [--strict|--lenient] filename.wav",
+            "info set ID TEXT [ID TEXT...] filename.wav",
+        ],
+        |args| info_command(args).map(|()| true),
+    ),
+    (
+        "export",
+        &[
+            "export filename.wav > filename.csv",
+            "export --format json|yaml|toml|xlsx|podcast|chaptertool|id3|cubase|reaper|audition|protools|fcpxml|premiere|edl|resolve|audacity|cuesheet|webvtt|srt filename.wav",
+            "export [--precision DIGITS] [--truncate|--round] filename.wav",
+            "export [--recursive] [--output DIR] filename.wav|DIRECTORY|PATTERN...",
+        ],
+        export_command,
+    ),
+    (
+        "import",
+        &["import --from csv|audacity|premiere [--fps RATE] [--merge|--replace] markers filename.wav"],
+        |args| import_command(args).map(|()| true),
+    ),
+    (
+        "edit",
+        &[
+            "edit [--add TIME[=LABEL]] [--remove ID] [--rename ID=LABEL] [--move ID=TIME] filename.wav",
+            "edit shift --by [-]TIME filename.wav",
+            "edit delete [--id 3,7-9] [--between TIME TIME] filename.wav",
+            "edit add --at TIME [--label LABEL] filename.wav",
+            "edit renumber filename.wav",
+        ],
+        |args| edit_command(args).map(|()| true),
+    ),
+    (
+        "split",
+        &["split [--pre 0.5s] [--post 1s] [--fade 5ms] [--fade-shape linear|equal-power] [--normalize-peak -1] [--normalize-loudness -23] [--name TEMPLATE] [--output DIR] filename.wav"],
+        |args| split_command(args).map(|()| true),
+    ),
+    ("validate", &["validate [--strict|--lenient] filename.wav..."], validate_command),
+    ("stat", &["stat filename.wav..."], |args| {
+        stat_command(args).map(|()| true)
+    }),
+    (
+        "bext",
+        &[
+            "bext set [--description TEXT] [--originator TEXT] [--origination-date YYYY-MM-DD] ... filename.wav",
+            "bext set-umid [--extended] filename.wav",
+            "bext set-time-reference --from mtime|filename filename.wav",
+            "bext append-history [--text LINE] filename.wav",
+        ],
+        |args| bext_command(args).map(|()| true),
+    ),
+    ("tracks", &["tracks filename.wav"], |args| {
+        tracks_command(args).map(|()| true)
+    }),
+    ("redact", &["redact [--replace TEXT] filename.wav"], |args| {
+        redact_command(args).map(|()| true)
+    }),
+    (
+        "region",
+        &["region --cue ID [--length DURATION] [--language N] filename.wav"],
+        |args| region_command(args).map(|()| true),
+    ),
+    (
+        "check",
+        &["check --against expected.csv [--tolerance 1ms] filename.wav"],
+        check_command,
+    ),
+    (
+        "thumbnails",
+        &["thumbnails [--window 2s] [--output DIR] filename.wav"],
+        |args| thumbnails_command(args).map(|()| true),
+    ),
+    ("reserve-cues", &["reserve-cues [--count 100] filename.wav"], |args| {
+        reserve_cues_command(args).map(|()| true)
+    }),
+    (
+        "analyze",
+        &["analyze [--silence-threshold -50] [--min-gap 2s] [--dry-run] filename.wav"],
+        |args| analyze_command(args).map(|()| true),
+    ),
+    ("append-cue", &["append-cue [--at now|TIME] filename.wav"], |args| {
+        append_cue_command(args).map(|()| true)
+    }),
+    ("duplicates", &["duplicates [--all] DIRECTORY..."], |args| {
+        duplicates_command(args).map(|()| true)
+    }),
+    (
+        "xmp",
+        &["xmp [--title TEXT] [--creator NAME] filename.wav"],
+        |args| xmp_command(args).map(|()| true),
+    ),
+    (
+        "cart",
+        &[
+            "cart show filename.wav",
+            "cart set [--title TEXT] [--artist TEXT] [--cut-id TEXT] ... filename.wav",
+            "cart to-cues|from-cues filename.wav",
+        ],
+        |args| cart_command(args).map(|()| true),
+    ),
+    ("chna", &["chna [--tracks 3,1-2] filename.wav"], |args| {
+        chna_command(args).map(|()| true)
+    }),
+    (
+        "levl",
+        &[
+            "levl show [--points] filename.wav",
+            "levl generate [--block-size 256] filename.wav",
+        ],
+        |args| levl_command(args).map(|()| true),
+    ),
+    (
+        "project",
+        &["project [--format csv|json] DIRECTORY|FILE..."],
+        |args| project_command(args).map(|()| true),
+    ),
+    (
+        "copy-metadata",
+        &["copy-metadata [--cues] [--bext] [--labels] --from source.wav filename.wav"],
+        |args| copy_metadata_command(args).map(|()| true),
+    ),
+    // short for copy-metadata, which takes the source without --from too
+    ("copy-meta", &[], |args| {
+        copy_metadata_command(args).map(|()| true)
+    }),
+    (
+        "join",
+        &["join --output joined.wav filename.wav|PATTERN..."],
+        |args| join_command(args).map(|()| true),
+    ),
+    (
+        "find",
+        &["find --before TIME|--nearest TIME|--from TIME --to TIME filename.wav"],
+        |args| find_command(args).map(|()| true),
+    ),
+    ("chunks", &["chunks filename.wav"], |args| {
+        chunks_command(args).map(|()| true)
+    }),
+    (
+        "extract-chunk",
+        &["extract-chunk --id ID [--index N] [-o OUTPUT] filename.wav"],
+        |args| extract_chunk_command(args).map(|()| true),
+    ),
+    (
+        "inject-chunk",
+        &["inject-chunk --id ID --from FILE [--index N|--add] filename.wav"],
+        |args| inject_chunk_command(args).map(|()| true),
+    ),
+    ("lint", &["lint [--fix] filename.wav..."], lint_command),
+    (
+        "merge",
+        &["merge --base original.wav --ours a.csv --theirs b.csv"],
+        merge_command,
+    ),
+    (
+        "convert-cues",
+        &["convert-cues --from RATE|--from-file original.wav [--to RATE] filename.wav"],
+        |args| convert_cues_command(args).map(|()| true),
+    ),
+    (
+        "generate",
+        &["generate [--duration 10s] [--cues N] [--bext] filename.wav"],
+        |args| generate_command(args).map(|()| true),
+    ),
+    (
+        "timecode",
+        &["timecode --fps RATE [--rate SAMPLING_RATE] SAMPLES|TIMECODE"],
+        |args| timecode_command(args).map(|()| true),
+    ),
+];
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        let mut prefix = "usage:";
+        for (_, usages, _) in COMMANDS {
+            for usage in usages.iter() {
+                eprintln!("{prefix} wav-cue {usage}");
+                prefix = "      ";
+            }
+        }
+        process::exit(2);
+    }
+    // without a command the arguments are for export
+    let result = match COMMANDS.iter().find(|(name, _, _)| *name == args[1]) {
+        Some((_, _, command)) => command(&args[2..]),
+        None => export_command(&args[1..]),
+    };
+    match result {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(2);
         }
-    } else {
-        eprintln!("usage: wav-cue filename.wav > filename.csv");
     }
 }
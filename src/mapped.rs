@@ -0,0 +1,215 @@
+// Parsing of a whole file held in memory, such as a memory mapped one.
+// Chunk payloads are slices of it, and labels, notes, coding history and
+// iXML are borrowed from it instead of copied through read buffers.
+use crate::bext::BEXT_FIXED_SIZE;
+use crate::riff::{ChunkInfo, ChunkReader};
+use crate::{Error, WaveError};
+use std::borrow::Cow;
+use std::io::Cursor;
+
+pub struct Chunk<'a> {
+    pub id: [u8; 4],
+    // of the payload in the file
+    pub offset: u64,
+    pub payload: &'a [u8],
+}
+
+pub struct MappedWave<'a> {
+    pub chunks: Vec<Chunk<'a>>,
+}
+
+// Text of a fixed size or NUL terminated field; borrowed unless it isn't
+// valid UTF-8
+fn text(data: &[u8]) -> Cow<'_, str> {
+    let end = data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end])
+}
+
+impl<'a> MappedWave<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<MappedWave<'a>, Error> {
+        let mut chunks = Vec::new();
+        let reader = ChunkReader::new(Cursor::new(data))?;
+        // borrowed payloads can't be swapped
+        if reader.is_big_endian() {
+            return Err(Error::from(WaveError {
+                message: String::from("Big-endian RIFX files can't be mapped"),
+            }));
+        }
+        for info in reader {
+            let ChunkInfo { id, offset, size } = info?;
+            let payload = offset
+                .checked_add(size)
+                .filter(|end| *end <= data.len() as u64)
+                .map(|end| &data[offset as usize..end as usize]);
+            match payload {
+                Some(payload) => chunks.push(Chunk {
+                    id,
+                    offset,
+                    payload,
+                }),
+                None => {
+                    return Err(Error::from(WaveError {
+                        message: format!(
+                            "{} chunk runs past the end of the file",
+                            String::from_utf8_lossy(&id).trim_end()
+                        ),
+                    }))
+                }
+            }
+        }
+        Ok(MappedWave { chunks })
+    }
+
+    // Payload of the first chunk with the id
+    pub fn chunk(&self, id: &[u8; 4]) -> Option<&'a [u8]> {
+        self.chunks
+            .iter()
+            .find(|chunk| &chunk.id == id)
+            .map(|chunk| chunk.payload)
+    }
+
+    // Sub-chunks of the LIST chunks of the type, as id and payload
+    fn list_items(
+        &self,
+        list_type: &'static [u8; 4],
+    ) -> impl Iterator<Item = ([u8; 4], &'a [u8])> + '_ {
+        self.chunks
+            .iter()
+            .filter(move |chunk| &chunk.id == b"LIST" && chunk.payload.starts_with(list_type))
+            .flat_map(|chunk| {
+                let mut payload = &chunk.payload[4..];
+                std::iter::from_fn(move || {
+                    if payload.len() < 8 {
+                        return None;
+                    }
+                    let id = *array_ref!(payload, 0, 4);
+                    let size = u32::from_le_bytes(*array_ref!(payload, 4, 4)) as usize;
+                    let item = payload.get(8..8 + size)?;
+                    payload = payload.get(8 + size + (size & 1)..).unwrap_or_default();
+                    Some((id, item))
+                })
+            })
+    }
+
+    // Texts of the labl or note sub-chunks of the adtl LIST, by cue id
+    fn adtl_texts(&self, kind: &[u8; 4]) -> Vec<(u32, Cow<'a, str>)> {
+        let kind = *kind;
+        self.list_items(b"adtl")
+            .filter(|(id, item)| *id == kind && item.len() >= 4)
+            .map(|(_, item)| {
+                (
+                    u32::from_le_bytes(*array_ref!(item, 0, 4)),
+                    text(&item[4..]),
+                )
+            })
+            .collect()
+    }
+
+    pub fn labels(&self) -> Vec<(u32, Cow<'a, str>)> {
+        self.adtl_texts(b"labl")
+    }
+
+    pub fn notes(&self) -> Vec<(u32, Cow<'a, str>)> {
+        self.adtl_texts(b"note")
+    }
+
+    pub fn coding_history(&self) -> Option<Cow<'a, str>> {
+        self.chunk(b"bext")
+            .and_then(|bext| bext.get(BEXT_FIXED_SIZE..))
+            .map(text)
+    }
+
+    pub fn ixml(&self) -> Option<Cow<'a, str>> {
+        self.chunk(b"iXML").map(text)
+    }
+}
+
+// A file mapped into memory read-only, for MappedWave::parse. Only on
+// 64-bit Unix, where off_t, the offset of mmap, is 64 bits.
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+pub struct Mmap {
+    address: *mut std::ffi::c_void,
+    length: usize,
+}
+
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+mod sys {
+    use std::ffi::c_void;
+    use std::os::raw::c_int;
+
+    pub const PROT_READ: c_int = 1;
+    pub const MAP_PRIVATE: c_int = 2;
+
+    extern "C" {
+        pub fn mmap(
+            address: *mut c_void,
+            length: usize,
+            protection: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: i64,
+        ) -> *mut c_void;
+        pub fn munmap(address: *mut c_void, length: usize) -> c_int;
+    }
+}
+
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+impl Mmap {
+    /// Maps the whole file at the path.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be truncated or changed, by this or any other
+    /// process, while the mapping lives: reading a truncated part raises
+    /// SIGBUS, and a changed one changes the bytes behind slices that are
+    /// taken to be immutable.
+    pub unsafe fn open(path: &std::path::Path) -> Result<Mmap, Error> {
+        use std::os::unix::io::AsRawFd;
+        let file = std::fs::File::open(path)?;
+        let length = file.metadata()?.len() as usize;
+        if length == 0 {
+            // mapping nothing fails, and there is nothing to parse anyway
+            return Err(Error::from(WaveError {
+                message: format!("{} is empty", path.display()),
+            }));
+        }
+        // a new private read-only mapping of the whole file, which stays
+        // valid after the file is closed
+        let address = sys::mmap(
+            std::ptr::null_mut(),
+            length,
+            sys::PROT_READ,
+            sys::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        );
+        // MAP_FAILED
+        if address as isize == -1 {
+            return Err(Error::from(std::io::Error::last_os_error()));
+        }
+        Ok(Mmap { address, length })
+    }
+}
+
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+impl std::ops::Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the mapping is readable for length bytes until dropped
+        unsafe { std::slice::from_raw_parts(self.address as *const u8, self.length) }
+    }
+}
+
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        // SAFETY: unmaps what open mapped, once
+        unsafe {
+            sys::munmap(self.address, self.length);
+        }
+    }
+}
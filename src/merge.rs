@@ -0,0 +1,130 @@
+// Three-way merge of marker lists: changes made to copies of the same
+// recording by different people are combined against the original
+type Marker = (f64, String);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    Unchanged,
+    Modified(Marker),
+    Deleted,
+}
+
+pub struct Merged {
+    pub markers: Vec<Marker>,
+    pub conflicts: Vec<String>,
+}
+
+fn same(a: &Marker, b: &Marker, tolerance: f64) -> bool {
+    (a.0 - b.0).abs() <= tolerance && a.1 == b.1
+}
+
+// What happened to each base marker on one side, and the markers added
+// there. A marker is matched to a base marker at the same position first
+// (it may have been renamed), then to one with the same label (it may
+// have been moved).
+fn changes(base: &[Marker], side: &[Marker], tolerance: f64) -> (Vec<Change>, Vec<Marker>) {
+    let mut matched: Vec<Option<&Marker>> = vec![None; base.len()];
+    let mut unmatched: Vec<&Marker> = side.iter().collect();
+    for by_position in [true, false] {
+        let pass = |base: &Marker, marker: &Marker| {
+            if by_position {
+                (base.0 - marker.0).abs() <= tolerance
+            } else {
+                base.1 == marker.1
+            }
+        };
+        unmatched.retain(|marker| {
+            let free = base
+                .iter()
+                .zip(matched.iter_mut())
+                .find(|(base, slot)| slot.is_none() && pass(base, marker));
+            match free {
+                Some((_, slot)) => {
+                    *slot = Some(*marker);
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+    let changes = base
+        .iter()
+        .zip(matched)
+        .map(|(base, marker)| match marker {
+            None => Change::Deleted,
+            Some(marker) if same(base, marker, tolerance) => Change::Unchanged,
+            Some(marker) => Change::Modified(marker.clone()),
+        })
+        .collect();
+    (changes, unmatched.into_iter().cloned().collect())
+}
+
+fn describe(marker: &Marker) -> String {
+    format!("{} at {:.3}s", marker.1, marker.0)
+}
+
+// On a conflict our version is kept, or the modified one when the other
+// side deleted the marker, and the conflict is reported
+pub fn merge(base: &[Marker], ours: &[Marker], theirs: &[Marker], tolerance: f64) -> Merged {
+    let (our_changes, our_additions) = changes(base, ours, tolerance);
+    let (their_changes, their_additions) = changes(base, theirs, tolerance);
+    let mut markers = Vec::new();
+    let mut conflicts = Vec::new();
+    for ((base, ours), theirs) in base.iter().zip(our_changes).zip(their_changes) {
+        match (ours, theirs) {
+            (Change::Unchanged, Change::Unchanged) => markers.push(base.clone()),
+            (Change::Unchanged, Change::Modified(marker))
+            | (Change::Modified(marker), Change::Unchanged) => markers.push(marker),
+            (Change::Modified(ours), Change::Modified(theirs)) => {
+                if !same(&ours, &theirs, tolerance) {
+                    conflicts.push(format!(
+                        "{}: changed to {} in ours and to {} in theirs",
+                        describe(base),
+                        describe(&ours),
+                        describe(&theirs)
+                    ));
+                }
+                markers.push(ours);
+            }
+            (Change::Modified(marker), Change::Deleted) => {
+                conflicts.push(format!(
+                    "{}: changed to {} in ours, deleted in theirs",
+                    describe(base),
+                    describe(&marker)
+                ));
+                markers.push(marker);
+            }
+            (Change::Deleted, Change::Modified(marker)) => {
+                conflicts.push(format!(
+                    "{}: deleted in ours, changed to {} in theirs",
+                    describe(base),
+                    describe(&marker)
+                ));
+                markers.push(marker);
+            }
+            (Change::Deleted, _) | (_, Change::Deleted) => (),
+        }
+    }
+    markers.extend(our_additions.iter().cloned());
+    for marker in their_additions {
+        if our_additions
+            .iter()
+            .any(|ours| same(ours, &marker, tolerance))
+        {
+            continue;
+        }
+        if let Some(ours) = our_additions
+            .iter()
+            .find(|ours| (ours.0 - marker.0).abs() <= tolerance)
+        {
+            conflicts.push(format!(
+                "added as {} in ours and as {} in theirs",
+                describe(ours),
+                describe(&marker)
+            ));
+        }
+        markers.push(marker);
+    }
+    markers.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Merged { markers, conflicts }
+}
@@ -0,0 +1,149 @@
+// The metadata of a file as a whole: read it, change the fields and write
+// it back, without dealing with the chunks it's stored in
+use crate::adtl::{self, Region};
+use crate::riff::{self, Chunk, ListItem};
+use crate::{cue_chunk, read_wave_file, CueEntry, Error, Header};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WaveMetadata {
+    // format of the audio; not written, as it has to match the audio
+    pub header: Header,
+    pub cues: Vec<CueEntry>,
+    pub labels: Vec<(u32, String)>,
+    pub notes: Vec<(u32, String)>,
+    pub regions: Vec<Region>,
+    // raw payload, edited with the functions of the bext module
+    pub bext: Option<Vec<u8>>,
+    // sub-chunks of the INFO list, such as INAM and ICMT
+    pub info: Vec<ListItem>,
+    pub ixml: Option<String>,
+}
+
+// Chunk id, LIST type and the new payload, if any
+type Replacement<'a> = ([u8; 4], Option<&'a [u8; 4]>, Option<Vec<u8>>);
+
+impl WaveMetadata {
+    pub fn read_from(path: &Path) -> Result<WaveMetadata, Error> {
+        let filename = path.to_string_lossy();
+        let wave = read_wave_file(&filename)?;
+        let mut metadata = WaveMetadata {
+            header: wave.header,
+            cues: wave.cues,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            regions: Vec::new(),
+            bext: None,
+            info: Vec::new(),
+            ixml: None,
+        };
+        let mut reader = BufReader::new(File::open(path)?);
+        for info in riff::walk_chunks(&mut reader)? {
+            match &info.id {
+                b"bext" => metadata.bext = Some(riff::read_payload(&mut reader, &info)?),
+                b"iXML" => {
+                    let payload = riff::read_payload(&mut reader, &info)?;
+                    metadata.ixml = Some(String::from_utf8_lossy(&payload).to_string());
+                }
+                b"LIST" => {
+                    let payload = riff::read_payload(&mut reader, &info)?;
+                    if payload.starts_with(b"adtl") {
+                        let adtl = adtl::parse(&payload)?;
+                        metadata.labels.extend(adtl.labels);
+                        metadata.notes.extend(adtl.notes);
+                        metadata.regions.extend(adtl.regions);
+                    } else if payload.starts_with(b"INFO") {
+                        metadata.info.extend(riff::parse_list(&payload)?.1);
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(metadata)
+    }
+
+    // Items of the adtl LIST for the labels, notes and regions
+    pub fn associated_data(&self) -> Vec<ListItem> {
+        let mut items: Vec<ListItem> = Vec::new();
+        for (cue_id, label) in &self.labels {
+            items.push(adtl::text_item(b"labl", *cue_id, label));
+        }
+        for (cue_id, note) in &self.notes {
+            items.push(adtl::text_item(b"note", *cue_id, note));
+        }
+        for region in &self.regions {
+            adtl::set_region(&mut items, region);
+        }
+        items
+    }
+
+    // Replaces the metadata of an existing file with this, keeping its
+    // audio and the chunks not covered here. Chunks are updated where they
+    // are; new ones are added after the audio, except bext which goes
+    // first. Empty metadata removes the chunk.
+    pub fn write_to(&self, path: &Path) -> Result<(), Error> {
+        let adtl = self.associated_data();
+        let mut replacements: Vec<Replacement> = vec![
+            (*b"bext", None, self.bext.clone()),
+            (
+                *b"cue ",
+                None,
+                (!self.cues.is_empty()).then(|| cue_chunk(&self.cues)),
+            ),
+            (
+                *b"LIST",
+                Some(b"adtl"),
+                (!adtl.is_empty()).then(|| riff::build_list(b"adtl", &adtl)),
+            ),
+            (
+                *b"LIST",
+                Some(b"INFO"),
+                (!self.info.is_empty()).then(|| riff::build_list(b"INFO", &self.info)),
+            ),
+            (
+                *b"iXML",
+                None,
+                self.ixml.as_ref().map(|ixml| ixml.as_bytes().to_vec()),
+            ),
+        ];
+        riff::rewrite_file(&path.to_string_lossy(), |reader, chunks| {
+            let mut kept = Vec::new();
+            for chunk in chunks.drain(..) {
+                let list_type = if &chunk.id == b"LIST" {
+                    chunk
+                        .payload(reader)?
+                        .get(0..4)
+                        .map(|list_type| *array_ref!(list_type, 0, 4))
+                } else {
+                    None
+                };
+                let replacement = replacements.iter_mut().find(|(id, wanted, _)| {
+                    *id == chunk.id && wanted.is_none_or(|wanted| Some(*wanted) == list_type)
+                });
+                match replacement {
+                    // the first chunk of the kind gets the new payload,
+                    // duplicates are dropped
+                    Some((id, _, payload)) => {
+                        if let Some(payload) = payload.take() {
+                            kept.push(Chunk::new(id, payload));
+                        }
+                    }
+                    None => kept.push(chunk),
+                }
+            }
+            for (id, _, payload) in replacements {
+                if let Some(payload) = payload {
+                    if &id == b"bext" {
+                        kept.insert(0, Chunk::new(&id, payload));
+                    } else {
+                        kept.push(Chunk::new(&id, payload));
+                    }
+                }
+            }
+            *chunks = kept;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,138 @@
+// Decoding of PCM and IEEE float sample data
+use crate::{CompressionCode, Error, Header, WaveError};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+#[derive(Debug, Clone, Copy)]
+enum SampleFormat {
+    Integer,
+    Float,
+}
+
+fn sample_format(header: &Header) -> Result<(SampleFormat, usize), Error> {
+    let channels = header.number_of_channels.max(1) as usize;
+    let bytes_per_sample = header.block_align as usize / channels;
+    let format = match (header.compression_code, bytes_per_sample) {
+        // PCM, or WAVE_FORMAT_EXTENSIBLE assumed to be PCM
+        (CompressionCode::Pcm | CompressionCode::Extensible, 1..=4) => SampleFormat::Integer,
+        (CompressionCode::IeeeFloat, 4 | 8) => SampleFormat::Float,
+        _ => {
+            return Err(Error::from(WaveError {
+                message: format!(
+                    "Cannot decode {} audio with {} bytes per sample",
+                    header.compression_code, bytes_per_sample
+                ),
+            }))
+        }
+    };
+    Ok((format, bytes_per_sample))
+}
+
+// Sample scaled to -1.0..1.0
+fn decode(format: SampleFormat, bytes: &[u8]) -> f64 {
+    match (format, bytes.len()) {
+        // 8 bit samples are unsigned
+        (SampleFormat::Integer, 1) => (bytes[0] as f64 - 128.0) / 128.0,
+        (SampleFormat::Integer, 2) => i16::from_le_bytes([bytes[0], bytes[1]]) as f64 / 32768.0,
+        (SampleFormat::Integer, 3) => {
+            (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f64 / 8388608.0
+        }
+        (SampleFormat::Integer, _) => {
+            i32::from_le_bytes(*array_ref!(bytes, 0, 4)) as f64 / 2147483648.0
+        }
+        (SampleFormat::Float, 4) => f32::from_le_bytes(*array_ref!(bytes, 0, 4)) as f64,
+        (SampleFormat::Float, _) => f64::from_le_bytes(*array_ref!(bytes, 0, 8)),
+    }
+}
+
+// Sample from -1.0..1.0, clipped for the integer formats
+fn encode(format: SampleFormat, value: f64, bytes: &mut [u8]) {
+    let integer = |scale: f64| (value * scale).round().clamp(-scale, scale - 1.0) as i32;
+    match (format, bytes.len()) {
+        (SampleFormat::Integer, 1) => bytes[0] = (integer(128.0) + 128) as u8,
+        (SampleFormat::Integer, 2) => {
+            bytes.copy_from_slice(&(integer(32768.0) as i16).to_le_bytes())
+        }
+        (SampleFormat::Integer, 3) => bytes.copy_from_slice(&integer(8388608.0).to_le_bytes()[..3]),
+        (SampleFormat::Integer, _) => bytes.copy_from_slice(&integer(2147483648.0).to_le_bytes()),
+        (SampleFormat::Float, 4) => bytes.copy_from_slice(&(value as f32).to_le_bytes()),
+        (SampleFormat::Float, _) => bytes.copy_from_slice(&value.to_le_bytes()),
+    }
+}
+
+// Multiplies the samples of each frame of the data by gain(frame index)
+pub fn apply_gain<F>(header: &Header, data: &mut [u8], gain: F) -> Result<(), Error>
+where
+    F: Fn(u64) -> f64,
+{
+    let (format, bytes_per_sample) = sample_format(header)?;
+    let block_align = header.block_align as usize;
+    for (index, frame) in data.chunks_exact_mut(block_align).enumerate() {
+        let gain = gain(index as u64);
+        if gain == 1.0 {
+            continue;
+        }
+        for bytes in frame.chunks_exact_mut(bytes_per_sample) {
+            encode(format, decode(format, bytes) * gain, bytes);
+        }
+    }
+    Ok(())
+}
+
+// Calls visit with each frame of the data chunk, one sample per channel
+pub fn for_each_frame<R, F>(
+    reader: &mut R,
+    header: &Header,
+    data_offset: u64,
+    data_size: u64,
+    mut visit: F,
+) -> Result<(), Error>
+where
+    R: Read + Seek,
+    F: FnMut(&[f64]),
+{
+    let (format, bytes_per_sample) = sample_format(header)?;
+    let block_align = header.block_align as usize;
+    let mut frame = vec![0f64; header.number_of_channels as usize];
+    reader.seek(SeekFrom::Start(data_offset))?;
+    let mut reader = BufReader::with_capacity(1 << 16, reader.take(data_size));
+    let mut block = vec![0u8; block_align * 4096];
+    loop {
+        let mut filled = 0;
+        while filled < block.len() {
+            match reader.read(&mut block[filled..])? {
+                0 => break,
+                read => filled += read,
+            }
+        }
+        for bytes in block[..filled - filled % block_align].chunks_exact(block_align) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let offset = channel * bytes_per_sample;
+                *sample = decode(format, &bytes[offset..offset + bytes_per_sample]);
+            }
+            visit(&frame);
+        }
+        if filled < block.len() {
+            return Ok(());
+        }
+    }
+}
+
+// Peak absolute sample value of each channel
+pub fn channel_peaks<R: Read + Seek>(
+    reader: &mut R,
+    header: &Header,
+    data_offset: u64,
+    data_size: u64,
+) -> Result<Vec<f64>, Error> {
+    let mut peaks = vec![0f64; header.number_of_channels as usize];
+    for_each_frame(reader, header, data_offset, data_size, |frame| {
+        for (peak, sample) in peaks.iter_mut().zip(frame) {
+            *peak = peak.max(sample.abs());
+        }
+    })?;
+    Ok(peaks)
+}
+
+pub fn to_dbfs(level: f64) -> f64 {
+    20.0 * level.log10()
+}
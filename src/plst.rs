@@ -0,0 +1,43 @@
+// Playlist chunk: the order in which segments starting at cue points are
+// played, with their length. Some editors keep the length of a segment
+// here rather than in an ltxt region.
+// https://www.recordingblogs.com/wiki/playlist-chunk-of-a-wave-file
+//   0    number of segments 4
+//   4    per segment: cue id 4, length in samples 4, number of repeats 4
+use crate::{Error, WaveError};
+
+const SEGMENT_SIZE: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Segment {
+    pub cue_id: u32,
+    pub sample_length: u32,
+    pub repeats: u32,
+}
+
+pub fn parse(data: &[u8]) -> Result<Vec<Segment>, Error> {
+    if data.len() < 4 {
+        return Err(Error::from(WaveError {
+            message: format!("plst chunk is too small: {}", data.len()),
+        }));
+    }
+    let count = u32::from_le_bytes(*array_ref!(data, 0, 4)) as usize;
+    let segments = data[4..].chunks_exact(SEGMENT_SIZE);
+    if segments.len() < count {
+        return Err(Error::from(WaveError {
+            message: format!(
+                "plst chunk has room for {} of its {count} segments",
+                segments.len()
+            ),
+        }));
+    }
+    Ok(segments
+        .take(count)
+        .map(|segment| Segment {
+            cue_id: u32::from_le_bytes(*array_ref!(segment, 0, 4)),
+            sample_length: u32::from_le_bytes(*array_ref!(segment, 4, 4)),
+            repeats: u32::from_le_bytes(*array_ref!(segment, 8, 4)),
+        })
+        .collect())
+}
@@ -0,0 +1,184 @@
+// Groups recordings that continue each other into projects, such as the
+// files a Zoom recorder starts when a take exceeds the maximum file size
+use crate::{read_wave_file, Error, WaveFileInfo};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct Take {
+    pub path: PathBuf,
+    pub wave: WaveFileInfo,
+    pub frames: u64,
+    // position of the take on the project timeline, in samples
+    pub offset: u64,
+}
+
+pub struct Project {
+    pub name: String,
+    pub takes: Vec<Take>,
+}
+
+#[derive(Debug)]
+pub struct Marker {
+    // on the project timeline
+    pub sample_start: u64,
+    pub take: usize,
+    pub cue_id: u32,
+    pub label: String,
+}
+
+impl Project {
+    pub fn sampling_rate(&self) -> u32 {
+        self.takes[0].wave.header.sampling_rate
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.takes
+            .last()
+            .map_or(0, |take| take.offset + take.frames)
+    }
+
+    pub fn markers(&self) -> Vec<Marker> {
+        let mut markers: Vec<Marker> = self
+            .takes
+            .iter()
+            .enumerate()
+            .flat_map(|(index, take)| {
+                take.wave.cues.iter().map(move |cue| Marker {
+                    sample_start: take.offset + cue.sample_start as u64,
+                    take: index,
+                    cue_id: cue.cue_id,
+                    label: cue.title(),
+                })
+            })
+            .collect();
+        markers.sort_by_key(|marker| marker.sample_start);
+        markers
+    }
+}
+
+// Name with the take and continuation numbers at the end removed, so that
+// e.g. ZOOM0001 and ZOOM0002, or ZOOM0001_Tr1 and ZOOM0001_Tr1-0001,
+// share it
+fn name_prefix(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    stem.trim_end_matches(|ch: char| ch.is_ascii_digit() || ch == '-' || ch == '_')
+        .to_string()
+}
+
+// Zoom H-series recorders write a .hprj project file next to the takes,
+// named like the first of them
+fn hprj_name(directory: &Path) -> Option<String> {
+    let mut names: Vec<String> = fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("hprj"))
+        })
+        .filter_map(|path| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        })
+        .collect();
+    names.sort();
+    (names.len() == 1).then(|| names.remove(0))
+}
+
+fn common_prefix(a: &Path, b: &Path) -> usize {
+    let (a, b) = (a.to_string_lossy(), b.to_string_lossy());
+    a.chars().zip(b.chars()).take_while(|(a, b)| a == b).count()
+}
+
+// Chains the takes of a group whose TimeReference continues where the
+// previous take ended, within the tolerance. When several chains could be
+// continued, the one whose last file name is closest is chosen, so that
+// the tracks of a multitrack recording stay apart.
+fn chain(mut takes: Vec<(PathBuf, WaveFileInfo, u64)>, tolerance: u64) -> Vec<Vec<Take>> {
+    let time_reference = |wave: &WaveFileInfo| wave.bext.as_ref().map(|bext| bext.time_reference);
+    takes.sort_by(|a, b| (time_reference(&a.1), &a.0).cmp(&(time_reference(&b.1), &b.0)));
+    let mut chains: Vec<Vec<Take>> = Vec::new();
+    for (path, wave, frames) in takes {
+        let continued = time_reference(&wave).and_then(|start| {
+            chains
+                .iter()
+                .enumerate()
+                .filter(|(_, chain)| {
+                    let last = chain.last().unwrap();
+                    time_reference(&last.wave).is_some_and(|previous| {
+                        (previous + last.frames).abs_diff(start) <= tolerance
+                    })
+                })
+                .max_by_key(|(_, chain)| common_prefix(&chain.last().unwrap().path, &path))
+                .map(|(index, _)| index)
+        });
+        match continued {
+            Some(index) => {
+                let chain = &mut chains[index];
+                let first = time_reference(&chain[0].wave).unwrap_or(0);
+                let offset = time_reference(&wave).unwrap_or(0).saturating_sub(first);
+                chain.push(Take {
+                    path,
+                    wave,
+                    frames,
+                    offset,
+                });
+            }
+            None => chains.push(vec![Take {
+                path,
+                wave,
+                frames,
+                offset: 0,
+            }]),
+        }
+    }
+    chains
+}
+
+// Projects of the files, in the order of their first files; files in the
+// same directory with the same name prefix and audio format are candidates
+// for continuing each other
+pub fn find_projects(files: &[PathBuf], tolerance: u64) -> (Vec<Project>, Vec<(PathBuf, Error)>) {
+    let mut groups: BTreeMap<_, Vec<(PathBuf, WaveFileInfo, u64)>> = BTreeMap::new();
+    let mut errors = Vec::new();
+    for path in files {
+        match read_wave_file(&path.to_string_lossy()) {
+            Ok(wave) => {
+                let frames = wave.frames().unwrap_or(0);
+                let key = (
+                    path.parent().map(Path::to_path_buf).unwrap_or_default(),
+                    name_prefix(path),
+                    wave.header.sampling_rate,
+                    wave.header.number_of_channels,
+                );
+                groups
+                    .entry(key)
+                    .or_default()
+                    .push((path.clone(), wave, frames));
+            }
+            Err(error) => errors.push((path.clone(), error)),
+        }
+    }
+    let mut projects = Vec::new();
+    for ((directory, _, _, _), takes) in groups {
+        let hprj = hprj_name(&directory);
+        for takes in chain(takes, tolerance) {
+            let stem = takes[0]
+                .path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            // the project file is named after the first take
+            let name = match &hprj {
+                Some(hprj) if stem.starts_with(hprj.as_str()) => hprj.clone(),
+                _ => stem,
+            };
+            projects.push(Project { name, takes });
+        }
+    }
+    projects.sort_by(|a, b| a.takes[0].path.cmp(&b.takes[0].path));
+    (projects, errors)
+}
@@ -0,0 +1,65 @@
+// Blanks or replaces the metadata that identifies people, leaving the
+// technical metadata and the audio untouched
+use crate::bext;
+use crate::riff::{self, Chunk};
+use crate::xml;
+use crate::Error;
+
+// iXML elements with free text entered by the user
+const IXML_ELEMENTS: [&str; 2] = ["USER", "NOTE"];
+
+// INFO fields naming people: artist, engineer and technician
+const INFO_FIELDS: [&[u8; 4]; 3] = [b"IART", b"IENG", b"ITCH"];
+
+// Returns descriptions of the redacted fields
+pub fn redact(filename: &str, replacement: &str) -> Result<Vec<String>, Error> {
+    let mut redacted = Vec::new();
+    riff::rewrite_file(filename, |reader, chunks| {
+        for chunk in chunks.iter_mut() {
+            match &chunk.id {
+                b"bext" => {
+                    let mut raw = chunk.payload(reader)?;
+                    for (name, field) in [
+                        ("Originator", bext::BEXT_ORIGINATOR),
+                        ("OriginatorReference", bext::BEXT_ORIGINATOR_REFERENCE),
+                    ] {
+                        if !bext::is_blank(&raw, field) {
+                            bext::set_string(&mut raw, field, replacement);
+                            redacted.push(format!("bext {name}"));
+                        }
+                    }
+                    *chunk = Chunk::new(b"bext", raw);
+                }
+                b"iXML" => {
+                    let mut text = String::from_utf8_lossy(&chunk.payload(reader)?).to_string();
+                    for name in IXML_ELEMENTS {
+                        let (replaced, count) = xml::replace_element_text(&text, name, replacement);
+                        if count > 0 {
+                            redacted.push(format!("iXML {name} ({count})"));
+                        }
+                        text = replaced;
+                    }
+                    *chunk = Chunk::new(b"iXML", text.into_bytes());
+                }
+                b"LIST" => {
+                    let payload = chunk.payload(reader)?;
+                    let (list_type, mut items) = riff::parse_list(&payload)?;
+                    if &list_type != b"INFO" {
+                        continue;
+                    }
+                    for (id, value) in items.iter_mut() {
+                        if INFO_FIELDS.contains(&&*id) {
+                            *value = replacement.as_bytes().to_vec();
+                            value.push(0);
+                            redacted.push(format!("INFO {}", String::from_utf8_lossy(id)));
+                        }
+                    }
+                    *chunk = Chunk::new(b"LIST", riff::build_list(&list_type, &items));
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    })?;
+    Ok(redacted)
+}
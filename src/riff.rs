@@ -0,0 +1,529 @@
+use crate::rifx;
+use crate::{Error, WavCueError, WaveError};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+// A chunk found while walking a RIFF file; offset points to the start of the payload
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkInfo {
+    pub id: [u8; 4],
+    pub offset: u64,
+    pub size: u64,
+}
+
+#[derive(Debug)]
+pub enum ChunkData {
+    // Payload is copied verbatim from the source file when writing
+    Source(ChunkInfo),
+    Owned(Vec<u8>),
+}
+
+#[derive(Debug)]
+pub struct Chunk {
+    pub id: [u8; 4],
+    pub data: ChunkData,
+}
+
+impl Chunk {
+    pub fn new(id: &[u8; 4], data: Vec<u8>) -> Chunk {
+        Chunk {
+            id: *id,
+            data: ChunkData::Owned(data),
+        }
+    }
+
+    pub fn payload<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<u8>, Error> {
+        match &self.data {
+            ChunkData::Source(info) => read_payload(reader, info),
+            ChunkData::Owned(data) => Ok(data.clone()),
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        match &self.data {
+            ChunkData::Source(info) => info.size,
+            ChunkData::Owned(data) => data.len() as u64,
+        }
+    }
+}
+
+// Recordings over 4 GiB are RF64 (EBU Tech 3306) or BW64 (ITU-R BS.2088)
+// files instead of RIFF; their sizes that don't fit in 32 bits are
+// 0xFFFFFFFF, with the real ones in the ds64 chunk that comes first
+pub fn is_riff_signature(signature: &[u8; 4]) -> bool {
+    signature == b"RIFF" || signature == b"RF64" || signature == b"BW64"
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ds64 {
+    pub riff_size: u64,
+    pub data_size: u64,
+    pub sample_count: u64,
+    // sizes of other chunks over 4 GiB
+    pub table: Vec<([u8; 4], u64)>,
+}
+
+impl Ds64 {
+    pub fn parse(data: &[u8]) -> Option<Ds64> {
+        if data.len() < 28 {
+            return None;
+        }
+        let table_length = u32::from_le_bytes(*array_ref!(data, 24, 4)) as usize;
+        let table = data[28..]
+            .chunks_exact(12)
+            .take(table_length)
+            .map(|entry| {
+                (
+                    *array_ref!(entry, 0, 4),
+                    u64::from_le_bytes(*array_ref!(entry, 4, 8)),
+                )
+            })
+            .collect();
+        Some(Ds64 {
+            riff_size: u64::from_le_bytes(*array_ref!(data, 0, 8)),
+            data_size: u64::from_le_bytes(*array_ref!(data, 8, 8)),
+            sample_count: u64::from_le_bytes(*array_ref!(data, 16, 8)),
+            table,
+        })
+    }
+
+    // The size of a chunk, from the ds64 chunk if its own doesn't fit
+    pub fn chunk_size(&self, id: &[u8; 4], size: u32) -> u64 {
+        if size != u32::MAX {
+            return size as u64;
+        }
+        if id == b"data" {
+            return self.data_size;
+        }
+        self.table
+            .iter()
+            .find(|(table_id, _)| table_id == id)
+            .map_or(size as u64, |(_, size)| *size)
+    }
+}
+
+// Walks the chunks one at a time, reading only their headers, for callers
+// that need some of the chunks; payloads are read when asked for. RF64
+// and BW64 sizes come from the ds64 chunk, and the payloads of a RIFX
+// file are read as RIFF ones. Iteration ends at the end of the file, or
+// after an error.
+pub struct ChunkReader<R> {
+    reader: R,
+    // where the next chunk header is
+    offset: u64,
+    // the size of the file, or u64::MAX for a stream
+    length: u64,
+    ds64: Option<Ds64>,
+    big_endian: bool,
+    done: bool,
+}
+
+impl<R: Read + Seek> ChunkReader<R> {
+    pub fn new(mut reader: R) -> Result<ChunkReader<R>, Error> {
+        let mut buf_riff: [u8; 12] = [0; 12];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut buf_riff)?;
+        let big_endian = rifx::is_rifx_signature(array_ref!(buf_riff, 0, 4));
+        if !is_riff_signature(array_ref!(buf_riff, 0, 4)) && !big_endian {
+            return Err(Error::from(WaveError {
+                message: String::from("Not a wav file (no RIFF found)"),
+            }));
+        }
+        if &buf_riff[8..12] != b"WAVE" {
+            return Err(Error::from(WaveError {
+                message: String::from("Not a wav file (no WAVE found)"),
+            }));
+        }
+        let length = match reader.seek(SeekFrom::End(0)) {
+            Ok(length) => length,
+            Err(error) if error.kind() == io::ErrorKind::Unsupported => u64::MAX,
+            Err(error) => return Err(error.into()),
+        };
+        Ok(ChunkReader {
+            reader,
+            offset: 12,
+            length,
+            ds64: None,
+            big_endian,
+            done: false,
+        })
+    }
+
+    // Whether it's a RIFX file
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    // The payload, with the numbers of a RIFX chunk little-endian
+    pub fn read_payload(&mut self, info: &ChunkInfo) -> Result<Vec<u8>, Error> {
+        let mut data = read_payload(&mut self.reader, info)?;
+        if self.big_endian {
+            rifx::to_little_endian(&info.id, &mut data);
+        }
+        Ok(data)
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn next_chunk(&mut self) -> Result<Option<ChunkInfo>, Error> {
+        let mut buf_header: [u8; 8] = [0; 8];
+        if self.offset >= self.length {
+            return Ok(None);
+        }
+        self.reader.seek(SeekFrom::Start(self.offset))?;
+        if self.reader.read_exact(&mut buf_header).is_err() {
+            return Ok(None);
+        }
+        let id = *array_ref!(buf_header, 0, 4);
+        let size = if self.big_endian {
+            u32::from_be_bytes(*array_ref!(buf_header, 4, 4))
+        } else {
+            u32::from_le_bytes(*array_ref!(buf_header, 4, 4))
+        };
+        let size = match &self.ds64 {
+            Some(ds64) => ds64.chunk_size(&id, size),
+            None => size as u64,
+        };
+        let info = ChunkInfo {
+            id,
+            offset: self.offset + 8,
+            size,
+        };
+        if &id == b"ds64" && self.offset == 12 {
+            self.ds64 = Ds64::parse(&read_payload(&mut self.reader, &info)?);
+            if self.ds64.is_none() {
+                return Err(Error::from(WaveError {
+                    message: format!("ds64 chunk is too small ({size} bytes)"),
+                }));
+            }
+        }
+        // chunks are word aligned; sizes from ds64 can be anything
+        self.offset = match info
+            .offset
+            .checked_add(size)
+            .and_then(|end| end.checked_add(size & 1))
+        {
+            Some(offset) => offset,
+            None => return Err(Error::from(WavCueError::TruncatedChunk { id })),
+        };
+        Ok(Some(info))
+    }
+}
+
+impl<R: Read + Seek> Iterator for ChunkReader<R> {
+    type Item = Result<ChunkInfo, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_chunk().transpose();
+        self.done = !matches!(next, Some(Ok(_)));
+        next
+    }
+}
+
+// The chunks of a RIFF file; RIFX files are only read through a
+// ChunkReader, as the payloads given by offset would be misread and
+// rewriting one would mix up byte orders
+pub fn walk_chunks<R: Read + Seek>(reader: &mut R) -> Result<Vec<ChunkInfo>, Error> {
+    let chunks = ChunkReader::new(reader)?;
+    if chunks.is_big_endian() {
+        return Err(Error::from(WaveError {
+            message: String::from("Big-endian RIFX files can only be read"),
+        }));
+    }
+    chunks.collect()
+}
+
+// The buffer grows with what is read, as a size from ds64 can be more than
+// any file has
+pub fn read_payload<R: Read + Seek>(reader: &mut R, info: &ChunkInfo) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    reader.seek(SeekFrom::Start(info.offset))?;
+    reader.take(info.size).read_to_end(&mut data)?;
+    if (data.len() as u64) < info.size {
+        return Err(Error::from(WavCueError::TruncatedChunk { id: info.id }));
+    }
+    Ok(data)
+}
+
+// Sub-chunk of a LIST chunk
+pub type ListItem = ([u8; 4], Vec<u8>);
+
+// Splits the payload of a LIST chunk into its type and sub-chunks
+pub fn parse_list(data: &[u8]) -> Result<([u8; 4], Vec<ListItem>), Error> {
+    if data.len() < 4 {
+        return Err(Error::from(WaveError {
+            message: String::from("LIST chunk is too small"),
+        }));
+    }
+    let list_type = *array_ref!(data, 0, 4);
+    let mut items = Vec::new();
+    let mut ofs = 4;
+    while ofs + 8 <= data.len() {
+        let id = *array_ref!(data, ofs, 4);
+        let size = u32::from_le_bytes(*array_ref!(data, ofs + 4, 4)) as usize;
+        ofs += 8;
+        if ofs + size > data.len() {
+            return Err(Error::from(WaveError {
+                message: format!(
+                    "LIST sub-chunk {} has unexpected size: {size}",
+                    String::from_utf8_lossy(&id)
+                ),
+            }));
+        }
+        items.push((id, data[ofs..ofs + size].to_vec()));
+        ofs += size + (size & 1);
+    }
+    Ok((list_type, items))
+}
+
+pub fn build_list(list_type: &[u8; 4], items: &[ListItem]) -> Vec<u8> {
+    let mut data = list_type.to_vec();
+    for (id, payload) in items {
+        data.extend_from_slice(id);
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            data.push(0);
+        }
+    }
+    data
+}
+
+pub fn write_riff<R: Read + Seek, W: Write>(
+    source: &mut R,
+    writer: &mut W,
+    chunks: &[Chunk],
+) -> Result<(), Error> {
+    // the file is always written as RIFF, so the sizes of an RF64 source
+    // are left out with its ds64 chunk
+    let chunks: Vec<&Chunk> = chunks.iter().filter(|chunk| &chunk.id != b"ds64").collect();
+    let riff_size = chunks
+        .iter()
+        .map(|chunk| 8 + chunk.size() + (chunk.size() & 1))
+        .sum::<u64>()
+        + 4;
+    if riff_size > u32::MAX as u64 {
+        return Err(Error::from(WaveError {
+            message: format!("Resulting file is too large for RIFF ({riff_size} bytes)"),
+        }));
+    }
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(riff_size as u32).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    for chunk in chunks {
+        writer.write_all(&chunk.id)?;
+        writer.write_all(&(chunk.size() as u32).to_le_bytes())?;
+        match &chunk.data {
+            ChunkData::Source(info) => {
+                source.seek(SeekFrom::Start(info.offset))?;
+                let copied = io::copy(&mut source.take(info.size), writer)?;
+                if copied != info.size {
+                    return Err(Error::from(WaveError {
+                        message: format!(
+                            "Chunk {} is truncated in the source file",
+                            String::from_utf8_lossy(&chunk.id)
+                        ),
+                    }));
+                }
+            }
+            ChunkData::Owned(data) => writer.write_all(data)?,
+        }
+        if chunk.size() & 1 == 1 {
+            writer.write_all(&[0])?;
+        }
+    }
+    Ok(())
+}
+
+// Rewrites the file via a temporary file next to it; chunks not touched by
+// edit are copied over as-is
+pub fn rewrite_file<F>(filename: &str, edit: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut BufReader<File>, &mut Vec<Chunk>) -> Result<(), Error>,
+{
+    let mut reader = BufReader::new(File::open(filename)?);
+    let mut chunks: Vec<Chunk> = walk_chunks(&mut reader)?
+        .into_iter()
+        .map(|info| Chunk {
+            id: info.id,
+            data: ChunkData::Source(info),
+        })
+        .collect();
+    edit(&mut reader, &mut chunks)?;
+    write_file(filename, |writer| write_riff(&mut reader, writer, &chunks))
+}
+
+// Writes the file via a temporary file next to it, so that a failed write
+// leaves what was there before
+pub fn write_file<F>(filename: &str, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+{
+    let tmp_filename = format!("{filename}.wav-cue.tmp");
+    let result = (|| -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(&tmp_filename)?);
+        write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    })();
+    match result {
+        Ok(()) => {
+            fs::rename(&tmp_filename, filename)?;
+            Ok(())
+        }
+        Err(error) => {
+            let _ = fs::remove_file(&tmp_filename);
+            Err(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn chunk(id: &[u8; 4], size: u32, payload: &[u8]) -> Vec<u8> {
+        let mut data = id.to_vec();
+        data.extend_from_slice(&size.to_le_bytes());
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn ds64(
+        riff_size: u64,
+        data_size: u64,
+        sample_count: u64,
+        table: &[([u8; 4], u64)],
+    ) -> Vec<u8> {
+        let mut data = Vec::new();
+        for number in [riff_size, data_size, sample_count] {
+            data.extend_from_slice(&number.to_le_bytes());
+        }
+        data.extend_from_slice(&(table.len() as u32).to_le_bytes());
+        for (id, size) in table {
+            data.extend_from_slice(id);
+            data.extend_from_slice(&size.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn parses_ds64() {
+        let ds64 = Ds64::parse(&ds64(
+            0x1_0000_0100,
+            0x1_0000_0000,
+            0x4000_0000,
+            &[(*b"axml", 0x1_0000_0002)],
+        ))
+        .unwrap();
+        assert_eq!(ds64.riff_size, 0x1_0000_0100);
+        assert_eq!(ds64.sample_count, 0x4000_0000);
+        assert_eq!(ds64.chunk_size(b"data", u32::MAX), 0x1_0000_0000);
+        assert_eq!(ds64.chunk_size(b"axml", u32::MAX), 0x1_0000_0002);
+        // sizes that fit are the chunks' own
+        assert_eq!(ds64.chunk_size(b"data", 16), 16);
+        assert_eq!(ds64.chunk_size(b"iXML", u32::MAX), u32::MAX as u64);
+        assert!(Ds64::parse(&[0; 27]).is_none());
+    }
+
+    #[test]
+    fn ignores_table_entries_past_the_end() {
+        let mut data = ds64(0, 0, 0, &[(*b"axml", 1)]);
+        data[24..28].copy_from_slice(&5u32.to_le_bytes());
+        assert_eq!(Ds64::parse(&data).unwrap().table, vec![(*b"axml", 1)]);
+    }
+
+    #[test]
+    fn reads_rf64_sizes() {
+        // mono 16 bit, two frames of audio, then a cue point
+        let fmt = [1, 0, 1, 0, 0x80, 0xbb, 0, 0, 0, 0x77, 1, 0, 2, 0, 16, 0];
+        let mut cue = 1u32.to_le_bytes().to_vec();
+        for number in [7u32, 1] {
+            cue.extend_from_slice(&number.to_le_bytes());
+        }
+        cue.extend_from_slice(b"data");
+        cue.extend_from_slice(&[0; 8]);
+        cue.extend_from_slice(&1u32.to_le_bytes());
+        let mut body = b"WAVE".to_vec();
+        let ds64_size = 28;
+        body.extend(chunk(b"fmt ", 16, &fmt));
+        body.extend(chunk(b"data", u32::MAX, &[0; 4]));
+        body.extend(chunk(b"cue ", 28, &cue));
+        let riff_size = (body.len() + 8 + ds64_size) as u64;
+        let mut file = b"RF64".to_vec();
+        file.extend_from_slice(&u32::MAX.to_le_bytes());
+        file.extend_from_slice(b"WAVE");
+        file.extend(chunk(
+            b"ds64",
+            ds64_size as u32,
+            &ds64(riff_size, 4, 2, &[]),
+        ));
+        file.extend_from_slice(&body[4..]);
+        let wave = crate::read_wave_with(Cursor::new(file), crate::ParseMode::Strict).unwrap();
+        assert_eq!(wave.data.as_ref().unwrap().size, 4);
+        assert_eq!(wave.frames(), Some(2));
+        assert_eq!(wave.cues.len(), 1);
+        assert_eq!(wave.cues[0].cue_id, 7);
+        assert_eq!(wave.cues[0].sample_start, 1);
+    }
+
+    #[test]
+    fn rejects_sizes_past_the_end_of_any_file() {
+        // a data chunk whose end is past 2^64 bytes
+        let mut file = b"RF64".to_vec();
+        file.extend_from_slice(&u32::MAX.to_le_bytes());
+        file.extend_from_slice(b"WAVE");
+        file.extend(chunk(b"ds64", 28, &ds64(56, u64::MAX - 43, 0, &[])));
+        file.extend(chunk(b"data", u32::MAX, &[0; 8]));
+        assert_eq!(file.len(), 64);
+        let chunks: Vec<_> = ChunkReader::new(Cursor::new(file.clone()))
+            .unwrap()
+            .collect();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[1].is_err());
+        for mode in [crate::ParseMode::Strict, crate::ParseMode::Lenient] {
+            assert!(crate::read_wave_with(Cursor::new(file.clone()), mode).is_err());
+        }
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_file() {
+        // the size of the data chunk from ds64 takes it past the end
+        let mut file = b"RF64".to_vec();
+        file.extend_from_slice(&u32::MAX.to_le_bytes());
+        file.extend_from_slice(b"WAVE");
+        file.extend(chunk(b"ds64", 28, &ds64(56, 1 << 40, 0, &[])));
+        file.extend(chunk(b"data", u32::MAX, &[0; 8]));
+        let chunks = ChunkReader::new(Cursor::new(file)).unwrap();
+        let ids: Vec<[u8; 4]> = chunks.map(|chunk| chunk.unwrap().id).collect();
+        assert_eq!(ids, vec![*b"ds64", *b"data"]);
+    }
+
+    #[test]
+    fn warns_of_a_broken_ixml() {
+        let fmt = [1, 0, 1, 0, 0x80, 0xbb, 0, 0, 0, 0x77, 1, 0, 2, 0, 16, 0];
+        let mut body = b"WAVE".to_vec();
+        body.extend(chunk(b"fmt ", 16, &fmt));
+        body.extend(chunk(b"data", 4, &[0; 4]));
+        body.extend(chunk(b"iXML", 6, b"<BWFXM"));
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        file.extend(body);
+        let wave = crate::read_wave_with(Cursor::new(file), crate::ParseMode::Strict).unwrap();
+        assert!(wave.ixml.is_none());
+        assert_eq!(wave.warnings.len(), 1);
+        assert!(wave.warnings[0].to_string().contains("iXML"));
+    }
+}
@@ -0,0 +1,236 @@
+// Big-endian RIFX files, written by some legacy tools: the chunks of a
+// RIFF file with every number in them big-endian. The payloads of the
+// chunks wav-cue decodes are swapped into the RIFF layout, so the usual
+// parsers read them.
+use crate::bext::{BEXT_LOUDNESS_OFFSET, BEXT_TIME_REFERENCE_OFFSET, BEXT_VERSION_OFFSET};
+use crate::levl;
+
+pub fn is_rifx_signature(signature: &[u8; 4]) -> bool {
+    signature == b"RIFX"
+}
+
+// The chunks with numbers in them that read_wave decodes
+pub fn has_numbers(id: &[u8; 4]) -> bool {
+    matches!(
+        id,
+        b"fmt "
+            | b"cue "
+            | b"LIST"
+            | b"smpl"
+            | b"bext"
+            | b"acid"
+            | b"DISP"
+            | b"fact"
+            | b"levl"
+            | b"plst"
+    )
+}
+
+// Reverses the bytes of the number at offset, if the payload holds it
+fn swap(payload: &mut [u8], offset: usize, size: usize) {
+    if let Some(number) = payload.get_mut(offset..offset + size) {
+        number.reverse();
+    }
+}
+
+fn u32_at(payload: &[u8], offset: usize) -> usize {
+    payload.get(offset..offset + 4).map_or(0, |number| {
+        u32::from_le_bytes(*array_ref!(number, 0, 4)) as usize
+    })
+}
+
+// Turns the numbers of a chunk payload little-endian, leaving text,
+// chunk ids and the other chunks as they are
+pub fn to_little_endian(id: &[u8; 4], payload: &mut [u8]) {
+    match id {
+        b"fmt " => {
+            // compression code, channels, sampling rate, bytes per second,
+            // block align, bits per sample and the extension size
+            for (offset, size) in [(0, 2), (2, 2), (4, 4), (8, 4), (12, 2), (14, 2), (16, 2)] {
+                swap(payload, offset, size);
+            }
+        }
+        b"cue " => {
+            swap(payload, 0, 4);
+            for index in 0..u32_at(payload, 0) {
+                let entry = 4 + 24 * index;
+                if entry + 24 > payload.len() {
+                    break;
+                }
+                // all but the data chunk id
+                for offset in [0, 4, 12, 16, 20] {
+                    swap(payload, entry + offset, 4);
+                }
+            }
+        }
+        b"LIST" => {
+            let adtl = payload.starts_with(b"adtl");
+            let mut ofs = 4;
+            while ofs + 8 <= payload.len() {
+                swap(payload, ofs + 4, 4);
+                let size = u32_at(payload, ofs + 4);
+                let sub_chunk = ofs + 8;
+                if adtl {
+                    // labl, note and ltxt start with the cue id
+                    swap(payload, sub_chunk, 4);
+                    if &payload[ofs..ofs + 4] == b"ltxt" && size >= 20 {
+                        // sample length, then the country, language,
+                        // dialect and code page after the purpose
+                        swap(payload, sub_chunk + 4, 4);
+                        for offset in [12, 14, 16, 18] {
+                            swap(payload, sub_chunk + offset, 2);
+                        }
+                    }
+                }
+                // sub-chunks are word aligned
+                ofs = sub_chunk + size + (size & 1);
+            }
+        }
+        b"smpl" => {
+            for index in 0..9 {
+                swap(payload, 4 * index, 4);
+            }
+            for index in 0..u32_at(payload, 28) {
+                let entry = 36 + 24 * index;
+                if entry + 24 > payload.len() {
+                    break;
+                }
+                for offset in (0..24).step_by(4) {
+                    swap(payload, entry + offset, 4);
+                }
+            }
+        }
+        b"bext" => {
+            swap(payload, BEXT_TIME_REFERENCE_OFFSET, 4);
+            swap(payload, BEXT_TIME_REFERENCE_OFFSET + 4, 4);
+            swap(payload, BEXT_VERSION_OFFSET, 2);
+            for index in 0..5 {
+                swap(payload, BEXT_LOUDNESS_OFFSET + 2 * index, 2);
+            }
+        }
+        // the clipboard format, the sample length
+        b"DISP" | b"fact" => swap(payload, 0, 4),
+        b"acid" => {
+            // flags, root note, the unknown fields, beats, meter and tempo
+            for (offset, size) in [
+                (0, 4),
+                (4, 2),
+                (6, 2),
+                (8, 4),
+                (12, 4),
+                (16, 2),
+                (18, 2),
+                (20, 4),
+            ] {
+                swap(payload, offset, size);
+            }
+        }
+        // the segment count, then cue id, length and repeats of each
+        b"plst" => {
+            for offset in (0..payload.len().saturating_sub(3)).step_by(4) {
+                swap(payload, offset, 4);
+            }
+        }
+        b"levl" => {
+            // the header fields, then the points if they are 16 bit
+            for index in 0..8 {
+                swap(payload, 4 * index, 4);
+            }
+            if u32_at(payload, 4) == levl::FORMAT_16_BIT as usize {
+                let start = u32_at(payload, 28).saturating_sub(8);
+                for offset in (start..payload.len().saturating_sub(1)).step_by(2) {
+                    swap(payload, offset, 2);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn chunk(id: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = id.to_vec();
+        data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        data.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            data.push(0);
+        }
+        data
+    }
+
+    fn numbers(numbers: &[u32]) -> Vec<u8> {
+        numbers
+            .iter()
+            .flat_map(|number| number.to_be_bytes())
+            .collect()
+    }
+
+    // stereo 24 bit at 48 kHz
+    fn fmt() -> Vec<u8> {
+        let mut fmt = vec![0, 1, 0, 2];
+        fmt.extend(numbers(&[48000, 288000]));
+        fmt.extend_from_slice(&[0, 6, 0, 24]);
+        fmt
+    }
+
+    #[test]
+    fn swaps_fmt() {
+        let mut payload = fmt();
+        to_little_endian(b"fmt ", &mut payload);
+        assert_eq!(
+            payload,
+            [1, 0, 2, 0, 0x80, 0xbb, 0, 0, 0, 0x65, 4, 0, 6, 0, 24, 0]
+        );
+    }
+
+    #[test]
+    fn swaps_cue_points_but_not_their_chunk_id() {
+        let mut payload = numbers(&[1, 3, 48000]);
+        payload.extend_from_slice(b"data");
+        payload.extend(numbers(&[0, 0, 48000]));
+        to_little_endian(b"cue ", &mut payload);
+        assert_eq!(&payload[0..4], [1, 0, 0, 0]);
+        assert_eq!(&payload[4..8], [3, 0, 0, 0]);
+        assert_eq!(&payload[12..16], b"data");
+        assert_eq!(&payload[24..28], 48000u32.to_le_bytes());
+    }
+
+    #[test]
+    fn leaves_truncated_payloads_alone() {
+        let mut payload = numbers(&[2, 1]);
+        to_little_endian(b"cue ", &mut payload);
+        assert_eq!(payload, [2, 0, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn reads_rifx_files() {
+        let mut cue = numbers(&[1, 5, 0, 0]);
+        cue[12..16].copy_from_slice(b"data");
+        cue.extend(numbers(&[0, 0, 24000]));
+        let mut labl = numbers(&[5]);
+        labl.extend_from_slice(b"Take 2\0");
+        let mut adtl = b"adtl".to_vec();
+        adtl.extend(chunk(b"labl", &labl));
+        let mut body = b"WAVE".to_vec();
+        body.extend(chunk(b"fmt ", &fmt()));
+        body.extend(chunk(b"data", &[0; 12]));
+        body.extend(chunk(b"cue ", &cue));
+        body.extend(chunk(b"LIST", &adtl));
+        let mut file = b"RIFX".to_vec();
+        file.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        file.extend(body);
+        let wave = crate::read_wave_with(Cursor::new(file), crate::ParseMode::Strict).unwrap();
+        assert!(wave.big_endian);
+        assert_eq!(wave.header.sampling_rate, 48000);
+        assert_eq!(wave.header.block_align, 6);
+        assert_eq!(wave.frames(), Some(2));
+        assert_eq!(wave.cues.len(), 1);
+        assert_eq!(wave.cues[0].cue_id, 5);
+        assert_eq!(wave.cues[0].sample_start, 24000);
+        assert_eq!(wave.cues[0].label.as_deref(), Some("Take 2"));
+    }
+}
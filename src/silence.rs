@@ -0,0 +1,70 @@
+// Silent gaps in the audio, for marking the segments of recordings that
+// weren't marked while recording
+use crate::pcm;
+use crate::{Error, Header};
+use std::io::{Read, Seek};
+
+// The level is measured as the peak over windows of this length
+const WINDOW_SECONDS: f64 = 0.01;
+
+// A run of silence, in frames from the start of the audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    pub start: u64,
+    pub end: u64,
+}
+
+// Gaps of at least min_gap frames in which the peak of all channels stays
+// below threshold_dbfs
+pub fn find_gaps<R: Read + Seek>(
+    reader: &mut R,
+    header: &Header,
+    data_offset: u64,
+    data_size: u64,
+    threshold_dbfs: f64,
+    min_gap: u64,
+) -> Result<Vec<Gap>, Error> {
+    let threshold = 10f64.powf(threshold_dbfs / 20.0);
+    let window = ((header.sampling_rate as f64 * WINDOW_SECONDS) as u64).max(1);
+    let mut gaps = Vec::new();
+    let mut silent_from = None;
+    let mut end_window = |window_start: u64, silent: bool| {
+        if silent {
+            silent_from.get_or_insert(window_start);
+        } else if let Some(start) = silent_from.take() {
+            if window_start - start >= min_gap {
+                gaps.push(Gap {
+                    start,
+                    end: window_start,
+                });
+            }
+        }
+    };
+    let mut frames = 0u64;
+    let mut peak = 0f64;
+    pcm::for_each_frame(reader, header, data_offset, data_size, |frame| {
+        for sample in frame {
+            peak = peak.max(sample.abs());
+        }
+        frames += 1;
+        if frames.is_multiple_of(window) {
+            end_window(frames - window, peak < threshold);
+            peak = 0.0;
+        }
+    })?;
+    if !frames.is_multiple_of(window) {
+        end_window(frames - frames % window, peak < threshold);
+    }
+    // the end of the audio ends the silence too
+    end_window(frames, false);
+    Ok(gaps)
+}
+
+// Where each segment of sound starts: at the end of the gaps, except for a
+// gap running to the end of the audio
+pub fn segment_starts(gaps: &[Gap], frames: u64) -> Vec<u64> {
+    gaps.iter()
+        .map(|gap| gap.end)
+        .filter(|end| *end < frames)
+        .collect()
+}
@@ -0,0 +1,120 @@
+// smpl chunk with the sampler settings and loops of an instrument sample:
+// https://www.recordingblogs.com/wiki/sample-chunk-of-a-wave-file
+//   0    Manufacturer, Product  4 each (MIDI manufacturer code)
+//   8    SamplePeriod  4 (nanoseconds)
+//   12   MIDIUnityNote  4, MIDIPitchFraction  4 (fraction of a semitone up)
+//   20   SMPTEFormat  4, SMPTEOffset  4
+//   28   NumSampleLoops  4, SamplerData  4
+//   36   Loops, 24 bytes each: CuePointID, Type, Start, End, Fraction,
+//                    PlayCount
+use crate::{Error, WaveError};
+
+const HEADER_SIZE: usize = 36;
+const LOOP_SIZE: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LoopType {
+    Forward,
+    Alternating,
+    Backward,
+    Other(u32),
+}
+
+impl LoopType {
+    fn from_u32(value: u32) -> LoopType {
+        match value {
+            0 => LoopType::Forward,
+            1 => LoopType::Alternating,
+            2 => LoopType::Backward,
+            value => LoopType::Other(value),
+        }
+    }
+}
+
+impl std::fmt::Display for LoopType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoopType::Forward => write!(f, "forward"),
+            LoopType::Alternating => write!(f, "alternating"),
+            LoopType::Backward => write!(f, "backward"),
+            LoopType::Other(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SampleLoop {
+    pub cue_id: u32,
+    pub loop_type: LoopType,
+    // sample frames, end inclusive
+    pub start: u32,
+    pub end: u32,
+    pub fraction: u32,
+    // 0 loops forever
+    pub play_count: u32,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sampler {
+    pub manufacturer: u32,
+    pub product: u32,
+    pub sample_period: u32,
+    pub midi_unity_note: u32,
+    pub midi_pitch_fraction: u32,
+    pub smpte_format: u32,
+    pub smpte_offset: u32,
+    pub loops: Vec<SampleLoop>,
+}
+
+impl Sampler {
+    // Pitch fraction in cents; the field is a fraction of 2^32 of a semitone
+    pub fn pitch_fraction_cents(&self) -> f64 {
+        self.midi_pitch_fraction as f64 * 100.0 / 4_294_967_296.0
+    }
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(*array_ref!(data, offset, 4))
+}
+
+pub fn parse(data: &[u8]) -> Result<Sampler, Error> {
+    if data.len() < HEADER_SIZE {
+        return Err(Error::from(WaveError {
+            message: format!("smpl chunk is too small: {}", data.len()),
+        }));
+    }
+    let loop_count = u32_at(data, 28) as usize;
+    if data.len() < HEADER_SIZE + loop_count * LOOP_SIZE {
+        return Err(Error::from(WaveError {
+            message: format!(
+                "smpl chunk of {} bytes cannot hold {loop_count} loops",
+                data.len()
+            ),
+        }));
+    }
+    let loops = data[HEADER_SIZE..]
+        .chunks_exact(LOOP_SIZE)
+        .take(loop_count)
+        .map(|entry| SampleLoop {
+            cue_id: u32_at(entry, 0),
+            loop_type: LoopType::from_u32(u32_at(entry, 4)),
+            start: u32_at(entry, 8),
+            end: u32_at(entry, 12),
+            fraction: u32_at(entry, 16),
+            play_count: u32_at(entry, 20),
+        })
+        .collect();
+    Ok(Sampler {
+        manufacturer: u32_at(data, 0),
+        product: u32_at(data, 4),
+        sample_period: u32_at(data, 8),
+        midi_unity_note: u32_at(data, 12),
+        midi_pitch_fraction: u32_at(data, 16),
+        smpte_format: u32_at(data, 20),
+        smpte_offset: u32_at(data, 24),
+        loops,
+    })
+}
@@ -0,0 +1,353 @@
+// Splits a file into segments at its cue points
+use crate::adtl;
+use crate::bext;
+use crate::duration::{self, Rounding};
+use crate::loudness;
+use crate::pcm;
+use crate::riff::{self, Chunk, ChunkData, ChunkInfo};
+use crate::{Error, WaveFileInfo};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FadeShape {
+    Linear,
+    EqualPower,
+}
+
+impl FadeShape {
+    // Gain at the given point of a fade in, 0.0..1.0
+    fn gain(self, position: f64) -> f64 {
+        match self {
+            FadeShape::Linear => position,
+            FadeShape::EqualPower => (position * std::f64::consts::FRAC_PI_2).sin(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalize {
+    // sample peak in dBFS
+    Peak(f64),
+    // integrated loudness in LUFS
+    Loudness(f64),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    // context included before and after each segment, in samples
+    pub pre: u64,
+    pub post: u64,
+    // length of the fades where a segment is cut from the audio, in samples
+    pub fade: u64,
+    pub fade_shape: FadeShape,
+    pub normalize: Option<Normalize>,
+}
+
+// Output filenames, without the extension: {stem} is the name of the
+// source file, {number} the number of the segment, {cue} the id of the cue
+// point it starts from and {label} the label of that cue point
+pub const DEFAULT_NAME_TEMPLATE: &str = "{stem}_{number}";
+
+// Leaves room for the collision suffix and extension within the 255 bytes
+// most file systems allow
+const MAX_NAME_LENGTH: usize = 200;
+
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub number: usize,
+    // cue point at the start of the segment
+    pub cue_id: Option<u32>,
+    // frames of the source, end exclusive
+    pub start: u64,
+    pub end: u64,
+}
+
+// Segments between consecutive cue points, from the start to the end of
+// the file, with the handles added and clamped to the file
+pub fn segments(wave: &WaveFileInfo, frames: u64, options: &Options) -> Vec<Segment> {
+    let mut boundaries: Vec<u64> = wave
+        .cues
+        .iter()
+        .map(|cue| cue.sample_start as u64)
+        .filter(|position| *position > 0 && *position < frames)
+        .collect();
+    boundaries.push(0);
+    boundaries.push(frames);
+    boundaries.sort_unstable();
+    boundaries.dedup();
+    boundaries
+        .windows(2)
+        .enumerate()
+        .map(|(index, pair)| Segment {
+            number: index + 1,
+            cue_id: wave
+                .cues
+                .iter()
+                .find(|cue| cue.sample_start as u64 == pair[0])
+                .map(|cue| cue.cue_id),
+            start: pair[0].saturating_sub(options.pre),
+            end: (pair[1] + options.post).min(frames),
+        })
+        .collect()
+}
+
+// Gain bringing the segment to the target level; silent segments are
+// left as they are
+fn normalization_gain(
+    reader: &mut BufReader<File>,
+    wave: &WaveFileInfo,
+    info: &ChunkInfo,
+    normalize: Normalize,
+) -> Result<f64, Error> {
+    let header = &wave.header;
+    let gain = match normalize {
+        Normalize::Peak(target) => {
+            let peaks = pcm::channel_peaks(reader, header, info.offset, info.size)?;
+            let peak = peaks.into_iter().fold(0.0, f64::max);
+            (peak > 0.0).then(|| 10f64.powf(target / 20.0) / peak)
+        }
+        Normalize::Loudness(target) => {
+            let mut meter = loudness::Meter::new(header.number_of_channels, header.sampling_rate);
+            pcm::for_each_frame(reader, header, info.offset, info.size, |frame| {
+                meter.add_frame(frame)
+            })?;
+            meter
+                .integrated()
+                .map(|level| 10f64.powf((target - level) / 20.0))
+        }
+    };
+    Ok(gain.unwrap_or(1.0))
+}
+
+// Segment audio with the normalization gain and fades applied where it's
+// cut out of the middle of the file; the fades are shortened to fit into
+// the segment
+fn processed_audio(
+    reader: &mut BufReader<File>,
+    wave: &WaveFileInfo,
+    info: ChunkInfo,
+    segment: &Segment,
+    frames: u64,
+    options: &Options,
+) -> Result<Vec<u8>, Error> {
+    let level = match options.normalize {
+        Some(normalize) => normalization_gain(reader, wave, &info, normalize)?,
+        None => 1.0,
+    };
+    let mut audio = riff::read_payload(reader, &info)?;
+    let length = segment.end - segment.start;
+    let fade_in = if segment.start > 0 {
+        options.fade.min(length)
+    } else {
+        0
+    };
+    let fade_out = if segment.end < frames {
+        options.fade.min(length)
+    } else {
+        0
+    };
+    pcm::apply_gain(&wave.header, &mut audio, |index| {
+        let mut gain = level;
+        if index < fade_in {
+            gain *= options.fade_shape.gain(index as f64 / fade_in as f64);
+        }
+        if length - index <= fade_out {
+            gain *= options
+                .fade_shape
+                .gain((length - index - 1) as f64 / fade_out as f64);
+        }
+        gain
+    })?;
+    Ok(audio)
+}
+
+// Metadata that still applies to a part of the file; markers and other
+// position based chunks are left out
+fn keep(reader: &mut BufReader<File>, info: &ChunkInfo) -> Result<bool, Error> {
+    Ok(match &info.id {
+        b"fmt " | b"bext" | b"iXML" => true,
+        b"LIST" => riff::read_payload(reader, info)?.starts_with(b"INFO"),
+        _ => false,
+    })
+}
+
+// A name that's valid on Windows, macOS and Linux: path separators,
+// characters Windows doesn't allow and control characters are replaced,
+// trailing dots and spaces removed, reserved device names avoided and the
+// length limited
+pub fn sanitize_file_name(name: &str) -> String {
+    let mut name: String = name
+        .chars()
+        .map(|ch| match ch {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            _ if ch.is_control() => '_',
+            _ => ch,
+        })
+        .collect();
+    if name.len() > MAX_NAME_LENGTH {
+        let mut end = MAX_NAME_LENGTH;
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name.truncate(end);
+    }
+    let name = name.trim_matches(|ch: char| ch == ' ' || ch == '.');
+    let base = name.split('.').next().unwrap_or_default();
+    if name.is_empty() {
+        String::from("_")
+    } else if RESERVED_NAMES
+        .iter()
+        .any(|reserved| base.eq_ignore_ascii_case(reserved))
+    {
+        format!("_{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+fn expand_template(template: &str, stem: &str, segment: &Segment, label: &str) -> String {
+    let name = template
+        .replace("{stem}", stem)
+        .replace("{number}", &format!("{:02}", segment.number))
+        .replace(
+            "{cue}",
+            &segment.cue_id.map(|id| id.to_string()).unwrap_or_default(),
+        )
+        .replace("{label}", label);
+    // separators left over from an empty field
+    let name = name.trim_matches(|ch: char| ch == ' ' || ch == '_' || ch == '-');
+    sanitize_file_name(name)
+}
+
+// Name not used by an earlier segment, nor by a file in the directory, with
+// _2, _3, ... appended when needed
+fn unique_path(output: &Path, name: &str, used: &mut HashSet<String>) -> PathBuf {
+    let mut candidate = name.to_string();
+    let mut suffix = 1;
+    loop {
+        let key = candidate.to_lowercase();
+        let path = output.join(format!("{candidate}.wav"));
+        if !used.contains(&key) && !path.exists() {
+            used.insert(key);
+            return path;
+        }
+        suffix += 1;
+        candidate = format!("{name}_{suffix}");
+    }
+}
+
+// CSV mapping the written files back to the source: file,start,end,cue,label
+// with the times in seconds from the start of the source
+fn manifest(written: &[(PathBuf, Segment)], labels: &[String], sampling_rate: u32) -> String {
+    let seconds = |samples| duration::format_seconds(samples, sampling_rate, 3, Rounding::Round);
+    let quote = |text: &str| format!("\"{}\"", text.replace('"', "\"\""));
+    let mut output = String::from("file,start,end,cue,label\n");
+    for ((path, segment), label) in written.iter().zip(labels) {
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        output.push_str(&format!(
+            "{},{},{},{},{}\n",
+            quote(&file),
+            seconds(segment.start),
+            seconds(segment.end),
+            segment.cue_id.map(|id| id.to_string()).unwrap_or_default(),
+            quote(label)
+        ));
+    }
+    output
+}
+
+// Writes the segments into the directory, named after the template, and
+// the manifest as stem_manifest.csv
+pub fn split(
+    filename: &str,
+    wave: &WaveFileInfo,
+    output: &Path,
+    name_template: &str,
+    options: &Options,
+) -> Result<(Vec<(PathBuf, Segment)>, PathBuf), Error> {
+    let data = wave.audio()?;
+    let block_align = wave.header.block_align.max(1) as u64;
+    let frames = data.size / block_align;
+    let stem = Path::new(filename)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut reader = BufReader::new(File::open(filename)?);
+    let infos = riff::walk_chunks(&mut reader)?;
+    let mut cue_labels = Vec::new();
+    for info in &infos {
+        if &info.id == b"LIST" {
+            let list = riff::read_payload(&mut reader, info)?;
+            if list.starts_with(b"adtl") {
+                cue_labels.extend(adtl::parse(&list)?.labels);
+            }
+        }
+    }
+    let mut written = Vec::new();
+    let mut labels = Vec::new();
+    let mut used = HashSet::new();
+    for segment in segments(wave, frames, options) {
+        let mut chunks = Vec::new();
+        for info in &infos {
+            if &info.id == b"data" {
+                let audio = ChunkInfo {
+                    id: info.id,
+                    offset: data.offset + segment.start * block_align,
+                    size: (segment.end - segment.start) * block_align,
+                };
+                chunks.push(if options.fade > 0 || options.normalize.is_some() {
+                    let audio =
+                        processed_audio(&mut reader, wave, audio, &segment, frames, options)?;
+                    Chunk::new(b"data", audio)
+                } else {
+                    Chunk {
+                        id: info.id,
+                        data: ChunkData::Source(audio),
+                    }
+                });
+            } else if &info.id == b"bext" {
+                let mut raw = riff::read_payload(&mut reader, info)?;
+                let time_reference = wave.bext.as_ref().map_or(0, |bext| bext.time_reference);
+                bext::set_time_reference(&mut raw, time_reference + segment.start);
+                if let Some(line) = bext::coding_history_line(&wave.header, "split") {
+                    bext::append_coding_history(&mut raw, &line);
+                }
+                chunks.push(Chunk::new(b"bext", raw));
+            } else if keep(&mut reader, info)? {
+                chunks.push(Chunk {
+                    id: info.id,
+                    data: ChunkData::Source(info.clone()),
+                });
+            }
+        }
+        let label = cue_labels
+            .iter()
+            .find(|(cue_id, _)| Some(*cue_id) == segment.cue_id)
+            .map(|(_, label)| label.clone())
+            .unwrap_or_default();
+        let name = expand_template(name_template, &stem, &segment, &label);
+        let path = unique_path(output, &name, &mut used);
+        let mut writer = BufWriter::new(File::create(&path)?);
+        riff::write_riff(&mut reader, &mut writer, &chunks)?;
+        writer.flush()?;
+        written.push((path, segment));
+        labels.push(label);
+    }
+    let manifest_path = output.join(format!("{stem}_manifest.csv"));
+    fs::write(
+        &manifest_path,
+        manifest(&written, &labels, wave.header.sampling_rate),
+    )?;
+    Ok((written, manifest_path))
+}
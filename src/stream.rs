@@ -0,0 +1,49 @@
+// Reading from pipes, which can't seek: seeking forward reads and drops
+// the bytes in between, and the end of the stream isn't known until it
+// is reached
+use std::io::{self, Read, Seek, SeekFrom};
+
+pub struct Forward<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> Forward<R> {
+    pub fn new(inner: R) -> Forward<R> {
+        Forward { inner, position: 0 }
+    }
+}
+
+impl<R: Read> Read for Forward<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buffer)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Read> Seek for Forward<R> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let target = match position {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+            SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "the end of a stream isn't known",
+                ))
+            }
+        };
+        if target < self.position as i64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot seek back in a stream",
+            ));
+        }
+        let skip = target as u64 - self.position;
+        io::copy(&mut (&mut self.inner).take(skip), &mut io::sink())?;
+        // like a file, a stream can be seeked past its end
+        self.position = target as u64;
+        Ok(self.position)
+    }
+}
@@ -0,0 +1,286 @@
+// Clean-up of label text for export formats that are picky about it.
+// Normalization only covers the Latin letters with one diacritic (Latin-1
+// Supplement and Latin Extended-A), which is what turns up in practice when
+// labels are typed on a Mac or copied from a web page; other decomposed
+// sequences are left as they are.
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sanitize {
+    pub nfc: bool,
+    pub strip_control: bool,
+    pub ascii: bool,
+}
+
+// base letter, combining mark, precomposed letter
+const COMPOSITIONS: &[(char, char, char)] = &[
+    ('A', '\u{0300}', 'À'),
+    ('A', '\u{0301}', 'Á'),
+    ('A', '\u{0302}', 'Â'),
+    ('A', '\u{0303}', 'Ã'),
+    ('A', '\u{0308}', 'Ä'),
+    ('A', '\u{030a}', 'Å'),
+    ('C', '\u{0327}', 'Ç'),
+    ('E', '\u{0300}', 'È'),
+    ('E', '\u{0301}', 'É'),
+    ('E', '\u{0302}', 'Ê'),
+    ('E', '\u{0308}', 'Ë'),
+    ('I', '\u{0300}', 'Ì'),
+    ('I', '\u{0301}', 'Í'),
+    ('I', '\u{0302}', 'Î'),
+    ('I', '\u{0308}', 'Ï'),
+    ('N', '\u{0303}', 'Ñ'),
+    ('O', '\u{0300}', 'Ò'),
+    ('O', '\u{0301}', 'Ó'),
+    ('O', '\u{0302}', 'Ô'),
+    ('O', '\u{0303}', 'Õ'),
+    ('O', '\u{0308}', 'Ö'),
+    ('U', '\u{0300}', 'Ù'),
+    ('U', '\u{0301}', 'Ú'),
+    ('U', '\u{0302}', 'Û'),
+    ('U', '\u{0308}', 'Ü'),
+    ('Y', '\u{0301}', 'Ý'),
+    ('a', '\u{0300}', 'à'),
+    ('a', '\u{0301}', 'á'),
+    ('a', '\u{0302}', 'â'),
+    ('a', '\u{0303}', 'ã'),
+    ('a', '\u{0308}', 'ä'),
+    ('a', '\u{030a}', 'å'),
+    ('c', '\u{0327}', 'ç'),
+    ('e', '\u{0300}', 'è'),
+    ('e', '\u{0301}', 'é'),
+    ('e', '\u{0302}', 'ê'),
+    ('e', '\u{0308}', 'ë'),
+    ('i', '\u{0300}', 'ì'),
+    ('i', '\u{0301}', 'í'),
+    ('i', '\u{0302}', 'î'),
+    ('i', '\u{0308}', 'ï'),
+    ('n', '\u{0303}', 'ñ'),
+    ('o', '\u{0300}', 'ò'),
+    ('o', '\u{0301}', 'ó'),
+    ('o', '\u{0302}', 'ô'),
+    ('o', '\u{0303}', 'õ'),
+    ('o', '\u{0308}', 'ö'),
+    ('u', '\u{0300}', 'ù'),
+    ('u', '\u{0301}', 'ú'),
+    ('u', '\u{0302}', 'û'),
+    ('u', '\u{0308}', 'ü'),
+    ('y', '\u{0301}', 'ý'),
+    ('y', '\u{0308}', 'ÿ'),
+    ('A', '\u{0304}', 'Ā'),
+    ('a', '\u{0304}', 'ā'),
+    ('A', '\u{0306}', 'Ă'),
+    ('a', '\u{0306}', 'ă'),
+    ('A', '\u{0328}', 'Ą'),
+    ('a', '\u{0328}', 'ą'),
+    ('C', '\u{0301}', 'Ć'),
+    ('c', '\u{0301}', 'ć'),
+    ('C', '\u{0302}', 'Ĉ'),
+    ('c', '\u{0302}', 'ĉ'),
+    ('C', '\u{0307}', 'Ċ'),
+    ('c', '\u{0307}', 'ċ'),
+    ('C', '\u{030c}', 'Č'),
+    ('c', '\u{030c}', 'č'),
+    ('D', '\u{030c}', 'Ď'),
+    ('d', '\u{030c}', 'ď'),
+    ('E', '\u{0304}', 'Ē'),
+    ('e', '\u{0304}', 'ē'),
+    ('E', '\u{0306}', 'Ĕ'),
+    ('e', '\u{0306}', 'ĕ'),
+    ('E', '\u{0307}', 'Ė'),
+    ('e', '\u{0307}', 'ė'),
+    ('E', '\u{0328}', 'Ę'),
+    ('e', '\u{0328}', 'ę'),
+    ('E', '\u{030c}', 'Ě'),
+    ('e', '\u{030c}', 'ě'),
+    ('G', '\u{0302}', 'Ĝ'),
+    ('g', '\u{0302}', 'ĝ'),
+    ('G', '\u{0306}', 'Ğ'),
+    ('g', '\u{0306}', 'ğ'),
+    ('G', '\u{0307}', 'Ġ'),
+    ('g', '\u{0307}', 'ġ'),
+    ('G', '\u{0327}', 'Ģ'),
+    ('g', '\u{0327}', 'ģ'),
+    ('H', '\u{0302}', 'Ĥ'),
+    ('h', '\u{0302}', 'ĥ'),
+    ('I', '\u{0303}', 'Ĩ'),
+    ('i', '\u{0303}', 'ĩ'),
+    ('I', '\u{0304}', 'Ī'),
+    ('i', '\u{0304}', 'ī'),
+    ('I', '\u{0306}', 'Ĭ'),
+    ('i', '\u{0306}', 'ĭ'),
+    ('I', '\u{0328}', 'Į'),
+    ('i', '\u{0328}', 'į'),
+    ('I', '\u{0307}', 'İ'),
+    ('J', '\u{0302}', 'Ĵ'),
+    ('j', '\u{0302}', 'ĵ'),
+    ('K', '\u{0327}', 'Ķ'),
+    ('k', '\u{0327}', 'ķ'),
+    ('L', '\u{0301}', 'Ĺ'),
+    ('l', '\u{0301}', 'ĺ'),
+    ('L', '\u{0327}', 'Ļ'),
+    ('l', '\u{0327}', 'ļ'),
+    ('L', '\u{030c}', 'Ľ'),
+    ('l', '\u{030c}', 'ľ'),
+    ('N', '\u{0301}', 'Ń'),
+    ('n', '\u{0301}', 'ń'),
+    ('N', '\u{0327}', 'Ņ'),
+    ('n', '\u{0327}', 'ņ'),
+    ('N', '\u{030c}', 'Ň'),
+    ('n', '\u{030c}', 'ň'),
+    ('O', '\u{0304}', 'Ō'),
+    ('o', '\u{0304}', 'ō'),
+    ('O', '\u{0306}', 'Ŏ'),
+    ('o', '\u{0306}', 'ŏ'),
+    ('O', '\u{030b}', 'Ő'),
+    ('o', '\u{030b}', 'ő'),
+    ('R', '\u{0301}', 'Ŕ'),
+    ('r', '\u{0301}', 'ŕ'),
+    ('R', '\u{0327}', 'Ŗ'),
+    ('r', '\u{0327}', 'ŗ'),
+    ('R', '\u{030c}', 'Ř'),
+    ('r', '\u{030c}', 'ř'),
+    ('S', '\u{0301}', 'Ś'),
+    ('s', '\u{0301}', 'ś'),
+    ('S', '\u{0302}', 'Ŝ'),
+    ('s', '\u{0302}', 'ŝ'),
+    ('S', '\u{0327}', 'Ş'),
+    ('s', '\u{0327}', 'ş'),
+    ('S', '\u{030c}', 'Š'),
+    ('s', '\u{030c}', 'š'),
+    ('T', '\u{0327}', 'Ţ'),
+    ('t', '\u{0327}', 'ţ'),
+    ('T', '\u{030c}', 'Ť'),
+    ('t', '\u{030c}', 'ť'),
+    ('U', '\u{0303}', 'Ũ'),
+    ('u', '\u{0303}', 'ũ'),
+    ('U', '\u{0304}', 'Ū'),
+    ('u', '\u{0304}', 'ū'),
+    ('U', '\u{0306}', 'Ŭ'),
+    ('u', '\u{0306}', 'ŭ'),
+    ('U', '\u{030a}', 'Ů'),
+    ('u', '\u{030a}', 'ů'),
+    ('U', '\u{030b}', 'Ű'),
+    ('u', '\u{030b}', 'ű'),
+    ('U', '\u{0328}', 'Ų'),
+    ('u', '\u{0328}', 'ų'),
+    ('W', '\u{0302}', 'Ŵ'),
+    ('w', '\u{0302}', 'ŵ'),
+    ('Y', '\u{0302}', 'Ŷ'),
+    ('y', '\u{0302}', 'ŷ'),
+    ('Y', '\u{0308}', 'Ÿ'),
+    ('Z', '\u{0301}', 'Ź'),
+    ('z', '\u{0301}', 'ź'),
+    ('Z', '\u{0307}', 'Ż'),
+    ('z', '\u{0307}', 'ż'),
+    ('Z', '\u{030c}', 'Ž'),
+    ('z', '\u{030c}', 'ž'),
+];
+
+// Letters without a decomposition and punctuation with an ASCII equivalent
+const TRANSLITERATIONS: &[(char, &str)] = &[
+    ('ß', "ss"),
+    ('Æ', "AE"),
+    ('æ', "ae"),
+    ('Ø', "O"),
+    ('ø', "o"),
+    ('Œ', "OE"),
+    ('œ', "oe"),
+    ('Đ', "D"),
+    ('đ', "d"),
+    ('Ð', "D"),
+    ('ð', "d"),
+    ('Ł', "L"),
+    ('ł', "l"),
+    ('Þ', "Th"),
+    ('þ', "th"),
+    ('ı', "i"),
+    ('\u{00a0}', " "),
+    ('\u{2018}', "'"),
+    ('\u{2019}', "'"),
+    ('\u{201c}', "\""),
+    ('\u{201d}', "\""),
+    ('\u{2013}', "-"),
+    ('\u{2014}', "-"),
+    ('\u{2026}', "..."),
+    ('«', "\""),
+    ('»', "\""),
+];
+
+fn is_combining(ch: char) -> bool {
+    ('\u{0300}'..='\u{036f}').contains(&ch)
+}
+
+pub fn nfc(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let composed = chars.peek().and_then(|mark| {
+            COMPOSITIONS
+                .iter()
+                .find(|(base, combining, _)| *base == ch && combining == mark)
+                .map(|(_, _, composed)| *composed)
+        });
+        match composed {
+            Some(composed) => {
+                result.push(composed);
+                chars.next();
+            }
+            None => result.push(ch),
+        }
+    }
+    result
+}
+
+// Whitespace controls (tabs, line breaks) become spaces, others are removed
+pub fn strip_control(text: &str) -> String {
+    text.chars()
+        .filter_map(|ch| {
+            if !ch.is_control() {
+                Some(ch)
+            } else if ch.is_whitespace() {
+                Some(' ')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// Diacritics are dropped; characters with no ASCII equivalent become ?
+pub fn to_ascii(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if ch.is_ascii() {
+            result.push(ch);
+        } else if is_combining(ch) {
+            continue;
+        } else if let Some((base, _, _)) = COMPOSITIONS.iter().find(|entry| entry.2 == ch) {
+            result.push(*base);
+        } else if let Some((_, ascii)) = TRANSLITERATIONS.iter().find(|entry| entry.0 == ch) {
+            result.push_str(ascii);
+        } else {
+            result.push('?');
+        }
+    }
+    result
+}
+
+impl Sanitize {
+    pub fn is_active(&self) -> bool {
+        self.nfc || self.strip_control || self.ascii
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        if self.nfc {
+            text = nfc(&text);
+        }
+        if self.strip_control {
+            text = strip_control(&text);
+        }
+        if self.ascii {
+            text = to_ascii(&text);
+        }
+        text
+    }
+}
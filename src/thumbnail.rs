@@ -0,0 +1,111 @@
+// Waveform thumbnails of the audio around each cue point
+use crate::{pcm, png, Error, WaveFileInfo};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const BACKGROUND: u8 = 0;
+const WAVEFORM: u8 = 1;
+const MARKER: u8 = 2;
+const PALETTE: [[u8; 3]; 3] = [[0xff, 0xff, 0xff], [0x30, 0x50, 0x80], [0xe0, 0x20, 0x20]];
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    // samples shown on each side of the cue
+    pub window: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Minimum and maximum of all channels for each column; None for columns
+// outside of the audio
+fn column_ranges(
+    filename: &str,
+    wave: &WaveFileInfo,
+    center: u64,
+    options: &Options,
+) -> Result<Vec<Option<(f64, f64)>>, Error> {
+    let data = wave.audio()?;
+    let block_align = wave.header.block_align.max(1) as u64;
+    let frames = data.size / block_align;
+    let start = center as i64 - options.window as i64;
+    let span = 2 * options.window + 1;
+    let first = start.max(0) as u64;
+    let last = ((start + span as i64) as u64).min(frames);
+    let mut columns = vec![None; options.width as usize];
+    if first >= last {
+        return Ok(columns);
+    }
+    let mut reader = BufReader::new(File::open(filename)?);
+    let mut frame_index = first;
+    pcm::for_each_frame(
+        &mut reader,
+        &wave.header,
+        data.offset + first * block_align,
+        (last - first) * block_align,
+        |frame| {
+            let column =
+                ((frame_index as i64 - start) as u64 * options.width as u64 / span) as usize;
+            let (low, high) = frame
+                .iter()
+                .fold((f64::MAX, f64::MIN), |(low, high), sample| {
+                    (low.min(*sample), high.max(*sample))
+                });
+            let range = columns[column].get_or_insert((low, high));
+            *range = (range.0.min(low), range.1.max(high));
+            frame_index += 1;
+        },
+    )?;
+    Ok(columns)
+}
+
+fn render(columns: &[Option<(f64, f64)>], options: &Options) -> Vec<u8> {
+    let (width, height) = (options.width as usize, options.height as usize);
+    let mut pixels = vec![BACKGROUND; width * height];
+    let row = |level: f64| -> usize {
+        let level = level.clamp(-1.0, 1.0);
+        (((1.0 - level) / 2.0 * (height - 1) as f64).round() as usize).min(height - 1)
+    };
+    for (x, range) in columns.iter().enumerate() {
+        if let Some((low, high)) = range {
+            for y in row(*high)..=row(*low) {
+                pixels[y * width + x] = WAVEFORM;
+            }
+        }
+    }
+    for y in 0..height {
+        pixels[y * width + width / 2] = MARKER;
+    }
+    pixels
+}
+
+// Writes filename_cueN.png for each cue into the output directory and
+// returns the paths
+pub fn write_thumbnails(
+    filename: &str,
+    wave: &WaveFileInfo,
+    output: &Path,
+    options: &Options,
+) -> Result<Vec<PathBuf>, Error> {
+    let stem = Path::new(filename)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut written = Vec::new();
+    for cue in &wave.cues {
+        let columns = column_ranges(filename, wave, cue.sample_start as u64, options)?;
+        let pixels = render(&columns, options);
+        let path = output.join(format!("{stem}_cue{}.png", cue.cue_id));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        png::write_indexed(
+            &mut writer,
+            options.width,
+            options.height,
+            &PALETTE,
+            &pixels,
+        )?;
+        writer.flush()?;
+        written.push(path);
+    }
+    Ok(written)
+}
@@ -0,0 +1,358 @@
+// SMPTE timecode conversions done in exact integer arithmetic, so that
+// fractional frame rates (24000/1001, 30000/1001, 60000/1001) don't drift
+// over long recordings the way floating point seconds would.
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameRate {
+    pub numerator: u64,
+    pub denominator: u64,
+    pub drop_frame: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timecode {
+    pub hours: u64,
+    pub minutes: u64,
+    pub seconds: u64,
+    pub frames: u64,
+    pub drop_frame: bool,
+}
+
+impl FrameRate {
+    // 23.976, 24, 25, 29.97, 30, 50, 59.94 or 60; a df suffix selects drop
+    // frame counting for 29.97 and 59.94
+    pub fn parse(rate: &str) -> Option<FrameRate> {
+        let lower = rate.to_lowercase();
+        let (rate, drop_frame) = if let Some(rate) = lower.strip_suffix("ndf") {
+            (rate.trim_end(), false)
+        } else if let Some(rate) = lower.strip_suffix("df") {
+            (rate.trim_end(), true)
+        } else {
+            (lower.as_str(), false)
+        };
+        let (numerator, denominator) = match rate {
+            "23.976" | "23.98" => (24000, 1001),
+            "24" => (24, 1),
+            "25" => (25, 1),
+            "29.97" => (30000, 1001),
+            "30" => (30, 1),
+            "50" => (50, 1),
+            "59.94" => (60000, 1001),
+            "60" => (60, 1),
+            _ => return None,
+        };
+        let frame_rate = FrameRate {
+            numerator,
+            denominator,
+            drop_frame,
+        };
+        if drop_frame && frame_rate.dropped_frames() == 0 {
+            return None;
+        }
+        Some(frame_rate)
+    }
+
+    // Rate written as a fraction, such as 30000/1001 in iXML TIMECODE_RATE
+    pub fn from_ratio(ratio: &str, drop_frame: bool) -> Option<FrameRate> {
+        let (numerator, denominator) = match ratio.trim().split_once('/') {
+            Some((numerator, denominator)) => (
+                numerator.trim().parse().ok()?,
+                denominator.trim().parse().ok()?,
+            ),
+            None => (ratio.trim().parse().ok()?, 1),
+        };
+        if numerator == 0 || denominator == 0 {
+            return None;
+        }
+        let frame_rate = FrameRate {
+            numerator,
+            denominator,
+            drop_frame: false,
+        };
+        Some(FrameRate {
+            drop_frame: drop_frame && frame_rate.dropped_frames() > 0,
+            ..frame_rate
+        })
+    }
+
+    // Frames per second the timecode counts, e.g. 30 for 29.97
+    pub fn nominal(&self) -> u64 {
+        self.numerator.div_ceil(self.denominator)
+    }
+
+    // Frame numbers skipped at the start of each minute, except every tenth
+    fn dropped_frames(&self) -> u64 {
+        if self.denominator == 1001 && self.nominal().is_multiple_of(30) {
+            self.nominal() / 15
+        } else {
+            0
+        }
+    }
+
+    // Index of the frame the sample falls in
+    pub fn samples_to_frames(&self, samples: u64, sampling_rate: u32) -> u64 {
+        (samples as u128 * self.numerator as u128
+            / (sampling_rate.max(1) as u128 * self.denominator as u128)) as u64
+    }
+
+    // First sample of the frame
+    pub fn frames_to_samples(&self, frames: u64, sampling_rate: u32) -> u64 {
+        let numerator = frames as u128 * sampling_rate as u128 * self.denominator as u128;
+        numerator.div_ceil(self.numerator as u128) as u64
+    }
+
+    pub fn frames_to_timecode(&self, frames: u64) -> Timecode {
+        let nominal = self.nominal();
+        let mut frames = frames;
+        if self.drop_frame {
+            let drop = self.dropped_frames();
+            let frames_per_minute = nominal * 60 - drop;
+            let frames_per_ten_minutes = nominal * 600 - drop * 9;
+            let ten_minutes = frames / frames_per_ten_minutes;
+            let remainder = frames % frames_per_ten_minutes;
+            frames += drop * 9 * ten_minutes;
+            if remainder > drop {
+                frames += drop * ((remainder - drop) / frames_per_minute);
+            }
+        }
+        Timecode {
+            hours: frames / (nominal * 3600),
+            minutes: frames / (nominal * 60) % 60,
+            seconds: frames / nominal % 60,
+            frames: frames % nominal,
+            drop_frame: self.drop_frame,
+        }
+    }
+
+    pub fn timecode_to_frames(&self, timecode: &Timecode) -> u64 {
+        let nominal = self.nominal();
+        let frames = ((timecode.hours * 60 + timecode.minutes) * 60 + timecode.seconds) * nominal
+            + timecode.frames;
+        if self.drop_frame {
+            let total_minutes = timecode.hours * 60 + timecode.minutes;
+            frames - self.dropped_frames() * (total_minutes - total_minutes / 10)
+        } else {
+            frames
+        }
+    }
+
+    pub fn samples_to_timecode(&self, samples: u64, sampling_rate: u32) -> Timecode {
+        self.frames_to_timecode(self.samples_to_frames(samples, sampling_rate))
+    }
+
+    // Time of day timecode, wrapping around at midnight
+    pub fn samples_to_time_of_day(&self, samples: u64, sampling_rate: u32) -> Timecode {
+        let day = self.timecode_to_frames(&Timecode {
+            hours: 24,
+            minutes: 0,
+            seconds: 0,
+            frames: 0,
+            drop_frame: self.drop_frame,
+        });
+        self.frames_to_timecode(self.samples_to_frames(samples, sampling_rate) % day)
+    }
+
+    pub fn timecode_to_samples(&self, timecode: &Timecode, sampling_rate: u32) -> u64 {
+        self.frames_to_samples(self.timecode_to_frames(timecode), sampling_rate)
+    }
+
+    // Checks that the timecode exists at this rate; drop frame timecode
+    // skips the first frame numbers of most minutes
+    pub fn is_valid(&self, timecode: &Timecode) -> bool {
+        timecode.minutes < 60
+            && timecode.seconds < 60
+            && timecode.frames < self.nominal()
+            && !(self.drop_frame
+                && timecode.seconds == 0
+                && !timecode.minutes.is_multiple_of(10)
+                && timecode.frames < self.dropped_frames())
+    }
+}
+
+impl Timecode {
+    // HH:MM:SS:FF, with ; or . as the last separator for drop frame
+    pub fn parse(timecode: &str) -> Option<Timecode> {
+        let drop_frame = timecode.contains(';') || timecode.contains('.');
+        let fields: Vec<&str> = timecode.split([':', ';', '.']).collect();
+        if fields.len() != 4 {
+            return None;
+        }
+        let mut values = [0u64; 4];
+        for (value, field) in values.iter_mut().zip(fields) {
+            if field.is_empty() || !field.chars().all(|ch| ch.is_ascii_digit()) {
+                return None;
+            }
+            *value = field.parse().ok()?;
+        }
+        Some(Timecode {
+            hours: values[0],
+            minutes: values[1],
+            seconds: values[2],
+            frames: values[3],
+            drop_frame,
+        })
+    }
+}
+
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.drop_frame { ';' } else { ':' };
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours, self.minutes, self.seconds, separator, self.frames
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(rate: &str) -> FrameRate {
+        FrameRate::parse(rate).unwrap()
+    }
+
+    fn timecode(timecode: &str) -> Timecode {
+        Timecode::parse(timecode).unwrap()
+    }
+
+    #[test]
+    fn parses_rates() {
+        assert_eq!(
+            rate("23.976"),
+            FrameRate::from_ratio("24000/1001", false).unwrap()
+        );
+        assert_eq!(
+            rate("29.97df"),
+            FrameRate::from_ratio("30000/1001", true).unwrap()
+        );
+        assert_eq!(rate("59.94 DF").numerator, 60000);
+        assert!(!rate("29.97ndf").drop_frame);
+        // only the NTSC rates drop frames
+        assert_eq!(FrameRate::parse("25df"), None);
+        assert!(!FrameRate::from_ratio("25", true).unwrap().drop_frame);
+        assert_eq!(FrameRate::from_ratio("30000/0", false), None);
+    }
+
+    #[test]
+    fn known_frame_counts() {
+        let vectors = [
+            ("29.97df", "00:00:59;29", 1799),
+            ("29.97df", "00:01:00;02", 1800),
+            ("29.97df", "00:10:00;00", 17982),
+            ("29.97df", "01:00:00;00", 107892),
+            ("29.97df", "23:59:59;29", 2589407),
+            ("29.97", "00:01:00:00", 1800),
+            ("29.97", "00:10:00:00", 18000),
+            ("29.97", "01:00:00:00", 108000),
+            ("59.94df", "00:01:00;04", 3600),
+            ("59.94df", "00:10:00;00", 35964),
+            ("59.94df", "01:00:00;00", 215784),
+            ("23.976", "00:00:01:00", 24),
+            ("23.976", "01:00:00:00", 86400),
+        ];
+        for (frame_rate, text, frames) in vectors {
+            let frame_rate = rate(frame_rate);
+            assert_eq!(
+                frame_rate.timecode_to_frames(&timecode(text)),
+                frames,
+                "{text}"
+            );
+            assert_eq!(frame_rate.frames_to_timecode(frames).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn known_sample_positions() {
+        // an hour of 23.976 timecode is 3603.6 seconds
+        let frame_rate = rate("23.976");
+        assert_eq!(frame_rate.frames_to_samples(86400, 48000), 172_972_800);
+        assert_eq!(frame_rate.samples_to_frames(172_972_800, 48000), 86400);
+        assert_eq!(frame_rate.samples_to_frames(172_972_799, 48000), 86399);
+        // an hour of 29.97 drop frame timecode is 3599.9964 seconds
+        let frame_rate = rate("29.97df");
+        let samples = frame_rate.timecode_to_samples(&timecode("01:00:00;00"), 48000);
+        assert_eq!(samples, 172_799_828);
+        assert_eq!(
+            frame_rate.samples_to_timecode(samples, 48000).to_string(),
+            "01:00:00;00"
+        );
+        assert_eq!(
+            frame_rate
+                .samples_to_timecode(samples - 1, 48000)
+                .to_string(),
+            "00:59:59;29"
+        );
+    }
+
+    #[test]
+    fn wraps_at_midnight() {
+        let frame_rate = rate("29.97df");
+        let day = frame_rate.timecode_to_samples(&timecode("24:00:00;00"), 48000);
+        assert_eq!(
+            frame_rate.samples_to_time_of_day(day, 48000).to_string(),
+            "00:00:00;00"
+        );
+        assert_eq!(
+            frame_rate
+                .samples_to_time_of_day(day + frame_rate.frames_to_samples(30, 48000), 48000)
+                .to_string(),
+            "00:00:01;00"
+        );
+    }
+
+    #[test]
+    fn round_trips_frames() {
+        for frame_rate in [
+            "23.976", "24", "25", "29.97", "29.97df", "30", "59.94df", "60",
+        ] {
+            let frame_rate = rate(frame_rate);
+            let day = frame_rate.timecode_to_frames(&Timecode {
+                hours: 24,
+                minutes: 0,
+                seconds: 0,
+                frames: 0,
+                drop_frame: frame_rate.drop_frame,
+            });
+            // every frame of the first twenty minutes, then a stride
+            // through the rest of the day
+            let frames = (0..frame_rate.nominal() * 1200).chain((0..day).step_by(997));
+            for frame in frames {
+                let timecode = frame_rate.frames_to_timecode(frame);
+                assert!(frame_rate.is_valid(&timecode), "{}", timecode);
+                assert_eq!(
+                    frame_rate.timecode_to_frames(&timecode),
+                    frame,
+                    "{timecode}"
+                );
+                let samples = frame_rate.frames_to_samples(frame, 48000);
+                assert_eq!(frame_rate.samples_to_frames(samples, 48000), frame);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_dropped_frame_numbers() {
+        let frame_rate = rate("29.97df");
+        assert!(!frame_rate.is_valid(&timecode("00:01:00;00")));
+        assert!(!frame_rate.is_valid(&timecode("00:01:00;01")));
+        assert!(frame_rate.is_valid(&timecode("00:01:00;02")));
+        assert!(frame_rate.is_valid(&timecode("00:10:00;00")));
+        let frame_rate = rate("59.94df");
+        assert!(!frame_rate.is_valid(&timecode("00:01:00;03")));
+        assert!(frame_rate.is_valid(&timecode("00:01:00;04")));
+    }
+
+    #[test]
+    fn parses_and_formats_timecode() {
+        assert_eq!(timecode("01:02:03:04").to_string(), "01:02:03:04");
+        assert_eq!(timecode("01:02:03.04").to_string(), "01:02:03;04");
+        assert!(timecode("01:02:03;04").drop_frame);
+        assert_eq!(Timecode::parse("01:02:03"), None);
+        assert_eq!(Timecode::parse("01:02:03:x4"), None);
+    }
+}
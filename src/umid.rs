@@ -0,0 +1,252 @@
+// SMPTE ST 330 Unique Material Identifier
+//
+// Basic UMID (32 bytes):
+//   Universal Label    12 bytes
+//   Length              1 byte  (0x13 basic, 0x33 extended)
+//   Instance Number     3 bytes
+//   Material Number    16 bytes
+// Extended UMID appends a 32 byte Source Pack:
+//   Time/Date           8 bytes
+//   Spatial Coordinates 12 bytes
+//   Country              4 bytes
+//   Organization         4 bytes
+//   User                 4 bytes
+use crate::datetime::DateTime;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const EXTENDED_UMID_SIZE: usize = 64;
+
+const UNIVERSAL_LABEL_PREFIX: [u8; 10] =
+    [0x06, 0x0a, 0x2b, 0x34, 0x01, 0x01, 0x01, 0x05, 0x01, 0x01];
+
+const MATERIAL_TYPE_SINGLE_AUDIO: u8 = 0x08;
+const MATERIAL_TYPE_MULTIPLE_AUDIO: u8 = 0x09;
+
+// Material number generated per the UUID/UL method, no instance number
+// method as the generated UMID always identifies an original
+const NUMBER_CREATION_METHOD: u8 = 0x20;
+
+const LENGTH_BASIC: u8 = 0x13;
+const LENGTH_EXTENDED: u8 = 0x33;
+
+// Days between 1858-11-17 (MJD epoch) and 1970-01-01
+const MJD_UNIX_EPOCH: u64 = 40587;
+
+#[derive(Debug, Default)]
+pub struct SourcePack {
+    pub country: String,
+    pub organization: String,
+    pub user: String,
+}
+
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    let mut bytes = Vec::with_capacity(len + 8);
+    while bytes.len() < len {
+        // every RandomState is seeded with fresh keys
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        hasher.write_u32(std::process::id());
+        hasher.write_usize(bytes.len());
+        bytes.extend_from_slice(&hasher.finish().to_le_bytes());
+    }
+    bytes.truncate(len);
+    bytes
+}
+
+fn bcd(value: u64) -> u8 {
+    (((value / 10) % 10) << 4 | (value % 10)) as u8
+}
+
+fn code_bytes(code: &str) -> [u8; 4] {
+    let mut bytes = [0u8; 4];
+    for (dst, src) in bytes.iter_mut().zip(code.bytes()) {
+        *dst = src;
+    }
+    bytes
+}
+
+// Time/date as a SMPTE 12M time code (frames, seconds, minutes, hours in BCD)
+// followed by the Modified Julian Date in BCD and a zero time zone code (UTC)
+fn time_date(now: SystemTime) -> [u8; 8] {
+    let seconds = now
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let time_of_day = seconds % 86400;
+    let mjd = seconds / 86400 + MJD_UNIX_EPOCH;
+    [
+        0,
+        bcd(time_of_day % 60),
+        bcd(time_of_day / 60 % 60),
+        bcd(time_of_day / 3600),
+        bcd(mjd / 10000),
+        bcd(mjd / 100),
+        bcd(mjd),
+        0,
+    ]
+}
+
+pub fn generate(number_of_channels: u16, source_pack: Option<&SourcePack>) -> Vec<u8> {
+    let mut umid = Vec::with_capacity(EXTENDED_UMID_SIZE);
+    umid.extend_from_slice(&UNIVERSAL_LABEL_PREFIX);
+    umid.push(if number_of_channels > 1 {
+        MATERIAL_TYPE_MULTIPLE_AUDIO
+    } else {
+        MATERIAL_TYPE_SINGLE_AUDIO
+    });
+    umid.push(NUMBER_CREATION_METHOD);
+    umid.push(if source_pack.is_some() {
+        LENGTH_EXTENDED
+    } else {
+        LENGTH_BASIC
+    });
+    umid.extend_from_slice(&[0, 0, 0]);
+
+    // random (version 4) UUID
+    let mut material_number = random_bytes(16);
+    material_number[6] = (material_number[6] & 0x0f) | 0x40;
+    material_number[8] = (material_number[8] & 0x3f) | 0x80;
+    umid.extend_from_slice(&material_number);
+
+    if let Some(source_pack) = source_pack {
+        umid.extend_from_slice(&time_date(SystemTime::now()));
+        // spatial coordinates are not known
+        umid.extend_from_slice(&[0; 12]);
+        umid.extend_from_slice(&code_bytes(&source_pack.country));
+        umid.extend_from_slice(&code_bytes(&source_pack.organization));
+        umid.extend_from_slice(&code_bytes(&source_pack.user));
+    }
+    umid
+}
+
+// A UMID read from a file
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Umid {
+    pub universal_label: [u8; 12],
+    pub instance_number: u32,
+    pub material_number: [u8; 16],
+    pub source_pack: Option<DecodedSourcePack>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodedSourcePack {
+    // UTC, None if not set or not valid BCD
+    pub time_date: Option<DateTime>,
+    // altitude, longitude and latitude; kept as they are
+    pub spatial_coordinates: [u8; 12],
+    pub country: String,
+    pub organization: String,
+    pub user: String,
+}
+
+impl Umid {
+    pub fn is_extended(&self) -> bool {
+        self.source_pack.is_some()
+    }
+
+    pub fn material_type(&self) -> &'static str {
+        match self.universal_label[10] {
+            0x01 => "picture",
+            0x02 => "audio",
+            0x03 => "data",
+            0x04 => "other",
+            0x05 => "single picture component",
+            0x06 => "multiple picture components",
+            0x08 => "single audio component",
+            0x09 => "multiple audio components",
+            0x0b => "single auxiliary component",
+            0x0c => "multiple auxiliary components",
+            0x0d => "mixed components",
+            0x0f => "not identified",
+            _ => "reserved",
+        }
+    }
+
+    // Method the material number was generated with, upper nibble
+    pub fn material_number_method(&self) -> &'static str {
+        match self.universal_label[11] >> 4 {
+            0 => "undefined",
+            1 => "SMPTE",
+            2 => "UUID/UL",
+            3 => "masked",
+            4 => "IEEE 1394",
+            _ => "reserved",
+        }
+    }
+
+    // Method the instance number was generated with, lower nibble
+    pub fn instance_number_method(&self) -> &'static str {
+        match self.universal_label[11] & 0x0f {
+            0 => "local registration",
+            1 => "24-bit PRS",
+            2 => "24-bit pseudo random",
+            0x0f => "live stream",
+            _ => "reserved",
+        }
+    }
+}
+
+fn from_bcd(byte: u8) -> Option<u64> {
+    let (high, low) = (byte >> 4, byte & 0x0f);
+    (high < 10 && low < 10).then(|| high as u64 * 10 + low as u64)
+}
+
+// Inverse of time_date(); the frames are ignored
+fn parse_time_date(bytes: &[u8]) -> Option<DateTime> {
+    if bytes.iter().all(|byte| *byte == 0) {
+        return None;
+    }
+    let second = from_bcd(bytes[1] & 0x7f)?;
+    let minute = from_bcd(bytes[2] & 0x7f)?;
+    let hour = from_bcd(bytes[3] & 0x3f)?;
+    let mjd = from_bcd(bytes[4])? * 10000 + from_bcd(bytes[5])? * 100 + from_bcd(bytes[6])?;
+    if second > 59 || minute > 59 || hour > 23 {
+        return None;
+    }
+    let days = mjd as i64 - MJD_UNIX_EPOCH as i64;
+    Some(DateTime::from_unix_seconds(
+        days * 86400 + (hour * 3600 + minute * 60 + second) as i64,
+    ))
+}
+
+fn code_text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches(char::from(0))
+        .to_string()
+}
+
+// Parses the UMID field of bext; None if it's blank or doesn't start with
+// the UMID universal label
+pub fn parse(field: &[u8]) -> Option<Umid> {
+    if field.len() < 32 || field[0..10] != UNIVERSAL_LABEL_PREFIX {
+        return None;
+    }
+    let extended = field[12] == LENGTH_EXTENDED
+        && field.len() >= EXTENDED_UMID_SIZE
+        && field[32..64].iter().any(|byte| *byte != 0);
+    let source_pack = extended.then(|| DecodedSourcePack {
+        time_date: parse_time_date(&field[32..40]),
+        spatial_coordinates: *array_ref!(field, 40, 12),
+        country: code_text(&field[52..56]),
+        organization: code_text(&field[56..60]),
+        user: code_text(&field[60..64]),
+    });
+    Some(Umid {
+        universal_label: *array_ref!(field, 0, 12),
+        instance_number: u32::from_be_bytes([0, field[13], field[14], field[15]]),
+        material_number: *array_ref!(field, 16, 16),
+        source_pack,
+    })
+}
+
+pub fn to_hex(umid: &[u8]) -> String {
+    umid.iter().map(|byte| format!("{byte:02X}")).collect()
+}
@@ -0,0 +1,303 @@
+// Checks of the structure and metadata of a WAV file. Errors make the
+// file unreliable to read; warnings are deviations from the
+// specifications most readers cope with.
+use crate::riff::{self, ChunkInfo, Ds64};
+use crate::{adtl, lint, read_wave_with, zip, Error, ParseMode};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+fn error(message: String) -> Finding {
+    Finding {
+        severity: Severity::Error,
+        message,
+    }
+}
+
+fn warning(message: String) -> Finding {
+    Finding {
+        severity: Severity::Warning,
+        message,
+    }
+}
+
+fn name(id: &[u8; 4]) -> String {
+    String::from_utf8_lossy(id).trim_end().to_string()
+}
+
+// Chunk ids are four printable ASCII characters
+fn is_fourcc(id: &[u8]) -> bool {
+    id.len() == 4
+        && id
+            .iter()
+            .all(|byte| byte.is_ascii_graphic() || *byte == b' ')
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, buffer: &mut [u8]) -> bool {
+    reader.seek(SeekFrom::Start(offset)).is_ok() && reader.read_exact(buffer).is_ok()
+}
+
+// What walking the chunks without trusting them found
+struct Structure {
+    chunks: Vec<ChunkInfo>,
+    errors: bool,
+}
+
+// Walks the chunks as far as they make sense: sizes against the file
+// length, pad bytes, chunk ids, duplicate and misplaced chunks. None if
+// it isn't a WAV file at all, which parsing reports.
+fn check_structure<R: Read + Seek>(
+    reader: &mut R,
+    findings: &mut Vec<Finding>,
+) -> Result<Option<Structure>, Error> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    let mut header = [0u8; 12];
+    if !read_at(reader, 0, &mut header)
+        || !riff::is_riff_signature(array_ref!(header, 0, 4))
+        || &header[8..12] != b"WAVE"
+    {
+        return Ok(None);
+    }
+    let first = findings.len();
+    let mut structure = Structure {
+        chunks: Vec::new(),
+        errors: false,
+    };
+    let mut riff_size = u32::from_le_bytes(*array_ref!(header, 4, 4)) as u64;
+    let mut ds64: Option<Ds64> = None;
+    // whatever follows the RIFF chunk isn't part of the file
+    let mut riff_end = file_size.min(riff_size + 8);
+    let mut offset = 12u64;
+    let mut chunk_header = [0u8; 8];
+    while offset + 8 <= riff_end && read_at(reader, offset, &mut chunk_header) {
+        let id = *array_ref!(chunk_header, 0, 4);
+        if !is_fourcc(&id) {
+            findings.push(error(format!(
+                "Unreadable chunk id at offset {offset}, the rest of the file is lost"
+            )));
+            offset = riff_end;
+            break;
+        }
+        let size = u32::from_le_bytes(*array_ref!(chunk_header, 4, 4));
+        let size = match &ds64 {
+            Some(ds64) => ds64.chunk_size(&id, size),
+            None => size as u64,
+        };
+        let info = ChunkInfo {
+            id,
+            offset: offset + 8,
+            size,
+        };
+        // a size from ds64 can take the end past 2^64
+        let end = info.offset.saturating_add(size);
+        if end > file_size {
+            findings.push(error(format!(
+                "{} chunk at offset {offset} runs {} bytes past the end of the file",
+                name(&id),
+                size - (file_size - info.offset)
+            )));
+            offset = riff_end;
+            break;
+        }
+        if &id == b"ds64" && structure.chunks.is_empty() {
+            ds64 = Ds64::parse(&riff::read_payload(reader, &info)?);
+            if let Some(ds64) = ds64.as_ref().filter(|_| riff_size == u32::MAX as u64) {
+                riff_size = ds64.riff_size;
+                riff_end = file_size.min(riff_size.saturating_add(8));
+            }
+        }
+        let seen = structure.chunks.iter().any(|chunk| chunk.id == id);
+        match &id {
+            b"fmt " | b"data" if seen => {
+                findings.push(error(format!("File has more than one {} chunk", name(&id))))
+            }
+            b"cue " | b"bext" if seen => findings.push(warning(format!(
+                "File has more than one {} chunk",
+                name(&id)
+            ))),
+            _ => (),
+        }
+        if &id == b"data" && !structure.chunks.iter().any(|chunk| &chunk.id == b"fmt ") {
+            findings.push(warning(String::from(
+                "data chunk comes before the fmt chunk",
+            )));
+        }
+        structure.chunks.push(info);
+        offset = end;
+        // chunks are word aligned, with a pad byte after odd sizes
+        if size & 1 == 1 {
+            let mut next = [0u8; 4];
+            if end >= riff_end {
+                findings.push(warning(format!(
+                    "{} chunk has an odd size and no pad byte at the end of the file",
+                    name(&id)
+                )));
+            } else if read_at(reader, end, &mut next) && next[0] != 0 {
+                // a chunk id right where the pad byte should be
+                if is_fourcc(&next) {
+                    findings.push(error(format!(
+                        "{} chunk has an odd size but no pad byte, readers skipping one misread the chunks after it",
+                        name(&id)
+                    )));
+                    continue;
+                }
+                findings.push(warning(format!(
+                    "Pad byte after the {} chunk is not zero",
+                    name(&id)
+                )));
+                offset += 1;
+            } else {
+                offset += 1;
+            }
+        }
+    }
+    if offset < riff_end {
+        findings.push(warning(format!(
+            "{} stray bytes at the end of the RIFF chunk",
+            riff_end - offset
+        )));
+    }
+    if riff_size + 8 > file_size {
+        findings.push(error(format!(
+            "RIFF size says the file has {} bytes, but it has {file_size}",
+            riff_size + 8
+        )));
+    } else if riff_size + 8 < file_size {
+        findings.push(warning(format!(
+            "RIFF size says the file has {} bytes, most readers ignore the other {}",
+            riff_size + 8,
+            file_size - riff_size - 8
+        )));
+    }
+    structure.errors = findings[first..]
+        .iter()
+        .any(|finding| finding.severity == Severity::Error);
+    Ok(Some(structure))
+}
+
+// Labels, notes and regions of cue points that aren't in the cue chunk
+fn check_orphans<R: Read + Seek>(
+    reader: &mut R,
+    chunks: &[ChunkInfo],
+    cue_ids: &[u32],
+    findings: &mut Vec<Finding>,
+) -> Result<(), Error> {
+    for info in chunks.iter().filter(|info| &info.id == b"LIST") {
+        let payload = riff::read_payload(reader, info)?;
+        if !payload.starts_with(b"adtl") {
+            continue;
+        }
+        // a broken adtl list is reported by parsing
+        let adtl = match adtl::parse(&payload) {
+            Ok(adtl) => adtl,
+            Err(_) => continue,
+        };
+        let entries = adtl
+            .labels
+            .iter()
+            .map(|(cue_id, _)| ("labl", *cue_id))
+            .chain(adtl.notes.iter().map(|(cue_id, _)| ("note", *cue_id)))
+            .chain(adtl.regions.iter().map(|region| ("ltxt", region.cue_id)));
+        for (kind, cue_id) in entries {
+            if !cue_ids.contains(&cue_id) {
+                findings.push(warning(format!(
+                    "{kind} for cue id {cue_id}, which has no cue point"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+// All the checks, structure first. With ParseMode::Lenient the cue
+// points are checked as far as they could be read, and with
+// ParseMode::Strict every finding is an error.
+pub fn validate(filename: &str, mode: ParseMode) -> Result<Vec<Finding>, Error> {
+    let mut findings = check(filename, mode)?;
+    if mode == ParseMode::Strict {
+        for finding in &mut findings {
+            finding.severity = Severity::Error;
+        }
+    }
+    Ok(findings)
+}
+
+fn check(filename: &str, mode: ParseMode) -> Result<Vec<Finding>, Error> {
+    let mode = match mode {
+        // the structure checks cover what strict parsing rejects
+        ParseMode::Strict => ParseMode::Normal,
+        mode => mode,
+    };
+    let mut findings = Vec::new();
+    let mut reader = zip::open(filename)?;
+    let structure = check_structure(&mut reader, &mut findings)?;
+    let structural_errors = structure.as_ref().is_some_and(|structure| structure.errors);
+    let wave = match read_wave_with(zip::open(filename)?, mode) {
+        Ok(wave) => wave,
+        // the structural errors tell why it can't be parsed
+        Err(_) if structural_errors => return Ok(findings),
+        Err(parse_error) => {
+            findings.push(error(parse_error.to_string()));
+            return Ok(findings);
+        }
+    };
+    // what --lenient skipped, unless the structure already tells why
+    if !structural_errors {
+        for skipped in &wave.warnings {
+            findings.push(warning(skipped.to_string()));
+        }
+    }
+    let frames = wave.frames();
+    if frames.is_none() {
+        findings.push(error(String::from("File has no data chunk")));
+    }
+    for (index, cue) in wave.cues.iter().enumerate() {
+        if wave.cues[..index]
+            .iter()
+            .any(|other| other.cue_id == cue.cue_id)
+        {
+            findings.push(error(format!(
+                "Cue id {} is used more than once",
+                cue.cue_id
+            )));
+        }
+        if let Some(frames) = frames.filter(|frames| cue.sample_start as u64 > *frames) {
+            findings.push(error(format!(
+                "Cue point {} at sample {} is past the end of the audio ({frames} frames)",
+                cue.cue_id, cue.sample_start
+            )));
+        }
+    }
+    if let Some(structure) = &structure {
+        let cue_ids: Vec<u32> = wave.cues.iter().map(|cue| cue.cue_id).collect();
+        check_orphans(&mut reader, &structure.chunks, &cue_ids, &mut findings)?;
+    }
+    // lint parses the file again, which can only fail as above
+    if let Ok(lint_findings) = lint::lint(filename) {
+        for finding in lint_findings {
+            let fix = if finding.fixable { " (lint --fix)" } else { "" };
+            findings.push(warning(format!("{}{fix}", finding.message)));
+        }
+    }
+    Ok(findings)
+}
@@ -0,0 +1,341 @@
+// Document model for the structured output formats
+use std::fmt::Write;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value {
+        Value::String(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value {
+        Value::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Value {
+        Value::Float(value)
+    }
+}
+
+macro_rules! from_integer {
+    ($($type:ty),*) => {
+        $(impl From<$type> for Value {
+            fn from(value: $type) -> Value {
+                Value::Integer(value as i64)
+            }
+        })*
+    };
+}
+
+from_integer!(u8, u16, u32, u64, i32, i64, usize);
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        match value {
+            Some(value) => value.into(),
+            None => Value::Null,
+        }
+    }
+}
+
+// Builds an object preserving the order of the fields
+#[derive(Default)]
+pub struct ObjectBuilder {
+    fields: Vec<(String, Value)>,
+}
+
+impl ObjectBuilder {
+    pub fn new() -> ObjectBuilder {
+        ObjectBuilder::default()
+    }
+
+    pub fn field<V: Into<Value>>(mut self, name: &str, value: V) -> ObjectBuilder {
+        self.fields.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn build(self) -> Value {
+        Value::Object(self.fields)
+    }
+}
+
+fn quote(text: &str) -> String {
+    let mut result = String::with_capacity(text.len() + 2);
+    result.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(result, "\\u{:04x}", ch as u32);
+            }
+            ch => result.push(ch),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn yaml_key(name: &str) -> String {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-')
+    {
+        name.to_string()
+    } else {
+        quote(name)
+    }
+}
+
+fn float(value: f64) -> String {
+    if value.is_finite() {
+        let text = format!("{value}");
+        if text.contains('.') || text.contains('e') {
+            text
+        } else {
+            format!("{text}.0")
+        }
+    } else {
+        String::from("null")
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some(String::from("null")),
+        Value::Bool(value) => Some(value.to_string()),
+        Value::Integer(value) => Some(value.to_string()),
+        Value::Float(value) => Some(float(*value)),
+        Value::String(value) => Some(quote(value)),
+        Value::Array(values) if values.is_empty() => Some(String::from("[]")),
+        Value::Object(fields) if fields.is_empty() => Some(String::from("{}")),
+        _ => None,
+    }
+}
+
+impl Value {
+    pub fn to_json(&self) -> String {
+        let mut output = String::new();
+        self.write_json(&mut output, 0);
+        output.push('\n');
+        output
+    }
+
+    fn write_json(&self, output: &mut String, indent: usize) {
+        if let Some(scalar) = scalar(self) {
+            output.push_str(&scalar);
+            return;
+        }
+        let padding = "  ".repeat(indent + 1);
+        match self {
+            Value::Array(values) => {
+                output.push_str("[\n");
+                for (index, value) in values.iter().enumerate() {
+                    output.push_str(&padding);
+                    value.write_json(output, indent + 1);
+                    output.push_str(if index + 1 < values.len() {
+                        ",\n"
+                    } else {
+                        "\n"
+                    });
+                }
+                output.push_str(&"  ".repeat(indent));
+                output.push(']');
+            }
+            Value::Object(fields) => {
+                output.push_str("{\n");
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    output.push_str(&padding);
+                    output.push_str(&quote(name));
+                    output.push_str(": ");
+                    value.write_json(output, indent + 1);
+                    output.push_str(if index + 1 < fields.len() {
+                        ",\n"
+                    } else {
+                        "\n"
+                    });
+                }
+                output.push_str(&"  ".repeat(indent));
+                output.push('}');
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn to_yaml(&self) -> String {
+        let mut output = String::new();
+        match scalar(self) {
+            Some(scalar) => {
+                output.push_str(&scalar);
+                output.push('\n');
+            }
+            None => self.write_yaml(&mut output, 0),
+        }
+        output
+    }
+
+    // Writes a non-scalar value as a block; strings are always quoted
+    fn write_yaml(&self, output: &mut String, indent: usize) {
+        let padding = "  ".repeat(indent);
+        match self {
+            Value::Array(values) => {
+                for value in values {
+                    output.push_str(&padding);
+                    output.push('-');
+                    match scalar(value) {
+                        Some(scalar) => {
+                            output.push(' ');
+                            output.push_str(&scalar);
+                            output.push('\n');
+                        }
+                        None => {
+                            // the first line of the block goes after the dash
+                            let mut block = String::new();
+                            value.write_yaml(&mut block, indent + 1);
+                            output.push(' ');
+                            output.push_str(&block[padding.len() + 2..]);
+                        }
+                    }
+                }
+            }
+            Value::Object(fields) => {
+                for (name, value) in fields {
+                    output.push_str(&padding);
+                    output.push_str(&yaml_key(name));
+                    output.push(':');
+                    match scalar(value) {
+                        Some(scalar) => {
+                            output.push(' ');
+                            output.push_str(&scalar);
+                            output.push('\n');
+                        }
+                        None => {
+                            output.push('\n');
+                            value.write_yaml(output, indent + 1);
+                        }
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Top level value must be an object. TOML has no null, so null fields
+    // are left out; arrays of objects become arrays of tables.
+    pub fn to_toml(&self) -> String {
+        let mut output = String::new();
+        if let Value::Object(fields) = self {
+            write_toml_table(&mut output, &[], fields);
+        }
+        output
+    }
+
+    // Top level value must be an object, whose arrays of objects get
+    // sheets of their own
+    pub fn to_xlsx(&self) -> Vec<u8> {
+        crate::xlsx::workbook(&crate::xlsx::sheets(self))
+    }
+}
+
+fn toml_key(name: &str) -> String {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-')
+    {
+        name.to_string()
+    } else {
+        quote(name)
+    }
+}
+
+fn is_table(value: &Value) -> bool {
+    matches!(value, Value::Object(fields) if !fields.is_empty())
+}
+
+fn is_table_array(value: &Value) -> bool {
+    matches!(value, Value::Array(values) if !values.is_empty() && values.iter().all(is_table))
+}
+
+// Value written on one line, with inline tables for nested objects
+fn toml_inline(value: &Value) -> String {
+    match value {
+        // null fields and elements are filtered out before getting here
+        Value::Null => String::new(),
+        Value::Bool(value) => value.to_string(),
+        Value::Integer(value) => value.to_string(),
+        Value::Float(value) if value.is_finite() => float(*value),
+        Value::Float(value) if value.is_nan() => String::from("nan"),
+        Value::Float(value) => String::from(if *value > 0.0 { "inf" } else { "-inf" }),
+        Value::String(value) => quote(value),
+        Value::Array(values) => format!(
+            "[{}]",
+            values
+                .iter()
+                .filter(|value| !matches!(value, Value::Null))
+                .map(toml_inline)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Object(fields) => format!(
+            "{{ {} }}",
+            fields
+                .iter()
+                .filter(|(_, value)| !matches!(value, Value::Null))
+                .map(|(name, value)| format!("{} = {}", toml_key(name), toml_inline(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn write_toml_table(output: &mut String, path: &[String], fields: &[(String, Value)]) {
+    // plain key/value pairs have to come before the sub-tables
+    for (name, value) in fields {
+        if !matches!(value, Value::Null) && !is_table(value) && !is_table_array(value) {
+            let _ = writeln!(output, "{} = {}", toml_key(name), toml_inline(value));
+        }
+    }
+    for (name, value) in fields {
+        let mut path = path.to_vec();
+        path.push(toml_key(name));
+        match value {
+            Value::Object(fields) if is_table(value) => {
+                let _ = writeln!(output, "\n[{}]", path.join("."));
+                write_toml_table(output, &path, fields);
+            }
+            Value::Array(values) if is_table_array(value) => {
+                for value in values {
+                    if let Value::Object(fields) = value {
+                        let _ = writeln!(output, "\n[[{}]]", path.join("."));
+                        write_toml_table(output, &path, fields);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
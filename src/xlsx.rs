@@ -0,0 +1,372 @@
+// Minimal XLSX writer for the reports: the SpreadsheetML parts in a
+// stored ZIP, with the strings inline in the cells instead of in a shared
+// string table. https://ecma-international.org/publications-and-standards/standards/ecma-376/
+// The scalar fields of the document, named by their path, go on a File
+// sheet; each array of objects, such as the markers and channels, gets a
+// sheet of its own with a column per field.
+use crate::value::Value;
+use crate::{xml, zip};
+
+// Excel's limit for sheet names
+const MAX_NAME_LENGTH: usize = 31;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Empty,
+    Bool(bool),
+    // as written, so integers stay exact
+    Number(String),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sheet {
+    pub name: String,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+fn is_table(value: &Value) -> bool {
+    matches!(value, Value::Array(values)
+        if !values.is_empty() && values.iter().all(|value| matches!(value, Value::Object(_))))
+}
+
+// The value on one line, for values that don't get columns of their own
+fn compact(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(values) => values.iter().map(compact).collect::<Vec<_>>().join(", "),
+        _ => value.to_json().lines().map(str::trim).collect(),
+    }
+}
+
+fn cell(value: &Value) -> Cell {
+    match value {
+        Value::Null => Cell::Empty,
+        Value::Bool(value) => Cell::Bool(*value),
+        Value::Integer(value) => Cell::Number(value.to_string()),
+        Value::Float(value) if value.is_finite() => Cell::Number(value.to_string()),
+        Value::Float(_) => Cell::Empty,
+        value => Cell::Text(compact(value)),
+    }
+}
+
+// The scalar fields under the path, with nested objects flattened to
+// dotted names; with tables, arrays of objects are set aside for sheets
+// of their own instead
+fn flatten<'a>(
+    path: &str,
+    value: &'a Value,
+    fields: &mut Vec<(String, Cell)>,
+    mut tables: Option<&mut Vec<(String, &'a [Value])>>,
+) {
+    match value {
+        Value::Object(members) if !members.is_empty() => {
+            for (name, value) in members {
+                let path = match path {
+                    "" => name.clone(),
+                    _ => format!("{path}.{name}"),
+                };
+                flatten(&path, value, fields, tables.as_deref_mut());
+            }
+        }
+        Value::Array(values) if is_table(value) && tables.is_some() => {
+            if let Some(tables) = tables {
+                tables.push((path.to_string(), values));
+            }
+        }
+        value => fields.push((path.to_string(), cell(value))),
+    }
+}
+
+fn table(name: &str, rows: &[Value]) -> Sheet {
+    let rows: Vec<Vec<(String, Cell)>> = rows
+        .iter()
+        .map(|row| {
+            let mut fields = Vec::new();
+            flatten("", row, &mut fields, None);
+            fields
+        })
+        .collect();
+    let mut columns: Vec<String> = Vec::new();
+    for (column, _) in rows.iter().flatten() {
+        if !columns.contains(column) {
+            columns.push(column.clone());
+        }
+    }
+    // an object that is null in some rows has columns for its fields
+    let nested: Vec<String> = columns.iter().map(|column| format!("{column}.")).collect();
+    columns = columns
+        .iter()
+        .zip(&nested)
+        .filter(|(_, prefix)| {
+            !columns
+                .iter()
+                .any(|column| column.starts_with(prefix.as_str()))
+        })
+        .map(|(column, _)| column.clone())
+        .collect();
+    let header = columns.iter().map(|column| Cell::Text(column.clone()));
+    let mut sheet = Sheet {
+        name: name.to_string(),
+        rows: vec![header.collect()],
+    };
+    for mut fields in rows {
+        let cells = columns.iter().map(|column| {
+            fields
+                .iter()
+                .position(|(name, _)| name == column)
+                .map_or(Cell::Empty, |index| fields.swap_remove(index).1)
+        });
+        sheet.rows.push(cells.collect());
+    }
+    sheet
+}
+
+// The sheets of a report; the top level value must be an object
+pub fn sheets(document: &Value) -> Vec<Sheet> {
+    let mut fields = Vec::new();
+    let mut tables = Vec::new();
+    flatten("", document, &mut fields, Some(&mut tables));
+    let mut sheets = vec![Sheet {
+        name: String::from("File"),
+        rows: fields
+            .into_iter()
+            .map(|(name, cell)| vec![Cell::Text(name), cell])
+            .collect(),
+    }];
+    sheets.extend(tables.iter().map(|(name, rows)| table(name, rows)));
+    sheets
+}
+
+// A, B, ... Z, AA, AB...
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.insert(0, b'A' + (index % 26) as u8);
+        match index / 26 {
+            0 => break,
+            next => index = next - 1,
+        }
+    }
+    String::from_utf8(name).unwrap_or_default()
+}
+
+// Control characters other than tab and line breaks aren't allowed in XML
+fn text(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|ch| !ch.is_control() || matches!(ch, '\t' | '\n' | '\r'))
+        .collect();
+    xml::escape(&text)
+}
+
+fn worksheet(sheet: &Sheet) -> String {
+    let mut output = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\"><sheetData>",
+    );
+    for (row, cells) in sheet.rows.iter().enumerate() {
+        output.push_str(&format!("<row r=\"{}\">", row + 1));
+        for (column, cell) in cells.iter().enumerate() {
+            let reference = format!("{}{}", column_name(column), row + 1);
+            match cell {
+                Cell::Empty => continue,
+                Cell::Bool(value) => output.push_str(&format!(
+                    "<c r=\"{reference}\" t=\"b\"><v>{}</v></c>",
+                    *value as u8
+                )),
+                Cell::Number(value) => {
+                    output.push_str(&format!("<c r=\"{reference}\"><v>{value}</v></c>"))
+                }
+                Cell::Text(value) => output.push_str(&format!(
+                    "<c r=\"{reference}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+                    text(value)
+                )),
+            }
+        }
+        output.push_str("</row>");
+    }
+    output.push_str("</sheetData></worksheet>\n");
+    output
+}
+
+// Sheet names are cut to the length Excel allows, made unique and cleared
+// of the characters it doesn't allow in them
+fn sheet_names(sheets: &[Sheet]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for sheet in sheets {
+        let base: String = sheet
+            .name
+            .chars()
+            .map(|ch| match ch {
+                '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+                ch => ch,
+            })
+            .take(MAX_NAME_LENGTH)
+            .collect();
+        let mut name = base.clone();
+        let mut number = 2;
+        while names.iter().any(|other| other.eq_ignore_ascii_case(&name)) {
+            let suffix = format!(" ({number})");
+            let kept = base.chars().take(MAX_NAME_LENGTH - suffix.len());
+            name = kept.chain(suffix.chars()).collect();
+            number += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+// The workbook as the bytes of an .xlsx file
+pub fn workbook(sheets: &[Sheet]) -> Vec<u8> {
+    const HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+    const RELATIONSHIPS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+    const DOCUMENT: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+    const SPREADSHEET: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml";
+    let names = sheet_names(sheets);
+    let mut content_types = format!(
+        "{HEADER}<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\"><Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/><Default Extension=\"xml\" ContentType=\"application/xml\"/><Override PartName=\"/xl/workbook.xml\" ContentType=\"{SPREADSHEET}.sheet.main+xml\"/>"
+    );
+    let mut workbook = format!(
+        "{HEADER}<workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" xmlns:r=\"{DOCUMENT}\"><sheets>"
+    );
+    let mut relationships = format!("{HEADER}<Relationships xmlns=\"{RELATIONSHIPS}\">");
+    let mut parts = Vec::new();
+    for (index, (sheet, name)) in sheets.iter().zip(&names).enumerate() {
+        let number = index + 1;
+        content_types.push_str(&format!(
+            "<Override PartName=\"/xl/worksheets/sheet{number}.xml\" ContentType=\"{SPREADSHEET}.worksheet+xml\"/>"
+        ));
+        workbook.push_str(&format!(
+            "<sheet name=\"{}\" sheetId=\"{number}\" r:id=\"rId{number}\"/>",
+            xml::escape(name)
+        ));
+        relationships.push_str(&format!(
+            "<Relationship Id=\"rId{number}\" Type=\"{DOCUMENT}/worksheet\" Target=\"worksheets/sheet{number}.xml\"/>"
+        ));
+        parts.push((
+            format!("xl/worksheets/sheet{number}.xml"),
+            worksheet(sheet).into_bytes(),
+        ));
+    }
+    content_types.push_str("</Types>\n");
+    workbook.push_str("</sheets></workbook>\n");
+    relationships.push_str("</Relationships>\n");
+    let package = format!(
+        "{HEADER}<Relationships xmlns=\"{RELATIONSHIPS}\"><Relationship Id=\"rId1\" Type=\"{DOCUMENT}/officeDocument\" Target=\"xl/workbook.xml\"/></Relationships>\n"
+    );
+    let mut members = vec![
+        ("[Content_Types].xml", content_types.into_bytes()),
+        ("_rels/.rels", package.into_bytes()),
+        ("xl/workbook.xml", workbook.into_bytes()),
+        ("xl/_rels/workbook.xml.rels", relationships.into_bytes()),
+    ];
+    members.extend(
+        parts
+            .iter()
+            .map(|(name, data)| (name.as_str(), data.clone())),
+    );
+    zip::write_stored(&members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::ObjectBuilder;
+
+    fn text(text: &str) -> Cell {
+        Cell::Text(text.to_string())
+    }
+
+    fn number(number: &str) -> Cell {
+        Cell::Number(number.to_string())
+    }
+
+    #[test]
+    fn names_columns() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(27), "AB");
+        assert_eq!(column_name(701), "ZZ");
+        assert_eq!(column_name(702), "AAA");
+    }
+
+    #[test]
+    fn puts_tables_on_sheets_of_their_own() {
+        let marker = |id: i64, label: Option<&str>, region: Option<i64>| {
+            let region = region.map(|length| {
+                ObjectBuilder::new()
+                    .field("sample_length", Value::Integer(length))
+                    .build()
+            });
+            ObjectBuilder::new()
+                .field("id", Value::Integer(id))
+                .field("label", label)
+                .field("region", region.unwrap_or(Value::Null))
+                .build()
+        };
+        let document = ObjectBuilder::new()
+            .field("filename", "take.wav")
+            .field(
+                "header",
+                ObjectBuilder::new()
+                    .field("sampling_rate", Value::Integer(48000))
+                    .build(),
+            )
+            .field(
+                "markers",
+                Value::Array(vec![
+                    marker(1, Some("Intro"), Some(100)),
+                    marker(2, None, None),
+                ]),
+            )
+            .field("warnings", Value::Array(Vec::new()))
+            .build();
+        let sheets = sheets(&document);
+        assert_eq!(sheets.len(), 2);
+        assert_eq!(
+            sheets[0].rows,
+            vec![
+                vec![text("filename"), text("take.wav")],
+                vec![text("header.sampling_rate"), number("48000")],
+                vec![text("warnings"), text("")],
+            ]
+        );
+        assert_eq!(sheets[1].name, "markers");
+        assert_eq!(
+            sheets[1].rows,
+            vec![
+                vec![text("id"), text("label"), text("region.sample_length")],
+                vec![number("1"), text("Intro"), number("100")],
+                vec![number("2"), Cell::Empty, Cell::Empty],
+            ]
+        );
+    }
+
+    #[test]
+    fn makes_sheet_names_unique() {
+        let sheet = |name: &str| Sheet {
+            name: name.to_string(),
+            rows: Vec::new(),
+        };
+        let long = "a".repeat(40);
+        let names = sheet_names(&[sheet("File"), sheet("file"), sheet(&long), sheet(&long)]);
+        assert_eq!(names[1], "file (2)");
+        assert_eq!(names[2].len(), MAX_NAME_LENGTH);
+        assert_eq!(names[3], format!("{} (2)", &long[..MAX_NAME_LENGTH - 4]));
+    }
+
+    #[test]
+    fn escapes_text_cells() {
+        let sheet = Sheet {
+            name: String::from("File"),
+            rows: vec![vec![text("a < b & \"c\"\u{1}"), Cell::Bool(true)]],
+        };
+        let xml = worksheet(&sheet);
+        assert!(xml.contains(
+            "<c r=\"A1\" t=\"inlineStr\"><is><t xml:space=\"preserve\">a &lt; b &amp; &quot;c&quot;</t></is></c>"
+        ));
+        assert!(xml.contains("<c r=\"B1\" t=\"b\"><v>1</v></c>"));
+    }
+}
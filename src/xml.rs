@@ -0,0 +1,351 @@
+// Minimal XML reader for the metadata chunks (iXML and friends). It builds an
+// element tree and handles comments, processing instructions, CDATA and the
+// predefined and numeric entities; DTDs and namespaces are not interpreted.
+use crate::{Error, WaveError};
+
+#[derive(Debug, Clone, Default)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+impl Element {
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    // Trimmed text of a child element
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+
+    pub fn new(name: &str) -> Element {
+        Element {
+            name: name.to_string(),
+            ..Element::default()
+        }
+    }
+
+    pub fn with_attribute(mut self, name: &str, value: &str) -> Element {
+        self.set_attribute(name, value);
+        self
+    }
+
+    pub fn with_text(mut self, text: &str) -> Element {
+        self.text = text.to_string();
+        self
+    }
+
+    pub fn with_child(mut self, child: Element) -> Element {
+        self.children.push(child);
+        self
+    }
+
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        match self
+            .attributes
+            .iter_mut()
+            .find(|(attribute, _)| attribute == name)
+        {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.attributes.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    // Replaces the children with the name by the new one, keeping its place
+    // among the other children
+    pub fn replace_child(&mut self, child: Element) {
+        match self
+            .children
+            .iter()
+            .position(|existing| existing.name == child.name)
+        {
+            Some(index) => {
+                self.children.retain(|existing| existing.name != child.name);
+                self.children.insert(index.min(self.children.len()), child);
+            }
+            None => self.children.push(child),
+        }
+    }
+
+    // Indented document; the text of elements with children is trimmed, as
+    // it's only whitespace in the documents this is used for
+    pub fn to_xml(&self) -> String {
+        let mut output = String::new();
+        self.write(&mut output, 0);
+        output
+    }
+
+    fn write(&self, output: &mut String, indent: usize) {
+        let padding = " ".repeat(indent);
+        output.push_str(&padding);
+        output.push('<');
+        output.push_str(&self.name);
+        for (name, value) in &self.attributes {
+            output.push_str(&format!(" {name}=\"{}\"", escape(value)));
+        }
+        let text = if self.children.is_empty() {
+            self.text.as_str()
+        } else {
+            self.text.trim()
+        };
+        if self.children.is_empty() && text.is_empty() {
+            output.push_str("/>\n");
+            return;
+        }
+        output.push('>');
+        output.push_str(&escape(text));
+        if !self.children.is_empty() {
+            output.push('\n');
+            for child in &self.children {
+                child.write(output, indent + 1);
+            }
+            output.push_str(&padding);
+        }
+        output.push_str(&format!("</{}>\n", self.name));
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+fn error(message: String) -> Error {
+    Error::from(WaveError {
+        message: format!("Malformed XML: {message}"),
+    })
+}
+
+pub fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) if end < 12 => end,
+            _ => {
+                result.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => {
+                if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+                } else if let Some(decimal) = entity.strip_prefix('#') {
+                    decimal.parse().ok().and_then(char::from_u32)
+                } else {
+                    None
+                }
+            }
+        };
+        match decoded {
+            Some(ch) => {
+                result.push(ch);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+pub fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '&' => result.push_str("&amp;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            ch => result.push(ch),
+        }
+    }
+    result
+}
+
+// Replaces the content of every element with the given name, leaving the
+// rest of the document byte for byte as it was
+pub fn replace_element_text(input: &str, name: &str, replacement: &str) -> (String, usize) {
+    let mut result = String::with_capacity(input.len());
+    let mut rest = input;
+    let mut count = 0;
+    let open = format!("<{name}");
+    let close = format!("</{name}>");
+    while let Some(start) = rest.find(&open) {
+        let after_name = &rest[start + open.len()..];
+        let tag_end = match after_name.find('>') {
+            Some(tag_end) => tag_end,
+            None => break,
+        };
+        // a longer name sharing the prefix, or an empty element
+        let boundary = after_name.chars().next();
+        if !matches!(
+            boundary,
+            Some('>') | Some(' ') | Some('\t') | Some('\r') | Some('\n')
+        ) || after_name[..tag_end].ends_with('/')
+        {
+            let skip = start + open.len();
+            result.push_str(&rest[..skip]);
+            rest = &rest[skip..];
+            continue;
+        }
+        let content_start = start + open.len() + tag_end + 1;
+        let content_end = match rest[content_start..].find(&close) {
+            Some(end) => content_start + end,
+            None => break,
+        };
+        result.push_str(&rest[..content_start]);
+        result.push_str(&escape(replacement));
+        result.push_str(&close);
+        rest = &rest[content_end + close.len()..];
+        count += 1;
+    }
+    result.push_str(rest);
+    (result, count)
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_until(&mut self, terminator: &str) -> Result<&'a str, Error> {
+        match self.rest().find(terminator) {
+            Some(index) => {
+                let skipped = &self.rest()[..index];
+                self.pos += index + terminator.len();
+                Ok(skipped)
+            }
+            None => Err(error(format!("missing {terminator}"))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    // Skips the prolog, comments and processing instructions
+    fn skip_misc(&mut self) -> Result<(), Error> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_until("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_until("-->")?;
+            } else if self.rest().starts_with("<!") {
+                self.skip_until(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, Error> {
+        let rest = self.rest();
+        let end = rest
+            .find(|ch: char| ch.is_whitespace() || ch == '>' || ch == '/' || ch == '=')
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return Err(error(format!("expected a name at offset {}", self.pos)));
+        }
+        self.pos += end;
+        Ok(rest[..end].to_string())
+    }
+
+    fn element(&mut self) -> Result<Element, Error> {
+        if !self.rest().starts_with('<') {
+            return Err(error(format!("expected an element at offset {}", self.pos)));
+        }
+        self.pos += 1;
+        let mut element = Element {
+            name: self.name()?,
+            ..Element::default()
+        };
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            } else if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let name = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(error(format!("attribute {name} has no value")));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(quote) if quote == '"' || quote == '\'' => quote,
+                _ => return Err(error(format!("attribute {name} is not quoted"))),
+            };
+            self.pos += 1;
+            let value = self.skip_until(&quote.to_string())?;
+            element.attributes.push((name, unescape(value)));
+        }
+
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.pos += 2;
+                let name = self.name()?;
+                if name != element.name {
+                    return Err(error(format!(
+                        "</{name}> does not close <{}>",
+                        element.name
+                    )));
+                }
+                self.skip_until(">")?;
+                return Ok(element);
+            } else if rest.starts_with("<!--") {
+                self.skip_until("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += 9;
+                let text = self.skip_until("]]>")?;
+                element.text.push_str(text);
+            } else if rest.starts_with("<?") {
+                self.skip_until("?>")?;
+            } else if rest.starts_with('<') {
+                element.children.push(self.element()?);
+            } else if rest.is_empty() {
+                return Err(error(format!("<{}> is not closed", element.name)));
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                element.text.push_str(&unescape(&rest[..end]));
+                self.pos += end;
+            }
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Element, Error> {
+    // chunks are often padded with NULs
+    let input = input.trim_start_matches('\u{feff}').trim_end_matches('\0');
+    let mut parser = Parser { input, pos: 0 };
+    parser.skip_misc()?;
+    parser.element()
+}
@@ -0,0 +1,197 @@
+// XMP metadata in the _PMX chunk, as read and written by Adobe applications.
+// Only dc:title, dc:creator and the xmpDM marker track are managed; other
+// properties of an existing packet are kept. The markers of every track
+// are read, so those Audition and Premiere set show up as cue points.
+use crate::xml::{self, Element};
+use crate::Error;
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const DC_NS: &str = "http://purl.org/dc/elements/1.1/";
+const XMP_DM_NS: &str = "http://ns.adobe.com/xmp/1.0/DynamicMedia/";
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Marker {
+    pub name: String,
+    // in samples
+    pub start_time: u64,
+    pub duration: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Update {
+    pub title: Option<String>,
+    pub creator: Option<String>,
+    pub markers: Option<Vec<Marker>>,
+}
+
+fn find<'a>(element: &'a Element, name: &str) -> Option<&'a Element> {
+    if element.name == name {
+        return Some(element);
+    }
+    element.children.iter().find_map(|child| find(child, name))
+}
+
+// A property of a resource, written either as an attribute or as a child
+// element, possibly of an rdf:Description inside it
+fn property(element: &Element, name: &str) -> Option<String> {
+    if let Some((_, value)) = element.attributes.iter().find(|(key, _)| key == name) {
+        return Some(value.trim().to_string());
+    }
+    if let Some(text) = element.child_text(name) {
+        return Some(text.to_string());
+    }
+    element
+        .child("rdf:Description")
+        .and_then(|description| property(description, name))
+}
+
+// Frames per second of an xmpDM frameRate such as f48000 or f30000s1001,
+// as a fraction
+fn frame_rate(text: &str) -> Option<(u64, u64)> {
+    let text = text.strip_prefix('f')?;
+    let (numerator, denominator) = text.split_once('s').unwrap_or((text, "1"));
+    match (numerator.parse(), denominator.parse()) {
+        (Ok(numerator), Ok(denominator)) if numerator > 0 && denominator > 0 => {
+            Some((numerator, denominator))
+        }
+        _ => None,
+    }
+}
+
+// Markers of all the tracks of xmpDM:Tracks, in samples; times are counted
+// in frames of the track's frameRate, samples if it has none
+pub fn markers(data: &[u8], sampling_rate: u32) -> Result<Vec<Marker>, Error> {
+    let root = xml::parse(&String::from_utf8_lossy(data))?;
+    let tracks = match find(&root, "xmpDM:Tracks") {
+        Some(tracks) => tracks,
+        None => return Ok(Vec::new()),
+    };
+    let mut markers = Vec::new();
+    for track in tracks
+        .children
+        .iter()
+        .flat_map(|bag| bag.children_named("rdf:li"))
+    {
+        let rate = property(track, "xmpDM:frameRate")
+            .and_then(|rate| frame_rate(&rate))
+            .unwrap_or((sampling_rate as u64, 1));
+        let items = find(track, "xmpDM:markers")
+            .into_iter()
+            .flat_map(|list| list.children.iter())
+            .flat_map(|sequence| sequence.children_named("rdf:li"));
+        for item in items {
+            // frames of the marker's own rate, if it gives one
+            let (numerator, denominator) = property(item, "xmpDM:frameRate")
+                .and_then(|rate| frame_rate(&rate))
+                .unwrap_or(rate);
+            let samples = |frames: u64| frames * denominator * sampling_rate as u64 / numerator;
+            let start_time = match property(item, "xmpDM:startTime").map(|time| time.parse()) {
+                Some(Ok(frames)) => samples(frames),
+                _ => continue,
+            };
+            let duration = property(item, "xmpDM:duration")
+                .and_then(|duration| duration.parse().ok())
+                .map_or(0, samples);
+            markers.push(Marker {
+                name: property(item, "xmpDM:name").unwrap_or_default(),
+                start_time,
+                duration,
+            });
+        }
+    }
+    Ok(markers)
+}
+
+fn find_mut<'a>(element: &'a mut Element, name: &str) -> Option<&'a mut Element> {
+    if element.name == name {
+        return Some(element);
+    }
+    element
+        .children
+        .iter_mut()
+        .find_map(|child| find_mut(child, name))
+}
+
+fn resource() -> Element {
+    Element::new("rdf:li").with_attribute("rdf:parseType", "Resource")
+}
+
+fn marker_track(markers: &[Marker], sampling_rate: u32) -> Element {
+    let mut sequence = Element::new("rdf:Seq");
+    for marker in markers {
+        let mut item = resource()
+            .with_child(Element::new("xmpDM:startTime").with_text(&marker.start_time.to_string()));
+        if marker.duration > 0 {
+            item = item
+                .with_child(Element::new("xmpDM:duration").with_text(&marker.duration.to_string()));
+        }
+        sequence
+            .children
+            .push(item.with_child(Element::new("xmpDM:name").with_text(&marker.name)));
+    }
+    // times are counted in samples, as frameRate says
+    let track = resource()
+        .with_child(Element::new("xmpDM:trackName").with_text("Markers"))
+        .with_child(Element::new("xmpDM:frameRate").with_text(&format!("f{sampling_rate}")))
+        .with_child(Element::new("xmpDM:markers").with_child(sequence));
+    Element::new("xmpDM:Tracks").with_child(Element::new("rdf:Bag").with_child(track))
+}
+
+fn empty_packet() -> Element {
+    Element::new("x:xmpmeta")
+        .with_attribute("xmlns:x", "adobe:ns:meta/")
+        .with_child(
+            Element::new("rdf:RDF")
+                .with_attribute("xmlns:rdf", RDF_NS)
+                .with_child(Element::new("rdf:Description").with_attribute("rdf:about", "")),
+        )
+}
+
+// Returns the new packet for the chunk, based on the existing one if any
+pub fn update(
+    existing: Option<&[u8]>,
+    update: &Update,
+    sampling_rate: u32,
+) -> Result<Vec<u8>, Error> {
+    let mut root = match existing {
+        Some(data) => xml::parse(&String::from_utf8_lossy(data))?,
+        None => empty_packet(),
+    };
+    let description = match find_mut(&mut root, "rdf:Description") {
+        Some(description) => description,
+        None => {
+            root = empty_packet();
+            find_mut(&mut root, "rdf:Description").expect("packet has a description")
+        }
+    };
+    if let Some(title) = &update.title {
+        description.set_attribute("xmlns:dc", DC_NS);
+        let item = Element::new("rdf:li")
+            .with_attribute("xml:lang", "x-default")
+            .with_text(title);
+        description.replace_child(
+            Element::new("dc:title").with_child(Element::new("rdf:Alt").with_child(item)),
+        );
+    }
+    if let Some(creator) = &update.creator {
+        description.set_attribute("xmlns:dc", DC_NS);
+        let item = Element::new("rdf:li").with_text(creator);
+        description.replace_child(
+            Element::new("dc:creator").with_child(Element::new("rdf:Seq").with_child(item)),
+        );
+    }
+    if let Some(markers) = &update.markers {
+        description.set_attribute("xmlns:xmpDM", XMP_DM_NS);
+        description.replace_child(marker_track(markers, sampling_rate));
+    }
+    let mut packet =
+        String::from("<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n");
+    packet.push_str(&root.to_xml());
+    packet.push_str("<?xpacket end=\"w\"?>");
+    // keep the chunk even sized
+    if packet.len() % 2 == 1 {
+        packet.push('\n');
+    }
+    Ok(packet.into_bytes())
+}
@@ -0,0 +1,326 @@
+// Reading WAV files straight from ZIP archives, as recorder cards are
+// often delivered, by giving a path like cards.zip/DAY1/ZOOM0001.WAV.
+// Stored members are read in place; deflated ones are decompressed into
+// memory, as deflate streams can't be seeked. Writing is only for the
+// small stored archives that XLSX workbooks are.
+use crate::inflate;
+use crate::png::crc32;
+use crate::stream::Forward;
+use crate::{Error, WaveError};
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub trait Input: Read + Seek {}
+
+impl<T: Read + Seek> Input for T {}
+
+static STDIN_OPENED: AtomicBool = AtomicBool::new(false);
+
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+const CENTRAL_DIRECTORY_ENTRY: u32 = 0x0201_4b50;
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const ZIP64_EXTRA_FIELD: u16 = 0x0001;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+#[derive(Debug)]
+struct Member {
+    name: String,
+    method: u16,
+    encrypted: bool,
+    compressed_size: u64,
+    size: u64,
+    local_header_offset: u64,
+}
+
+// Part of a file, seekable on its own
+struct Window<R> {
+    inner: R,
+    start: u64,
+    size: u64,
+    position: u64,
+}
+
+impl<R: Read + Seek> Read for Window<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let left = self.size.saturating_sub(self.position);
+        let wanted = (buffer.len() as u64).min(left) as usize;
+        if wanted == 0 {
+            return Ok(0);
+        }
+        self.inner
+            .seek(SeekFrom::Start(self.start + self.position))?;
+        let read = self.inner.read(&mut buffer[..wanted])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for Window<R> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) => self.size as i64 + offset,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+        if position < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the member",
+            ));
+        }
+        self.position = position as u64;
+        Ok(self.position)
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::from(WaveError { message })
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(*array_ref!(data, offset, 2))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(*array_ref!(data, offset, 4))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref!(data, offset, 8))
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, size: usize) -> Result<Vec<u8>, Error> {
+    let mut data = vec![0u8; size];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+// Offset and size of the central directory
+fn central_directory<R: Read + Seek>(reader: &mut R) -> Result<(u64, u64), Error> {
+    let length = reader.seek(SeekFrom::End(0))?;
+    // the record is 22 bytes followed by a comment of up to 64 KiB
+    let tail_size = length.min(22 + 0xffff);
+    let tail = read_at(reader, length - tail_size, tail_size as usize)?;
+    let record = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|offset| u32_at(&tail, *offset) == END_OF_CENTRAL_DIRECTORY)
+        .ok_or_else(|| invalid(String::from("Not a ZIP archive")))?;
+    let size = u32_at(&tail, record + 12);
+    let offset = u32_at(&tail, record + 16);
+    if size != u32::MAX && offset != u32::MAX {
+        return Ok((offset as u64, size as u64));
+    }
+    // ZIP64: a locator right before the record points to the real one
+    let locator = (record >= 20)
+        .then(|| record - 20)
+        .filter(|locator| u32_at(&tail, *locator) == ZIP64_LOCATOR)
+        .ok_or_else(|| invalid(String::from("ZIP64 locator not found")))?;
+    let zip64 = read_at(reader, u64_at(&tail, locator + 8), 56)?;
+    if u32_at(&zip64, 0) != ZIP64_END_OF_CENTRAL_DIRECTORY {
+        return Err(invalid(String::from(
+            "ZIP64 end of central directory not found",
+        )));
+    }
+    Ok((u64_at(&zip64, 48), u64_at(&zip64, 40)))
+}
+
+fn members<R: Read + Seek>(reader: &mut R) -> Result<Vec<Member>, Error> {
+    let (offset, size) = central_directory(reader)?;
+    let directory = read_at(reader, offset, size as usize)?;
+    let mut members = Vec::new();
+    let mut position = 0;
+    while position + 46 <= directory.len()
+        && u32_at(&directory, position) == CENTRAL_DIRECTORY_ENTRY
+    {
+        let entry = &directory[position..];
+        let name_length = u16_at(entry, 28) as usize;
+        let extra_length = u16_at(entry, 30) as usize;
+        let comment_length = u16_at(entry, 32) as usize;
+        if 46 + name_length + extra_length > entry.len() {
+            return Err(invalid(String::from("Truncated ZIP central directory")));
+        }
+        let mut member = Member {
+            name: String::from_utf8_lossy(&entry[46..46 + name_length]).to_string(),
+            method: u16_at(entry, 10),
+            encrypted: u16_at(entry, 8) & 1 == 1,
+            compressed_size: u32_at(entry, 20) as u64,
+            size: u32_at(entry, 24) as u64,
+            local_header_offset: u32_at(entry, 42) as u64,
+        };
+        // ZIP64 extra field has the values that didn't fit, in this order
+        let mut extra = &entry[46 + name_length..46 + name_length + extra_length];
+        while extra.len() >= 4 {
+            let (id, length) = (u16_at(extra, 0), u16_at(extra, 2) as usize);
+            let field = &extra[4..(4 + length).min(extra.len())];
+            if id == ZIP64_EXTRA_FIELD {
+                let mut values = field.chunks_exact(8).map(|value| u64_at(value, 0));
+                for slot in [
+                    &mut member.size,
+                    &mut member.compressed_size,
+                    &mut member.local_header_offset,
+                ] {
+                    if *slot == u32::MAX as u64 {
+                        *slot = values.next().unwrap_or(*slot);
+                    }
+                }
+            }
+            extra = &extra[(4 + length).min(extra.len())..];
+        }
+        members.push(member);
+        position += 46 + name_length + extra_length + comment_length;
+    }
+    Ok(members)
+}
+
+fn open_member(archive: &Path, name: &str) -> Result<Box<dyn Input>, Error> {
+    let mut reader = BufReader::new(File::open(archive)?);
+    let member = members(&mut reader)?
+        .into_iter()
+        .find(|member| member.name == name)
+        .ok_or_else(|| invalid(format!("{name} not found in {}", archive.display())))?;
+    if member.encrypted {
+        return Err(invalid(format!("{name} is encrypted")));
+    }
+    let header = read_at(&mut reader, member.local_header_offset, 30)?;
+    if u32_at(&header, 0) != LOCAL_HEADER {
+        return Err(invalid(format!("Local header of {name} not found")));
+    }
+    let start =
+        member.local_header_offset + 30 + u16_at(&header, 26) as u64 + u16_at(&header, 28) as u64;
+    match member.method {
+        METHOD_STORED => Ok(Box::new(Window {
+            inner: reader,
+            start,
+            size: member.size,
+            position: 0,
+        })),
+        METHOD_DEFLATED => {
+            let compressed = read_at(&mut reader, start, member.compressed_size as usize)?;
+            let data = inflate::inflate(&compressed, member.size as usize)?;
+            Ok(Box::new(Cursor::new(data)))
+        }
+        method => Err(invalid(format!(
+            "{name} uses unsupported compression method {method}"
+        ))),
+    }
+}
+
+// Opens the file, or the member of a ZIP archive when the path goes
+// through one: a path that doesn't exist is looked up from the last
+// existing .zip file along it. - is standard input.
+pub fn open(filename: &str) -> Result<Box<dyn Input>, Error> {
+    if filename == "-" {
+        // what was read from it is gone, so it can't be read twice
+        if STDIN_OPENED.swap(true, Ordering::SeqCst) {
+            return Err(Error::from(WaveError {
+                message: String::from(
+                    "Standard input can only be read once, save it to a file for this",
+                ),
+            }));
+        }
+        return Ok(Box::new(Forward::new(BufReader::new(io::stdin()))));
+    }
+    let path = Path::new(filename);
+    if path.exists() {
+        return Ok(Box::new(BufReader::new(File::open(path)?)));
+    }
+    let archive: Option<PathBuf> = path
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| {
+            ancestor.is_file()
+                && ancestor
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+        })
+        .map(Path::to_path_buf);
+    match archive {
+        Some(archive) => {
+            let name: Vec<String> = path
+                .strip_prefix(&archive)
+                .unwrap_or(path)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect();
+            open_member(&archive, &name.join("/"))
+        }
+        // reports the missing file as usual
+        None => Ok(Box::new(BufReader::new(File::open(path)?))),
+    }
+}
+
+// 1980-01-01, the earliest date a ZIP member can have
+const DOS_DATE: u16 = 0x21;
+
+// An archive of the members stored uncompressed, without ZIP64 fields
+pub fn write_stored(members: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in members {
+        let offset = archive.len() as u32;
+        // version 2.0, no flags, stored, time and date, CRC and sizes
+        let mut fields = Vec::new();
+        for value in [20, 0, METHOD_STORED, 0, DOS_DATE] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [crc32(data), data.len() as u32, data.len() as u32] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        // no extra field
+        fields.extend_from_slice(&[0, 0]);
+        archive.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        archive.extend_from_slice(&fields);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(data);
+        directory.extend_from_slice(&CENTRAL_DIRECTORY_ENTRY.to_le_bytes());
+        // made by version 2.0
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&fields);
+        // no comment, disk 0, no attributes
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+    let offset = archive.len() as u32;
+    archive.extend_from_slice(&directory);
+    archive.extend_from_slice(&END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+    archive.extend_from_slice(&[0; 4]);
+    for _ in 0..2 {
+        archive.extend_from_slice(&(members.len() as u16).to_le_bytes());
+    }
+    archive.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&offset.to_le_bytes());
+    archive.extend_from_slice(&[0, 0]);
+    archive
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_what_it_writes() {
+        let archive = write_stored(&[
+            ("a.txt", b"first".to_vec()),
+            ("dir/b.txt", b"second member".to_vec()),
+        ]);
+        let mut reader = Cursor::new(archive.clone());
+        let members = members(&mut reader).unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[1].name, "dir/b.txt");
+        assert_eq!(members[1].method, METHOD_STORED);
+        assert_eq!(members[1].size, 13);
+        // the data follows the 30 byte local header and the name
+        let start = members[1].local_header_offset as usize + 30 + 9;
+        assert_eq!(&archive[start..start + 13], b"second member");
+        assert_eq!(u32_at(&archive, start - 39 + 14), crc32(b"second member"));
+    }
+}